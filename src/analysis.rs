@@ -0,0 +1,401 @@
+//! Structure analysis of a batch of UVCIs: character classes per position, vaccination month series
+//! and clusters of opaque identifiers sharing a pattern
+use crate::{parse, parse_blocks, YearMonth};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// Character class counts of one position of the opaque segments
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PositionStats {
+    /// Number of digits at the position
+    pub digits: usize,
+    /// Number of letters at the position
+    pub letters: usize,
+    /// Number of other characters at the position
+    pub other: usize,
+}
+
+impl PositionStats {
+    /// Number of opaque segments long enough to have the position
+    pub fn total(&self) -> usize {
+        return self.digits + self.letters + self.other;
+    }
+
+    /// The character class of the position, '9' for digits, 'A' for letters, '*' for other or mixed
+    pub fn class(&self) -> char {
+        if self.total() == self.digits {
+            return '9';
+        }
+        if self.total() == self.letters {
+            return 'A';
+        }
+        return '*';
+    }
+}
+
+/// Structure of the opaque segments of a set of UVCIs
+///
+/// The opaque segment is the opaque unique string as it appears in the UVCI, before any country specific
+/// decoding, e.g. "V12907267LAJW" for "URN:UVCI:01:SE:EHM/V12907267LAJW#E".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StructureReport {
+    /// Number of analyzed opaque segments, UVCIs without opaque segment are skipped
+    pub samples: usize,
+    /// Character class counts by position
+    pub positions: Vec<PositionStats>,
+    /// Number of opaque segments by length
+    pub lengths: BTreeMap<usize, usize>,
+    /// Number of opaque segments by leading letters, e.g. "V" for "V12907267LAJW", empty if starting with a digit
+    pub prefixes: BTreeMap<String, usize>,
+    /// Number of opaque segments by shape, e.g. "A99999999AAAA" for "V12907267LAJW"
+    pub shapes: BTreeMap<String, usize>,
+}
+
+impl StructureReport {
+    /// The character class of every position, e.g. "A99999999AAAA" if all segments have that shape
+    pub fn pattern(&self) -> String {
+        return self
+            .positions
+            .iter()
+            .map(|position| position.class())
+            .collect();
+    }
+}
+
+/// Display the structure report as text, with the counts of every length, prefix and shape
+impl fmt::Display for StructureReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "samples  : {}", self.samples)?;
+        writeln!(f, "pattern  : {}", self.pattern())?;
+        writeln!(f, "lengths  :")?;
+        for (length, count) in &self.lengths {
+            writeln!(f, "    {:<16} {}", length, count)?;
+        }
+        writeln!(f, "prefixes :")?;
+        for (prefix, count) in &self.prefixes {
+            writeln!(f, "    {:<16} {}", format!("\"{}\"", prefix), count)?;
+        }
+        writeln!(f, "shapes   :")?;
+        for (shape, count) in &self.shapes {
+            writeln!(f, "    {:<16} {}", shape, count)?;
+        }
+        writeln!(f, "positions:   digits letters other")?;
+        for (index, position) in self.positions.iter().enumerate() {
+            writeln!(
+                f,
+                "    {:<8} {:>6} {:>7} {:>5}",
+                index, position.digits, position.letters, position.other
+            )?;
+        }
+        return Ok(());
+    }
+}
+
+/// Analyze the structure of the opaque segments of UVCIs, to help reverse-engineer undocumented national schemes
+///
+/// Computes the character class frequencies of every position, the length distribution and the prefix and
+/// shape patterns of the opaque segments.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn analyze_structure(cert_ids: &[String]) -> StructureReport {
+    let mut report = StructureReport::default();
+    for cert_id in cert_ids {
+        let opaque = parse_blocks(cert_id.trim()).opaque_unique_string.clone();
+        if opaque.is_empty() {
+            continue;
+        }
+        report.samples += 1;
+        *report.lengths.entry(opaque.chars().count()).or_insert(0) += 1;
+
+        let prefix: String = opaque.chars().take_while(|c| c.is_alphabetic()).collect();
+        *report.prefixes.entry(prefix).or_insert(0) += 1;
+
+        let mut shape = "".to_string();
+        for (index, c) in opaque.chars().enumerate() {
+            if report.positions.len() <= index {
+                report.positions.push(PositionStats::default());
+            }
+            let position = &mut report.positions[index];
+            if c.is_ascii_digit() {
+                position.digits += 1;
+                shape.push('9');
+            } else if c.is_alphabetic() {
+                position.letters += 1;
+                shape.push('A');
+            } else {
+                position.other += 1;
+                shape.push('*');
+            }
+        }
+        *report.shapes.entry(shape).or_insert(0) += 1;
+    }
+    return report;
+}
+
+/// Number of UVCIs by estimated vaccination month
+///
+/// Only UVCIs with a decoded vaccination month are counted, i.e. Sweden EHM-issued ones.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn vaccination_months(cert_ids: &[String]) -> BTreeMap<YearMonth, usize> {
+    let mut months = BTreeMap::new();
+    for cert_id in cert_ids {
+        if let Some(month) = parse(cert_id.trim()).opaque_vaccination {
+            *months.entry(month).or_insert(0) += 1;
+        }
+    }
+    return months;
+}
+
+/// Text bar chart of the number of UVCIs by vaccination month, one line per month
+///
+/// Months without UVCIs between the first and the last month are shown with an empty bar, the longest
+/// bar is `width` characters long, e.g. "2021-08 | ######## 4".
+/// # Arguments
+///
+/// * `months` - the number of UVCIs by month, e.g. of `vaccination_months`
+/// * `width` - the length of the longest bar
+pub fn histogram(months: &BTreeMap<YearMonth, usize>, width: usize) -> String {
+    let mut output = "".to_string();
+    let max = months.values().copied().max().unwrap_or(0).max(1);
+    for (month, count) in month_series(months) {
+        // Round up, so that every month with UVCIs has a visible bar
+        let bar = (count * width).div_ceil(max);
+        output.push_str(&format!(
+            "{} | {:<width$} {}\n",
+            month,
+            "#".repeat(bar),
+            count,
+            width = width
+        ));
+    }
+    return output;
+}
+
+/// The number of UVCIs of every month from the first to the last month, 0 for the months without UVCIs
+///
+/// # Arguments
+///
+/// * `months` - the number of UVCIs by month, e.g. of `vaccination_months`
+pub fn month_series(months: &BTreeMap<YearMonth, usize>) -> Vec<(YearMonth, usize)> {
+    let mut series = Vec::new();
+    let (first, last) = match (months.keys().next(), months.keys().next_back()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return series,
+    };
+    let mut month = first;
+    while month <= last {
+        series.push((month, months.get(&month).copied().unwrap_or(0)));
+        month = month.next();
+    }
+    return series;
+}
+
+/// Group of UVCIs sharing a structural signature, see `cluster_by_pattern`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PatternCluster {
+    /// The signature, e.g. "URN:UVCI:[9]2:[A]2:[A]3/[A9]13#[A]1" for "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub signature: String,
+    /// Number of UVCIs with the signature
+    pub count: usize,
+    /// The country codes of the UVCIs with the signature, e.g. "SE"
+    pub countries: BTreeSet<String>,
+    /// The first UVCIs with the signature, at most 3
+    pub examples: Vec<String>,
+}
+
+/// The structural signature of a UVCI: every segment between the separators ':', '/' and '#' as its
+/// character classes and length
+///
+/// The classes are 'A' for uppercase letters, 'a' for lowercase letters, '9' for digits and '*' for
+/// other characters, e.g. "[A9]13" for "V12907267LAJW". The "URN:UVCI:" prefix is kept as is.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn pattern_signature(cert_id: &str) -> String {
+    let cert_id = cert_id.trim();
+    let (mut signature, rest) = match cert_id.strip_prefix("URN:UVCI:") {
+        Some(rest) => ("URN:UVCI:".to_string(), rest),
+        None => ("".to_string(), cert_id),
+    };
+    let mut segment = "".to_string();
+    let push_segment = |signature: &mut String, segment: &str| {
+        let mut classes: Vec<char> = segment
+            .chars()
+            .map(|c| match c {
+                'A'..='Z' => 'A',
+                'a'..='z' => 'a',
+                '0'..='9' => '9',
+                _ => '*',
+            })
+            .collect();
+        classes.sort_unstable_by_key(|class| "Aa9*".find(*class));
+        classes.dedup();
+        signature.push('[');
+        signature.extend(classes);
+        signature.push(']');
+        signature.push_str(&segment.chars().count().to_string());
+    };
+    for c in rest.chars() {
+        if c == ':' || c == '/' || c == '#' {
+            push_segment(&mut signature, &segment);
+            signature.push(c);
+            segment.clear();
+        } else {
+            segment.push(c);
+        }
+    }
+    push_segment(&mut signature, &segment);
+    return signature;
+}
+
+/// Group UVCIs by structural signature, to see how many distinct national formats a mixed file has
+///
+/// The clusters are ordered by decreasing count, so the outliers come last. Empty lines are skipped,
+/// see `pattern_signature` for the signatures.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn cluster_by_pattern(cert_ids: &[String]) -> Vec<PatternCluster> {
+    let mut clusters: HashMap<String, PatternCluster> = HashMap::new();
+    for cert_id in cert_ids {
+        let cert_id = cert_id.trim();
+        if cert_id.is_empty() {
+            continue;
+        }
+        let signature = pattern_signature(cert_id);
+        let cluster = clusters
+            .entry(signature.clone())
+            .or_insert_with(|| PatternCluster {
+                signature,
+                ..PatternCluster::default()
+            });
+        cluster.count += 1;
+        if let Some(country) = parse_blocks(cert_id).country {
+            cluster.countries.insert(country.as_str().to_string());
+        }
+        if cluster.examples.len() < 3 {
+            cluster.examples.push(cert_id.to_string());
+        }
+    }
+    let mut clusters: Vec<PatternCluster> = clusters.into_values().collect();
+    clusters.sort_by(|a, b| b.count.cmp(&a.count).then(a.signature.cmp(&b.signature)));
+    return clusters;
+}
+
+/// Text report of the clusters, one line per cluster with its count, signature, countries and first example
+///
+/// # Arguments
+///
+/// * `clusters` - the clusters, e.g. of `cluster_by_pattern`
+pub fn clusters_to_text(clusters: &[PatternCluster]) -> String {
+    let mut output = format!("clusters : {}\n", clusters.len());
+    for cluster in clusters {
+        let countries: Vec<&str> = cluster.countries.iter().map(|c| c.as_str()).collect();
+        output.push_str(&format!(
+            "{:>8} {:<40} {:<12} {}\n",
+            cluster.count,
+            cluster.signature,
+            countries.join(","),
+            cluster
+                .examples
+                .first()
+                .map_or("", |example| example.as_str())
+        ));
+    }
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        analyze_structure, cluster_by_pattern, clusters_to_text, histogram, pattern_signature,
+        vaccination_months,
+    };
+    use crate::YearMonth;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn structure_report() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+            "".to_string(),
+        ];
+        let report = analyze_structure(&cert_ids);
+        assert!(report.samples == 3);
+        assert!(report.lengths[&13] == 2);
+        assert!(report.lengths[&12] == 1);
+        assert!(report.prefixes["V"] == 2);
+        assert!(report.prefixes[""] == 1);
+        assert!(report.shapes["A99999999AAAA"] == 2);
+        assert!(report.positions[0].letters == 2 && report.positions[0].digits == 1);
+        assert!(report.positions[12].total() == 2);
+        assert!(report.pattern() == "*99999999AAAA");
+        assert!(report.to_string().starts_with("samples  : 3\n"));
+    }
+
+    #[test]
+    fn pattern_clusters() {
+        assert!(
+            pattern_signature("URN:UVCI:01:SE:EHM/V12907267LAJW#E")
+                == "URN:UVCI:[9]2:[A]2:[A]3/[A9]13#[A]1"
+        );
+        assert!(pattern_signature("01:nl:187/3751-2422923") == "[9]2:[a]2:[9]3/[9*]12");
+        assert!(pattern_signature("URN:UVCI:01::") == "URN:UVCI:[9]2:[]0:[]0");
+
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+        ];
+        let clusters = cluster_by_pattern(&cert_ids);
+        assert!(clusters.len() == 2);
+        assert!(clusters[0].count == 3 && clusters[0].examples.len() == 3);
+        assert!(clusters[0].countries.iter().eq(["SE"].iter()));
+        assert!(clusters[1].signature == "URN:UVCI:[9]2:[A]2:[9]3/[9]11");
+        let text = clusters_to_text(&clusters);
+        assert!(text.starts_with("clusters : 2\n"));
+        assert!(text.lines().count() == 3);
+    }
+
+    #[test]
+    fn vaccination_month_histogram() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+        let months = vaccination_months(&cert_ids);
+        assert!(months.values().sum::<usize>() == 2);
+
+        let mut months = BTreeMap::new();
+        months.insert(
+            YearMonth {
+                year: 2021,
+                month: 11,
+            },
+            4,
+        );
+        months.insert(
+            YearMonth {
+                year: 2022,
+                month: 1,
+            },
+            1,
+        );
+        let chart = histogram(&months, 8);
+        assert!(
+            chart
+                == "2021-11 | ######## 4\n\
+                    2021-12 |          0\n\
+                    2022-01 | ##       1\n"
+        );
+        assert!(histogram(&BTreeMap::new(), 8).is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! Export of parsed UVCIs to Apache Arrow record batches with typed columns
+use crate::{parse, Uvci};
+use arrow::array::{ArrayRef, BooleanArray, Date32Array, StringArray, UInt16Array, UInt8Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+/// Apache Arrow schema of the parsed EU Digital COVID Certificate UVCI data
+///
+/// The columns are the fields of `Uvci::FIELD_NAMES` with their native types, null when unknown
+/// instead of the empty strings and zeros of the CSV export, followed by the estimated vaccination
+/// month as Date32 "vaccination_date" (the first day of the month).
+pub fn uvci_arrow_schema() -> Schema {
+    let text = |name: &str, nullable: bool| Field::new(name, DataType::Utf8, nullable);
+    return Schema::new(vec![
+        Field::new("version", DataType::UInt8, true),
+        text("country", true),
+        Field::new("schema_option_number", DataType::UInt8, false),
+        text("schema_option_desc", false),
+        text("issuing_entity", true),
+        text("vaccine_id", true),
+        text("opaque_unique_string", false),
+        text("opaque_id", true),
+        text("opaque_issuance", true),
+        Field::new("opaque_vaccination_month", DataType::UInt8, true),
+        Field::new("opaque_vaccination_year", DataType::UInt16, true),
+        text("checksum", true),
+        Field::new("checksum_verification", DataType::Boolean, false),
+        Field::new("vaccination_date", DataType::Date32, true),
+    ]);
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to an Apache Arrow record batch
+///
+/// The batch has the columns of `uvci_arrow_schema`, one row per UVCI, to query with DataFusion or
+/// hand to Polars without serializing to disk.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_record_batch(cert_ids: &[String]) -> Result<RecordBatch, ArrowError> {
+    let uvcis: Vec<Uvci> = cert_ids.iter().map(|cert_id| parse(cert_id)).collect();
+    return to_record_batch(&uvcis);
+}
+
+/// Export parsed EU Digital COVID Certificate UVCI data to an Apache Arrow record batch
+///
+/// # Arguments
+///
+/// * `uvcis` - the parsed UVCIs, one row each
+pub fn to_record_batch(uvcis: &[Uvci]) -> Result<RecordBatch, ArrowError> {
+    let text = |value: fn(&Uvci) -> Option<&str>| -> ArrayRef {
+        Arc::new(uvcis.iter().map(value).collect::<StringArray>())
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(uvcis.iter().map(|u| u.version).collect::<UInt8Array>()),
+        text(|u| u.country.as_ref().map(|country| country.as_str())),
+        Arc::new(
+            uvcis
+                .iter()
+                .map(|u| Some(u.schema_option.number()))
+                .collect::<UInt8Array>(),
+        ),
+        text(|u| Some(u.schema_option.desc())),
+        text(|u| u.issuing_entity.as_deref()),
+        text(|u| u.vaccine_id.as_deref()),
+        text(|u| Some(u.opaque_unique_string.as_str())),
+        text(|u| u.opaque_id.as_deref()),
+        text(|u| u.opaque_issuance.as_deref()),
+        Arc::new(
+            uvcis
+                .iter()
+                .map(|u| u.opaque_vaccination.map(|date| date.month))
+                .collect::<UInt8Array>(),
+        ),
+        Arc::new(
+            uvcis
+                .iter()
+                .map(|u| u.opaque_vaccination.map(|date| date.year))
+                .collect::<UInt16Array>(),
+        ),
+        text(|u| u.checksum.as_deref()),
+        Arc::new(
+            uvcis
+                .iter()
+                .map(|u| Some(u.checksum_verification))
+                .collect::<BooleanArray>(),
+        ),
+        Arc::new(
+            uvcis
+                .iter()
+                .map(|u| u.opaque_vaccination.map(|date| date.days_since_epoch()))
+                .collect::<Date32Array>(),
+        ),
+    ];
+    return RecordBatch::try_new(Arc::new(uvci_arrow_schema()), columns);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uvcis_to_record_batch;
+    use arrow::array::{Array, Date32Array, StringArray, UInt16Array};
+
+    #[test]
+    fn arrow_record_batch() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+        ];
+        let batch = uvcis_to_record_batch(&cert_ids).unwrap();
+        assert!(batch.num_rows() == 2 && batch.num_columns() == 14);
+        let opaque_id = batch
+            .column_by_name("opaque_id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(opaque_id.value(0) == "V12916227");
+        let year = batch.column_by_name("opaque_vaccination_year").unwrap();
+        let year = year.as_any().downcast_ref::<UInt16Array>().unwrap();
+        assert!(year.value(0) == 2021 && year.is_null(1));
+        assert!(batch.column_by_name("checksum").unwrap().is_null(1));
+        let date = batch.column_by_name("vaccination_date").unwrap();
+        let date = date.as_any().downcast_ref::<Date32Array>().unwrap();
+        assert!(date.value_as_date(0).unwrap().to_string() == "2021-08-01");
+    }
+}
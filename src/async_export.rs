@@ -0,0 +1,133 @@
+//! Async variants of the batch CSV, JSON and graph exporters, reading from an `AsyncRead` and writing
+//! to an `AsyncWrite`
+use crate::batch::BatchResult;
+use crate::{parse, to_json, uvci_to_graph, write_csv, CSV_LINE_CAPACITY};
+use std::collections::HashSet;
+use std::io;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Export EU Digital COVID Certificate UVCIs read line by line to CSV, without blocking the runtime
+///
+/// The output is the same as `uvcis_to_csv`, written one line per UVCI as the input is read. Returns
+/// the summary of the UVCIs exported.
+/// # Arguments
+///
+/// * `reader` - the UVCI input, one UVCI per line
+/// * `writer` - the CSV output
+pub async fn uvcis_to_csv_async<R, W>(reader: R, writer: &mut W) -> io::Result<BatchResult>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let mut lines = BufReader::new(reader).lines();
+    let mut line = String::with_capacity(CSV_LINE_CAPACITY);
+    while let Some(cert_id) = lines.next_line().await? {
+        let uvci = parse(&cert_id);
+        result.record(&cert_id, &uvci);
+        line.clear();
+        write_csv(&uvci, &mut line);
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await?;
+    }
+    writer.flush().await?;
+    return Ok(result.finish(started));
+}
+
+/// Export EU Digital COVID Certificate UVCIs read line by line to a JSON array, without blocking the runtime
+///
+/// The output is the same as `uvcis_to_json`, written one element per UVCI as the input is read.
+/// Returns the summary of the UVCIs exported.
+/// # Arguments
+///
+/// * `reader` - the UVCI input, one UVCI per line
+/// * `writer` - the JSON output
+pub async fn uvcis_to_json_async<R, W>(reader: R, writer: &mut W) -> io::Result<BatchResult>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let mut lines = BufReader::new(reader).lines();
+    let mut separator = "";
+    writer.write_all(b"[").await?;
+    while let Some(cert_id) = lines.next_line().await? {
+        let uvci = parse(&cert_id);
+        result.record(&cert_id, &uvci);
+        writer.write_all(separator.as_bytes()).await?;
+        writer
+            .write_all(to_json(&uvci).to_string().as_bytes())
+            .await?;
+        separator = ",";
+    }
+    writer.write_all(b"]").await?;
+    writer.flush().await?;
+    return Ok(result.finish(started));
+}
+
+/// Export EU Digital COVID Certificate UVCIs read line by line to Neo4j Cypher Graph, without blocking the runtime
+///
+/// Only for Sweden EHM-issued COVID certificates. The output is the same as `uvcis_to_graph`,
+/// duplicate statements are skipped as the input is read. Returns the summary of the UVCIs exported.
+/// # Arguments
+///
+/// * `reader` - the UVCI input, one UVCI per line
+/// * `writer` - the Cypher output
+pub async fn uvcis_to_graph_async<R, W>(reader: R, writer: &mut W) -> io::Result<BatchResult>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let mut lines = BufReader::new(reader).lines();
+    let mut statements = HashSet::new();
+    while let Some(cert_id) = lines.next_line().await? {
+        result.record(&cert_id, &parse(&cert_id));
+        for statement in uvci_to_graph(&cert_id).lines() {
+            if statements.insert(statement.to_string()) {
+                writer.write_all(b"\n").await?;
+                writer.write_all(statement.as_bytes()).await?;
+            }
+        }
+    }
+    writer.flush().await?;
+    return Ok(result.finish(started));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{uvcis_to_csv_async, uvcis_to_graph_async, uvcis_to_json_async};
+    use crate::{uvcis_to_csv, uvcis_to_graph, uvcis_to_json};
+
+    #[tokio::test]
+    async fn async_exports_match_batch_exports() {
+        let input = "URN:UVCI:01:SE:EHM/V12907267LAJW#E\n\
+                     URN:UVCI:01:SE:EHM/V12916227TFJJ#Q\n\
+                     URN:UVCI:01:SE:EHM/V12916227TFJJ#Q\n\
+                     URN:UVCI:01:SE:EHM/C878/123456789ABC#B\n";
+        let cert_ids: Vec<String> = input.lines().map(|l| l.to_string()).collect();
+
+        let mut csv = Vec::new();
+        let result = uvcis_to_csv_async(input.as_bytes(), &mut csv)
+            .await
+            .unwrap();
+        assert!(String::from_utf8(csv).unwrap() == uvcis_to_csv(&cert_ids));
+        assert!(result.processed == 4 && result.per_country.get("SE") == Some(&4));
+
+        let mut json = Vec::new();
+        uvcis_to_json_async(input.as_bytes(), &mut json)
+            .await
+            .unwrap();
+        assert!(String::from_utf8(json).unwrap() == uvcis_to_json(&cert_ids));
+
+        let mut graph = Vec::new();
+        uvcis_to_graph_async(input.as_bytes(), &mut graph)
+            .await
+            .unwrap();
+        assert!(String::from_utf8(graph).unwrap() == uvcis_to_graph(&cert_ids));
+    }
+}
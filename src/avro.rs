@@ -0,0 +1,117 @@
+//! Export of parsed UVCIs to Apache Avro object container files
+use crate::{parse, Uvci};
+use apache_avro::types::{Record, Value};
+use apache_avro::{Schema, Writer};
+
+/// Apache Avro schema of the parsed EU Digital COVID Certificate UVCI data, one field per `Uvci` field
+pub const UVCI_AVRO_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "Uvci",
+    "namespace": "eu.dcc",
+    "doc": "EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier) data",
+    "fields": [
+        {"name": "version", "type": "int"},
+        {"name": "country", "type": "string"},
+        {"name": "schema_option_number", "type": "int"},
+        {"name": "schema_option_desc", "type": "string"},
+        {"name": "issuing_entity", "type": "string"},
+        {"name": "vaccine_id", "type": "string"},
+        {"name": "opaque_unique_string", "type": "string"},
+        {"name": "opaque_id", "type": "string"},
+        {"name": "opaque_issuance", "type": "string"},
+        {"name": "opaque_vaccination_month", "type": "int"},
+        {"name": "opaque_vaccination_year", "type": "int"},
+        {"name": "checksum", "type": "string"},
+        {"name": "checksum_verification", "type": "boolean"}
+    ]
+}"#;
+
+/// The parsed Apache Avro schema of the EU Digital COVID Certificate UVCI data
+pub fn uvci_avro_schema() -> Schema {
+    return Schema::parse_str(UVCI_AVRO_SCHEMA).expect("invalid UVCI Avro schema");
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to an Apache Avro object container file
+///
+/// The schema `UVCI_AVRO_SCHEMA` is embedded in the container, one record per UVCI.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+// The error is the one of apache_avro, returned as is
+#[allow(clippy::result_large_err)]
+pub fn uvcis_to_avro(cert_ids: &[String]) -> Result<Vec<u8>, apache_avro::Error> {
+    let schema = uvci_avro_schema();
+    let mut writer = Writer::new(&schema, Vec::new());
+    for cert_id in cert_ids {
+        writer.append(to_avro(&schema, parse(cert_id)))?;
+    }
+    return writer.into_inner();
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to an Apache Avro record value
+pub(crate) fn to_avro(schema: &Schema, mut uvci: Uvci) -> Value {
+    let mut record = Record::new(schema).expect("UVCI Avro schema is not a record");
+    record.put("version", uvci.version.unwrap_or(0) as i32);
+    record.put("country", uvci.country_str().to_string());
+    record.put("schema_option_number", uvci.schema_option.number() as i32);
+    record.put("schema_option_desc", uvci.schema_option.desc());
+    record.put(
+        "issuing_entity",
+        uvci.issuing_entity.take().unwrap_or_default(),
+    );
+    record.put("vaccine_id", uvci.vaccine_id.take().unwrap_or_default());
+    record.put(
+        "opaque_unique_string",
+        std::mem::take(&mut uvci.opaque_unique_string),
+    );
+    record.put("opaque_id", uvci.opaque_id.take().unwrap_or_default());
+    record.put(
+        "opaque_issuance",
+        uvci.opaque_issuance.take().unwrap_or_default(),
+    );
+    record.put(
+        "opaque_vaccination_month",
+        uvci.opaque_vaccination.map_or(0, |date| date.month) as i32,
+    );
+    record.put(
+        "opaque_vaccination_year",
+        uvci.opaque_vaccination.map_or(0, |date| date.year) as i32,
+    );
+    record.put("checksum", uvci.checksum.take().unwrap_or_default());
+    record.put("checksum_verification", uvci.checksum_verification);
+    return record.into();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uvcis_to_avro;
+    use apache_avro::types::Value;
+    use apache_avro::Reader;
+
+    #[test]
+    fn avro_container_roundtrip() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+        let bytes = uvcis_to_avro(&cert_ids).unwrap();
+        let records: Vec<Value> = Reader::new(&bytes[..])
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert!(records.len() == 2);
+        match &records[0] {
+            Value::Record(fields) => {
+                assert!(
+                    fields[7]
+                        == (
+                            "opaque_id".to_string(),
+                            Value::String("V12916227".to_string())
+                        )
+                );
+                assert!(fields[10] == ("opaque_vaccination_year".to_string(), Value::Int(2021)));
+            }
+            _ => panic!("not a record"),
+        }
+    }
+}
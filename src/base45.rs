@@ -0,0 +1,114 @@
+//! Base45 encoding and decoding according to RFC 9285, as used by the QR codes of EU Digital COVID Certificates
+use std::fmt;
+
+/// The Base45 alphabet
+const ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Error decoding a Base45 string
+#[derive(Clone, Debug, PartialEq)]
+pub enum Base45Error {
+    /// A character outside of the Base45 alphabet, with its byte offset
+    InvalidCharacter(char, usize),
+    /// The length of the input is not valid, i.e. the length modulo 3 is 1
+    InvalidLength(usize),
+    /// A character group decodes to a value that does not fit, with the byte offset of the group
+    Overflow(usize),
+}
+
+impl fmt::Display for Base45Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Base45Error::InvalidCharacter(c, offset) => {
+                write!(f, "invalid Base45 character '{}' at offset {}", c, offset)
+            }
+            Base45Error::InvalidLength(len) => write!(f, "invalid Base45 length {}", len),
+            Base45Error::Overflow(offset) => {
+                write!(f, "Base45 value overflow at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Base45Error {}
+
+/// Encode bytes to Base45
+///
+/// # Arguments
+///
+/// * `bytes` - the bytes to encode, e.g. b"AB" which encodes to "BB8"
+pub fn base45_encode(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(2) * 3);
+    for chunk in bytes.chunks(2) {
+        let mut n = if chunk.len() == 2 {
+            (chunk[0] as usize) * 256 + (chunk[1] as usize)
+        } else {
+            chunk[0] as usize
+        };
+        let digits = if chunk.len() == 2 { 3 } else { 2 };
+        for _ in 0..digits {
+            output.push(ALPHABET[n % 45] as char);
+            n /= 45;
+        }
+    }
+    return output;
+}
+
+/// Decode a Base45 string to bytes
+///
+/// # Arguments
+///
+/// * `input` - the Base45 string, e.g. "BB8" which decodes to b"AB"
+pub fn base45_decode(input: &str) -> Result<Vec<u8>, Base45Error> {
+    if input.len() % 3 == 1 {
+        return Err(Base45Error::InvalidLength(input.len()));
+    }
+
+    let mut values = Vec::with_capacity(input.len());
+    for (offset, c) in input.char_indices() {
+        match ALPHABET.iter().position(|&a| a as char == c) {
+            Some(value) => values.push(value),
+            None => return Err(Base45Error::InvalidCharacter(c, offset)),
+        }
+    }
+
+    let mut output = Vec::with_capacity(input.len() / 3 * 2 + 1);
+    for (i, chunk) in values.chunks(3).enumerate() {
+        let offset = i * 3;
+        if chunk.len() == 3 {
+            let n = chunk[0] + chunk[1] * 45 + chunk[2] * 45 * 45;
+            if n > 0xffff {
+                return Err(Base45Error::Overflow(offset));
+            }
+            output.push((n / 256) as u8);
+            output.push((n % 256) as u8);
+        } else {
+            let n = chunk[0] + chunk[1] * 45;
+            if n > 0xff {
+                return Err(Base45Error::Overflow(offset));
+            }
+            output.push(n as u8);
+        }
+    }
+    return Ok(output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base45_decode, base45_encode, Base45Error};
+
+    #[test]
+    fn base45_rfc_9285_examples() {
+        assert!(base45_encode(b"AB") == "BB8");
+        assert!(base45_encode(b"Hello!!") == "%69 VD92EX0");
+        assert!(base45_encode(b"base-45") == "UJCLQE7W581");
+        assert!(base45_decode("QED8WEX0").unwrap() == b"ietf!");
+        assert!(base45_decode("%69 VD92EX0").unwrap() == b"Hello!!");
+    }
+
+    #[test]
+    fn base45_invalid() {
+        assert!(base45_decode("GGW") == Err(Base45Error::Overflow(0)));
+        assert!(base45_decode("BB8a") == Err(Base45Error::InvalidLength(4)));
+        assert!(base45_decode("B_") == Err(Base45Error::InvalidCharacter('_', 1)));
+    }
+}
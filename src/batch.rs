@@ -0,0 +1,98 @@
+//! Summary of the batch operations
+use crate::{diagnose, Diagnostic, Severity, Uvci};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Summary of a batch operation, returned in addition to its output
+///
+/// Callers learn how many UVCIs were processed, how many are valid or invalid and from which countries
+/// without re-scanning the output. A UVCI fails if it has an error diagnostic of `diagnose`, failed
+/// UVCIs are still exported unless the operation rejects them, e.g. `export::export_all_logged`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchResult {
+    /// The number of UVCIs processed
+    pub processed: usize,
+    /// The number of UVCIs without error diagnostic
+    pub succeeded: usize,
+    /// The number of UVCIs with an error diagnostic
+    pub failed: usize,
+    /// The number of warning diagnostics of all UVCIs
+    pub warnings: usize,
+    /// The time the operation took
+    pub duration: Duration,
+    /// The number of UVCIs processed by ISO 3166-1 country code, without the UVCIs of unknown country
+    pub per_country: HashMap<String, usize>,
+}
+
+impl BatchResult {
+    /// Count a processed UVCI
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    /// * `uvci` - the UVCI parsed from `cert_id`
+    pub fn record(&mut self, cert_id: &str, uvci: &Uvci) {
+        self.record_diagnosed(uvci, &diagnose(cert_id));
+    }
+
+    /// Count a processed UVCI already diagnosed
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    /// * `diagnostics` - the diagnostics of the UVCI, see `diagnose`
+    pub fn record_diagnosed(&mut self, uvci: &Uvci, diagnostics: &[Diagnostic]) {
+        self.processed += 1;
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            self.failed += 1;
+        } else {
+            self.succeeded += 1;
+        }
+        self.warnings += diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+            .count();
+        if uvci.country.is_some() {
+            *self
+                .per_country
+                .entry(uvci.country_str().to_string())
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// Set the duration of the operation started at the given instant
+    pub fn finish(mut self, started: Instant) -> Self {
+        self.duration = started.elapsed();
+        return self;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BatchResult;
+    use crate::parse;
+    use std::time::Instant;
+
+    #[test]
+    fn batch_result() {
+        let started = Instant::now();
+        let mut result = BatchResult::default();
+        for cert_id in [
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E",
+            "URN:UVCI:01:NL:187/37512422923",
+            "URN:UVCI:01",
+        ] {
+            result.record(cert_id, &parse(cert_id));
+        }
+        let result = result.finish(started);
+        assert!(result.processed == 3);
+        assert!(result.succeeded == 2 && result.failed == 1);
+        assert!(result.warnings == 2);
+        assert!(result.per_country.get("SE") == Some(&1));
+        assert!(result.per_country.get("NL") == Some(&1));
+        assert!(result.per_country.len() == 2);
+    }
+}
@@ -0,0 +1,149 @@
+//! Bloom filter of the DCC revocation specification
+//!
+//! The filter hashes its elements with SHA-256 and serializes to the layout of the `dgc-bloomfilter`
+//! reference implementation. It backs the revocation filters of `revocation` and the shards of
+//! `dedup::SeenSet`.
+use sha2::{Digest, Sha256};
+
+/// Version of the serialized bloom filter layout
+const BLOOM_FILTER_VERSION: u16 = 1;
+/// Size of the serialized bloom filter header in bytes
+const HEADER_LEN: usize = 2 + 1 + 4 * 4;
+
+/// Bloom filter over byte strings, with SHA-256 based index hashing, as the bloom filters of the DCC
+/// revocation specification (the `dgc-bloomfilter` reference implementation of the DCC gateway)
+///
+/// Index `i` of an element is SHA-256(element || i as u32 big-endian), read as an unsigned big-endian
+/// integer, modulo the number of bits. Bit `index % 32` of word `index / 32` is set.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BloomFilter {
+    probability_rate: f32,
+    num_hashes: u8,
+    num_bits: u32,
+    current_elements: u32,
+    defined_elements: u32,
+    words: Vec<u32>,
+}
+
+impl BloomFilter {
+    /// Create an empty bloom filter sized for the expected number of elements and false-positive rate
+    pub(crate) fn new(expected_elements: u32, probability_rate: f32) -> BloomFilter {
+        let num_bits = num_bits(expected_elements, probability_rate);
+        let n = expected_elements.max(1) as f64;
+        let num_hashes = ((num_bits as f64) / n * std::f64::consts::LN_2)
+            .round()
+            .clamp(1.0, u8::MAX as f64) as u8;
+        return BloomFilter {
+            probability_rate,
+            num_hashes,
+            num_bits,
+            current_elements: 0,
+            defined_elements: expected_elements,
+            words: vec![0; (num_bits as usize).div_ceil(32)],
+        };
+    }
+
+    /// Deserialize a bloom filter, None if the layout is invalid
+    ///
+    /// The big-endian layout of the DCC revocation bloom filters is version (u16, 1), number of
+    /// hashes (u8), probability rate (f32), defined number of elements (u32), current number of
+    /// elements (u32), number of words (u32), followed by the bit array as u32 words. The number of
+    /// bits is not serialized, it follows from the defined number of elements and the probability rate.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<BloomFilter> {
+        if bytes.len() < HEADER_LEN {
+            return None;
+        }
+        let u32_at =
+            |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let version = u16::from_be_bytes([bytes[0], bytes[1]]);
+        if version != BLOOM_FILTER_VERSION {
+            return None;
+        }
+        let num_hashes = bytes[2];
+        let probability_rate = f32::from_bits(u32_at(3));
+        let defined_elements = u32_at(7);
+        let current_elements = u32_at(11);
+        let num_words = u32_at(15) as usize;
+        let num_bits = num_bits(defined_elements, probability_rate);
+        if num_bits == 0
+            || num_hashes == 0
+            || num_words != (num_bits as usize).div_ceil(32)
+            || bytes.len() != HEADER_LEN + num_words * 4
+        {
+            return None;
+        }
+        let words = (0..num_words).map(|w| u32_at(HEADER_LEN + w * 4)).collect();
+        return Some(BloomFilter {
+            probability_rate,
+            num_hashes,
+            num_bits,
+            current_elements,
+            defined_elements,
+            words,
+        });
+    }
+
+    /// Serialize the bloom filter, see `from_bytes` for the layout
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.words.len() * 4);
+        bytes.extend_from_slice(&BLOOM_FILTER_VERSION.to_be_bytes());
+        bytes.push(self.num_hashes);
+        bytes.extend_from_slice(&self.probability_rate.to_bits().to_be_bytes());
+        bytes.extend_from_slice(&self.defined_elements.to_be_bytes());
+        bytes.extend_from_slice(&self.current_elements.to_be_bytes());
+        bytes.extend_from_slice(&(self.words.len() as u32).to_be_bytes());
+        for word in &self.words {
+            bytes.extend_from_slice(&word.to_be_bytes());
+        }
+        return bytes;
+    }
+
+    /// Add an element to the bloom filter
+    pub(crate) fn insert(&mut self, element: &[u8]) {
+        for i in 0..self.num_hashes as u32 {
+            let index = self.index(element, i);
+            self.words[(index / 32) as usize] |= 1 << (index % 32);
+        }
+        self.current_elements = self.current_elements.saturating_add(1);
+    }
+
+    /// Whether the element is probably in the bloom filter, false positives are possible
+    pub(crate) fn contains(&self, element: &[u8]) -> bool {
+        for i in 0..self.num_hashes as u32 {
+            let index = self.index(element, i);
+            if self.words[(index / 32) as usize] & (1 << (index % 32)) == 0 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Number of elements added to the bloom filter
+    pub(crate) fn len(&self) -> u32 {
+        return self.current_elements;
+    }
+
+    /// Bit index of an element for the given hash number
+    fn index(&self, element: &[u8], hash_number: u32) -> u32 {
+        let mut hasher = Sha256::new();
+        hasher.update(element);
+        hasher.update(hash_number.to_be_bytes());
+        let digest = hasher.finalize();
+        let mut index: u64 = 0;
+        for byte in digest.iter() {
+            index = (index * 256 + *byte as u64) % self.num_bits as u64;
+        }
+        return index as u32;
+    }
+}
+
+/// Number of bits of a bloom filter for the defined number of elements and the probability rate
+///
+/// The formula of the reference implementation, ceil(n * ln(p) / ln(1 / 2^ln(2))), so the number of
+/// bits of a deserialized filter is the one it was created with.
+fn num_bits(defined_elements: u32, probability_rate: f32) -> u32 {
+    let n = defined_elements.max(1) as f64;
+    let p = (probability_rate as f64).clamp(f64::MIN_POSITIVE, 0.5);
+    let ln2 = std::f64::consts::LN_2;
+    return ((n * p.ln()) / (1.0 / 2f64.powf(ln2)).ln()).ceil() as u32;
+}
@@ -0,0 +1,70 @@
+//! Export of parsed UVCIs to MongoDB BSON documents and mongoimport extended JSON
+use crate::{normalized_cert_id, parse, Uvci};
+use bson::{doc, Bson, Document};
+
+/// Export a vector of EU Digital COVID Certificate UVCI to MongoDB BSON documents
+///
+/// Every document has `_id` set to the normalized UVCI as given, numeric fields are stored as 32-bit integers
+/// and the checksum verification as a boolean.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_bson_docs(cert_ids: &[String]) -> Vec<Document> {
+    return cert_ids
+        .iter()
+        .map(|cert_id| to_bson_doc(parse(cert_id)))
+        .collect();
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to mongoimport-compatible extended JSON
+///
+/// One canonical extended JSON document per line, as read by `mongoimport` without `--jsonArray`.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_mongoimport_json(cert_ids: &[String]) -> String {
+    let mut output = "".to_string();
+    for doc in uvcis_to_bson_docs(cert_ids) {
+        output.push_str(&Bson::Document(doc).into_canonical_extjson().to_string());
+        output.push_str("\n");
+    }
+    return output;
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to a BSON document
+fn to_bson_doc(mut uvci: Uvci) -> Document {
+    return doc! {
+        "_id": normalized_cert_id(&uvci.cert_id),
+        "version": uvci.version.unwrap_or(0) as i32,
+        "country": uvci.country_str(),
+        "schema_option_number": uvci.schema_option.number() as i32,
+        "schema_option_desc": uvci.schema_option.desc(),
+        "issuing_entity": uvci.issuing_entity.take().unwrap_or_default(),
+        "vaccine_id": uvci.vaccine_id.take().unwrap_or_default(),
+        "opaque_unique_string": std::mem::take(&mut uvci.opaque_unique_string),
+        "opaque_id": uvci.opaque_id.take().unwrap_or_default(),
+        "opaque_issuance": uvci.opaque_issuance.take().unwrap_or_default(),
+        "opaque_vaccination_month": uvci.opaque_vaccination.map_or(0, |date| date.month) as i32,
+        "opaque_vaccination_year": uvci.opaque_vaccination.map_or(0, |date| date.year) as i32,
+        "checksum": uvci.checksum.take().unwrap_or_default(),
+        "checksum_verification": uvci.checksum_verification,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{uvcis_to_bson_docs, uvcis_to_mongoimport_json};
+
+    #[test]
+    fn bson_docs() {
+        let cert_ids = vec!["01:SE:EHM/V12916227TFJJ#Q".to_string()];
+        let docs = uvcis_to_bson_docs(&cert_ids);
+        assert!(docs[0].get_str("_id").unwrap() == "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q");
+        assert!(docs[0].get_i32("opaque_vaccination_year").unwrap() == 2021);
+        assert!(docs[0].get_bool("checksum_verification").unwrap());
+
+        let json = uvcis_to_mongoimport_json(&cert_ids);
+        assert!(json.lines().count() == 1);
+        assert!(json.contains(r#""opaque_vaccination_year":{"$numberInt":"2021"}"#));
+    }
+}
@@ -0,0 +1,199 @@
+//! Compact representation of parsed UVCIs, storing short fields inline
+use crate::{parse, split_vaccine_id, CountryCode, SchemaOption, Uvci, YearMonth};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Maximum length in bytes of a string stored inline, so a `CompactStr` is as large as a `String`
+pub const INLINE_CAPACITY: usize = 22;
+
+/// Immutable string stored inline up to `INLINE_CAPACITY` bytes, on the heap otherwise
+///
+/// Almost all UVCI fields are short, so they are stored without a heap allocation.
+#[derive(Clone)]
+pub enum CompactStr {
+    /// String of at most `INLINE_CAPACITY` bytes, the first `len` bytes are used
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    /// Longer string
+    Heap(Box<str>),
+}
+
+impl CompactStr {
+    pub fn new(value: &str) -> CompactStr {
+        if value.len() > INLINE_CAPACITY {
+            return CompactStr::Heap(value.into());
+        }
+        let mut bytes = [0; INLINE_CAPACITY];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        return CompactStr::Inline {
+            len: value.len() as u8,
+            bytes,
+        };
+    }
+
+    /// The string
+    pub fn as_str(&self) -> &str {
+        match self {
+            CompactStr::Inline { len, bytes } => std::str::from_utf8(&bytes[..*len as usize])
+                .expect("inline bytes are copied from a str"),
+            CompactStr::Heap(value) => value,
+        }
+    }
+
+    /// Whether the string is stored inline, without a heap allocation
+    pub fn is_inline(&self) -> bool {
+        return matches!(self, CompactStr::Inline { .. });
+    }
+}
+
+impl Deref for CompactStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        return self.as_str();
+    }
+}
+
+impl From<&str> for CompactStr {
+    fn from(value: &str) -> CompactStr {
+        return CompactStr::new(value);
+    }
+}
+
+impl PartialEq for CompactStr {
+    fn eq(&self, other: &CompactStr) -> bool {
+        return self.as_str() == other.as_str();
+    }
+}
+
+impl Eq for CompactStr {}
+
+impl Hash for CompactStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl fmt::Debug for CompactStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for CompactStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// EU Digital COVID Certificate UVCI data with the strings stored inline, for batch processing
+///
+/// The fields are those of `Uvci`. A UVCI is at most 72 characters long and its blocks are mostly
+/// shorter than `INLINE_CAPACITY`, so a `CompactUvci` usually needs no heap allocation at all and a
+/// vector of them is contiguous in memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactUvci {
+    pub version: Option<u8>,
+    pub country: Option<CountryCode>,
+    pub schema_option: SchemaOption,
+    pub issuing_entity: Option<CompactStr>,
+    pub vaccine_id: Option<CompactStr>,
+    pub opaque_unique_string: CompactStr,
+    pub opaque_id: Option<CompactStr>,
+    pub opaque_issuance: Option<CompactStr>,
+    pub opaque_vaccination: Option<YearMonth>,
+    pub checksum: Option<CompactStr>,
+    pub checksum_verification: bool,
+}
+
+impl CompactUvci {
+    /// The owned `Uvci`, e.g. to export it
+    ///
+    /// The UVCI as given is not kept, `cert_id` is the UVCI rebuilt from the fields.
+    pub fn to_uvci(&self) -> Uvci {
+        let owned = |value: &Option<CompactStr>| value.as_deref().map(|value| value.to_string());
+        let vaccine_id = owned(&self.vaccine_id);
+        let (vaccine_product_code, vaccine_lot) = split_vaccine_id(&vaccine_id);
+        let mut uvci = Uvci {
+            cert_id: "".to_string(),
+            version: self.version,
+            country: self.country,
+            schema_option: self.schema_option,
+            issuing_entity: owned(&self.issuing_entity),
+            vaccine_id,
+            vaccine_product_code,
+            vaccine_lot,
+            opaque_unique_string: self.opaque_unique_string.to_string(),
+            opaque_id: owned(&self.opaque_id),
+            opaque_issuance: owned(&self.opaque_issuance),
+            opaque_vaccination: self.opaque_vaccination,
+            checksum: owned(&self.checksum),
+            checksum_verification: self.checksum_verification,
+        };
+        uvci.cert_id = uvci.rebuild();
+        return uvci;
+    }
+}
+
+impl From<&Uvci> for CompactUvci {
+    fn from(uvci: &Uvci) -> CompactUvci {
+        let compact = |value: &Option<String>| value.as_deref().map(CompactStr::new);
+        return CompactUvci {
+            version: uvci.version,
+            country: uvci.country,
+            schema_option: uvci.schema_option,
+            issuing_entity: compact(&uvci.issuing_entity),
+            vaccine_id: compact(&uvci.vaccine_id),
+            opaque_unique_string: CompactStr::new(&uvci.opaque_unique_string),
+            opaque_id: compact(&uvci.opaque_id),
+            opaque_issuance: compact(&uvci.opaque_issuance),
+            opaque_vaccination: uvci.opaque_vaccination,
+            checksum: compact(&uvci.checksum),
+            checksum_verification: uvci.checksum_verification,
+        };
+    }
+}
+
+/// Parse a EU Digital COVID Certificate UVCI to a `CompactUvci`
+///
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn parse_compact(cert_id: &str) -> CompactUvci {
+    return CompactUvci::from(&parse(cert_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_compact, CompactStr, INLINE_CAPACITY};
+    use crate::{parse, to_csv};
+    use std::mem::size_of;
+
+    #[test]
+    fn compact_str() {
+        assert!(size_of::<CompactStr>() == size_of::<String>());
+        let short = CompactStr::new("V12907267LAJW");
+        assert!(short.is_inline() && short.as_str() == "V12907267LAJW");
+        let long = "A".repeat(INLINE_CAPACITY + 1);
+        let long = CompactStr::new(&long);
+        assert!(!long.is_inline() && long.len() == INLINE_CAPACITY + 1);
+        assert!(CompactStr::new("Ä") == CompactStr::from("Ä"));
+    }
+
+    #[test]
+    fn compact_uvci() {
+        for cert_id in &[
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E",
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B",
+            "01:NL:187/37512422923",
+            "not a UVCI",
+        ] {
+            let uvci = parse_compact(cert_id);
+            assert!(uvci.opaque_unique_string.is_inline());
+            assert!(to_csv(&uvci.to_uvci()) == to_csv(&parse(cert_id)));
+        }
+    }
+}
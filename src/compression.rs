@@ -0,0 +1,233 @@
+//! Reading of gzip and Zstandard compressed input, detected from the magic bytes, and compressed output
+use crate::{parse, parse_sourced, SourcedUvci, Uvci};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Magic bytes of a gzip stream
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Magic bytes of a Zstandard frame
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Wrap a reader of UVCI input, decompressing gzip and Zstandard input transparently
+///
+/// The compression is detected from the magic bytes, uncompressed input is read as is. Decompressing
+/// gzip input requires the `flate2` feature, Zstandard input the `zstd` feature.
+/// # Arguments
+///
+/// * `reader` - the possibly compressed input, e.g. an opened "uvci.txt.gz" file
+pub fn decompressed_reader<R: Read + 'static>(reader: R) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(reader);
+    let magic = reader.fill_buf()?;
+    if magic.starts_with(GZIP_MAGIC) {
+        #[cfg(feature = "flate2")]
+        return Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(reader),
+        )));
+        #[cfg(not(feature = "flate2"))]
+        return Err(unsupported("gzip", "flate2"));
+    }
+    if magic.starts_with(ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return Ok(Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )));
+        #[cfg(not(feature = "zstd"))]
+        return Err(unsupported("Zstandard", "zstd"));
+    }
+    return Ok(Box::new(reader));
+}
+
+/// Compression of written output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// gzip, requires the `flate2` feature
+    Gzip,
+    /// Zstandard, requires the `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    /// The code of the compression as used in the executable, i.e. "gz" or "zst"
+    pub fn code(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+
+    /// The compression of a code, i.e. "gz" or "zst"
+    pub fn from_code(code: &str) -> Option<Compression> {
+        match code {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Writer compressing the output written to it, or writing it as is without compression
+///
+/// `finish` must be called to write the end of the compressed stream.
+pub struct CompressedWriter<W: Write> {
+    inner: CompressedInner<W>,
+}
+
+enum CompressedInner<W: Write> {
+    Plain(W),
+    #[cfg(feature = "flate2")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Create a writer with the given compression, fails if the feature of the compression is not enabled
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the output, e.g. a "uvci.csv.gz" file
+    /// * `compression` - the compression, None to write the output as is
+    pub fn new(writer: W, compression: Option<Compression>) -> io::Result<CompressedWriter<W>> {
+        let inner = match compression {
+            None => CompressedInner::Plain(writer),
+            #[cfg(feature = "flate2")]
+            Some(Compression::Gzip) => CompressedInner::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            #[cfg(not(feature = "flate2"))]
+            Some(Compression::Gzip) => return Err(unsupported("gzip", "flate2")),
+            #[cfg(feature = "zstd")]
+            Some(Compression::Zstd) => {
+                CompressedInner::Zstd(zstd::stream::write::Encoder::new(writer, 0)?)
+            }
+            #[cfg(not(feature = "zstd"))]
+            Some(Compression::Zstd) => return Err(unsupported("Zstandard", "zstd")),
+        };
+        return Ok(CompressedWriter { inner });
+    }
+
+    /// Write the end of the compressed stream and return the output
+    pub fn finish(self) -> io::Result<W> {
+        match self.inner {
+            CompressedInner::Plain(writer) => Ok(writer),
+            #[cfg(feature = "flate2")]
+            CompressedInner::Gzip(encoder) => encoder.finish(),
+            #[cfg(feature = "zstd")]
+            CompressedInner::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            CompressedInner::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "flate2")]
+            CompressedInner::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            CompressedInner::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            CompressedInner::Plain(writer) => writer.flush(),
+            #[cfg(feature = "flate2")]
+            CompressedInner::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            CompressedInner::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Parse every line of a possibly gzip or Zstandard compressed UVCI input
+///
+/// # Arguments
+///
+/// * `reader` - the possibly compressed input, one UVCI per line
+pub fn parse_compressed_lines<R: Read + 'static>(reader: R) -> io::Result<Vec<Uvci>> {
+    let mut uvcis = Vec::new();
+    for cert_id in decompressed_reader(reader)?.lines() {
+        uvcis.push(parse(&cert_id?));
+    }
+    return Ok(uvcis);
+}
+
+/// Parse every line of a possibly gzip or Zstandard compressed UVCI file, with the file and line of every UVCI
+///
+/// Empty lines are skipped, see `parse_sourced`.
+/// # Arguments
+///
+/// * `path` - the possibly compressed input file, one UVCI per line
+pub fn parse_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<SourcedUvci>> {
+    let reader = decompressed_reader(File::open(&path)?)?;
+    let cert_ids = reader.lines().collect::<io::Result<Vec<String>>>()?;
+    return Ok(parse_sourced(
+        &path.as_ref().display().to_string(),
+        &cert_ids,
+    ));
+}
+
+/// Error for compressed input of a compression whose feature is not enabled
+#[cfg(not(all(feature = "flate2", feature = "zstd")))]
+fn unsupported(compression: &str, feature: &str) -> io::Error {
+    return io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{} compressed input requires the {} feature",
+            compression, feature
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_compressed_lines;
+
+    const INPUT: &str = "URN:UVCI:01:SE:EHM/V12907267LAJW#E\nURN:UVCI:01:SE:EHM/V12916227TFJJ#Q\n";
+
+    #[test]
+    fn uncompressed_lines() {
+        let uvcis = parse_compressed_lines(INPUT.as_bytes()).unwrap();
+        assert!(uvcis.len() == 2);
+        assert!(uvcis[1].opaque_id.as_deref() == Some("V12916227"));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn gzip_lines() {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(INPUT.as_bytes()).unwrap();
+        let uvcis =
+            parse_compressed_lines(std::io::Cursor::new(encoder.finish().unwrap())).unwrap();
+        assert!(uvcis.len() == 2);
+        assert!(uvcis[0].opaque_id.as_deref() == Some("V12907267"));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_lines() {
+        let compressed = zstd::encode_all(INPUT.as_bytes(), 0).unwrap();
+        let uvcis = parse_compressed_lines(std::io::Cursor::new(compressed)).unwrap();
+        assert!(uvcis.len() == 2);
+        assert!(uvcis[1].checksum_verification);
+    }
+
+    #[cfg(all(feature = "flate2", feature = "zstd"))]
+    #[test]
+    fn compressed_writer_roundtrip() {
+        use super::{CompressedWriter, Compression};
+        use std::io::Write;
+        for compression in &[Compression::Gzip, Compression::Zstd] {
+            let mut writer = CompressedWriter::new(Vec::new(), Some(*compression)).unwrap();
+            writer.write_all(INPUT.as_bytes()).unwrap();
+            let compressed = writer.finish().unwrap();
+            let uvcis = parse_compressed_lines(std::io::Cursor::new(compressed)).unwrap();
+            assert!(uvcis.len() == 2, "{} roundtrip", compression.code());
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! Conformance check against the test certificates of the official EU DCC test data
+use crate::reject::rejection;
+use crate::GroupType;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The GitHub repository of the official EU DCC test data
+pub const DGC_TESTDATA_REPOSITORY: &str = "eu-digital-green-certificates/dgc-testdata";
+
+/// Error downloading the test data corpus
+#[derive(Debug)]
+pub enum FetchError {
+    /// Downloading requires the `reqwest` feature
+    Unsupported,
+    /// The request could not be sent
+    #[cfg(feature = "reqwest")]
+    Request(reqwest::Error),
+    /// GitHub answered with an unexpected HTTP status, with the URL
+    Status(u16, String),
+    /// The file listing of the repository is not the expected JSON, with the reason
+    InvalidListing(String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchError::Unsupported => write!(f, "fetching test data requires the reqwest feature"),
+            #[cfg(feature = "reqwest")]
+            FetchError::Request(e) => write!(f, "test data request failed: {}", e),
+            FetchError::Status(status, url) => {
+                write!(f, "{} answered with HTTP status {}", url, status)
+            }
+            FetchError::InvalidListing(reason) => {
+                write!(f, "invalid test data file listing: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> FetchError {
+        return FetchError::Request(e);
+    }
+}
+
+/// Outcome of the conformance check of a UVCI of the test data
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConformanceOutcome {
+    /// The UVCI is valid
+    Pass,
+    /// The UVCI is invalid, with the diagnostic code and reason, e.g. "E009 checksum mismatch"
+    Fail(String),
+    /// The test file was not checked, with the reason, e.g. no UVCI in the payload
+    Skipped(String),
+}
+
+impl ConformanceOutcome {
+    /// The code of the outcome as used in the JSON report, i.e. "pass", "fail" or "skipped"
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConformanceOutcome::Pass => "pass",
+            ConformanceOutcome::Fail(_) => "fail",
+            ConformanceOutcome::Skipped(_) => "skipped",
+        }
+    }
+}
+
+/// Conformance check of a UVCI of a test file, or of a test file without UVCI
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConformanceResult {
+    /// The path of the test file relative to the test data directory, e.g. "AT/2DCode/raw/1.json"
+    pub file: String,
+    /// The country directory of the test file, e.g. "AT"
+    pub country: String,
+    /// The certificate group of the UVCI, None for a skipped file
+    pub group: Option<GroupType>,
+    /// The UVCI, None for a skipped file
+    pub cert_id: Option<String>,
+    /// The outcome of the check
+    pub outcome: ConformanceOutcome,
+}
+
+/// Number of passed, failed and skipped checks
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConformanceCounts {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl ConformanceCounts {
+    fn add(&mut self, outcome: &ConformanceOutcome) {
+        match outcome {
+            ConformanceOutcome::Pass => self.passed += 1,
+            ConformanceOutcome::Fail(_) => self.failed += 1,
+            ConformanceOutcome::Skipped(_) => self.skipped += 1,
+        }
+    }
+}
+
+/// Pass/fail conformance report of the UVCIs of a test data corpus
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConformanceReport {
+    /// The checks, in the sorted order of the test files
+    pub results: Vec<ConformanceResult>,
+}
+
+impl ConformanceReport {
+    /// The number of passed, failed and skipped checks of all countries
+    pub fn counts(&self) -> ConformanceCounts {
+        let mut counts = ConformanceCounts::default();
+        for result in &self.results {
+            counts.add(&result.outcome);
+        }
+        return counts;
+    }
+
+    /// The number of passed, failed and skipped checks of every country
+    pub fn countries(&self) -> BTreeMap<String, ConformanceCounts> {
+        let mut countries: BTreeMap<String, ConformanceCounts> = BTreeMap::new();
+        for result in &self.results {
+            countries
+                .entry(result.country.clone())
+                .or_default()
+                .add(&result.outcome);
+        }
+        return countries;
+    }
+
+    /// Whether no UVCI failed
+    pub fn passed(&self) -> bool {
+        return self.counts().failed == 0;
+    }
+
+    /// The report as a JSON object with the "passed", "failed" and "skipped" counts, the counts of every
+    /// country and the "results"
+    pub fn to_json(&self) -> String {
+        let counts = self.counts();
+        let countries: serde_json::Map<String, serde_json::Value> = self
+            .countries()
+            .into_iter()
+            .map(|(country, counts)| {
+                let counts = serde_json::json!({
+                    "passed": counts.passed,
+                    "failed": counts.failed,
+                    "skipped": counts.skipped,
+                });
+                (country, counts)
+            })
+            .collect();
+        let results: Vec<serde_json::Value> = self
+            .results
+            .iter()
+            .map(|result| {
+                let reason = match &result.outcome {
+                    ConformanceOutcome::Pass => None,
+                    ConformanceOutcome::Fail(reason) | ConformanceOutcome::Skipped(reason) => {
+                        Some(reason)
+                    }
+                };
+                serde_json::json!({
+                    "file": result.file,
+                    "country": result.country,
+                    "group": result.group.map(|group| group.key()),
+                    "uvci": result.cert_id,
+                    "outcome": result.outcome.code(),
+                    "reason": reason,
+                })
+            })
+            .collect();
+        return serde_json::json!({
+            "passed": counts.passed,
+            "failed": counts.failed,
+            "skipped": counts.skipped,
+            "countries": countries,
+            "results": results,
+        })
+        .to_string();
+    }
+}
+
+/// Display the counts, the counts of every country, then one line per failed UVCI
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = self.counts();
+        writeln!(f, "passed   : {}", counts.passed)?;
+        writeln!(f, "failed   : {}", counts.failed)?;
+        writeln!(f, "skipped  : {}", counts.skipped)?;
+        for (country, counts) in self.countries() {
+            writeln!(
+                f,
+                "{:<8} : {} passed, {} failed, {} skipped",
+                country, counts.passed, counts.failed, counts.skipped
+            )?;
+        }
+        for result in &self.results {
+            if let ConformanceOutcome::Fail(reason) = &result.outcome {
+                writeln!(
+                    f,
+                    "FAIL {} {}: {}",
+                    result.file,
+                    result.cert_id.as_deref().unwrap_or(""),
+                    reason
+                )?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Check the UVCIs of a test file of the dgc-testdata repository layout
+///
+/// The UVCIs are the "ci" fields of the decoded certificate in the "JSON" field. A UVCI passes if it has
+/// no error diagnostic, see `diagnose`. Files whose payload is expected to fail the schema validation
+/// or to be an invalid object ("EXPECTEDRESULTS") are skipped, their UVCIs may be invalid on purpose.
+/// # Arguments
+///
+/// * `file` - the path of the test file relative to the test data directory, e.g. "AT/2DCode/raw/1.json"
+/// * `content` - the content of the test file
+pub fn check_testdata(file: &str, content: &str) -> Vec<ConformanceResult> {
+    let country = file.split(['/', '\\']).next().unwrap_or("").to_string();
+    let skipped = |reason: &str| {
+        vec![ConformanceResult {
+            file: file.to_string(),
+            country: country.clone(),
+            group: None,
+            cert_id: None,
+            outcome: ConformanceOutcome::Skipped(reason.to_string()),
+        }]
+    };
+    let test: serde_json::Value = match serde_json::from_str(content) {
+        Ok(test) => test,
+        Err(_) => return skipped("not a JSON test file"),
+    };
+    let dcc = match test.get("JSON").filter(|dcc| dcc.is_object()) {
+        Some(dcc) => dcc,
+        None => return skipped("no decoded certificate in the JSON field"),
+    };
+    let expected = &test["EXPECTEDRESULTS"];
+    if expected["EXPECTEDSCHEMAVALIDATION"] == false || expected["EXPECTEDVALIDOBJECT"] == false {
+        return skipped("the certificate is expected to be invalid");
+    }
+
+    let mut results = Vec::new();
+    for group in &[GroupType::Vaccination, GroupType::Recovery, GroupType::Test] {
+        let entries = dcc.get(group.key()).and_then(|entries| entries.as_array());
+        for entry in entries.into_iter().flatten() {
+            let cert_id = match entry.get("ci").and_then(|ci| ci.as_str()) {
+                Some(cert_id) => cert_id,
+                None => continue,
+            };
+            let outcome = match rejection(cert_id) {
+                Some(diagnostic) => {
+                    ConformanceOutcome::Fail(format!("{} {}", diagnostic.code, diagnostic.message))
+                }
+                None => ConformanceOutcome::Pass,
+            };
+            results.push(ConformanceResult {
+                file: file.to_string(),
+                country: country.clone(),
+                group: Some(*group),
+                cert_id: Some(cert_id.to_string()),
+                outcome,
+            });
+        }
+    }
+    if results.is_empty() {
+        return skipped("no UVCI in the certificate");
+    }
+    return results;
+}
+
+/// Check the UVCIs of all test files of a checkout of the dgc-testdata repository
+///
+/// The JSON files of the country directories are checked in sorted order with `check_testdata`, e.g.
+/// "AT/2DCode/raw/1.json". Hidden directories and JSON files that are not test files, i.e. without a
+/// "JSON" or "TESTCTX" field, are ignored.
+/// # Arguments
+///
+/// * `dir` - the test data directory, e.g. a clone of https://github.com/eu-digital-green-certificates/dgc-testdata
+pub fn run_conformance<P: AsRef<Path>>(dir: P) -> io::Result<ConformanceReport> {
+    let mut files = Vec::new();
+    collect_json_files(dir.as_ref(), &mut files)?;
+    files.sort();
+    let mut report = ConformanceReport::default();
+    for path in files {
+        let content = fs::read_to_string(&path)?;
+        let file = path.strip_prefix(dir.as_ref()).unwrap_or(&path);
+        let file = file.display().to_string().replace('\\', "/");
+        check_test_file(&mut report, &file, &content);
+    }
+    return Ok(report);
+}
+
+/// Download the current test data corpus of the countries from GitHub and check its UVCIs
+///
+/// Lists the files of the default branch of the repository with the GitHub API, then downloads the JSON
+/// files of the country directories and checks them like `run_conformance`, so integrators can track
+/// drift between the specification, the national test data and this parser without a local checkout.
+/// Requires the `reqwest` feature, fails with `FetchError::Unsupported` otherwise.
+/// # Arguments
+///
+/// * `repository` - the GitHub repository, e.g. `DGC_TESTDATA_REPOSITORY`
+/// * `countries` - the country directories to check, e.g. "AT", all if empty
+pub fn fetch_conformance(
+    repository: &str,
+    countries: &[String],
+) -> Result<ConformanceReport, FetchError> {
+    #[cfg(feature = "reqwest")]
+    return fetch_and_check(repository, countries);
+    #[cfg(not(feature = "reqwest"))]
+    return {
+        let _ = (repository, countries);
+        Err(FetchError::Unsupported)
+    };
+}
+
+#[cfg(feature = "reqwest")]
+fn fetch_and_check(
+    repository: &str,
+    countries: &[String],
+) -> Result<ConformanceReport, FetchError> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent("covid_cert_uvci")
+        .build()?;
+    let get = |url: &str| -> Result<String, FetchError> {
+        let response = client.get(url).send()?;
+        if !response.status().is_success() {
+            return Err(FetchError::Status(
+                response.status().as_u16(),
+                url.to_string(),
+            ));
+        }
+        return Ok(response.text()?);
+    };
+
+    let listing_url = format!(
+        "https://api.github.com/repos/{}/git/trees/HEAD?recursive=1",
+        repository
+    );
+    let listing: serde_json::Value = serde_json::from_str(&get(&listing_url)?)
+        .map_err(|e| FetchError::InvalidListing(e.to_string()))?;
+    let tree = match listing["tree"].as_array() {
+        Some(tree) => tree,
+        None => return Err(FetchError::InvalidListing("no tree".to_string())),
+    };
+    let mut files: Vec<&str> = tree
+        .iter()
+        .filter(|entry| entry["type"] == "blob")
+        .filter_map(|entry| entry["path"].as_str())
+        .filter(|path| path.ends_with(".json") && path.contains('/'))
+        .filter(|path| {
+            let country = path.split('/').next().unwrap_or("");
+            countries.is_empty()
+                || countries
+                    .iter()
+                    .any(|selected| selected.eq_ignore_ascii_case(country))
+        })
+        .collect();
+    files.sort();
+
+    let mut report = ConformanceReport::default();
+    for file in files {
+        let content = get(&format!(
+            "https://raw.githubusercontent.com/{}/HEAD/{}",
+            repository, file
+        ))?;
+        check_test_file(&mut report, file, &content);
+    }
+    return Ok(report);
+}
+
+/// Add the checks of a JSON file to the report, ignoring JSON files that are not test files
+fn check_test_file(report: &mut ConformanceReport, file: &str, content: &str) {
+    let test: serde_json::Value = serde_json::from_str(content).unwrap_or_default();
+    if test.get("JSON").is_none() && test.get("TESTCTX").is_none() {
+        return;
+    }
+    report.results.append(&mut check_testdata(file, content));
+}
+
+fn collect_json_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_json_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(path);
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_testdata, run_conformance, ConformanceOutcome};
+
+    #[test]
+    fn conformance_report() {
+        let dir = std::env::temp_dir().join(format!("uvci_testdata_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let raw = dir.join("AT").join("2DCode").join("raw");
+        std::fs::create_dir_all(&raw).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(
+            raw.join("1.json"),
+            r#"{"JSON": {"ver": "1.3.0", "t": [{"ci": "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#S"}]},
+                "TESTCTX": {"DESCRIPTION": "test"}, "EXPECTEDRESULTS": {"EXPECTEDDECODE": true}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            raw.join("2.json"),
+            r#"{"JSON": {"v": [{"ci": "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#X"}]}, "TESTCTX": {}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            raw.join("3.json"),
+            r#"{"JSON": {"v": [{"ci": "invalid"}]}, "EXPECTEDRESULTS": {"EXPECTEDSCHEMAVALIDATION": false}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("package.json"), r#"{"name": "dgc-testdata"}"#).unwrap();
+
+        let report = run_conformance(&dir).unwrap();
+        let counts = report.counts();
+        assert!(counts.passed == 1 && counts.failed == 1 && counts.skipped == 1);
+        assert!(!report.passed());
+        assert!(report.results[0].file == "AT/2DCode/raw/1.json");
+        assert!(report.countries()["AT"].failed == 1);
+        match &report.results[1].outcome {
+            ConformanceOutcome::Fail(reason) => assert!(reason.starts_with("E009")),
+            _ => panic!("expected a checksum failure"),
+        }
+        assert!(report.to_string().contains(
+            "FAIL AT/2DCode/raw/2.json URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#X"
+        ));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert!(json["countries"]["AT"]["passed"] == 1);
+        assert!(json["results"][2]["outcome"] == "skipped");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let results = check_testdata("SE/1.json", r#"{"JSON": {"v": []}}"#);
+        assert!(
+            results[0].outcome
+                == ConformanceOutcome::Skipped("no UVCI in the certificate".to_string())
+        );
+    }
+}
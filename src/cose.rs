@@ -0,0 +1,300 @@
+//! Verification of the COSE_Sign1 signature of HCERT payloads
+//!
+//! `verify_hcert_cbor` checks the ES256 or PS256 signature of a COSE_Sign1 structure against the DSC
+//! public keys of a `TrustList`, selected by key identifier, and parses the UVCIs of its HCERT only if
+//! the signature is valid.
+use crate::hcert::{cwt_claims, hcert_dcc, TAG_COSE_SIGN1};
+use crate::{parse_dcc_value, GroupType, Uvci};
+use ciborium::value::Value;
+use std::fmt;
+
+/// COSE header label of the algorithm
+const HEADER_ALG: i128 = 1;
+/// COSE header label of the key identifier
+const HEADER_KID: i128 = 4;
+/// COSE algorithm ECDSA w/ SHA-256
+const ALG_ES256: i128 = -7;
+/// COSE algorithm RSASSA-PSS w/ SHA-256
+const ALG_PS256: i128 = -37;
+
+/// Error verifying the COSE_Sign1 signature of a EU Digital COVID Certificate
+#[derive(Clone, Debug, PartialEq)]
+pub enum CoseError {
+    /// The input is not a valid COSE_Sign1 structure
+    InvalidStructure,
+    /// The signature algorithm is not supported, only ES256 (-7) and PS256 (-37) are
+    UnsupportedAlgorithm(i128),
+    /// The DSC public key is not a valid ES256 or PS256 SubjectPublicKeyInfo
+    InvalidKey,
+    /// No DSC public key of the trust list matches the key identifier of the certificate
+    UnknownKey,
+    /// The signature does not verify against any matching DSC public key
+    InvalidSignature,
+}
+
+impl fmt::Display for CoseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoseError::InvalidStructure => write!(f, "invalid COSE_Sign1 structure"),
+            CoseError::UnsupportedAlgorithm(alg) => {
+                write!(f, "unsupported COSE algorithm {}", alg)
+            }
+            CoseError::InvalidKey => write!(f, "invalid DSC public key"),
+            CoseError::UnknownKey => write!(f, "no DSC public key for the key identifier"),
+            CoseError::InvalidSignature => write!(f, "invalid COSE_Sign1 signature"),
+        }
+    }
+}
+
+impl std::error::Error for CoseError {}
+
+/// DSC (Document Signer Certificate) public key
+#[derive(Clone, Debug)]
+enum PublicKey {
+    Es256(p256::ecdsa::VerifyingKey),
+    Ps256(rsa::RsaPublicKey),
+}
+
+/// Trust list of DSC (Document Signer Certificate) public keys, indexed by key identifier
+#[derive(Clone, Debug, Default)]
+pub struct TrustList {
+    keys: Vec<(Vec<u8>, PublicKey)>,
+}
+
+impl TrustList {
+    /// Create an empty trust list
+    pub fn new() -> TrustList {
+        return TrustList { keys: Vec::new() };
+    }
+
+    /// Add a DSC public key to the trust list
+    ///
+    /// # Arguments
+    ///
+    /// * `kid` - the key identifier, i.e. the first 8 bytes of the SHA-256 fingerprint of the DSC
+    /// * `public_key_der` - the DER-encoded SubjectPublicKeyInfo of the DSC, an EC P-256 or RSA key
+    pub fn add(&mut self, kid: &[u8], public_key_der: &[u8]) -> Result<(), CoseError> {
+        use p256::pkcs8::DecodePublicKey;
+
+        let key = if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_der(public_key_der) {
+            PublicKey::Es256(key)
+        } else if let Ok(key) = rsa::RsaPublicKey::from_public_key_der(public_key_der) {
+            PublicKey::Ps256(key)
+        } else {
+            return Err(CoseError::InvalidKey);
+        };
+        self.keys.push((kid.to_vec(), key));
+        return Ok(());
+    }
+
+    /// Number of DSC public keys in the trust list
+    pub fn len(&self) -> usize {
+        return self.keys.len();
+    }
+
+    /// Whether the trust list holds no DSC public keys
+    pub fn is_empty(&self) -> bool {
+        return self.keys.is_empty();
+    }
+}
+
+/// Verify the COSE_Sign1 signature of a CBOR-encoded HCERT and parse its UVCIs
+///
+/// The UVCIs are only returned if the signature verifies against a DSC public key of the trust list
+/// with a matching key identifier. If the certificate carries no key identifier, all keys are tried.
+/// # Arguments
+///
+/// * `bytes` - the CBOR-encoded COSE_Sign1, i.e. the QR payload after Base45 decoding and decompression
+/// * `trust_list` - the trusted DSC public keys
+pub fn verify_hcert_cbor(
+    bytes: &[u8],
+    trust_list: &TrustList,
+) -> Result<Vec<(GroupType, Uvci)>, CoseError> {
+    let value: Value = ciborium::de::from_reader(bytes).map_err(|_| CoseError::InvalidStructure)?;
+    let items = match value {
+        Value::Tag(TAG_COSE_SIGN1, inner) => match *inner {
+            Value::Array(items) => items,
+            _ => return Err(CoseError::InvalidStructure),
+        },
+        Value::Array(items) => items,
+        _ => return Err(CoseError::InvalidStructure),
+    };
+    if items.len() != 4 {
+        return Err(CoseError::InvalidStructure);
+    }
+    let (protected, payload, signature) = match (&items[0], &items[2], &items[3]) {
+        (Value::Bytes(p), Value::Bytes(b), Value::Bytes(s)) => (p, b, s),
+        _ => return Err(CoseError::InvalidStructure),
+    };
+    let protected_header: Value = if protected.is_empty() {
+        Value::Map(Vec::new())
+    } else {
+        ciborium::de::from_reader(&protected[..]).map_err(|_| CoseError::InvalidStructure)?
+    };
+    let unprotected_header = &items[1];
+
+    // Algorithm and key identifier, the protected header takes precedence
+    let alg = header_get(&protected_header, HEADER_ALG)
+        .or_else(|| header_get(unprotected_header, HEADER_ALG))
+        .and_then(|v| v.as_integer())
+        .map(i128::from)
+        .ok_or(CoseError::InvalidStructure)?;
+    let kid = header_get(&protected_header, HEADER_KID)
+        .or_else(|| header_get(unprotected_header, HEADER_KID))
+        .and_then(|v| v.as_bytes());
+
+    // Sig_structure = ["Signature1", body_protected, external_aad, payload]
+    let sig_structure = Value::Array(vec![
+        Value::Text("Signature1".to_string()),
+        Value::Bytes(protected.clone()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.clone()),
+    ]);
+    let mut message = Vec::new();
+    ciborium::ser::into_writer(&sig_structure, &mut message)
+        .map_err(|_| CoseError::InvalidStructure)?;
+
+    let candidates: Vec<&PublicKey> = trust_list
+        .keys
+        .iter()
+        .filter(|(k, _)| kid.is_none_or(|kid| k == kid))
+        .map(|(_, key)| key)
+        .collect();
+    if candidates.is_empty() {
+        return Err(CoseError::UnknownKey);
+    }
+    let mut verified = false;
+    for key in candidates {
+        if verify_signature(alg, key, &message, signature)? {
+            verified = true;
+            break;
+        }
+    }
+    if !verified {
+        return Err(CoseError::InvalidSignature);
+    }
+
+    let claims = ciborium::de::from_reader(&payload[..])
+        .ok()
+        .and_then(cwt_claims)
+        .ok_or(CoseError::InvalidStructure)?;
+    let dcc = hcert_dcc(&claims).ok_or(CoseError::InvalidStructure)?;
+    return Ok(parse_dcc_value(&dcc));
+}
+
+/// Verify a signature with a DSC public key, the algorithm must match the key type
+fn verify_signature(
+    alg: i128,
+    key: &PublicKey,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool, CoseError> {
+    match (alg, key) {
+        (ALG_ES256, PublicKey::Es256(key)) => {
+            use p256::ecdsa::signature::Verifier;
+            let signature = match p256::ecdsa::Signature::from_slice(signature) {
+                Ok(signature) => signature,
+                Err(_) => return Ok(false),
+            };
+            Ok(key.verify(message, &signature).is_ok())
+        }
+        (ALG_PS256, PublicKey::Ps256(key)) => {
+            use rsa::signature::Verifier;
+            use std::convert::TryFrom;
+            let verifying_key = rsa::pss::VerifyingKey::<sha2::Sha256>::new(key.clone());
+            let signature = match rsa::pss::Signature::try_from(signature) {
+                Ok(signature) => signature,
+                Err(_) => return Ok(false),
+            };
+            Ok(verifying_key.verify(message, &signature).is_ok())
+        }
+        (ALG_ES256, _) | (ALG_PS256, _) => Ok(false),
+        (alg, _) => Err(CoseError::UnsupportedAlgorithm(alg)),
+    }
+}
+
+/// Look up an integer label in a COSE header map
+fn header_get(header: &Value, label: i128) -> Option<&Value> {
+    for (k, v) in header.as_map()? {
+        if let Value::Integer(k) = k {
+            if i128::from(*k) == label {
+                return Some(v);
+            }
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_hcert_cbor, CoseError, TrustList};
+    use ciborium::value::Value;
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use p256::pkcs8::EncodePublicKey;
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(value, &mut bytes).unwrap();
+        return bytes;
+    }
+
+    fn signed_hcert(signing_key: &SigningKey, kid: &[u8]) -> Vec<u8> {
+        let dcc = Value::Map(vec![(
+            Value::Text("v".to_string()),
+            Value::Array(vec![Value::Map(vec![(
+                Value::Text("ci".to_string()),
+                Value::Text("URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string()),
+            )])]),
+        )]);
+        let claims = Value::Map(vec![(
+            Value::Integer((-260).into()),
+            Value::Map(vec![(Value::Integer(1.into()), dcc)]),
+        )]);
+        let protected = encode(&Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer((-7).into())),
+            (Value::Integer(4.into()), Value::Bytes(kid.to_vec())),
+        ]));
+        let payload = encode(&claims);
+        let sig_structure = Value::Array(vec![
+            Value::Text("Signature1".to_string()),
+            Value::Bytes(protected.clone()),
+            Value::Bytes(vec![]),
+            Value::Bytes(payload.clone()),
+        ]);
+        let signature: Signature = signing_key.sign(&encode(&sig_structure));
+        return encode(&Value::Tag(
+            18,
+            Box::new(Value::Array(vec![
+                Value::Bytes(protected),
+                Value::Map(vec![]),
+                Value::Bytes(payload),
+                Value::Bytes(signature.to_bytes().to_vec()),
+            ])),
+        ));
+    }
+
+    #[test]
+    fn cose_es256() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let other_key = SigningKey::from_bytes(&[9u8; 32].into()).unwrap();
+        let public_key_der = signing_key
+            .verifying_key()
+            .to_public_key_der()
+            .unwrap()
+            .to_vec();
+        let mut trust_list = TrustList::new();
+        trust_list.add(b"kid00001", &public_key_der).unwrap();
+
+        let uvcis = verify_hcert_cbor(&signed_hcert(&signing_key, b"kid00001"), &trust_list);
+        assert!(uvcis.unwrap()[0].1.opaque_id.as_deref() == Some("V12907267"));
+        assert!(
+            verify_hcert_cbor(&signed_hcert(&other_key, b"kid00001"), &trust_list).err()
+                == Some(CoseError::InvalidSignature)
+        );
+        assert!(
+            verify_hcert_cbor(&signed_hcert(&signing_key, b"kid00002"), &trust_list).err()
+                == Some(CoseError::UnknownKey)
+        );
+        assert!(trust_list.add(b"kid00003", b"not a key") == Err(CoseError::InvalidKey));
+    }
+}
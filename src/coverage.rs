@@ -0,0 +1,218 @@
+//! Estimated vaccination coverage by country from the UVCIs of a batch and population data
+//!
+//! The doses of Sweden are estimated with the dose model of `dose_curve::estimate_dose_curve`, so they
+//! share its assumptions and its accuracy of approximately +/- 1 month; the other countries have no dose
+//! model and only report the certificates of the batch per capita. The figures are estimates derived
+//! from identifiers, not official vaccination statistics.
+use crate::batch::BatchResult;
+use crate::dose_curve::{estimate_dose_curve, DoseCurve};
+use crate::{parse, YearMonth};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// Coverage of one country of the batch
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountryCoverage {
+    /// The ISO 3166-1 country code, e.g. "SE"
+    pub country: String,
+    /// Number of UVCIs of the country in the batch
+    pub certificates: usize,
+    /// The population of the country, None if missing from the population data
+    pub population: Option<u64>,
+    /// UVCIs of the batch per inhabitant
+    pub certificates_per_capita: Option<f64>,
+    /// Estimated cumulative doses up to `estimated_until`, None without dose model for the country
+    pub estimated_doses: Option<u64>,
+    /// The last month of the estimated doses
+    pub estimated_until: Option<YearMonth>,
+    /// Estimated doses per inhabitant
+    pub estimated_doses_per_capita: Option<f64>,
+}
+
+/// Coverage report of a batch of UVCIs, see `coverage_report`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    /// The counts of the batch
+    pub batch: BatchResult,
+    /// The dose curve estimated from the Swedish UVCIs of the batch
+    pub dose_curve: DoseCurve,
+    /// The coverage of every country of the batch, by country code
+    pub countries: Vec<CountryCoverage>,
+}
+
+/// Display the report as a table, every estimated figure labeled as estimate
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        writeln!(
+            f,
+            "ESTIMATE: doses are estimated from the UVCIs with the Swedish dose model (+/- 1 month), they are not official statistics"
+        )?;
+        writeln!(f, "uvcis    : {}", self.batch.processed)?;
+        writeln!(f, "valid    : {}", self.batch.succeeded)?;
+        writeln!(
+            f,
+            "{:<8} {:>12} {:>12} {:>12} {:>22} {:>20}",
+            "country",
+            "certificates",
+            "population",
+            "per capita",
+            "estimated doses",
+            "estimated per capita"
+        )?;
+        for country in &self.countries {
+            writeln!(
+                f,
+                "{:<8} {:>12} {:>12} {:>12} {:>22} {:>20}",
+                country.country,
+                country.certificates,
+                or_dash(country.population.map(|population| population.to_string())),
+                or_dash(
+                    country
+                        .certificates_per_capita
+                        .map(|ratio| format!("{:.6}", ratio))
+                ),
+                or_dash(
+                    country
+                        .estimated_doses
+                        .zip(country.estimated_until)
+                        .map(|(doses, month)| format!("{} to {}", doses, month))
+                ),
+                or_dash(
+                    country
+                        .estimated_doses_per_capita
+                        .map(|ratio| format!("{:.3}", ratio))
+                ),
+            )?;
+        }
+        return Ok(());
+    }
+}
+
+/// Estimate the vaccination coverage of every country of a batch of UVCIs
+///
+/// Combines the number of UVCIs of every country with its population, and for Sweden the doses of the
+/// dose model estimated from the Swedish UVCIs of the batch, see the module documentation.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `population_by_country` - the population by ISO 3166-1 country code, e.g. "SE" => 10379295
+pub fn coverage_report(
+    cert_ids: &[String],
+    population_by_country: &HashMap<String, u64>,
+) -> CoverageReport {
+    let mut report = CoverageReport::default();
+    let mut swedish = Vec::new();
+    for cert_id in cert_ids {
+        if cert_id.trim().is_empty() {
+            continue;
+        }
+        let uvci = parse(cert_id.trim());
+        report.batch.record(cert_id.trim(), &uvci);
+        if uvci.country_str() == "SE" {
+            swedish.push(cert_id.clone());
+        }
+    }
+    report.dose_curve = estimate_dose_curve(&swedish);
+
+    let mut countries: Vec<(&String, &usize)> = report.batch.per_country.iter().collect();
+    countries.sort();
+    for (country, certificates) in countries {
+        let population = population_by_country.get(country).copied();
+        let per_capita = |count: u64| {
+            population
+                .filter(|p| *p > 0)
+                .map(|p| count as f64 / p as f64)
+        };
+        let (estimated_doses, estimated_until) = match report.dose_curve.points.last() {
+            Some(point) if country == "SE" => {
+                (Some(report.dose_curve.estimated_total), Some(point.month))
+            }
+            _ => (None, None),
+        };
+        report.countries.push(CountryCoverage {
+            country: country.clone(),
+            certificates: *certificates,
+            population,
+            certificates_per_capita: per_capita(*certificates as u64),
+            estimated_doses,
+            estimated_until,
+            estimated_doses_per_capita: estimated_doses.and_then(per_capita),
+        });
+    }
+    return report;
+}
+
+/// Read the population by country of CSV lines "country,population", e.g. "SE,10379295"
+///
+/// Empty lines and a first line without a number, e.g. a "country,population" header, are skipped.
+/// # Arguments
+///
+/// * `reader` - the CSV lines
+pub fn read_population<R: BufRead>(reader: R) -> io::Result<HashMap<String, u64>> {
+    let mut population_by_country = HashMap::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (country, population) = line.split_once(',').unwrap_or((&line, ""));
+        match population.trim().parse::<u64>() {
+            Ok(population) => {
+                population_by_country.insert(country.trim().to_uppercase(), population);
+            }
+            Err(_) if index == 0 => {}
+            Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected country,population: {}", index + 1, line),
+                ))
+            }
+        }
+    }
+    return Ok(population_by_country);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coverage_report, read_population};
+
+    #[test]
+    fn coverage() {
+        let population =
+            read_population("country,population\nSE,10379295\n\nnl,17475415\n".as_bytes()).unwrap();
+        assert!(population.get("NL") == Some(&17475415));
+        assert!(read_population("SE,10379295\nNL\n".as_bytes()).is_err());
+
+        let mut cert_ids: Vec<String> = include_str!("../examples/covid_uvci_33.txt")
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        cert_ids.push("URN:UVCI:01:NL:187/37512422923".to_string());
+        cert_ids.push("URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#B".to_string());
+        let report = coverage_report(&cert_ids, &population);
+        assert!(report.batch.processed == 35);
+        let countries: Vec<&str> = report
+            .countries
+            .iter()
+            .map(|c| c.country.as_str())
+            .collect();
+        assert!(countries == ["AT", "NL", "SE"]);
+
+        let sweden = &report.countries[2];
+        assert!(sweden.certificates == 33);
+        assert!(sweden.estimated_doses == Some(report.dose_curve.estimated_total));
+        assert!(sweden.estimated_until == report.dose_curve.points.last().map(|p| p.month));
+        let per_capita = sweden.estimated_doses_per_capita.unwrap();
+        assert!(per_capita == report.dose_curve.estimated_total as f64 / 10379295.0);
+        assert!(report.countries[1].estimated_doses.is_none());
+        assert!(report.countries[1].certificates_per_capita == Some(1.0 / 17475415.0));
+        assert!(report.countries[0].population.is_none());
+        assert!(report.countries[0].certificates_per_capita.is_none());
+
+        let text = report.to_string();
+        assert!(text.starts_with("ESTIMATE: "));
+        assert!(text.contains("\nAT "));
+    }
+}
@@ -0,0 +1,149 @@
+//! Deduplication of the UVCIs of large inputs with a sharded bloom filter
+use crate::bloom::BloomFilter;
+use crate::{bare_identifier, Uvci};
+
+/// Maximum number of bits of a bloom filter shard
+const MAX_SHARD_BITS: f64 = u32::MAX as f64;
+
+/// Probabilistic set of the UVCIs seen by a streaming pipeline, to drop the previously seen ones
+///
+/// The set is a bloom filter, split in shards of at most 2^32 bits for histories of billions of UVCIs,
+/// so its memory is fixed by the expected number of UVCIs and the false-positive rate, e.g. about
+/// 1.2 GB for 1 billion UVCIs at 0.01. There are no false negatives, but a UVCI never seen before may
+/// be reported as seen, i.e. dropped, at the false-positive rate. UVCIs are compared by the bare
+/// identifier of the UVCI as given, so the same identifier with or without the prefix and checksum is
+/// the same UVCI, and distinct identifiers that cannot be parsed stay distinct.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeenSet {
+    shards: Vec<BloomFilter>,
+}
+
+impl SeenSet {
+    /// Create an empty set
+    ///
+    /// # Arguments
+    ///
+    /// * `expected_elements` - the number of distinct UVCIs the set is sized for
+    /// * `false_positive_rate` - the false-positive rate at the expected number of UVCIs, e.g. 0.001
+    pub fn new(expected_elements: u64, false_positive_rate: f32) -> SeenSet {
+        let p = (false_positive_rate as f64).clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = expected_elements.max(1) as f64 * -p.ln() / (ln2 * ln2);
+        let num_shards = (bits / MAX_SHARD_BITS).ceil().max(1.0) as u64;
+        let shard_elements = expected_elements.div_ceil(num_shards);
+        return SeenSet {
+            shards: (0..num_shards)
+                .map(|_| BloomFilter::new(shard_elements as u32, false_positive_rate))
+                .collect(),
+        };
+    }
+
+    /// Load a set serialized with `to_bytes`, None if the layout is invalid
+    pub fn from_bytes(bytes: &[u8]) -> Option<SeenSet> {
+        let u32_at = |i: usize| -> Option<u32> {
+            let word = bytes.get(i..i + 4)?;
+            return Some(u32::from_be_bytes([word[0], word[1], word[2], word[3]]));
+        };
+        let num_shards = u32_at(0)?;
+        let mut offset = 4;
+        let mut shards = Vec::new();
+        for _ in 0..num_shards {
+            let len = u32_at(offset)? as usize;
+            let shard = bytes.get(offset + 4..offset + 4 + len)?;
+            shards.push(BloomFilter::from_bytes(shard)?);
+            offset += 4 + len;
+        }
+        if shards.is_empty() || offset != bytes.len() {
+            return None;
+        }
+        return Some(SeenSet { shards });
+    }
+
+    /// Serialize the set, to resume deduplicating a history in a later run
+    ///
+    /// The big-endian layout is the number of shards (u32), followed by the length (u32) and the
+    /// serialized bloom filter of each shard.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.shards.len() as u32).to_be_bytes().to_vec();
+        for shard in &self.shards {
+            let shard = shard.to_bytes();
+            bytes.extend_from_slice(&(shard.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&shard);
+        }
+        return bytes;
+    }
+
+    /// Add a UVCI to the set, true if it was probably seen before
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    pub fn insert_and_check(&mut self, uvci: &Uvci) -> bool {
+        let key = seen_key(uvci);
+        let shard = self.shard(&key);
+        if self.shards[shard].contains(key.as_bytes()) {
+            return true;
+        }
+        self.shards[shard].insert(key.as_bytes());
+        return false;
+    }
+
+    /// Whether the UVCI was probably seen before, false positives are possible
+    pub fn contains(&self, uvci: &Uvci) -> bool {
+        let key = seen_key(uvci);
+        return self.shards[self.shard(&key)].contains(key.as_bytes());
+    }
+
+    /// Number of distinct UVCIs added to the set, less the false positives
+    pub fn len(&self) -> u64 {
+        return self.shards.iter().map(|shard| shard.len() as u64).sum();
+    }
+
+    /// Whether no UVCIs were added to the set
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Shard of a key, by the FNV-1a hash of the key
+    fn shard(&self, key: &str) -> usize {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in key.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        return (hash % self.shards.len() as u64) as usize;
+    }
+}
+
+/// The bare identifier of the UVCI as given, the key of the set
+fn seen_key(uvci: &Uvci) -> String {
+    return bare_identifier(&uvci.cert_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SeenSet;
+    use crate::parse;
+
+    #[test]
+    fn seen_set() {
+        let mut seen = SeenSet::new(1000, 0.0001);
+        assert!(seen.is_empty());
+        assert!(!seen.insert_and_check(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")));
+        assert!(seen.insert_and_check(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")));
+        assert!(seen.insert_and_check(&parse("01:se:ehm/v12907267lajw")));
+        assert!(!seen.insert_and_check(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q")));
+        assert!(seen.len() == 2);
+        assert!(!seen.contains(&parse("URN:UVCI:01:SE:EHM/C878/123456789ABC#B")));
+        // Distinct UVCIs that cannot be parsed are distinct keys
+        assert!(!seen.insert_and_check(&parse("URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W")));
+        assert!(!seen.insert_and_check(&parse("URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T")));
+        assert!(!seen.insert_and_check(&parse("URN:UVCI:V1:MT:ABC#X")));
+
+        let loaded = SeenSet::from_bytes(&seen.to_bytes()).unwrap();
+        assert!(loaded == seen);
+        assert!(loaded.contains(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q")));
+        assert!(SeenSet::from_bytes(&[0, 0, 0, 1]).is_none());
+        assert!(SeenSet::from_bytes(&[]).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Curated dataset of the UVCI versions and schema options deployed by each country
+use crate::issuers::known_issuers;
+use crate::{or_empty, SchemaOption, Uvci};
+use std::fmt;
+
+/// The curated dataset of the versions and schema options deployed by each country
+const KNOWN_DEPLOYMENTS: &str = include_str!("../data/known_deployments.csv");
+
+/// The versions and schema options deployed by a country, empty if the country is not in the dataset
+///
+/// # Arguments
+///
+/// * `country` - the ISO 3166-1 country code, e.g. "SE"
+pub fn known_deployments(country: &str) -> Vec<(u8, SchemaOption)> {
+    return KNOWN_DEPLOYMENTS
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            match fields.as_slice() {
+                [c, version, option] if c.eq_ignore_ascii_case(country) => Some((
+                    version.parse::<u8>().ok()?,
+                    SchemaOption::from_number(option.parse::<u8>().ok()?),
+                )),
+                _ => None,
+            }
+        })
+        .collect();
+}
+
+/// A way a UVCI deviates from what its country deployed
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inconsistency {
+    /// The country never deployed the version, 0 if the version is not a number
+    Version(u8),
+    /// The country never deployed the schema option with the version
+    SchemaOption(SchemaOption),
+    /// The country never used the issuing entity, "" for a missing issuing entity
+    IssuingEntity(String),
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inconsistency::Version(version) => write!(f, "version {:02} never deployed", version),
+            Inconsistency::SchemaOption(option) => {
+                write!(f, "schema option {} never deployed", option.number())
+            }
+            Inconsistency::IssuingEntity(issuer) => {
+                write!(f, "issuing entity '{}' never used", issuer)
+            }
+        }
+    }
+}
+
+/// Consistency of a UVCI with the known deployments of its country
+#[derive(Clone, Debug, PartialEq)]
+pub enum Consistency {
+    /// The version, schema option and issuing entity match a known deployment of the country
+    Consistent,
+    /// The country is not in the dataset, so the UVCI cannot be checked
+    UnknownCountry,
+    /// The UVCI deviates from the known deployments of the country
+    Inconsistent(Vec<Inconsistency>),
+}
+
+impl Uvci {
+    /// Check the version, schema option and issuing entity against what the country actually deployed
+    ///
+    /// e.g. a Swedish UVCI with schema option 1 and an issuing entity Sweden never used is
+    /// `Inconsistent` with both deviations.
+    pub fn consistent_with_known_deployments(&self) -> Consistency {
+        let deployments = known_deployments(self.country_str());
+        if deployments.is_empty() {
+            return Consistency::UnknownCountry;
+        }
+        let mut inconsistencies = Vec::new();
+        let version = self.version.unwrap_or(0);
+        if !deployments.iter().any(|(deployed, _)| *deployed == version) {
+            inconsistencies.push(Inconsistency::Version(version));
+        } else if !deployments.contains(&(version, self.schema_option)) {
+            inconsistencies.push(Inconsistency::SchemaOption(self.schema_option));
+        }
+        let issuer = or_empty(&self.issuing_entity);
+        if !known_issuers(self.country_str()).contains(&issuer) {
+            inconsistencies.push(Inconsistency::IssuingEntity(issuer.to_string()));
+        }
+        if inconsistencies.is_empty() {
+            return Consistency::Consistent;
+        }
+        return Consistency::Inconsistent(inconsistencies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{known_deployments, Consistency, Inconsistency};
+    use crate::{parse, SchemaOption};
+
+    #[test]
+    fn deployment_consistency() {
+        assert!(known_deployments("se") == vec![(1, SchemaOption::SomeSemantics)]);
+        let consistent = [
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q",
+            "01:NL:187/37512422923",
+            "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#B",
+        ];
+        for cert_id in &consistent {
+            let consistency = parse(cert_id).consistent_with_known_deployments();
+            assert!(consistency == Consistency::Consistent, "{}", cert_id);
+        }
+
+        let uvci = parse("URN:UVCI:01:SE:XYZ/C878/123456789ABC");
+        assert!(
+            uvci.consistent_with_known_deployments()
+                == Consistency::Inconsistent(vec![
+                    Inconsistency::SchemaOption(SchemaOption::WithSemantics),
+                    Inconsistency::IssuingEntity("XYZ".to_string()),
+                ])
+        );
+        let uvci = parse("URN:UVCI:02:SE:EHM/V12916227TFJJ");
+        assert!(
+            uvci.consistent_with_known_deployments()
+                == Consistency::Inconsistent(vec![Inconsistency::Version(2)])
+        );
+        let uvci = parse("URN:UVCI:01:ZZ:EHM/V12916227TFJJ");
+        assert!(uvci.consistent_with_known_deployments() == Consistency::UnknownCountry);
+    }
+}
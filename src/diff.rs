@@ -0,0 +1,215 @@
+//! Differences between two datasets of UVCIs: identifiers added, removed and changed
+use crate::{bare_identifier, parse};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How the UVCI of an identifier present in both datasets changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeKind {
+    /// The UVCI has a checksum in the new dataset only
+    ChecksumAdded,
+    /// The UVCI has a checksum in the old dataset only
+    ChecksumRemoved,
+    /// The checksum differs between the datasets
+    ChecksumChanged,
+}
+
+impl ChangeKind {
+    /// The code of the change as used in the JSON output, e.g. "checksum_added"
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChangeKind::ChecksumAdded => "checksum_added",
+            ChangeKind::ChecksumRemoved => "checksum_removed",
+            ChangeKind::ChecksumChanged => "checksum_changed",
+        }
+    }
+}
+
+/// Identifier present in both datasets with a different UVCI
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangedUvci {
+    /// The identifier without prefix and checksum, e.g. "01:SE:EHM/V12907267LAJW"
+    pub identifier: String,
+    /// The UVCI in the old dataset
+    pub old: String,
+    /// The UVCI in the new dataset
+    pub new: String,
+    /// How the UVCI changed
+    pub change: ChangeKind,
+}
+
+/// Delta between two dumps of UVCIs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DatasetDiff {
+    /// UVCIs of the new dataset only, in the order of the new dataset
+    pub added: Vec<String>,
+    /// UVCIs of the old dataset only, in the order of the old dataset
+    pub removed: Vec<String>,
+    /// Identifiers of both datasets whose UVCI changed, in the order of the new dataset
+    pub changed: Vec<ChangedUvci>,
+}
+
+impl DatasetDiff {
+    /// Whether the datasets have the same identifiers with the same UVCIs
+    pub fn is_empty(&self) -> bool {
+        return self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty();
+    }
+
+    /// The delta as a JSON object with "added", "removed" and "changed" arrays
+    pub fn to_json(&self) -> String {
+        let changed: Vec<serde_json::Value> = self
+            .changed
+            .iter()
+            .map(|changed| {
+                serde_json::json!({
+                    "identifier": changed.identifier,
+                    "old": changed.old,
+                    "new": changed.new,
+                    "change": changed.change.code(),
+                })
+            })
+            .collect();
+        return serde_json::json!({
+            "added": self.added,
+            "removed": self.removed,
+            "changed": changed,
+        })
+        .to_string();
+    }
+}
+
+/// Display the counts, then one line per UVCI: "+" added, "-" removed and "~" changed
+impl fmt::Display for DatasetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "added    : {}", self.added.len())?;
+        writeln!(f, "removed  : {}", self.removed.len())?;
+        writeln!(f, "changed  : {}", self.changed.len())?;
+        for cert_id in &self.added {
+            writeln!(f, "+ {}", cert_id)?;
+        }
+        for cert_id in &self.removed {
+            writeln!(f, "- {}", cert_id)?;
+        }
+        for changed in &self.changed {
+            writeln!(
+                f,
+                "~ {} -> {} ({})",
+                changed.old,
+                changed.new,
+                changed.change.code()
+            )?;
+        }
+        return Ok(());
+    }
+}
+
+/// Compare two dumps of UVCIs, e.g. periodic full exports of a registry
+///
+/// UVCIs are matched by their identifier without prefix and checksum, so a UVCI whose checksum is newly
+/// present is changed, not removed and added. Differences in case or prefix only are not changes.
+/// Empty lines are ignored, and only the first occurrence of an identifier in a dataset is compared.
+/// # Arguments
+///
+/// * `old` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier) of the old dump
+/// * `new` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier) of the new dump
+pub fn diff_datasets(old: &[String], new: &[String]) -> DatasetDiff {
+    let old = first_occurrences(old);
+    let new = first_occurrences(new);
+    let old_by_identifier: HashMap<&str, &str> = old
+        .iter()
+        .map(|(identifier, cert_id)| (identifier.as_str(), *cert_id))
+        .collect();
+    let new_identifiers: HashSet<&str> = new
+        .iter()
+        .map(|(identifier, _)| identifier.as_str())
+        .collect();
+
+    let mut diff = DatasetDiff::default();
+    for (identifier, cert_id) in &new {
+        let old_cert_id = match old_by_identifier.get(identifier.as_str()) {
+            Some(old_cert_id) => *old_cert_id,
+            None => {
+                diff.added.push(cert_id.to_string());
+                continue;
+            }
+        };
+        let change = match (
+            parse(old_cert_id).checksum.clone(),
+            parse(cert_id).checksum.clone(),
+        ) {
+            (None, Some(_)) => ChangeKind::ChecksumAdded,
+            (Some(_), None) => ChangeKind::ChecksumRemoved,
+            (Some(old_checksum), Some(new_checksum)) if old_checksum != new_checksum => {
+                ChangeKind::ChecksumChanged
+            }
+            _ => continue,
+        };
+        diff.changed.push(ChangedUvci {
+            identifier: identifier.clone(),
+            old: old_cert_id.to_string(),
+            new: cert_id.to_string(),
+            change,
+        });
+    }
+    for (identifier, cert_id) in &old {
+        if !new_identifiers.contains(identifier.as_str()) {
+            diff.removed.push(cert_id.to_string());
+        }
+    }
+    return diff;
+}
+
+/// The identifier and trimmed UVCI of the first occurrence of every identifier, in input order
+fn first_occurrences(cert_ids: &[String]) -> Vec<(String, &str)> {
+    let mut seen = HashSet::new();
+    let mut occurrences = Vec::new();
+    for cert_id in cert_ids {
+        let cert_id = cert_id.trim();
+        if cert_id.is_empty() {
+            continue;
+        }
+        let identifier = bare_identifier(cert_id);
+        if seen.insert(identifier.clone()) {
+            occurrences.push((identifier, cert_id));
+        }
+    }
+    return occurrences;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_datasets, ChangeKind};
+
+    #[test]
+    fn dataset_diff() {
+        let old = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+            "".to_string(),
+        ];
+        let new = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "urn:uvci:01:se:ehm/v12916227tfjj#q".to_string(),
+            "01:NL:187/37512422923".to_string(),
+            "01:NL:187/37512422923".to_string(),
+        ];
+        let diff = diff_datasets(&old, &new);
+        assert!(diff.added == vec!["01:NL:187/37512422923".to_string()]);
+        assert!(diff.removed == vec!["URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string()]);
+        assert!(diff.changed.len() == 1);
+        assert!(diff.changed[0].identifier == "01:SE:EHM/V12907267LAJW");
+        assert!(diff.changed[0].change == ChangeKind::ChecksumAdded);
+
+        let text = diff.to_string();
+        assert!(text.starts_with("added    : 1\nremoved  : 1\nchanged  : 1\n"));
+        assert!(text.contains(
+            "~ URN:UVCI:01:SE:EHM/V12907267LAJW -> URN:UVCI:01:SE:EHM/V12907267LAJW#E (checksum_added)"
+        ));
+        let json: serde_json::Value = serde_json::from_str(&diff.to_json()).unwrap();
+        assert!(json["changed"][0]["change"] == "checksum_added");
+        assert!(json["removed"].as_array().unwrap().len() == 1);
+
+        assert!(diff_datasets(&new, &new).is_empty());
+    }
+}
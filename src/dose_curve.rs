@@ -0,0 +1,134 @@
+//! Estimation of the cumulative number of certificates issued by month from a sample of Swedish UVCIs
+//!
+//! The estimation inverts the dose/date model used to decode the vaccination month of Sweden
+//! EHM-issued UVCIs, under these assumptions:
+//!
+//! * The number of the opaque_id, e.g. 12907267 for "V12907267", is assigned sequentially, so it
+//!   approximates the cumulative number of doses certified when the certificate was issued.
+//! * The cumulative number of doses by month follows the model fitted against the national statistics
+//!   of the Public Health Agency of Sweden, with an accuracy of approximately +/- 1 month.
+//! * The sample is drawn uniformly from all certificates, so the share of the sample in a month
+//!   estimates the sampling coverage of the month.
+//!
+//! As the opaque_ids are sequential, the highest opaque_id of the sample issued up to a month is a
+//! lower bound of the certificates issued by then; the estimate is never below it.
+use crate::{get_vaccination_doses_tan, parse, YearMonth};
+use std::collections::BTreeMap;
+
+/// Estimated cumulative certificates issued up to the end of a month
+#[derive(Clone, Debug, PartialEq)]
+pub struct DoseCurvePoint {
+    /// The vaccination month
+    pub month: YearMonth,
+    /// Number of UVCIs of the sample estimated in the month
+    pub sampled: usize,
+    /// Number of UVCIs of the sample estimated up to the end of the month
+    pub cumulative_sampled: usize,
+    /// Highest opaque_id number of the sample up to the end of the month
+    pub max_opaque_id: u64,
+    /// Estimated cumulative certificates issued up to the end of the month
+    pub estimated_issued: u64,
+    /// Share of the estimated cumulative certificates in the sample, from 0 to 1
+    pub coverage: f64,
+}
+
+/// Cumulative dose curve estimated from a sample of Swedish UVCIs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DoseCurve {
+    /// Estimates of every month from the first to the last month of the sample
+    pub points: Vec<DoseCurvePoint>,
+    /// Number of UVCIs of the sample with a decodable opaque_id
+    pub sample_size: usize,
+    /// Estimated certificates issued up to the end of the last month of the sample
+    pub estimated_total: u64,
+    /// Share of the estimated certificates in the sample, from 0 to 1
+    pub coverage: f64,
+}
+
+/// Estimate the cumulative certificates issued by month and the sampling coverage from a sample of UVCIs
+///
+/// Only Sweden EHM-issued UVCIs with a decodable opaque_id are used, see the module documentation for
+/// the assumptions of the estimation.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn estimate_dose_curve(cert_ids: &[String]) -> DoseCurve {
+    // Number of UVCIs and highest opaque_id by month
+    let mut months: BTreeMap<YearMonth, (usize, u64)> = BTreeMap::new();
+    for cert_id in cert_ids {
+        let uvci = parse(cert_id.trim());
+        let opaque_id = uvci
+            .opaque_id
+            .as_deref()
+            .and_then(|id| id.trim_start_matches('V').parse::<u64>().ok());
+        if let (Some(month), Some(opaque_id)) = (uvci.opaque_vaccination, opaque_id) {
+            let entry = months.entry(month).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 = entry.1.max(opaque_id);
+        }
+    }
+
+    let mut curve = DoseCurve::default();
+    let (mut month, last) = match (months.keys().next(), months.keys().next_back()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return curve,
+    };
+    let mut cumulative_sampled = 0;
+    let mut max_opaque_id = 0;
+    while month <= last {
+        let (sampled, max_id) = months.get(&month).copied().unwrap_or((0, 0));
+        cumulative_sampled += sampled;
+        max_opaque_id = max_opaque_id.max(max_id);
+        let estimated_issued = (get_vaccination_doses_tan(month) as u64).max(max_opaque_id);
+        curve.points.push(DoseCurvePoint {
+            month,
+            sampled,
+            cumulative_sampled,
+            max_opaque_id,
+            estimated_issued,
+            coverage: coverage(cumulative_sampled, estimated_issued),
+        });
+        month = month.next();
+    }
+    curve.sample_size = cumulative_sampled;
+    curve.estimated_total = curve
+        .points
+        .last()
+        .map_or(0, |point| point.estimated_issued);
+    curve.coverage = coverage(curve.sample_size, curve.estimated_total);
+    return curve;
+}
+
+/// Share of the issued certificates in the sample, 0 if none were issued
+fn coverage(sampled: usize, issued: u64) -> f64 {
+    if issued == 0 {
+        return 0.0;
+    }
+    return sampled as f64 / issued as f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_dose_curve;
+
+    #[test]
+    fn dose_curve() {
+        let cert_ids: Vec<String> = include_str!("../examples/covid_uvci_33.txt")
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        let curve = estimate_dose_curve(&cert_ids);
+        assert!(curve.sample_size == 33);
+        assert!(curve.points.first().unwrap().month.to_string() == "2020-12");
+        assert!(curve.points.last().unwrap().cumulative_sampled == 33);
+        for pair in curve.points.windows(2) {
+            assert!(pair[0].estimated_issued <= pair[1].estimated_issued);
+            assert!(pair[1].month == pair[0].month.next());
+        }
+        for point in &curve.points {
+            assert!(point.estimated_issued >= point.max_opaque_id);
+        }
+        assert!(curve.coverage > 0.0 && curve.coverage < 0.001);
+        assert!(estimate_dose_curve(&[]).points.is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+//! Export of parsed UVCIs as typed rows of a DuckDB table
+use crate::export::Exporter;
+use crate::Uvci;
+use duckdb::types::Value;
+use std::io;
+use std::path::Path;
+
+/// Statement creating the table of the UVCIs if it does not exist, with the table name placeholder
+/// "{table}"
+///
+/// The columns are the fields of `Uvci::FIELD_NAMES` with their native types, null when unknown, then
+/// the estimated vaccination month as DATE (the first day of the month) and the input file and line.
+pub const UVCI_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS {table} (\
+    version UTINYINT, \
+    country VARCHAR, \
+    schema_option_number UTINYINT NOT NULL, \
+    schema_option_desc VARCHAR NOT NULL, \
+    issuing_entity VARCHAR, \
+    vaccine_id VARCHAR, \
+    opaque_unique_string VARCHAR NOT NULL, \
+    opaque_id VARCHAR, \
+    opaque_issuance VARCHAR, \
+    opaque_vaccination_month UTINYINT, \
+    opaque_vaccination_year USMALLINT, \
+    checksum VARCHAR, \
+    checksum_verification BOOLEAN NOT NULL, \
+    vaccination_date DATE, \
+    source VARCHAR, \
+    line UBIGINT)";
+
+/// Exporter appending every parsed UVCI as a row of a DuckDB table with the appender API
+///
+/// The table is created if it does not exist, see `UVCI_TABLE_SQL`, and rows are appended in batches,
+/// so any UVCI file becomes a local analytical database without an ETL script.
+pub struct DuckDbExporter {
+    connection: duckdb::Connection,
+    table: String,
+    batch_size: usize,
+    rows: Vec<(Uvci, Option<(String, usize)>)>,
+    source: Option<(String, usize)>,
+}
+
+impl DuckDbExporter {
+    /// Open or create the DuckDB database file and the table of the UVCIs
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the database file, e.g. "uvcis.duckdb"
+    /// * `table` - the table name, letters, digits and underscores, e.g. "uvcis"
+    pub fn open<P: AsRef<Path>>(path: P, table: &str) -> io::Result<DuckDbExporter> {
+        let connection = duckdb::Connection::open(path).map_err(io::Error::other)?;
+        return DuckDbExporter::new(connection, table);
+    }
+
+    /// Append to the table of the UVCIs of an open database, e.g. in memory, creating the table
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - the DuckDB connection
+    /// * `table` - the table name, letters, digits and underscores, e.g. "uvcis"
+    pub fn new(connection: duckdb::Connection, table: &str) -> io::Result<DuckDbExporter> {
+        if table.is_empty() || !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid table name: {}", table),
+            ));
+        }
+        connection
+            .execute_batch(&UVCI_TABLE_SQL.replace("{table}", table))
+            .map_err(io::Error::other)?;
+        return Ok(DuckDbExporter {
+            connection,
+            table: table.to_string(),
+            batch_size: 10000,
+            rows: Vec::new(),
+            source: None,
+        });
+    }
+
+    /// Number of rows appended at once, at least 1, 10000 by default
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        return self;
+    }
+
+    /// The DuckDB connection, e.g. to query the table once the UVCIs are appended
+    pub fn connection(&self) -> &duckdb::Connection {
+        return &self.connection;
+    }
+
+    /// Append the pending rows to the table
+    fn append_rows(&mut self) -> io::Result<()> {
+        let mut appender = self
+            .connection
+            .appender(&self.table)
+            .map_err(io::Error::other)?;
+        for (uvci, source) in &self.rows {
+            let vaccination_date = uvci
+                .opaque_vaccination
+                .map(|date| Value::Date32(date.days_since_epoch()));
+            appender
+                .append_row(duckdb::params![
+                    uvci.version,
+                    uvci.country.as_ref().map(|country| country.as_str()),
+                    uvci.schema_option.number(),
+                    uvci.schema_option.desc(),
+                    uvci.issuing_entity,
+                    uvci.vaccine_id,
+                    uvci.opaque_unique_string,
+                    uvci.opaque_id,
+                    uvci.opaque_issuance,
+                    uvci.opaque_vaccination.map(|date| date.month),
+                    uvci.opaque_vaccination.map(|date| date.year),
+                    uvci.checksum,
+                    uvci.checksum_verification,
+                    vaccination_date,
+                    source.as_ref().map(|(file, _)| file.as_str()),
+                    source.as_ref().map(|(_, line)| *line as u64),
+                ])
+                .map_err(io::Error::other)?;
+        }
+        appender.flush().map_err(io::Error::other)?;
+        self.rows.clear();
+        return Ok(());
+    }
+}
+
+impl Exporter for DuckDbExporter {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        self.rows.push((uvci.clone(), self.source.take()));
+        if self.rows.len() >= self.batch_size {
+            return self.append_rows();
+        }
+        return Ok(());
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.source = Some((file.to_string(), line));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.append_rows();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        return self.append_rows();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DuckDbExporter;
+    use crate::export::{export_all, Exporter};
+    use crate::parse;
+
+    #[test]
+    fn duckdb_appender() {
+        let connection = duckdb::Connection::open_in_memory().unwrap();
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+        ];
+        let exporter = DuckDbExporter::new(connection.try_clone().unwrap(), "uvcis")
+            .unwrap()
+            .batch_size(2);
+        export_all(&cert_ids, exporter).unwrap();
+        let (count, dated): (u64, String) = connection
+            .query_row(
+                "SELECT count(*), CAST(min(vaccination_date) AS VARCHAR) FROM uvcis WHERE country = 'SE'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(count == 2 && dated == "2021-08-01");
+        let nulls: u64 = connection
+            .query_row(
+                "SELECT count(*) FROM uvcis WHERE checksum IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(nulls == 1);
+
+        let mut exporter = DuckDbExporter::new(connection.try_clone().unwrap(), "sourced").unwrap();
+        exporter.set_source("covid_uvci.txt", 7);
+        exporter.export(&parse(&cert_ids[0])).unwrap();
+        exporter.finish().unwrap();
+        let line: u64 = connection
+            .query_row(
+                "SELECT line FROM sourced WHERE source = 'covid_uvci.txt'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(line == 7);
+        assert!(DuckDbExporter::new(connection, "uvcis; DROP TABLE uvcis").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Editing of the fields of a parsed UVCI, re-emitted with a freshly computed checksum
+use crate::grammar::{ParseError, ParseErrorKind};
+use crate::{or_empty, try_parse, with_prefix_and_checksum, SchemaOption, Uvci};
+
+/// Builder changing the fields of a parsed UVCI, e.g. to correct a mistyped issuing entity
+///
+/// The fields are uppercased and validated by `build`, which re-emits the UVCI with a freshly computed
+/// checksum. Setting the issuing entity of an opaque UVCI makes it a "some semantics" UVCI, setting the
+/// vaccine product identifier an "identifier with semantics" UVCI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UvciEdit {
+    version: u8,
+    country: String,
+    schema_option: SchemaOption,
+    issuing_entity: Option<String>,
+    vaccine_id: Option<String>,
+    opaque_unique_string: String,
+}
+
+impl Uvci {
+    /// Start editing the fields of the UVCI, see `UvciEdit`
+    pub fn edit(&self) -> UvciEdit {
+        return UvciEdit {
+            version: self.version.unwrap_or(1),
+            country: self.country_str().to_string(),
+            schema_option: self.schema_option,
+            issuing_entity: self.issuing_entity.clone(),
+            vaccine_id: self.vaccine_id.clone(),
+            opaque_unique_string: self.opaque_unique_string.clone(),
+        };
+    }
+}
+
+impl UvciEdit {
+    /// Set the version of the UVCI schema, e.g. 1
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        return self;
+    }
+
+    /// Set the ISO 3166-1 country code, e.g. "SE"
+    pub fn country(mut self, country: &str) -> Self {
+        self.country = country.to_string();
+        return self;
+    }
+
+    /// Set the issuing entity, e.g. "EHM"
+    pub fn issuing_entity(mut self, issuing_entity: &str) -> Self {
+        self.issuing_entity = Some(issuing_entity.to_string());
+        if self.schema_option != SchemaOption::WithSemantics {
+            self.schema_option = SchemaOption::SomeSemantics;
+        }
+        return self;
+    }
+
+    /// Set the vaccine product identifier, e.g. "C1"
+    pub fn vaccine_id(mut self, vaccine_id: &str) -> Self {
+        self.vaccine_id = Some(vaccine_id.to_string());
+        self.schema_option = SchemaOption::WithSemantics;
+        return self;
+    }
+
+    /// Set the opaque unique string, e.g. "V12907267LAJW"
+    pub fn opaque_unique_string(mut self, opaque_unique_string: &str) -> Self {
+        self.opaque_unique_string = opaque_unique_string.to_string();
+        return self;
+    }
+
+    /// The edited UVCI with a freshly computed checksum, use `Uvci::canonical` for the UVCI string
+    ///
+    /// Fails with the first field that is not valid, at its offset in the canonical UVCI, e.g. a version
+    /// of more than two digits, an empty block or a block with characters other than letters and digits.
+    pub fn build(&self) -> Result<Uvci, ParseError> {
+        if self.version > 99 {
+            return Err(ParseError {
+                kind: ParseErrorKind::Version,
+                offset: "URN:UVCI:".len(),
+                found: self.version.to_string(),
+            });
+        }
+        let mut bare_id = format!("{:02}:{}:", self.version, self.country.to_uppercase());
+        let blocks = match self.schema_option {
+            SchemaOption::WithSemantics => vec![
+                or_empty(&self.issuing_entity),
+                or_empty(&self.vaccine_id),
+                &self.opaque_unique_string,
+            ],
+            SchemaOption::SomeSemantics => {
+                vec![or_empty(&self.issuing_entity), &self.opaque_unique_string]
+            }
+            _ => vec![self.opaque_unique_string.as_str()],
+        };
+        for (index, block) in blocks.iter().enumerate() {
+            if index > 0 {
+                bare_id.push('/');
+            }
+            let block = block.to_uppercase();
+            if block.is_empty()
+                || !block
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Block,
+                    offset: "URN:UVCI:".len() + bare_id.len(),
+                    found: block,
+                });
+            }
+            bare_id.push_str(&block);
+        }
+        return try_parse(&with_prefix_and_checksum(&bare_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::ParseErrorKind;
+    use crate::{parse, SchemaOption};
+
+    #[test]
+    fn uvci_edit() {
+        // Correct a mistyped issuing entity, the checksum is recomputed
+        let uvci = parse("URN:UVCI:01:SE:EHN/V12907267LAJW#E");
+        assert!(!uvci.checksum_verification);
+        let edited = uvci.edit().issuing_entity("ehm").build().unwrap();
+        assert!(edited.canonical() == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(edited.checksum_verification);
+
+        let edited = parse("URN:UVCI:01:NL:187/37512422923")
+            .edit()
+            .vaccine_id("C1")
+            .build()
+            .unwrap();
+        assert!(edited.schema_option == SchemaOption::WithSemantics);
+        assert!(edited.vaccine_id == Some("C1".to_string()));
+        assert!(edited.checksum_verification);
+
+        let error = uvci.edit().country("S3").build().err().unwrap();
+        assert!(error.kind == ParseErrorKind::Country);
+        let error = uvci.edit().issuing_entity("EH/M").build().err().unwrap();
+        assert!(error.kind == ParseErrorKind::Block && error.offset == 15);
+        let error = uvci.edit().version(100).build().err().unwrap();
+        assert!(error.kind == ParseErrorKind::Version);
+    }
+}
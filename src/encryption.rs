@@ -0,0 +1,127 @@
+//! Encryption of output files to age recipients
+use std::io::{self, Write};
+
+/// Writer encrypting the output written to it to age recipients, or writing it as is without recipients
+///
+/// The output is an age file (https://age-encryption.org/v1), decrypted with e.g.
+/// `age --decrypt -i key.txt uvci.csv.age`. `finish` must be called to write the last chunk of the
+/// encrypted stream, otherwise the output is truncated and fails to decrypt.
+pub struct EncryptedWriter<W: Write> {
+    inner: EncryptedInner<W>,
+}
+
+enum EncryptedInner<W: Write> {
+    Plain(W),
+    #[cfg(feature = "age")]
+    Age(age::stream::StreamWriter<W>),
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    /// Create a writer encrypting to the given recipients, fails if a recipient is invalid or the
+    /// `age` feature is not enabled
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the output, e.g. a "uvci.csv.age" file
+    /// * `recipients` - the age X25519 public keys, e.g. "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p", empty to write the output as is
+    pub fn new(writer: W, recipients: &[String]) -> io::Result<EncryptedWriter<W>> {
+        if recipients.is_empty() {
+            return Ok(EncryptedWriter {
+                inner: EncryptedInner::Plain(writer),
+            });
+        }
+        #[cfg(feature = "age")]
+        return Ok(EncryptedWriter {
+            inner: EncryptedInner::Age(age_writer(writer, recipients)?),
+        });
+        #[cfg(not(feature = "age"))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "encrypted output requires the age feature",
+        ));
+    }
+
+    /// Write the end of the encrypted stream and return the output
+    pub fn finish(self) -> io::Result<W> {
+        match self.inner {
+            EncryptedInner::Plain(writer) => Ok(writer),
+            #[cfg(feature = "age")]
+            EncryptedInner::Age(writer) => writer.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            EncryptedInner::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "age")]
+            EncryptedInner::Age(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            EncryptedInner::Plain(writer) => writer.flush(),
+            #[cfg(feature = "age")]
+            EncryptedInner::Age(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Parse the recipients and write the age header
+#[cfg(feature = "age")]
+fn age_writer<W: Write>(
+    writer: W,
+    recipients: &[String],
+) -> io::Result<age::stream::StreamWriter<W>> {
+    let mut parsed = Vec::new();
+    for recipient in recipients {
+        match recipient.trim().parse::<age::x25519::Recipient>() {
+            Ok(recipient) => parsed.push(recipient),
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid age recipient {}: {}", recipient, e),
+                ))
+            }
+        }
+    }
+    let encryptor = age::Encryptor::with_recipients(
+        parsed
+            .iter()
+            .map(|recipient| recipient as &dyn age::Recipient),
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    return encryptor.wrap_output(writer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EncryptedWriter;
+    use std::io::Write;
+
+    const INPUT: &str = "URN:UVCI:01:SE:EHM/V12907267LAJW#E\n";
+
+    #[test]
+    fn plain_writer() {
+        let mut writer = EncryptedWriter::new(Vec::new(), &[]).unwrap();
+        writer.write_all(INPUT.as_bytes()).unwrap();
+        assert!(writer.finish().unwrap() == INPUT.as_bytes());
+    }
+
+    #[cfg(feature = "age")]
+    #[test]
+    fn age_roundtrip() {
+        let identity = age::x25519::Identity::generate();
+        let recipients = vec![identity.to_public().to_string()];
+        let mut writer = EncryptedWriter::new(Vec::new(), &recipients).unwrap();
+        writer.write_all(INPUT.as_bytes()).unwrap();
+        let encrypted = writer.finish().unwrap();
+        assert!(encrypted.starts_with(b"age-encryption.org/v1\n"));
+        assert!(!String::from_utf8_lossy(&encrypted).contains("EHM"));
+        assert!(age::decrypt(&identity, &encrypted).unwrap() == INPUT.as_bytes());
+
+        assert!(EncryptedWriter::new(Vec::new(), &["age1invalid".to_string()]).is_err());
+    }
+}
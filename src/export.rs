@@ -0,0 +1,1642 @@
+//! Exporters of parsed UVCIs
+//!
+//! The `Exporter` trait is the sink of a batch: the CSV, JSON Lines, Cypher, cypher-shell,
+//! apoc.periodic.iterate, Splunk HEC, DOT, neo4j-admin and edge list exporters implement it, and
+//! `export_all` and its variants feed a batch into any of them, with provenance and a reject log.
+//! `SortingExporter` and `RedactingExporter` wrap another exporter, and `ExportOptions` sets the date
+//! format of the output.
+use crate::batch::BatchResult;
+use crate::reject::RejectLog;
+use crate::{
+    csv_quoted, diagnose, graph_certificate_status, graph_constraints, graph_vac_date,
+    graph_vaccination_date, or_empty, parse, to_ehealth_json, to_graph, to_graph_merge_with_dates,
+    to_graph_with_dates, to_json, write_csv_with_vaccination, Severity, SourcedUvci, Uvci,
+    YearMonth, CSV_LINE_CAPACITY,
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Sink for parsed EU Digital COVID Certificate UVCIs
+///
+/// Implemented by the CSV, JSON Lines, Cypher and DOT exporters; implement it to add a custom sink.
+pub trait Exporter {
+    /// Export one parsed UVCI
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()>;
+
+    /// Set the input file and line of the next UVCI, written as provenance columns by the CSV and
+    /// JSON Lines exporters when merging several input files
+    fn set_source(&mut self, _file: &str, _line: usize) {}
+
+    /// Export a parsed UVCI with its input file and line, as provenance columns where supported
+    fn export_sourced(&mut self, sourced: &SourcedUvci) -> io::Result<()> {
+        self.set_source(&sourced.source, sourced.line);
+        return self.export(&sourced.uvci);
+    }
+
+    /// Flush the output written so far, e.g. before recording a checkpoint
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+
+    /// Write any trailing output and flush the sink
+    fn finish(self) -> io::Result<()>
+    where
+        Self: Sized;
+}
+
+/// Parse and export a vector of UVCIs, then finish the exporter
+///
+/// Returns the summary of the UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `exporter` - the sink
+pub fn export_all<E: Exporter>(cert_ids: &[String], mut exporter: E) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    for cert_id in cert_ids {
+        let uvci = parse(cert_id);
+        result.record(cert_id, &uvci);
+        exporter.export(&uvci)?;
+    }
+    exporter.finish()?;
+    return Ok(result.finish(started));
+}
+
+/// Export parsed UVCIs with their input file and line, then finish the exporter
+///
+/// The CSV and JSON Lines exporters write the provenance as extra columns, e.g. of `parse_sourced`.
+/// Returns the summary of the UVCIs exported, diagnosed in their canonical form.
+/// # Arguments
+///
+/// * `uvcis` - the parsed UVCIs with their provenance
+/// * `exporter` - the sink
+pub fn export_all_sourced<E: Exporter>(
+    uvcis: &[SourcedUvci],
+    mut exporter: E,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    for sourced in uvcis {
+        result.record(&sourced.uvci.canonical(), &sourced.uvci);
+        exporter.export_sourced(sourced)?;
+    }
+    exporter.finish()?;
+    return Ok(result.finish(started));
+}
+
+/// Parse and export a vector of UVCIs, logging and skipping invalid ones, then finish the exporter
+///
+/// Returns the summary of the UVCIs, the exported ones succeeded and the rejected ones failed.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), one per input line
+/// * `exporter` - the sink
+/// * `rejects` - the log of rejected lines
+pub fn export_all_logged<E: Exporter, W: Write>(
+    cert_ids: &[String],
+    mut exporter: E,
+    rejects: &mut RejectLog<W>,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    for (index, cert_id) in cert_ids.iter().enumerate() {
+        let uvci = parse(cert_id);
+        let diagnostics = diagnose(cert_id);
+        result.record_diagnosed(&uvci, &diagnostics);
+        match diagnostics
+            .into_iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            Some(diagnostic) => rejects.reject(index + 1, cert_id, &diagnostic)?,
+            None => exporter.export(&uvci)?,
+        }
+    }
+    exporter.finish()?;
+    return Ok(result.finish(started));
+}
+
+/// Month names of the locales of `DateFormat::MonthName`, abbreviated
+const MONTH_NAMES: &[(&str, [&str; 12])] = &[
+    (
+        "en",
+        [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+    ),
+    (
+        "de",
+        [
+            "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+    ),
+    (
+        "es",
+        [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+        ],
+    ),
+    (
+        "it",
+        [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+    ),
+    (
+        "nl",
+        [
+            "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+        ],
+    ),
+    (
+        "sv",
+        [
+            "jan", "feb", "mars", "apr", "maj", "juni", "juli", "aug", "sep", "okt", "nov", "dec",
+        ],
+    ),
+];
+
+/// Rendering of the estimated vaccination month in the CSV and JSON Lines exports
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DateFormat {
+    /// Separate month and year integer columns, e.g. 8 and 2021, 0 if unknown
+    #[default]
+    Columns,
+    /// One ISO 8601 month column, e.g. "2021-08"
+    IsoMonth,
+    /// One ISO 8601 week column of the first day of the month, e.g. "2021-W30"
+    IsoWeek,
+    /// One column of the abbreviated month name of a locale and the year, e.g. "Aug 2021"
+    MonthName(&'static [&'static str; 12]),
+}
+
+impl DateFormat {
+    /// The date format of a code, "columns", "iso-month", "iso-week" or "month-name" followed by an
+    /// optional locale, e.g. "month-name:de", None if it is not a valid code
+    pub fn from_code(code: &str) -> Option<DateFormat> {
+        match code.split_once(':') {
+            Some(("month-name", locale)) => MONTH_NAMES
+                .iter()
+                .find(|(code, _)| *code == locale)
+                .map(|(_, names)| DateFormat::MonthName(names)),
+            Some(_) => None,
+            None => match code {
+                "columns" => Some(DateFormat::Columns),
+                "iso-month" => Some(DateFormat::IsoMonth),
+                "iso-week" => Some(DateFormat::IsoWeek),
+                "month-name" => DateFormat::from_code("month-name:en"),
+                _ => None,
+            },
+        }
+    }
+
+    /// The vaccination month rendered in the format, empty if unknown or in separate columns
+    ///
+    /// # Arguments
+    ///
+    /// * `month` - the estimated vaccination month, e.g. of `Uvci::opaque_vaccination`
+    pub fn render(&self, month: Option<YearMonth>) -> String {
+        let month = match month {
+            Some(month) => month,
+            None => return "".to_string(),
+        };
+        match self {
+            DateFormat::Columns => "".to_string(),
+            DateFormat::IsoMonth => month.to_string(),
+            DateFormat::IsoWeek => {
+                let (year, week) = iso_week(month.year as i64, month.month as i64, 1);
+                format!("{:04}-W{:02}", year, week)
+            }
+            DateFormat::MonthName(names) => {
+                format!("{} {}", names[month.month as usize - 1], month.year)
+            }
+        }
+    }
+}
+
+/// Number of days from 1970-01-01 to a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    return era * 146097 + day_of_era - 719468;
+}
+
+/// The ISO 8601 year and week of a date, e.g. (2021, 30) for 2021-08-01
+fn iso_week(year: i64, month: i64, day: i64) -> (i64, i64) {
+    let days = days_from_civil(year, month, day);
+    // 1970-01-01 is a Thursday, weekday 4 with Monday 1
+    let weekday = (days + 3).rem_euclid(7) + 1;
+    let ordinal = days - days_from_civil(year, 1, 1) + 1;
+    let week = (ordinal - weekday + 10) / 7;
+    let weeks_in_year = |year: i64| {
+        let jan1 = (days_from_civil(year, 1, 1) + 3).rem_euclid(7) + 1;
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        if jan1 == 4 || (leap && jan1 == 3) {
+            53
+        } else {
+            52
+        }
+    };
+    if week < 1 {
+        return (year - 1, weeks_in_year(year - 1));
+    }
+    if week > weeks_in_year(year) {
+        return (year + 1, 1);
+    }
+    return (year, week);
+}
+
+/// Options of the CSV, JSON Lines and Cypher graph exporters
+///
+/// By default the estimated vaccination month is written as separate month and year integer columns,
+/// and only as vac_date node of the graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportOptions {
+    date_format: DateFormat,
+    native_dates: bool,
+}
+
+impl ExportOptions {
+    pub fn new() -> ExportOptions {
+        return ExportOptions::default();
+    }
+
+    /// Render the estimated vaccination month in the format
+    ///
+    /// Other formats than `DateFormat::Columns` replace the "opaque_vaccination_month" and
+    /// "opaque_vaccination_year" columns by one "opaque_vaccination" column, empty if unknown.
+    pub fn date_format(mut self, date_format: DateFormat) -> Self {
+        self.date_format = date_format;
+        return self;
+    }
+
+    /// Set the estimated vaccination date as Neo4j `date()` property "vaccination_date" of the
+    /// certificate nodes of the graph, the first day of the month, for temporal range queries
+    ///
+    /// The constraints of the graph then include a range index on the property.
+    pub fn native_dates(mut self, native_dates: bool) -> Self {
+        self.native_dates = native_dates;
+        return self;
+    }
+
+    /// The constraints and indexes written before the graph, see `graph_constraints`
+    fn graph_constraints(&self) -> String {
+        let mut cypher_cmd = graph_constraints();
+        if self.native_dates {
+            cypher_cmd.push_str(
+                "CREATE INDEX reissue_id_vaccination_date IF NOT EXISTS FOR (n:reissue_id) ON (n.vaccination_date);\n",
+            );
+        }
+        return cypher_cmd;
+    }
+}
+
+/// Exporter writing one CSV line per UVCI, the same lines as `uvcis_to_csv`
+///
+/// Once a source is set, the input file, quoted, and line are appended as two provenance columns.
+pub struct CsvExporter<W: Write> {
+    writer: W,
+    source: Option<(String, usize)>,
+    /// Line buffer reused across UVCIs
+    line: String,
+    options: ExportOptions,
+}
+
+impl<W: Write> CsvExporter<W> {
+    pub fn new(writer: W) -> CsvExporter<W> {
+        return CsvExporter {
+            writer,
+            source: None,
+            line: String::with_capacity(CSV_LINE_CAPACITY),
+            options: ExportOptions::default(),
+        };
+    }
+
+    /// Write the lines with the export options, e.g. the date format
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        return self;
+    }
+}
+
+impl<W: Write> Exporter for CsvExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        self.line.clear();
+        let vaccination = match self.options.date_format {
+            DateFormat::Columns => None,
+            date_format => Some(date_format.render(uvci.opaque_vaccination)),
+        };
+        write_csv_with_vaccination(uvci, vaccination.as_deref(), &mut self.line);
+        return match &self.source {
+            Some((file, line)) => {
+                writeln!(self.writer, "{},{},{}", self.line, csv_quoted(file), line)
+            }
+            None => writeln!(self.writer, "{}", self.line),
+        };
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.source = Some((file.to_string(), line));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+}
+
+/// Exporter writing one JSON object per line (JSON Lines), the objects of `uvci_to_json`
+///
+/// Once a source is set, the input file and line are added as "source_file" and "source_line".
+pub struct JsonlExporter<W: Write> {
+    writer: W,
+    source: Option<(String, usize)>,
+    camel_case: bool,
+    options: ExportOptions,
+}
+
+impl<W: Write> JsonlExporter<W> {
+    pub fn new(writer: W) -> JsonlExporter<W> {
+        return JsonlExporter {
+            writer,
+            source: None,
+            camel_case: false,
+            options: ExportOptions::default(),
+        };
+    }
+
+    /// Write the objects with the export options, e.g. the date format, not for camelCase objects
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        return self;
+    }
+
+    /// Write the objects of `uvci_to_ehealth_json` instead, with camelCase field names
+    pub fn camel_case(mut self, camel_case: bool) -> Self {
+        self.camel_case = camel_case;
+        return self;
+    }
+}
+
+impl<W: Write> Exporter for JsonlExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        if self.camel_case {
+            let mut value = to_ehealth_json(uvci);
+            if let Some((file, line)) = &self.source {
+                value["sourceFile"] = serde_json::json!(file);
+                value["sourceLine"] = serde_json::json!(line);
+            }
+            return writeln!(self.writer, "{}", value);
+        }
+        let mut value = to_json(uvci);
+        if self.options.date_format != DateFormat::Columns {
+            if let Some(object) = value.as_object_mut() {
+                object.remove("opaque_vaccination_month");
+                object.remove("opaque_vaccination_year");
+            }
+            value["opaque_vaccination"] =
+                serde_json::json!(self.options.date_format.render(uvci.opaque_vaccination));
+        }
+        if let Some((file, line)) = &self.source {
+            value["source_file"] = serde_json::json!(file);
+            value["source_line"] = serde_json::json!(line);
+        }
+        return writeln!(self.writer, "{}", value);
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.source = Some((file.to_string(), line));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+}
+
+/// Exporter writing the Neo4j Cypher Graph, the same output as `uvcis_to_graph`
+///
+/// Only for Sweden EHM-issued COVID certificates, duplicate statements are skipped.
+pub struct CypherExporter<W: Write> {
+    writer: W,
+    statements: HashSet<String>,
+    return_all: bool,
+    constraints: bool,
+    started: bool,
+    options: ExportOptions,
+}
+
+impl<W: Write> CypherExporter<W> {
+    pub fn new(writer: W) -> CypherExporter<W> {
+        return CypherExporter {
+            writer,
+            statements: HashSet::new(),
+            return_all: false,
+            constraints: false,
+            started: false,
+            options: ExportOptions::default(),
+        };
+    }
+
+    /// Skip the statements already written, e.g. when resuming an interrupted export
+    pub fn with_statements(mut self, statements: HashSet<String>) -> CypherExporter<W> {
+        self.statements = statements;
+        return self;
+    }
+
+    /// End the graph with `RETURN *`, as expected when running it in the Neo4j Browser
+    pub fn return_all(mut self, return_all: bool) -> CypherExporter<W> {
+        self.return_all = return_all;
+        return self;
+    }
+
+    /// Start the graph with the uniqueness constraints of `graph_constraints`
+    pub fn constraints(mut self, constraints: bool) -> CypherExporter<W> {
+        self.constraints = constraints;
+        return self;
+    }
+
+    /// Set the export options, e.g. native vaccination dates
+    pub fn options(mut self, options: ExportOptions) -> CypherExporter<W> {
+        self.options = options;
+        return self;
+    }
+
+    /// Write the constraints before the first statement
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started && self.constraints {
+            self.writer
+                .write_all(self.options.graph_constraints().as_bytes())?;
+        }
+        self.started = true;
+        return Ok(());
+    }
+}
+
+impl<W: Write> Exporter for CypherExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        self.start()?;
+        for statement in to_graph_with_dates(uvci, self.options.native_dates).lines() {
+            // Only allocate the statements not written yet
+            if !self.statements.contains(statement) {
+                write!(self.writer, "\n{}", statement)?;
+                self.statements.insert(statement.to_string());
+            }
+        }
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.start()?;
+        if self.return_all {
+            self.writer.write_all(b"\nRETURN *\n")?;
+        }
+        return self.writer.flush();
+    }
+}
+
+/// Exporter writing the Neo4j Cypher Graph as a cypher-shell script of self-contained MERGE statements
+///
+/// Only for Sweden EHM-issued COVID certificates. Every `chunk_size` certificates are wrapped in
+/// `:begin` and `:commit`, so large imports do not exceed the transaction memory of Neo4j.
+pub struct CypherShellExporter<W: Write> {
+    writer: W,
+    chunk_size: usize,
+    in_chunk: usize,
+    statements: usize,
+    constraints: bool,
+    started: bool,
+    options: ExportOptions,
+}
+
+impl<W: Write> CypherShellExporter<W> {
+    /// Create an exporter committing every `chunk_size` certificates, at least 1
+    pub fn new(writer: W, chunk_size: usize) -> CypherShellExporter<W> {
+        return CypherShellExporter {
+            writer,
+            chunk_size: chunk_size.max(1),
+            in_chunk: 0,
+            statements: 0,
+            constraints: false,
+            started: false,
+            options: ExportOptions::default(),
+        };
+    }
+
+    /// Start the script with the uniqueness constraints of `graph_constraints`, outside of the transactions
+    pub fn constraints(mut self, constraints: bool) -> CypherShellExporter<W> {
+        self.constraints = constraints;
+        return self;
+    }
+
+    /// Set the export options, e.g. native vaccination dates
+    pub fn options(mut self, options: ExportOptions) -> CypherShellExporter<W> {
+        self.options = options;
+        return self;
+    }
+
+    /// Write the constraints before the first transaction
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started && self.constraints {
+            self.writer
+                .write_all(self.options.graph_constraints().as_bytes())?;
+        }
+        self.started = true;
+        return Ok(());
+    }
+
+    /// Number of statements written
+    pub fn statements(&self) -> usize {
+        return self.statements;
+    }
+}
+
+impl<W: Write> Exporter for CypherShellExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        self.start()?;
+        let statement = to_graph_merge_with_dates(uvci, self.options.native_dates);
+        if statement.is_empty() {
+            return Ok(());
+        }
+        if self.in_chunk == 0 {
+            self.writer.write_all(b":begin\n")?;
+        }
+        writeln!(self.writer, "{};", statement)?;
+        self.in_chunk += 1;
+        self.statements += 1;
+        if self.in_chunk == self.chunk_size {
+            self.writer.write_all(b":commit\n")?;
+            self.in_chunk = 0;
+        }
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.start()?;
+        if self.in_chunk > 0 {
+            self.writer.write_all(b":commit\n")?;
+        }
+        return self.writer.flush();
+    }
+}
+
+/// Exporter writing the Neo4j Cypher Graph as one `apoc.periodic.iterate` call, for very large imports
+///
+/// Only for Sweden EHM-issued COVID certificates. Every certificate is passed as a row parameter and
+/// merged in batches of `batch_size` rows, each batch in its own transaction. Requires the APOC plugin.
+pub struct ApocIterateExporter<W: Write> {
+    writer: W,
+    batch_size: usize,
+    constraints: bool,
+    rows: usize,
+    options: ExportOptions,
+}
+
+impl<W: Write> ApocIterateExporter<W> {
+    /// Create an exporter merging `batch_size` rows per transaction, at least 1
+    pub fn new(writer: W, batch_size: usize) -> ApocIterateExporter<W> {
+        return ApocIterateExporter {
+            writer,
+            batch_size: batch_size.max(1),
+            constraints: false,
+            rows: 0,
+            options: ExportOptions::default(),
+        };
+    }
+
+    /// Start the script with the constraints and indexes of `graph_constraints`
+    pub fn constraints(mut self, constraints: bool) -> ApocIterateExporter<W> {
+        self.constraints = constraints;
+        return self;
+    }
+
+    /// Set the export options, e.g. native vaccination dates
+    pub fn options(mut self, options: ExportOptions) -> ApocIterateExporter<W> {
+        self.options = options;
+        return self;
+    }
+}
+
+/// Quote a string as a Cypher string literal
+fn cypher_string(value: &str) -> String {
+    return format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"));
+}
+
+impl<W: Write> Exporter for ApocIterateExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        if to_graph(uvci).is_empty() {
+            return Ok(());
+        }
+        if self.rows == 0 {
+            if self.constraints {
+                self.writer
+                    .write_all(self.options.graph_constraints().as_bytes())?;
+            }
+            self.writer.write_all(b"CALL apoc.periodic.iterate(\n")?;
+            self.writer
+                .write_all(b"  'UNWIND $rows AS row RETURN row',\n")?;
+            self.writer.write_all(
+                b"  'MERGE (c:country {name: row.country}) \
+                  MERGE (i:issuing_entity {name: row.issuing_entity}) \
+                  MERGE (c)-[:COUNTRY_OF]->(i) \
+                  MERGE (o:opaque_id {name: row.opaque_id}) \
+                  MERGE (i)-[:ISSUER_OF]->(o) \
+                  MERGE (d:vac_date {name: row.vac_date}) \
+                  MERGE (d)-[:VAC_DATE_OF]->(o) \
+                  MERGE (r:reissue_id {name: row.reissue_id})-[:REISSUE_OF]->(o) \
+                  SET r.checksum_valid = row.checksum_valid, r.quality_score = row.quality_score, \
+                  r.schema_option = row.schema_option ",
+            )?;
+            if self.options.native_dates {
+                self.writer
+                    .write_all(b"SET r.vaccination_date = date(row.vaccination_date) ")?;
+            }
+            self.writer.write_all(
+                b"FOREACH (_ IN CASE WHEN row.invalid THEN [1] ELSE [] END | SET r:invalid_certificate)',\n",
+            )?;
+            writeln!(
+                self.writer,
+                "  {{batchSize: {}, parallel: false, params: {{rows: [",
+                self.batch_size
+            )?;
+        } else {
+            self.writer.write_all(b",\n")?;
+        }
+        let (_, vac_date) = graph_vac_date(uvci);
+        let (label, checksum_valid, quality_score) = graph_certificate_status(uvci);
+        write!(
+            self.writer,
+            "    {{country: {}, issuing_entity: {}, opaque_id: {}, vac_date: {}, reissue_id: {}, \
+             checksum_valid: {}, quality_score: {}, schema_option: {}, invalid: {}",
+            cypher_string("Sweden"),
+            cypher_string("E-Hälso Myndigheten"),
+            cypher_string(or_empty(&uvci.opaque_id)),
+            cypher_string(&vac_date),
+            cypher_string(or_empty(&uvci.opaque_issuance)),
+            checksum_valid,
+            quality_score,
+            uvci.schema_option.number(),
+            !label.is_empty()
+        )?;
+        if self.options.native_dates {
+            let vaccination_date = graph_vaccination_date(uvci)
+                .map_or("null".to_string(), |date| cypher_string(&date));
+            write!(self.writer, ", vaccination_date: {}", vaccination_date)?;
+        }
+        self.writer.write_all(b"}")?;
+        self.rows += 1;
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if self.rows > 0 {
+            self.writer.write_all(b"\n  ]}}\n);\n")?;
+        } else if self.constraints {
+            self.writer
+                .write_all(self.options.graph_constraints().as_bytes())?;
+        }
+        return self.writer.flush();
+    }
+}
+
+/// Exporter writing Splunk HTTP Event Collector (HEC) JSON events, one per line
+///
+/// Every event holds the object of `uvci_to_json` with the sourcetype, "covid_cert_uvci" by default,
+/// and the input file as source once set. The timestamp is the estimated vaccination date (the first
+/// day of the month, UTC) when known, otherwise the processing time, so the body can be posted as is to
+/// the `/services/collector/event` endpoint.
+pub struct SplunkHecExporter<W: Write> {
+    writer: W,
+    source: Option<(String, usize)>,
+    sourcetype: String,
+    index: Option<String>,
+}
+
+impl<W: Write> SplunkHecExporter<W> {
+    pub fn new(writer: W) -> SplunkHecExporter<W> {
+        return SplunkHecExporter {
+            writer,
+            source: None,
+            sourcetype: "covid_cert_uvci".to_string(),
+            index: None,
+        };
+    }
+
+    /// Set the sourcetype of the events
+    pub fn sourcetype(mut self, sourcetype: &str) -> Self {
+        self.sourcetype = sourcetype.to_string();
+        return self;
+    }
+
+    /// Send the events to a Splunk index instead of the default index of the HEC token
+    pub fn index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        return self;
+    }
+}
+
+impl<W: Write> Exporter for SplunkHecExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        let time = match uvci.opaque_vaccination {
+            Some(date) => date.days_since_epoch() as f64 * 86400.0,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |elapsed| elapsed.as_millis() as f64 / 1000.0),
+        };
+        let mut event = to_json(uvci);
+        event["vaccination_date_estimated"] = serde_json::json!(uvci.opaque_vaccination.is_some());
+        let mut value = serde_json::json!({
+            "time": time,
+            "sourcetype": self.sourcetype,
+            "event": event,
+        });
+        if let Some((file, line)) = &self.source {
+            value["source"] = serde_json::json!(file);
+            value["event"]["source_line"] = serde_json::json!(line);
+        }
+        if let Some(index) = &self.index {
+            value["index"] = serde_json::json!(index);
+        }
+        return writeln!(self.writer, "{}", value);
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.source = Some((file.to_string(), line));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+}
+
+/// Exporter writing the graph of `uvcis_to_graph` as a Graphviz DOT digraph
+///
+/// Only for Sweden EHM-issued COVID certificates, duplicate nodes and edges are skipped.
+pub struct DotExporter<W: Write> {
+    writer: W,
+    lines: HashSet<String>,
+    started: bool,
+}
+
+impl<W: Write> DotExporter<W> {
+    pub fn new(writer: W) -> DotExporter<W> {
+        return DotExporter {
+            writer,
+            lines: HashSet::new(),
+            started: false,
+        };
+    }
+
+    /// Write a node or edge line unless it was already written
+    fn write_line(&mut self, line: String) -> io::Result<()> {
+        if !self.started {
+            self.writer.write_all(b"digraph uvci {\n")?;
+            self.started = true;
+        }
+        if self.lines.insert(line.clone()) {
+            writeln!(self.writer, "    {}", line)?;
+        }
+        return Ok(());
+    }
+}
+
+impl<W: Write> Exporter for DotExporter<W> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        if to_graph(uvci).is_empty() {
+            return Ok(());
+        }
+        let (date_name, date_data) = graph_vac_date(uvci);
+        self.write_line(format!("\"{}\" [label=\"Sweden\"];", uvci.country_str()))?;
+        self.write_line(format!(
+            "\"{}\" [label=\"E-Hälso Myndigheten\"];",
+            or_empty(&uvci.issuing_entity)
+        ))?;
+        self.write_line(format!("\"{}\" [label=\"{}\"];", date_name, date_data))?;
+        self.write_line(format!(
+            "\"{}\" [label=\"{}\"];",
+            uvci.opaque_unique_string,
+            or_empty(&uvci.opaque_issuance)
+        ))?;
+        self.write_line(format!(
+            "\"{}\" -> \"{}\" [label=\"COUNTRY_OF\"];",
+            uvci.country_str(),
+            or_empty(&uvci.issuing_entity)
+        ))?;
+        self.write_line(format!(
+            "\"{}\" -> \"{}\" [label=\"ISSUER_OF\"];",
+            or_empty(&uvci.issuing_entity),
+            or_empty(&uvci.opaque_id)
+        ))?;
+        self.write_line(format!(
+            "\"{}\" -> \"{}\" [label=\"VAC_DATE_OF\"];",
+            date_name,
+            or_empty(&uvci.opaque_id)
+        ))?;
+        self.write_line(format!(
+            "\"{}\" -> \"{}\" [label=\"REISSUE_OF\"];",
+            uvci.opaque_unique_string,
+            or_empty(&uvci.opaque_id)
+        ))?;
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if !self.started {
+            self.writer.write_all(b"digraph uvci {\n")?;
+        }
+        self.writer.write_all(b"}\n")?;
+        return self.writer.flush();
+    }
+}
+
+/// Node of the graph of `uvcis_to_graph` as (id, name, label), the id being the variable name of the
+/// Cypher graph
+type GraphNode = (String, String, &'static str);
+
+/// Edge of the graph of `uvcis_to_graph` as (start id, end id, type)
+type GraphEdge = (String, String, &'static str);
+
+/// The nodes and edges of the graph of a UVCI, empty if it is not part of the graph
+fn graph_elements(uvci: &Uvci) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+    if to_graph(uvci).is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+    let country = uvci.country_str().to_string();
+    let issuer = or_empty(&uvci.issuing_entity).to_string();
+    let opaque_id = or_empty(&uvci.opaque_id).to_string();
+    let reissue = uvci.opaque_unique_string.clone();
+    let (date_name, date_data) = graph_vac_date(uvci);
+    let nodes = vec![
+        (country.clone(), "Sweden".to_string(), "country"),
+        (
+            issuer.clone(),
+            "E-Hälso Myndigheten".to_string(),
+            "issuing_entity",
+        ),
+        (opaque_id.clone(), opaque_id.clone(), "opaque_id"),
+        (date_name.clone(), date_data, "vac_date"),
+        (
+            reissue.clone(),
+            or_empty(&uvci.opaque_issuance).to_string(),
+            "reissue_id",
+        ),
+    ];
+    let edges = vec![
+        (country, issuer.clone(), "COUNTRY_OF"),
+        (issuer, opaque_id.clone(), "ISSUER_OF"),
+        (date_name, opaque_id.clone(), "VAC_DATE_OF"),
+        (reissue, opaque_id, "REISSUE_OF"),
+    ];
+    return (nodes, edges);
+}
+
+/// Header of the node file of `Neo4jAdminExporter`
+const NEO4J_ADMIN_NODES_HEADER: &[u8] = b"id:ID,name,:LABEL\n";
+
+/// Header of the relationship file of `Neo4jAdminExporter`
+const NEO4J_ADMIN_RELATIONSHIPS_HEADER: &[u8] = b":START_ID,:END_ID,:TYPE\n";
+
+/// Exporter writing the graph of `uvcis_to_graph` as the node and relationship CSV files of
+/// `neo4j-admin database import`, orders of magnitude faster than Cypher for initial bulk loads
+///
+/// Only for Sweden EHM-issued COVID certificates, duplicate nodes and relationships are skipped. The
+/// node ids are the variable names of the Cypher graph, e.g. "V12916227", import with
+/// `neo4j-admin database import full --nodes=nodes.csv --relationships=relationships.csv`.
+pub struct Neo4jAdminExporter<N: Write, R: Write> {
+    nodes: N,
+    relationships: R,
+    lines: HashSet<String>,
+    started: bool,
+}
+
+impl<N: Write, R: Write> Neo4jAdminExporter<N, R> {
+    /// Create an exporter writing the nodes and the relationships to separate files
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes` - the node CSV output, e.g. "nodes.csv"
+    /// * `relationships` - the relationship CSV output, e.g. "relationships.csv"
+    pub fn new(nodes: N, relationships: R) -> Neo4jAdminExporter<N, R> {
+        return Neo4jAdminExporter {
+            nodes,
+            relationships,
+            lines: HashSet::new(),
+            started: false,
+        };
+    }
+
+    /// Write the headers before the first line
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.nodes.write_all(NEO4J_ADMIN_NODES_HEADER)?;
+            self.relationships
+                .write_all(NEO4J_ADMIN_RELATIONSHIPS_HEADER)?;
+            self.started = true;
+        }
+        return Ok(());
+    }
+
+    /// Write a node unless it was already written
+    fn write_node(&mut self, id: &str, name: &str, label: &str) -> io::Result<()> {
+        let line = format!("{},\"{}\",{}", id, name.replace('"', "\"\""), label);
+        if self.lines.insert(line.clone()) {
+            writeln!(self.nodes, "{}", line)?;
+        }
+        return Ok(());
+    }
+
+    /// Write a relationship unless it was already written
+    fn write_relationship(&mut self, start: &str, end: &str, kind: &str) -> io::Result<()> {
+        let line = format!("{},{},{}", start, end, kind);
+        if self.lines.insert(line.clone()) {
+            writeln!(self.relationships, "{}", line)?;
+        }
+        return Ok(());
+    }
+}
+
+impl<N: Write, R: Write> Exporter for Neo4jAdminExporter<N, R> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        self.start()?;
+        let (nodes, edges) = graph_elements(uvci);
+        for (id, name, label) in nodes {
+            self.write_node(&id, &name, label)?;
+        }
+        for (start, end, kind) in edges {
+            self.write_relationship(&start, &end, kind)?;
+        }
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.nodes.flush()?;
+        return self.relationships.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.start()?;
+        return self.flush();
+    }
+}
+
+/// Header of the node attribute file of `EdgeListExporter`
+const EDGE_LIST_NODES_HEADER: &[u8] = b"id,label,name\n";
+
+/// Exporter writing the graph of `uvcis_to_graph` as a plain edge list and a node attribute CSV file,
+/// to analyze the graph with NetworkX or igraph without any graph database
+///
+/// Only for Sweden EHM-issued COVID certificates, duplicate nodes and edges are skipped. Every edge is a
+/// line of the start and end node ids and the edge type separated by spaces, e.g.
+/// "EHM V12916227 ISSUER_OF", read with `nx.read_edgelist("graph.edgelist", data=[("type", str)],
+/// create_using=nx.DiGraph)`. The node ids are the variable names of the Cypher graph, the node file has
+/// their label and name.
+pub struct EdgeListExporter<E: Write, N: Write> {
+    edges: E,
+    nodes: N,
+    lines: HashSet<String>,
+    edge_types: bool,
+    started: bool,
+}
+
+impl<E: Write, N: Write> EdgeListExporter<E, N> {
+    /// Create an exporter writing the edges and the node attributes to separate files
+    ///
+    /// # Arguments
+    ///
+    /// * `edges` - the edge list output, e.g. "graph.edgelist"
+    /// * `nodes` - the node attribute CSV output, e.g. "graph.nodes.csv"
+    pub fn new(edges: E, nodes: N) -> EdgeListExporter<E, N> {
+        return EdgeListExporter {
+            edges,
+            nodes,
+            lines: HashSet::new(),
+            edge_types: true,
+            started: false,
+        };
+    }
+
+    /// Write the edge type as third column, or only the two node ids of every edge
+    pub fn edge_types(mut self, edge_types: bool) -> EdgeListExporter<E, N> {
+        self.edge_types = edge_types;
+        return self;
+    }
+}
+
+impl<E: Write, N: Write> Exporter for EdgeListExporter<E, N> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        if !self.started {
+            self.nodes.write_all(EDGE_LIST_NODES_HEADER)?;
+            self.started = true;
+        }
+        let (nodes, edges) = graph_elements(uvci);
+        for (id, name, label) in nodes {
+            let line = format!("{},{},\"{}\"", id, label, name.replace('"', "\"\""));
+            if self.lines.insert(line.clone()) {
+                writeln!(self.nodes, "{}", line)?;
+            }
+        }
+        for (start, end, kind) in edges {
+            let line = format!("{} {} {}", start, end, kind);
+            if self.lines.insert(line.clone()) {
+                if self.edge_types {
+                    writeln!(self.edges, "{}", line)?;
+                } else {
+                    writeln!(self.edges, "{} {}", start, end)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.edges.flush()?;
+        return self.nodes.flush();
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if !self.started {
+            self.nodes.write_all(EDGE_LIST_NODES_HEADER)?;
+        }
+        return self.flush();
+    }
+}
+
+/// Order of the UVCIs of a `SortingExporter`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    /// Chronologically by the estimated vaccination month, UVCIs without one last
+    Date,
+    /// By country code, UVCIs without a valid one last
+    Country,
+    /// By issuing entity, UVCIs without one last
+    Issuer,
+}
+
+impl SortKey {
+    /// The sort key of its command line code, i.e. "date", "country" or "issuer"
+    pub fn from_code(code: &str) -> Option<SortKey> {
+        match code {
+            "date" => Some(SortKey::Date),
+            "country" => Some(SortKey::Country),
+            "issuer" => Some(SortKey::Issuer),
+            _ => None,
+        }
+    }
+}
+
+/// Exporter buffering the UVCIs to export them sorted to another exporter when finished
+///
+/// The sort is stable, UVCIs with the same key keep their input order. All UVCIs are held in memory
+/// until the export is finished.
+pub struct SortingExporter<E: Exporter> {
+    exporter: E,
+    key: SortKey,
+    source: Option<(String, usize)>,
+    uvcis: Vec<(Uvci, Option<(String, usize)>)>,
+}
+
+impl<E: Exporter> SortingExporter<E> {
+    pub fn new(exporter: E, key: SortKey) -> SortingExporter<E> {
+        return SortingExporter {
+            exporter,
+            key,
+            source: None,
+            uvcis: Vec::new(),
+        };
+    }
+}
+
+impl<E: Exporter> Exporter for SortingExporter<E> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        self.uvcis.push((uvci.clone(), self.source.clone()));
+        return Ok(());
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.source = Some((file.to_string(), line));
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        // Unknown values sort last, as None sorts before Some
+        match self.key {
+            SortKey::Date => self.uvcis.sort_by_key(|(uvci, _)| {
+                (uvci.opaque_vaccination.is_none(), uvci.opaque_vaccination)
+            }),
+            SortKey::Country => self
+                .uvcis
+                .sort_by_key(|(uvci, _)| (uvci.country.is_none(), uvci.country)),
+            SortKey::Issuer => self.uvcis.sort_by(|(a, _), (b, _)| {
+                (a.issuing_entity.is_none(), &a.issuing_entity)
+                    .cmp(&(b.issuing_entity.is_none(), &b.issuing_entity))
+            }),
+        }
+        for (uvci, source) in &self.uvcis {
+            if let Some((file, line)) = source {
+                self.exporter.set_source(file, *line);
+            }
+            self.exporter.export(uvci)?;
+        }
+        return self.exporter.finish();
+    }
+}
+
+/// Redaction of the opaque identifier fields of exported UVCIs, i.e. the opaque unique string, opaque id
+/// and opaque issuance
+///
+/// The country, issuing entity, vaccine, estimated vaccination month and checksum are kept, so redacted
+/// output can still be analyzed but no longer identifies a vaccination.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Redaction {
+    /// The fields are exported as is
+    None,
+    /// Every character of the fields is replaced by 'X', keeping their length
+    Mask,
+    /// The fields are replaced by "H" and the first 64 bits of their SHA-256 in uppercase hex, so equal
+    /// values stay equal, e.g. for counting reissues of the same opaque id, and graph variable names
+    /// stay valid
+    Hash,
+}
+
+impl Default for Redaction {
+    fn default() -> Redaction {
+        return Redaction::None;
+    }
+}
+
+impl Redaction {
+    /// The redaction of its command line code, i.e. "none", "mask" or "hash"
+    pub fn from_code(code: &str) -> Option<Redaction> {
+        match code {
+            "none" => Some(Redaction::None),
+            "mask" => Some(Redaction::Mask),
+            "hash" => Some(Redaction::Hash),
+            _ => None,
+        }
+    }
+
+    /// The UVCI with its opaque identifier fields redacted
+    ///
+    /// The UVCI as given is replaced by the UVCI rebuilt from the redacted fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    pub fn redact(&self, uvci: &Uvci) -> Uvci {
+        let mut redacted = uvci.clone();
+        if *self == Redaction::None {
+            return redacted;
+        }
+        redacted.opaque_unique_string = self.redact_field(&uvci.opaque_unique_string);
+        redacted.opaque_id = uvci.opaque_id.as_deref().map(|id| self.redact_field(id));
+        redacted.opaque_issuance = uvci
+            .opaque_issuance
+            .as_deref()
+            .map(|issuance| self.redact_field(issuance));
+        redacted.cert_id = redacted.rebuild();
+        return redacted;
+    }
+
+    fn redact_field(&self, value: &str) -> String {
+        if value.is_empty() {
+            return "".to_string();
+        }
+        match self {
+            Redaction::None => value.to_string(),
+            Redaction::Mask => "X".repeat(value.chars().count()),
+            Redaction::Hash => {
+                let digest = Sha256::digest(value.as_bytes());
+                let hex: String = digest[..8].iter().map(|b| format!("{:02X}", b)).collect();
+                format!("H{}", hex)
+            }
+        }
+    }
+}
+
+/// Exporter redacting the opaque identifier fields of the UVCIs before exporting them to another exporter
+///
+/// Wraps any exporter, so one `Redaction` makes every output format safe to share.
+pub struct RedactingExporter<E: Exporter> {
+    exporter: E,
+    redaction: Redaction,
+}
+
+impl<E: Exporter> RedactingExporter<E> {
+    pub fn new(exporter: E, redaction: Redaction) -> RedactingExporter<E> {
+        return RedactingExporter {
+            exporter,
+            redaction,
+        };
+    }
+}
+
+impl<E: Exporter> Exporter for RedactingExporter<E> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        return self.exporter.export(&self.redaction.redact(uvci));
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.exporter.set_source(file, line);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.exporter.flush();
+    }
+
+    fn finish(self) -> io::Result<()> {
+        return self.exporter.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        export_all, export_all_logged, export_all_sourced, ApocIterateExporter, CsvExporter,
+        CypherExporter, CypherShellExporter, DateFormat, DotExporter, EdgeListExporter,
+        ExportOptions, Exporter, JsonlExporter, Neo4jAdminExporter, RedactingExporter, Redaction,
+        SortKey, SortingExporter, SplunkHecExporter,
+    };
+    use crate::reject::RejectLog;
+    use crate::{parse, parse_sourced, uvcis_to_csv, uvcis_to_graph};
+
+    fn cert_ids() -> Vec<String> {
+        return vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+    }
+
+    #[test]
+    fn export_date_format() {
+        let august = Some(crate::YearMonth {
+            year: 2021,
+            month: 8,
+        });
+        assert!(DateFormat::IsoMonth.render(august) == "2021-08");
+        assert!(DateFormat::IsoWeek.render(august) == "2021-W30");
+        assert!(DateFormat::from_code("month-name").unwrap().render(august) == "Aug 2021");
+        assert!(
+            DateFormat::from_code("month-name:de")
+                .unwrap()
+                .render(august)
+                == "Aug 2021"
+        );
+        assert!(DateFormat::from_code("month-name:xx").is_none());
+        let january = Some(crate::YearMonth {
+            year: 2021,
+            month: 1,
+        });
+        assert!(DateFormat::IsoWeek.render(january) == "2020-W53");
+
+        let options = ExportOptions::new().date_format(DateFormat::IsoMonth);
+        let mut csv = Vec::new();
+        export_all(
+            &cert_ids(),
+            CsvExporter::new(&mut csv).options(options.clone()),
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.lines().next().unwrap().ends_with(",2021-08,E,true"));
+        assert!(csv.lines().all(|line| line.split(',').count() == 12));
+
+        let mut jsonl = Vec::new();
+        export_all(&cert_ids(), JsonlExporter::new(&mut jsonl).options(options)).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(String::from_utf8(jsonl).unwrap().lines().next().unwrap())
+                .unwrap();
+        assert!(first["opaque_vaccination"] == "2021-08");
+        assert!(first.get("opaque_vaccination_month").is_none());
+    }
+
+    #[test]
+    fn exporters_match_batch_exports() {
+        let mut csv = Vec::new();
+        export_all(&cert_ids(), CsvExporter::new(&mut csv)).unwrap();
+        assert!(String::from_utf8(csv).unwrap() == uvcis_to_csv(&cert_ids()));
+
+        let mut graph = Vec::new();
+        export_all(&cert_ids(), CypherExporter::new(&mut graph)).unwrap();
+        assert!(String::from_utf8(graph).unwrap() == uvcis_to_graph(&cert_ids()));
+
+        let mut jsonl = Vec::new();
+        export_all(&cert_ids(), JsonlExporter::new(&mut jsonl)).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        assert!(jsonl.lines().count() == 4);
+        let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert!(first["opaque_id"] == "V12907267");
+    }
+
+    #[test]
+    fn sorted_export() {
+        let mut cert_ids = cert_ids();
+        cert_ids.insert(0, "URN:UVCI:01:DE:X/Y/Z".to_string());
+        let mut csv = Vec::new();
+        export_all(
+            &cert_ids,
+            SortingExporter::new(CsvExporter::new(&mut csv), SortKey::Date),
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let sorted: Vec<_> = csv.lines().collect();
+        assert!(sorted.len() == 5);
+        // The dated Swedish UVCIs first, in chronological order, then the others in input order
+        let dates: Vec<_> = sorted
+            .iter()
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                (fields[10].to_string(), fields[9].parse::<u8>().unwrap())
+            })
+            .collect();
+        assert!(dates[0] <= dates[1] && dates[1] == dates[2] && dates[0].1 != 0);
+        assert!(sorted[3].starts_with("1,DE,") && sorted[4].contains("C878"));
+        assert!(SortKey::from_code("issuer") == Some(SortKey::Issuer));
+    }
+
+    #[test]
+    fn redacted_export() {
+        let cert_ids = vec!["URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string()];
+        let mut csv = Vec::new();
+        export_all(
+            &cert_ids,
+            RedactingExporter::new(CsvExporter::new(&mut csv), Redaction::Mask),
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains(",EHM,,XXXXXXXXXXXXX,XXXXXXXXX,XXXX,8,2021,Q,true"));
+
+        let mut jsonl = Vec::new();
+        let mut exporter = RedactingExporter::new(JsonlExporter::new(&mut jsonl), Redaction::Hash);
+        for _ in 0..2 {
+            exporter.export(&parse(&cert_ids[0])).unwrap();
+        }
+        exporter.finish().unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let opaque_id = lines[0]["opaque_id"].as_str().unwrap();
+        assert!(opaque_id.len() == 17 && opaque_id.starts_with('H'));
+        assert!(lines[1]["opaque_id"] == opaque_id);
+        assert!(!lines[0].to_string().contains("V12916227"));
+
+        let mut graph = Vec::new();
+        export_all(
+            &cert_ids,
+            RedactingExporter::new(CypherExporter::new(&mut graph), Redaction::Hash),
+        )
+        .unwrap();
+        assert!(!String::from_utf8(graph).unwrap().contains("TFJJ"));
+        assert!(Redaction::from_code("hash") == Some(Redaction::Hash));
+    }
+
+    #[test]
+    fn dot_export() {
+        let mut dot = Vec::new();
+        export_all(&cert_ids(), DotExporter::new(&mut dot)).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph uvci {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.matches("\"SE\" -> \"EHM\"").count() == 1);
+        assert!(dot.contains("\"V12916227TFJJ\" -> \"V12916227\" [label=\"REISSUE_OF\"];"));
+    }
+
+    #[test]
+    fn neo4j_admin_export() {
+        let mut nodes = Vec::new();
+        let mut relationships = Vec::new();
+        export_all(
+            &cert_ids(),
+            Neo4jAdminExporter::new(&mut nodes, &mut relationships),
+        )
+        .unwrap();
+        let nodes = String::from_utf8(nodes).unwrap();
+        let relationships = String::from_utf8(relationships).unwrap();
+        assert!(nodes.starts_with("id:ID,name,:LABEL\n"));
+        assert!(nodes.matches(",country\n").count() == 1);
+        assert!(nodes.contains("\nV12916227TFJJ,\"TFJJ\",reissue_id\n"));
+        assert!(nodes.contains("\nd20218,\"Aug 2021\",vac_date\n"));
+        assert!(relationships.starts_with(":START_ID,:END_ID,:TYPE\n"));
+        assert!(relationships.matches("SE,EHM,COUNTRY_OF").count() == 1);
+        assert!(relationships.contains("\nV12916227TFJJ,V12916227,REISSUE_OF\n"));
+    }
+
+    #[test]
+    fn edge_list_export() {
+        let mut edges = Vec::new();
+        let mut nodes = Vec::new();
+        export_all(&cert_ids(), EdgeListExporter::new(&mut edges, &mut nodes)).unwrap();
+        let edges = String::from_utf8(edges).unwrap();
+        let nodes = String::from_utf8(nodes).unwrap();
+        assert!(edges.starts_with("SE EHM COUNTRY_OF\n"));
+        assert!(edges.matches("SE EHM").count() == 1);
+        assert!(edges.contains("\nV12916227TFJJ V12916227 REISSUE_OF\n"));
+        assert!(nodes.starts_with("id,label,name\nSE,country,\"Sweden\"\n"));
+        assert!(nodes.contains("\nd20218,vac_date,\"Aug 2021\"\n"));
+
+        let mut edges = Vec::new();
+        export_all(
+            &cert_ids(),
+            EdgeListExporter::new(&mut edges, Vec::new()).edge_types(false),
+        )
+        .unwrap();
+        assert!(String::from_utf8(edges).unwrap().starts_with("SE EHM\n"));
+    }
+
+    #[test]
+    fn export_splunk_hec() {
+        let mut hec = Vec::new();
+        let mut exporter = SplunkHecExporter::new(&mut hec)
+            .sourcetype("uvci:audit")
+            .index("vaccination");
+        exporter.set_source("day1.txt", 7);
+        exporter
+            .export(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q"))
+            .unwrap();
+        exporter
+            .export(&parse("URN:UVCI:01:NL:187/37512422923"))
+            .unwrap();
+        exporter.finish().unwrap();
+        let events: Vec<serde_json::Value> = String::from_utf8(hec)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(events.len() == 2);
+        // 2021-08-01T00:00:00Z
+        assert!(events[0]["time"] == 1627776000.0);
+        assert!(events[0]["sourcetype"] == "uvci:audit" && events[0]["index"] == "vaccination");
+        assert!(events[0]["source"] == "day1.txt" && events[0]["event"]["source_line"] == 7);
+        assert!(events[0]["event"]["opaque_id"] == "V12916227");
+        assert!(events[0]["event"]["vaccination_date_estimated"] == true);
+        assert!(events[1]["time"].as_f64().unwrap() > 1627776000.0);
+        assert!(events[1]["event"]["vaccination_date_estimated"] == false);
+    }
+
+    #[test]
+    fn export_logged() {
+        let mut cert_ids = cert_ids();
+        cert_ids.insert(1, "URN:UVCI:01:SE".to_string());
+        let mut csv = Vec::new();
+        let mut rejects = RejectLog::new(Vec::new());
+        let result =
+            export_all_logged(&cert_ids, CsvExporter::new(&mut csv), &mut rejects).unwrap();
+        assert!(result.succeeded == 4);
+        assert!(result.processed == 5 && result.failed == 1);
+        assert!(String::from_utf8(csv).unwrap() == uvcis_to_csv(&self::cert_ids()));
+        let rejects = String::from_utf8(rejects.into_inner().unwrap()).unwrap();
+        let reject: serde_json::Value =
+            serde_json::from_str(rejects.lines().next().unwrap()).unwrap();
+        assert!(
+            reject["code"] == "E004" && reject["input"] == "URN:UVCI:01:SE" && reject["line"] == 2
+        );
+    }
+
+    #[test]
+    fn export_provenance() {
+        let mut csv = Vec::new();
+        let mut exporter = CsvExporter::new(&mut csv);
+        exporter.set_source("day1.txt", 7);
+        exporter
+            .export(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q"))
+            .unwrap();
+        exporter.finish().unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .ends_with(",Q,true,\"day1.txt\",7\n"));
+
+        let mut jsonl = Vec::new();
+        let mut exporter = JsonlExporter::new(&mut jsonl);
+        exporter.set_source("day2.txt", 3);
+        exporter
+            .export(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q"))
+            .unwrap();
+        exporter.finish().unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&jsonl).unwrap();
+        assert!(value["source_file"] == "day2.txt");
+        assert!(value["source_line"] == 3);
+
+        let sourced = parse_sourced(
+            "day3.txt",
+            &[
+                "".to_string(),
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            ],
+        );
+        let mut csv = Vec::new();
+        export_all_sourced(&sourced, CsvExporter::new(&mut csv)).unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .ends_with(",Q,true,\"day3.txt\",2\n"));
+
+        let mut csv = Vec::new();
+        let mut exporter = CsvExporter::new(&mut csv);
+        exporter.set_source("exports/2021,\"08\".txt", 1);
+        exporter
+            .export(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q"))
+            .unwrap();
+        exporter.finish().unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .ends_with(",Q,true,\"exports/2021,\"\"08\"\".txt\",1\n"));
+    }
+
+    #[test]
+    fn cypher_shell_chunks() {
+        let mut script = Vec::new();
+        export_all(&cert_ids(), CypherShellExporter::new(&mut script, 2)).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.matches(":begin\n").count() == 2);
+        assert!(script.matches(":commit\n").count() == 2);
+        assert!(script.matches(";\n").count() == 3);
+        assert!(script.ends_with(";\n:commit\n"));
+
+        let mut script = Vec::new();
+        let exporter = CypherShellExporter::new(&mut script, 2).constraints(true);
+        export_all(&cert_ids(), exporter).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.starts_with("CREATE CONSTRAINT country_name IF NOT EXISTS"));
+        assert!(script.matches("IS UNIQUE;\n").count() == 4);
+        assert!(script.find("ON (n.name);\n:begin\n").is_some());
+    }
+
+    #[test]
+    fn apoc_iterate() {
+        let mut script = Vec::new();
+        export_all(&cert_ids(), ApocIterateExporter::new(&mut script, 500)).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.starts_with("CALL apoc.periodic.iterate(\n"));
+        assert!(script.contains("{batchSize: 500, parallel: false, params: {rows: [\n"));
+        assert!(
+            script.contains("opaque_id: 'V12916227', vac_date: 'Aug 2021', reissue_id: 'TFJJ', checksum_valid: true, quality_score: 100, schema_option: 3, invalid: false}")
+        );
+        assert!(script.contains("SET r:invalid_certificate)',\n"));
+        assert!(script.matches("{country: 'Sweden'").count() == 3);
+        assert!(script.ends_with("}\n  ]}}\n);\n"));
+    }
+
+    #[test]
+    fn graph_native_dates() {
+        let options = ExportOptions::new().native_dates(true);
+        let mut script = Vec::new();
+        let exporter = CypherExporter::new(&mut script).options(options.clone());
+        export_all(&cert_ids(), exporter).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("schema_option:3, vaccination_date:date('2021-08-01')}"));
+
+        let mut script = Vec::new();
+        let exporter = CypherShellExporter::new(&mut script, 2)
+            .constraints(true)
+            .options(options.clone());
+        export_all(&cert_ids(), exporter).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("ON (n.vaccination_date);\n"));
+        assert!(script.contains("TFJJ.vaccination_date = date('2021-08-01')"));
+
+        let mut script = Vec::new();
+        let exporter = ApocIterateExporter::new(&mut script, 500).options(options);
+        export_all(&cert_ids(), exporter).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("SET r.vaccination_date = date(row.vaccination_date) "));
+        assert!(script.contains("invalid: false, vaccination_date: '2021-08-01'}"));
+    }
+}
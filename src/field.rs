@@ -0,0 +1,184 @@
+//! Generic access to the fields of a parsed UVCI, by field or by column name
+use crate::{or_empty, Uvci};
+use std::fmt;
+
+/// Field of a parsed UVCI, in the column order of the exports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UvciField {
+    /// The version of the UVCI schema
+    Version,
+    /// The ISO 3166-1 country code
+    Country,
+    /// The number of the schema option
+    SchemaOptionNumber,
+    /// The description of the schema option
+    SchemaOptionDesc,
+    /// The issuing entity
+    IssuingEntity,
+    /// The vaccine product identifier
+    VaccineId,
+    /// The opaque unique string
+    OpaqueUniqueString,
+    /// The opaque identifier of the vaccination
+    OpaqueId,
+    /// The opaque issuance of the vaccination
+    OpaqueIssuance,
+    /// The month of the vaccination
+    OpaqueVaccinationMonth,
+    /// The year of the vaccination
+    OpaqueVaccinationYear,
+    /// The checksum
+    Checksum,
+    /// Whether the checksum verifies
+    ChecksumVerification,
+}
+
+impl UvciField {
+    /// All the fields, in the column order of the exports
+    pub const ALL: [UvciField; 13] = [
+        UvciField::Version,
+        UvciField::Country,
+        UvciField::SchemaOptionNumber,
+        UvciField::SchemaOptionDesc,
+        UvciField::IssuingEntity,
+        UvciField::VaccineId,
+        UvciField::OpaqueUniqueString,
+        UvciField::OpaqueId,
+        UvciField::OpaqueIssuance,
+        UvciField::OpaqueVaccinationMonth,
+        UvciField::OpaqueVaccinationYear,
+        UvciField::Checksum,
+        UvciField::ChecksumVerification,
+    ];
+
+    /// The name of the field in the exports, e.g. "issuing_entity"
+    pub fn name(&self) -> &'static str {
+        match self {
+            UvciField::Version => "version",
+            UvciField::Country => "country",
+            UvciField::SchemaOptionNumber => "schema_option_number",
+            UvciField::SchemaOptionDesc => "schema_option_desc",
+            UvciField::IssuingEntity => "issuing_entity",
+            UvciField::VaccineId => "vaccine_id",
+            UvciField::OpaqueUniqueString => "opaque_unique_string",
+            UvciField::OpaqueId => "opaque_id",
+            UvciField::OpaqueIssuance => "opaque_issuance",
+            UvciField::OpaqueVaccinationMonth => "opaque_vaccination_month",
+            UvciField::OpaqueVaccinationYear => "opaque_vaccination_year",
+            UvciField::Checksum => "checksum",
+            UvciField::ChecksumVerification => "checksum_verification",
+        }
+    }
+
+    /// The field of an export name, e.g. "issuing_entity", None if there is no such field
+    pub fn from_name(name: &str) -> Option<UvciField> {
+        return UvciField::ALL
+            .iter()
+            .copied()
+            .find(|field| field.name() == name);
+    }
+}
+
+/// Display the name of the field in the exports
+impl fmt::Display for UvciField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Value of a field of a parsed UVCI, with the defaults of the exports for unknown values
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    /// A number, 0 if unknown, e.g. the version
+    Number(u16),
+    /// A string, empty if unknown, e.g. the issuing entity
+    Text(String),
+    /// A flag, e.g. the checksum verification
+    Bool(bool),
+}
+
+/// Display the value as written by the CSV export
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Number(number) => write!(f, "{}", number),
+            FieldValue::Text(text) => write!(f, "{}", text),
+            FieldValue::Bool(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+impl Uvci {
+    /// The names of the fields in the exports, in column order
+    pub const FIELD_NAMES: [&'static str; 13] = [
+        "version",
+        "country",
+        "schema_option_number",
+        "schema_option_desc",
+        "issuing_entity",
+        "vaccine_id",
+        "opaque_unique_string",
+        "opaque_id",
+        "opaque_issuance",
+        "opaque_vaccination_month",
+        "opaque_vaccination_year",
+        "checksum",
+        "checksum_verification",
+    ];
+
+    /// The value of a field, as exported
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - the field, e.g. `UvciField::IssuingEntity`
+    pub fn get(&self, field: UvciField) -> FieldValue {
+        let text = |value: &str| FieldValue::Text(value.to_string());
+        match field {
+            UvciField::Version => FieldValue::Number(self.version.unwrap_or(0) as u16),
+            UvciField::Country => text(self.country_str()),
+            UvciField::SchemaOptionNumber => FieldValue::Number(self.schema_option.number() as u16),
+            UvciField::SchemaOptionDesc => text(self.schema_option.desc()),
+            UvciField::IssuingEntity => text(or_empty(&self.issuing_entity)),
+            UvciField::VaccineId => text(or_empty(&self.vaccine_id)),
+            UvciField::OpaqueUniqueString => text(&self.opaque_unique_string),
+            UvciField::OpaqueId => text(or_empty(&self.opaque_id)),
+            UvciField::OpaqueIssuance => text(or_empty(&self.opaque_issuance)),
+            UvciField::OpaqueVaccinationMonth => {
+                FieldValue::Number(self.opaque_vaccination.map_or(0, |date| date.month as u16))
+            }
+            UvciField::OpaqueVaccinationYear => {
+                FieldValue::Number(self.opaque_vaccination.map_or(0, |date| date.year))
+            }
+            UvciField::Checksum => text(or_empty(&self.checksum)),
+            UvciField::ChecksumVerification => FieldValue::Bool(self.checksum_verification),
+        }
+    }
+
+    /// The (name, value) pairs of the fields, in column order
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, FieldValue)> + '_ {
+        return UvciField::ALL
+            .iter()
+            .map(move |field| (field.name(), self.get(*field)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FieldValue, UvciField};
+    use crate::{parse, Uvci};
+
+    #[test]
+    fn uvci_fields() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(uvci.get(UvciField::IssuingEntity) == FieldValue::Text("EHM".to_string()));
+        assert!(uvci.get(UvciField::OpaqueVaccinationYear) == FieldValue::Number(2021));
+        assert!(uvci.get(UvciField::ChecksumVerification) == FieldValue::Bool(true));
+        assert!(UvciField::from_name("opaque_id") == Some(UvciField::OpaqueId));
+        assert!(UvciField::from_name("cert_id").is_none());
+
+        let names: Vec<&str> = uvci.fields().map(|(name, _)| name).collect();
+        assert!(names == Uvci::FIELD_NAMES);
+        let values: Vec<String> = uvci.fields().map(|(_, value)| value.to_string()).collect();
+        assert!(values.join(",") == crate::uvci_to_csv("URN:UVCI:01:SE:EHM/V12907267LAJW#E"));
+    }
+}
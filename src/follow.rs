@@ -0,0 +1,200 @@
+//! Follow mode: UVCIs of the lines appended to a growing log file, like `tail -f`
+//!
+//! `LogFollower` returns the complete lines appended to a file since the last poll, starting over when
+//! the file is truncated or rotated. `extract_uvcis` is the free-text scanner finding the UVCIs in log
+//! lines, and `follow_record` the NDJSON object of a UVCI streamed by the follow mode of the executable,
+//! to monitor verification logs live for malformed or revoked identifiers.
+use crate::revocation::RevocationFilter;
+use crate::{diagnose, diagnostics_to_json, parse, to_json, Severity};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Find the UVCIs in a line of free text, e.g. a log line
+///
+/// A UVCI starts with the "URN:UVCI:" prefix, in any case, and runs over the letters, digits and
+/// separators ':', '/' and '#' following it. Trailing separators, e.g. the colon of "URN:UVCI:...:
+/// rejected", are not part of the UVCI.
+/// # Arguments
+///
+/// * `text` - the text to scan, e.g. "2021-08-03 12:00:01 verify URN:UVCI:01:SE:EHM/V12907267LAJW#E ok"
+pub fn extract_uvcis(text: &str) -> Vec<&str> {
+    let mut uvcis = Vec::new();
+    let upper = text.to_ascii_uppercase();
+    let mut start = 0;
+    while let Some(found) = upper[start..].find("URN:UVCI:") {
+        let begin = start + found;
+        let length = text[begin..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == ':' || c == '/' || c == '#'))
+            .unwrap_or(text.len() - begin);
+        let uvci = text[begin..begin + length].trim_end_matches(&[':', '/', '#'][..]);
+        if uvci.len() > "URN:UVCI:".len() {
+            uvcis.push(uvci);
+        }
+        start = begin + length.max(1);
+    }
+    return uvcis;
+}
+
+/// The NDJSON object of a UVCI found by the follow mode
+///
+/// The object has the line number, the UVCI as found, whether it is valid (no error diagnostic), the
+/// parsed fields of `uvci_to_json` and the diagnostics of `diagnose`, and with a revocation filter
+/// whether it is probably revoked.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `line` - the line number of the UVCI, see `LogFollower::line`
+/// * `filter` - the revocation filter to check the UVCI against, if any
+pub fn follow_record(
+    cert_id: &str,
+    line: usize,
+    filter: Option<&RevocationFilter>,
+) -> serde_json::Value {
+    let uvci = parse(cert_id);
+    let diagnostics = diagnose(cert_id);
+    let mut record = serde_json::json!({
+        "line": line,
+        "cert_id": cert_id,
+        "valid": !diagnostics.iter().any(|d| d.severity == Severity::Error),
+        "uvci": to_json(&uvci),
+        "diagnostics": diagnostics_to_json(&diagnostics),
+    });
+    if let Some(filter) = filter {
+        record["probably_revoked"] = serde_json::json!(filter.probably_revoked(&uvci));
+    }
+    return record;
+}
+
+/// Follower of a growing log file, returning the lines appended since the last poll
+pub struct LogFollower {
+    path: PathBuf,
+    file: Option<File>,
+    position: u64,
+    line: usize,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// Follow a file from its end, only the lines appended from now on are returned, like `tail -f`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the log file, e.g. "app.log"
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<LogFollower> {
+        let mut follower = LogFollower::from_start(path)?;
+        if let Some(file) = follower.file.as_mut() {
+            follower.position = file.seek(SeekFrom::End(0))?;
+        }
+        return Ok(follower);
+    }
+
+    /// Follow a file from its start, its current lines are returned by the first poll
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the log file, e.g. "app.log"
+    pub fn from_start<P: AsRef<Path>>(path: P) -> io::Result<LogFollower> {
+        return Ok(LogFollower {
+            path: path.as_ref().to_path_buf(),
+            file: Some(File::open(path)?),
+            position: 0,
+            line: 0,
+            partial: Vec::new(),
+        });
+    }
+
+    /// Number of the last line returned, counted from where the file is followed or was last truncated
+    pub fn line(&self) -> usize {
+        return self.line;
+    }
+
+    /// The complete lines appended since the last poll, with their line numbers
+    ///
+    /// A last line without line break is kept until it is complete. If the file is shorter than the
+    /// position read, it was truncated or rotated and is followed again from its start; while a rotated
+    /// file is missing, no lines are returned.
+    pub fn poll(&mut self) -> io::Result<Vec<(usize, String)>> {
+        let length = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => {
+                self.file = None;
+                return Ok(Vec::new());
+            }
+            Err(why) => return Err(why),
+        };
+        if self.file.is_none() || length < self.position {
+            self.file = Some(File::open(&self.path)?);
+            self.position = 0;
+            self.line = 0;
+            self.partial.clear();
+        }
+        let file = self.file.as_mut().unwrap();
+        file.seek(SeekFrom::Start(self.position))?;
+        let read = file.read_to_end(&mut self.partial)?;
+        self.position += read as u64;
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.partial.iter().position(|b| *b == b'\n') {
+            let bytes: Vec<u8> = self.partial.drain(..=end).collect();
+            self.line += 1;
+            let line = String::from_utf8_lossy(&bytes);
+            lines.push((
+                self.line,
+                line.trim_end_matches(&['\r', '\n'][..]).to_string(),
+            ));
+        }
+        return Ok(lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_uvcis, follow_record, LogFollower};
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    #[test]
+    fn follow_extract_uvcis() {
+        let line = "12:00:01 verify urn:uvci:01:se:ehm/v12907267lajw#e: ok, \
+                    retry URN:UVCI:01:NL:187/37512422923.";
+        assert!(
+            extract_uvcis(line)
+                == vec![
+                    "urn:uvci:01:se:ehm/v12907267lajw#e",
+                    "URN:UVCI:01:NL:187/37512422923"
+                ]
+        );
+        assert!(extract_uvcis("no identifier, URN:UVCI: only").is_empty());
+
+        let record = follow_record("URN:UVCI:01:SE:EHM/V12907267LAJW#X", 3, None);
+        assert!(record["line"] == 3 && record["valid"] == false);
+        assert!(record["diagnostics"][0]["code"] == "E009");
+        assert!(record.get("probably_revoked").is_none());
+    }
+
+    #[test]
+    fn follow_log_file() {
+        let path = std::env::temp_dir().join(format!("uvci_follow_{}.log", std::process::id()));
+        fs::write(&path, "old line\n").unwrap();
+        let mut follower = LogFollower::open(&path).unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        log.write_all(b"first\nsec").unwrap();
+        assert!(follower.poll().unwrap() == vec![(1, "first".to_string())]);
+        log.write_all(b"ond\r\n").unwrap();
+        assert!(follower.poll().unwrap() == vec![(2, "second".to_string())]);
+
+        // Truncated, e.g. by copytruncate log rotation
+        fs::write(&path, "new\n").unwrap();
+        assert!(follower.poll().unwrap() == vec![(1, "new".to_string())]);
+        fs::remove_file(&path).unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+
+        fs::write(&path, "a\nb\n").unwrap();
+        let mut follower = LogFollower::from_start(&path).unwrap();
+        assert!(follower.poll().unwrap().len() == 2 && follower.line() == 2);
+        fs::remove_file(&path).unwrap();
+    }
+}
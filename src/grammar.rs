@@ -0,0 +1,483 @@
+//! Formal grammar of the UVCI, after eHealth Network Guidelines on 'verifiable vaccination certificates -
+//! basic interoperability elements' - Release 2
+//!
+//! ```text
+//! uvci       = [ "URN:UVCI:" ] version ":" country ":" identifier [ "#" checksum ]
+//! version    = 2DIGIT
+//! country    = 2UPPER                        ; ISO 3166-1 alpha-2
+//! identifier = block [ "/" block ] / block "/" vaccine-id "/" block
+//! vaccine-id = block *( "-" block )              ; product code and lot, e.g. "C878-AB123"
+//! block      = 1*( UPPER / DIGIT )
+//! checksum   = UPPER / DIGIT / "/" / ":"   ; the alphabet of the LUHN-10 mod N checksum
+//! ```
+//!
+//! With the ISO 7064 MOD 37-2 checksum of some national schemes the check character is of its own
+//! alphabet instead, an uppercase letter, a digit or "*".
+//!
+//! There is at most one "#" and exactly one check character after it, anything trailing the check
+//! character is an error, as is a second "#".
+//!
+//! The number of blocks of the identifier is the schema option: three blocks for option 1, one for
+//! option 2 and two for option 3. The input is at most 72 characters long. If the identifier or the
+//! checksum does not parse, no block is kept: the raw input after the country is the remainder.
+use crate::CHECKSUM_ALPHABET;
+use nom::bytes::complete::{tag, take_while1, take_while_m_n};
+use nom::character::complete::char;
+use nom::multi::separated_list1;
+use nom::sequence::preceded;
+use nom::IResult;
+use std::fmt;
+
+/// Reason of a parse failure
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// The input is empty
+    Empty,
+    /// The input is longer than 72 characters
+    TooLong,
+    /// The version is not two digits
+    Version,
+    /// The country is not two uppercase letters
+    Country,
+    /// A ":" separator is missing
+    Separator,
+    /// A block of the identifier is empty or has characters other than uppercase letters and digits
+    Block,
+    /// The identifier has more than three slash-separated blocks, empty blocks included
+    TooManyBlocks,
+    /// The checksum is not a single uppercase letter or digit, e.g. characters trail the check character
+    Checksum,
+    /// The check character is not of the checksum alphabet, i.e. an uppercase letter, a digit, "/" or ":"
+    /// for LUHN-10 mod N
+    ChecksumCharacter,
+    /// There is more than one "#" separator, e.g. "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q#X"
+    MultipleChecksumSeparators,
+    /// A character that is not allowed anywhere in a UVCI, e.g. a lowercase letter or "_", or a "-"
+    /// outside the vaccine product identifier
+    InvalidCharacter,
+}
+
+/// Parse failure of a UVCI, with the byte offset and the offending substring of the problem in the input
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// Reason of the failure
+    pub kind: ParseErrorKind,
+    /// Byte offset of the problem in the input
+    pub offset: usize,
+    /// The offending substring at the offset, e.g. the malformed block or the invalid character, empty
+    /// if the input ends there
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.kind == ParseErrorKind::InvalidCharacter {
+            return write!(
+                f,
+                "invalid character '{}' at offset {}",
+                self.found, self.offset
+            );
+        }
+        let reason = match self.kind {
+            ParseErrorKind::Empty => "empty UVCI",
+            ParseErrorKind::TooLong => "UVCI longer than 72 characters",
+            ParseErrorKind::Version => "expected a two-digit version",
+            ParseErrorKind::Country => "expected an ISO 3166-1 alpha-2 country code",
+            ParseErrorKind::Separator => "expected ':'",
+            ParseErrorKind::Block => "expected a block of uppercase letters and digits",
+            ParseErrorKind::TooManyBlocks => "expected at most three blocks",
+            ParseErrorKind::Checksum => "expected a single checksum character",
+            ParseErrorKind::ChecksumCharacter => "expected a check character of the UVCI alphabet",
+            ParseErrorKind::MultipleChecksumSeparators => "expected a single '#' separator",
+            ParseErrorKind::InvalidCharacter => "invalid character",
+        };
+        if self.found.is_empty() {
+            write!(f, "{} at offset {}", reason, self.offset)
+        } else {
+            write!(
+                f,
+                "{} at offset {}, found '{}'",
+                reason, self.offset, self.found
+            )
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The syntactic parts of a UVCI, borrowed from the input
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Syntax<'a> {
+    pub(crate) version: Option<&'a str>,
+    pub(crate) country: Option<&'a str>,
+    pub(crate) blocks: Vec<&'a str>,
+    pub(crate) checksum: Option<&'a str>,
+    /// The raw identifier and checksum after the country if they don't parse, the blocks are then empty
+    pub(crate) remainder: Option<&'a str>,
+}
+
+fn is_block_char(c: char) -> bool {
+    return c.is_ascii_uppercase() || c.is_ascii_digit();
+}
+
+fn is_vaccine_id_char(c: char) -> bool {
+    return is_block_char(c) || c == '-';
+}
+
+fn version(input: &str) -> IResult<&str, &str> {
+    return take_while_m_n(2, 2, |c: char| c.is_ascii_digit())(input);
+}
+
+fn country(input: &str) -> IResult<&str, &str> {
+    return take_while_m_n(2, 2, |c: char| c.is_ascii_uppercase())(input);
+}
+
+fn identifier(input: &str) -> IResult<&str, Vec<&str>> {
+    return separated_list1(char('/'), take_while1(is_vaccine_id_char))(input);
+}
+
+fn checksum<'a>(input: &'a str, alphabet: &str) -> IResult<&'a str, &'a str> {
+    return preceded(
+        char('#'),
+        take_while_m_n(1, 1, |c: char| alphabet.contains(c)),
+    )(input);
+}
+
+/// Parse the syntactic parts of an uppercase UVCI
+///
+/// Returns the parts parsed up to the first error together with the error, so callers can still use
+/// the parts before it.
+pub(crate) fn parse_syntax(input: &str) -> (Syntax<'_>, Option<ParseError>) {
+    return parse_syntax_with(input, CHECKSUM_ALPHABET);
+}
+
+/// Parse the syntactic parts of an uppercase UVCI, with the check character of a checksum alphabet
+///
+/// # Arguments
+///
+/// * `input` - the uppercase UVCI, e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `alphabet` - the check characters of the checksum algorithm, see `ChecksumAlgorithm::alphabet`
+pub(crate) fn parse_syntax_with<'a>(
+    input: &'a str,
+    alphabet: &str,
+) -> (Syntax<'a>, Option<ParseError>) {
+    let mut syntax = Syntax::default();
+    if input.is_empty() {
+        return (syntax, Some(error(input, input, ParseErrorKind::Empty)));
+    }
+    if input.len() > 72 {
+        // The first character boundary from offset 72, as the input is not necessarily ASCII
+        let offset = (72..input.len())
+            .find(|offset| input.is_char_boundary(*offset))
+            .unwrap_or(72);
+        return (
+            syntax,
+            Some(error(input, &input[offset..], ParseErrorKind::TooLong)),
+        );
+    }
+
+    let rest =
+        tag::<_, _, nom::error::Error<&str>>("URN:UVCI:")(input).map_or(input, |(rest, _)| rest);
+    let (rest, version) = match version(rest) {
+        Ok(parsed) => parsed,
+        Err(_) => return (syntax, Some(error(input, rest, ParseErrorKind::Version))),
+    };
+    let rest = match separator(rest) {
+        Some(rest) => rest,
+        None => return (syntax, Some(error(input, rest, ParseErrorKind::Separator))),
+    };
+    syntax.version = Some(version);
+    let (rest, country) = match country(rest) {
+        Ok(parsed) => parsed,
+        Err(_) => return (syntax, Some(error(input, rest, ParseErrorKind::Country))),
+    };
+    let rest = match separator(rest) {
+        Some(rest) => rest,
+        None => return (syntax, Some(error(input, rest, ParseErrorKind::Separator))),
+    };
+    syntax.country = Some(country);
+    return match identifier_and_checksum(input, rest, alphabet) {
+        Ok((blocks, checksum)) => {
+            syntax.blocks = blocks;
+            syntax.checksum = checksum;
+            (syntax, None)
+        }
+        Err(error) => {
+            syntax.remainder = Some(rest);
+            (syntax, Some(error))
+        }
+    };
+}
+
+/// Parse the identifier and the checksum, the rest of the input after the country
+fn identifier_and_checksum<'a>(
+    input: &str,
+    rest: &'a str,
+    alphabet: &str,
+) -> Result<(Vec<&'a str>, Option<&'a str>), ParseError> {
+    // More than three blocks is no schema option, whatever the blocks are, e.g. "URN:UVCI:01:SE://////"
+    let identifier_start = rest;
+    if let Some((fourth, _)) = field(rest, "#").match_indices('/').nth(2) {
+        return Err(error(input, &rest[fourth..], ParseErrorKind::TooManyBlocks));
+    }
+    let (rest, blocks) = match identifier(rest) {
+        Ok(parsed) => parsed,
+        Err(_) => return Err(error(input, rest, ParseErrorKind::Block)),
+    };
+    // A "-" only separates the product code and lot of the vaccine id, the middle of three blocks
+    let mut block_offset = 0;
+    for (index, block) in blocks.iter().enumerate() {
+        let bytes = block.as_bytes();
+        let vaccine_id = blocks.len() == 3 && index == 1;
+        let dash = (0..bytes.len()).find(|i| {
+            bytes[*i] == b'-'
+                && !(vaccine_id && *i > 0 && *i + 1 < bytes.len() && bytes[*i - 1] != b'-')
+        });
+        if let Some(dash) = dash {
+            let dash = &identifier_start[block_offset + dash..];
+            return Err(error(input, dash, ParseErrorKind::Block));
+        }
+        block_offset += block.len() + 1;
+    }
+    if rest.is_empty() {
+        return Ok((blocks, None));
+    }
+    if let Some(second) = rest.strip_prefix('#').and_then(|after| after.find('#')) {
+        return Err(error(
+            input,
+            &rest[1 + second..],
+            ParseErrorKind::MultipleChecksumSeparators,
+        ));
+    }
+    return match checksum(rest, alphabet) {
+        Ok(("", checksum)) => Ok((blocks, Some(checksum))),
+        Ok((trailing, _)) => Err(error(input, trailing, ParseErrorKind::Checksum)),
+        Err(_) if rest == "#" => Err(error(input, &rest[1..], ParseErrorKind::Checksum)),
+        Err(_) if rest.starts_with('#') => {
+            Err(error(input, &rest[1..], ParseErrorKind::ChecksumCharacter))
+        }
+        Err(_) => Err(error(input, rest, ParseErrorKind::Block)),
+    };
+}
+
+/// The input after a ":" separator, None if there is none
+fn separator(input: &str) -> Option<&str> {
+    return char::<_, nom::error::Error<&str>>(':')(input)
+        .ok()
+        .map(|(rest, _)| rest);
+}
+
+/// Parse error at the start of the unparsed rest of the input
+///
+/// The offending substring is the field at the start of the rest, or the character if it is not
+/// allowed anywhere in a UVCI, in which case the error is an `InvalidCharacter` error.
+fn error(input: &str, rest: &str, kind: ParseErrorKind) -> ParseError {
+    let offset = input.len() - rest.len();
+    // Too long input and a check character outside the alphabet keep their own kind
+    let keeps_kind = kind == ParseErrorKind::TooLong || kind == ParseErrorKind::ChecksumCharacter;
+    if let Some(c) = rest.chars().next().filter(|_| !keeps_kind) {
+        if !(is_block_char(c) || ":/#".contains(c)) {
+            return ParseError {
+                kind: ParseErrorKind::InvalidCharacter,
+                offset,
+                found: c.to_string(),
+            };
+        }
+    }
+    let found = match kind {
+        ParseErrorKind::Version | ParseErrorKind::Country => field(rest, ":"),
+        ParseErrorKind::Block => field(rest, "/#"),
+        ParseErrorKind::TooManyBlocks => field(rest, "#"),
+        ParseErrorKind::Separator | ParseErrorKind::ChecksumCharacter => {
+            rest.chars().take(1).collect()
+        }
+        ParseErrorKind::Empty
+        | ParseErrorKind::TooLong
+        | ParseErrorKind::Checksum
+        | ParseErrorKind::MultipleChecksumSeparators
+        | ParseErrorKind::InvalidCharacter => rest.to_string(),
+    };
+    return ParseError {
+        kind,
+        offset,
+        found,
+    };
+}
+
+/// The start of the input up to the first of the terminators
+fn field(input: &str, terminators: &str) -> String {
+    return input
+        .chars()
+        .take_while(|c| !terminators.contains(*c))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_syntax, parse_syntax_with, ParseErrorKind};
+    use crate::ISO7064_MOD37_2_ALPHABET;
+
+    #[test]
+    fn valid_syntax() {
+        let (syntax, error) = parse_syntax("URN:UVCI:01:SE:EHM/C878/123456789ABC#B");
+        assert!(error.is_none());
+        assert!(syntax.version == Some("01") && syntax.country == Some("SE"));
+        assert!(syntax.blocks == vec!["EHM", "C878", "123456789ABC"]);
+        assert!(syntax.checksum == Some("B"));
+
+        let (syntax, error) = parse_syntax("01:NL:187/37512422923");
+        assert!(error.is_none());
+        assert!(syntax.blocks.len() == 2 && syntax.checksum.is_none());
+
+        // '/' and ':' are check characters of the LUHN-10 mod N alphabet
+        let (syntax, error) = parse_syntax("URN:UVCI:01:SE:EHM/V12907267LAJW#/");
+        assert!(error.is_none() && syntax.checksum == Some("/"));
+        // '*' is a check character of ISO 7064 MOD 37-2 only
+        let cert_id = "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD89E#*";
+        let (syntax, error) = parse_syntax_with(cert_id, ISO7064_MOD37_2_ALPHABET);
+        assert!(error.is_none() && syntax.checksum == Some("*"));
+        assert!(parse_syntax(cert_id).1.unwrap().kind == ParseErrorKind::ChecksumCharacter);
+
+        // The vaccine id of option 1 may encode product code and lot
+        let (syntax, error) = parse_syntax("URN:UVCI:01:SE:EHM/C878-AB123/123456789ABC");
+        assert!(error.is_none());
+        assert!(syntax.blocks == vec!["EHM", "C878-AB123", "123456789ABC"]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let cases = [
+            ("", ParseErrorKind::Empty, 0),
+            (
+                "URN:UVCI:1:SE:EHM/V12907267LAJW",
+                ParseErrorKind::Version,
+                9,
+            ),
+            (
+                "URN:UVCI:01:S3:EHM/V12907267LAJW",
+                ParseErrorKind::Country,
+                12,
+            ),
+            (
+                "URN:UVCI:01:SEX:EHM/V12907267LAJW",
+                ParseErrorKind::Separator,
+                14,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V1291_227TFJJ",
+                ParseErrorKind::InvalidCharacter,
+                24,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM//V12907267LAJW",
+                ParseErrorKind::Block,
+                18,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12907267-LAJW",
+                ParseErrorKind::InvalidCharacter,
+                28,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/C878-/123456789ABC",
+                ParseErrorKind::InvalidCharacter,
+                23,
+            ),
+            ("URN:UVCI:01:SE:A/B/C/D", ParseErrorKind::TooManyBlocks, 20),
+            (
+                "URN:UVCI:01:SE:A/B/C//#E",
+                ParseErrorKind::TooManyBlocks,
+                20,
+            ),
+            (
+                "URN:UVCI:01:SE://////////",
+                ParseErrorKind::TooManyBlocks,
+                17,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12907267LAJW#",
+                ParseErrorKind::Checksum,
+                33,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12907267LAJW#EE",
+                ParseErrorKind::Checksum,
+                34,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12907267LAJW#EEXTRA",
+                ParseErrorKind::Checksum,
+                34,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q#X",
+                ParseErrorKind::MultipleChecksumSeparators,
+                34,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#_",
+                ParseErrorKind::ChecksumCharacter,
+                33,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#Ä",
+                ParseErrorKind::ChecksumCharacter,
+                33,
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ##Q",
+                ParseErrorKind::MultipleChecksumSeparators,
+                33,
+            ),
+        ];
+        for (input, kind, offset) in &cases {
+            let error = parse_syntax(input).1.unwrap();
+            assert!(error.kind == *kind, "{}: {:?}", input, error);
+            assert!(error.offset == *offset, "{}: {:?}", input, error);
+        }
+        let (syntax, _) = parse_syntax("URN:UVCI:01:S3:EHM/V12907267LAJW");
+        assert!(syntax.version == Some("01") && syntax.country.is_none());
+        // No block is kept after an error in the identifier
+        let (syntax, _) = parse_syntax("URN:UVCI:01:SE:EHM/V12916227 TFJJ#Q");
+        assert!(syntax.blocks.is_empty() && syntax.checksum.is_none());
+        assert!(syntax.remainder == Some("EHM/V12916227 TFJJ#Q"));
+    }
+
+    #[test]
+    fn syntax_error_messages() {
+        let messages = [
+            (
+                "URN:UVCI:01:SE:EHM/V1291_227TFJJ",
+                "invalid character '_' at offset 24",
+            ),
+            (
+                "URN:UVCI:1:SE:EHM/V12907267LAJW",
+                "expected a two-digit version at offset 9, found '1'",
+            ),
+            (
+                "URN:UVCI:01:S3:EHM/V12907267LAJW",
+                "expected an ISO 3166-1 alpha-2 country code at offset 12, found 'S3'",
+            ),
+            (
+                "URN:UVCI:01:SE:A/B/C/D#E",
+                "expected at most three blocks at offset 20, found '/D'",
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12907267LAJW#",
+                "expected a single checksum character at offset 33",
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q#X",
+                "expected a single '#' separator at offset 34, found '#X'",
+            ),
+            (
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#*",
+                "expected a check character of the UVCI alphabet at offset 33, found '*'",
+            ),
+        ];
+        for (input, message) in &messages {
+            let error = parse_syntax(input).1.unwrap();
+            assert!(error.to_string() == *message, "{}: {}", input, error);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Parsing of the UVCIs of CBOR-encoded HCERT payloads
+//!
+//! A HCERT is the CWT of an EU Digital COVID Certificate QR code after Base45 decoding and
+//! decompression, signed as a COSE_Sign1 structure. `parse_hcert_cbor` reads the certificate from
+//! claim -260/1 without verifying the signature, see `cose::verify_hcert_cbor` for that.
+use crate::{parse_dcc_value, GroupType, Uvci};
+use ciborium::value::Value;
+
+/// CWT claim key of the HCERT (Health Certificate) claim
+const CLAIM_HCERT: i128 = -260;
+/// Key of the EU Digital COVID Certificate inside the HCERT claim
+const HCERT_EU_DCC: i128 = 1;
+/// CBOR tag of a COSE_Sign1 structure
+pub(crate) const TAG_COSE_SIGN1: u64 = 18;
+
+/// Parse all UVCIs of a CBOR-encoded HCERT payload
+///
+/// Accepts a COSE_Sign1 structure (tagged or untagged) or a bare CWT claims map, i.e. the QR payload
+/// after Base45 decoding and decompression. The EU Digital COVID Certificate is read from claim -260/1
+/// and the "ci" field of every "v", "r" and "t" entry is parsed.
+/// Returns an empty vector if the payload is not a valid HCERT.
+/// # Arguments
+///
+/// * `bytes` - the CBOR-encoded COSE_Sign1 or CWT
+pub fn parse_hcert_cbor(bytes: &[u8]) -> Vec<(GroupType, Uvci)> {
+    let value: Value = match ciborium::de::from_reader(bytes) {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+    let claims = match cwt_claims(value) {
+        Some(claims) => claims,
+        None => return Vec::new(),
+    };
+    let dcc = match hcert_dcc(&claims) {
+        Some(dcc) => dcc,
+        None => return Vec::new(),
+    };
+    return parse_dcc_value(&dcc);
+}
+
+/// Read the EU Digital COVID Certificate from claim -260/1 of a CWT claims map, converted to JSON
+pub(crate) fn hcert_dcc(claims: &Value) -> Option<serde_json::Value> {
+    let dcc = map_get(claims, CLAIM_HCERT).and_then(|hcert| map_get(hcert, HCERT_EU_DCC))?;
+    return Some(to_json(dcc));
+}
+
+/// Unwrap the CWT claims map from a COSE_Sign1 structure, or return the value itself if it is a map
+pub(crate) fn cwt_claims(value: Value) -> Option<Value> {
+    let value = match value {
+        Value::Tag(TAG_COSE_SIGN1, inner) => *inner,
+        value => value,
+    };
+    match value {
+        Value::Map(_) => Some(value),
+        Value::Array(items) if items.len() == 4 => match &items[2] {
+            Value::Bytes(payload) => ciborium::de::from_reader(&payload[..]).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Look up an integer key in a CBOR map
+fn map_get(map: &Value, key: i128) -> Option<&Value> {
+    let entries = map.as_map()?;
+    for (k, v) in entries {
+        if let Value::Integer(k) = k {
+            if i128::from(*k) == key {
+                return Some(v);
+            }
+        }
+    }
+    return None;
+}
+
+/// Convert a CBOR value to JSON, map keys that are not text are converted to their JSON representation
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Integer(i) => {
+            let i = i128::from(*i);
+            serde_json::Value::from(i as i64)
+        }
+        Value::Float(f) => serde_json::Value::from(*f),
+        Value::Text(s) => serde_json::Value::String(s.clone()),
+        Value::Bytes(b) => {
+            serde_json::Value::Array(b.iter().map(|x| serde_json::Value::from(*x)).collect())
+        }
+        Value::Tag(_, inner) => to_json(inner),
+        Value::Array(items) => serde_json::Value::Array(items.iter().map(to_json).collect()),
+        Value::Map(entries) => {
+            let mut map = serde_json::Map::new();
+            for (k, v) in entries {
+                let key = match k {
+                    Value::Text(s) => s.clone(),
+                    k => to_json(k).to_string(),
+                };
+                map.insert(key, to_json(v));
+            }
+            serde_json::Value::Object(map)
+        }
+        _ => serde_json::Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_hcert_cbor;
+    use crate::GroupType;
+    use ciborium::value::Value;
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(value, &mut bytes).unwrap();
+        return bytes;
+    }
+
+    #[test]
+    fn hcert_cose_sign1() {
+        let dcc = Value::Map(vec![(
+            Value::Text("v".to_string()),
+            Value::Array(vec![Value::Map(vec![(
+                Value::Text("ci".to_string()),
+                Value::Text("URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string()),
+            )])]),
+        )]);
+        let claims = Value::Map(vec![(
+            Value::Integer((-260).into()),
+            Value::Map(vec![(Value::Integer(1.into()), dcc)]),
+        )]);
+        let cose = Value::Tag(
+            18,
+            Box::new(Value::Array(vec![
+                Value::Bytes(vec![]),
+                Value::Map(vec![]),
+                Value::Bytes(encode(&claims)),
+                Value::Bytes(vec![0; 64]),
+            ])),
+        );
+
+        let uvcis = parse_hcert_cbor(&encode(&cose));
+        assert!(uvcis.len() == 1, "wrong number of UVCIs");
+        assert!(uvcis[0].0 == GroupType::Vaccination);
+        assert!(uvcis[0].1.opaque_id.as_deref() == Some("V12907267"));
+        assert!(
+            parse_hcert_cbor(&encode(&claims)).len() == 1,
+            "bare CWT not accepted"
+        );
+        assert!(parse_hcert_cbor(&[0xff, 0x00]).is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! Incremental Neo4j Cypher graph export, emitting only the nodes and edges not exported before
+use crate::export::Exporter;
+use crate::{graph_vac_date, or_empty, to_graph_merge, Uvci};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Keys of the nodes and edges of the Neo4j Cypher Graph already emitted, to export only what is new
+///
+/// A node key is the label and name of the node, e.g. "opaque_id:V12916227", an edge key the type and
+/// the names of its nodes, e.g. "ISSUER_OF:EHM:V12916227". With a state file, the keys are loaded from
+/// and saved to the file, one key per line, so nightly loads only emit the certificates of the fresh
+/// batch that add nodes or edges to the graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphState {
+    keys: BTreeSet<String>,
+    path: Option<PathBuf>,
+}
+
+impl GraphState {
+    /// Create an empty state without state file
+    pub fn new() -> GraphState {
+        return GraphState::default();
+    }
+
+    /// Create a state with a state file, loading the keys of the file if it exists
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the state file, one key per line
+    pub fn with_state_file<P: AsRef<Path>>(path: P) -> io::Result<GraphState> {
+        let mut keys = BTreeSet::new();
+        if path.as_ref().exists() {
+            for line in fs::read_to_string(&path)?.lines() {
+                if !line.is_empty() {
+                    keys.insert(line.to_string());
+                }
+            }
+        }
+        return Ok(GraphState {
+            keys,
+            path: Some(path.as_ref().to_path_buf()),
+        });
+    }
+
+    /// Add the keys of the nodes and edges of a UVCI, returns whether any of them is new
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    pub fn insert(&mut self, uvci: &Uvci) -> bool {
+        let mut new = false;
+        for key in graph_keys(uvci) {
+            new |= self.keys.insert(key);
+        }
+        return new;
+    }
+
+    /// Whether the graph has the node or edge
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the key of the node or edge, e.g. "opaque_id:V12916227"
+    pub fn contains(&self, key: &str) -> bool {
+        return self.keys.contains(key);
+    }
+
+    /// Number of nodes and edges of the graph
+    pub fn len(&self) -> usize {
+        return self.keys.len();
+    }
+
+    /// Whether no node was emitted yet
+    pub fn is_empty(&self) -> bool {
+        return self.keys.is_empty();
+    }
+
+    /// Atomically replace the state file with the keys of the graph, if there is one
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut output = "".to_string();
+        for key in &self.keys {
+            output.push_str(key);
+            output.push('\n');
+        }
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, output)?;
+        return fs::rename(&temp_path, path);
+    }
+}
+
+/// Keys of the nodes and edges of the graph of a UVCI, empty if it is not part of the graph
+fn graph_keys(uvci: &Uvci) -> Vec<String> {
+    if to_graph_merge(uvci).is_empty() {
+        return Vec::new();
+    }
+    let country = uvci.country_str();
+    let issuer = or_empty(&uvci.issuing_entity);
+    let opaque_id = or_empty(&uvci.opaque_id);
+    let (vac_date, _) = graph_vac_date(uvci);
+    let reissue = &uvci.opaque_unique_string;
+    return vec![
+        format!("country:{}", country),
+        format!("issuing_entity:{}", issuer),
+        format!("COUNTRY_OF:{}:{}", country, issuer),
+        format!("opaque_id:{}", opaque_id),
+        format!("ISSUER_OF:{}:{}", issuer, opaque_id),
+        format!("vac_date:{}", vac_date),
+        format!("VAC_DATE_OF:{}:{}", vac_date, opaque_id),
+        format!("reissue_id:{}", reissue),
+        format!("REISSUE_OF:{}:{}", reissue, opaque_id),
+    ];
+}
+
+/// Exporter passing only the UVCIs adding nodes or edges to the graph state to another exporter
+///
+/// The other exporter must write self-contained MERGE statements, e.g. `CypherShellExporter` or
+/// `ApocIterateExporter`, as the statements are run against the existing graph. The state file is not
+/// saved by the exporter, call `GraphState::save` once the export succeeded, so a failed export is
+/// emitted again by the next run.
+pub struct IncrementalGraphExporter<'a, E: Exporter> {
+    exporter: E,
+    state: &'a mut GraphState,
+    skipped: usize,
+}
+
+impl<'a, E: Exporter> IncrementalGraphExporter<'a, E> {
+    pub fn new(exporter: E, state: &'a mut GraphState) -> IncrementalGraphExporter<'a, E> {
+        return IncrementalGraphExporter {
+            exporter,
+            state,
+            skipped: 0,
+        };
+    }
+
+    /// Number of UVCIs skipped, already in the graph or not part of it
+    pub fn skipped(&self) -> usize {
+        return self.skipped;
+    }
+}
+
+impl<'a, E: Exporter> Exporter for IncrementalGraphExporter<'a, E> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        if !self.state.insert(uvci) {
+            self.skipped += 1;
+            return Ok(());
+        }
+        return self.exporter.export(uvci);
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.exporter.set_source(file, line);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.exporter.flush();
+    }
+
+    fn finish(self) -> io::Result<()> {
+        return self.exporter.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphState, IncrementalGraphExporter};
+    use crate::export::{export_all, CypherShellExporter};
+
+    #[test]
+    fn incremental_graph_export() {
+        let path =
+            std::env::temp_dir().join(format!("uvci_graph_state_{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let first = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+        ];
+        let mut state = GraphState::with_state_file(&path).unwrap();
+        let mut script = Vec::new();
+        export_all(
+            &first,
+            IncrementalGraphExporter::new(CypherShellExporter::new(&mut script, 10), &mut state),
+        )
+        .unwrap();
+        assert!(
+            String::from_utf8(script)
+                .unwrap()
+                .matches("MERGE (V12916227TFJJ:")
+                .count()
+                == 1
+        );
+        assert!(state.contains("ISSUER_OF:EHM:V12916227"));
+        assert!(state.len() == 9);
+        state.save().unwrap();
+
+        // The next batch only emits the new reissue
+        let second = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227LAJW".to_string(),
+        ];
+        let mut state = GraphState::with_state_file(&path).unwrap();
+        let mut script = Vec::new();
+        export_all(
+            &second,
+            IncrementalGraphExporter::new(CypherShellExporter::new(&mut script, 10), &mut state),
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("V12916227LAJW") && !script.contains("V12916227TFJJ"));
+        assert!(state.len() == 11);
+        let _ = std::fs::remove_file(&path);
+    }
+}
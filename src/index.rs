@@ -0,0 +1,232 @@
+//! Persistent index of the UVCIs seen by batch runs, in an embedded sled database
+use crate::{normalized_cert_id, parse, Uvci};
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Error of the UVCI index store
+#[derive(Debug)]
+pub enum IndexError {
+    /// The embedded database failed, e.g. to open or write the index
+    Store(sled::Error),
+    /// The stored entry of the UVCI is truncated or not UTF-8
+    InvalidEntry(String),
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IndexError::Store(e) => write!(f, "UVCI index store error: {}", e),
+            IndexError::InvalidEntry(key) => write!(f, "invalid UVCI index entry for {}", key),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+impl From<sled::Error> for IndexError {
+    fn from(e: sled::Error) -> IndexError {
+        return IndexError::Store(e);
+    }
+}
+
+/// Indexed UVCI with its first-seen metadata
+#[derive(Clone)]
+pub struct IndexEntry {
+    /// The parsed UVCI
+    pub uvci: Uvci,
+    /// The UVCI as first seen, before normalization
+    pub cert_id: String,
+    /// When the UVCI was first seen, in seconds since the Unix epoch
+    pub first_seen: u64,
+    /// Where the UVCI was first seen, e.g. the input file name
+    pub source: Option<String>,
+}
+
+/// Persistent index of the UVCIs seen by batch runs, in an embedded sled database
+///
+/// The key is the normalized UVCI as given (see `normalized_cert_id`), so the same identifier in
+/// lowercase or without the prefix is the same entry. Only the first sighting of a UVCI is stored, later insertions leave it unchanged.
+pub struct UvciIndex {
+    tree: sled::Db,
+}
+
+impl UvciIndex {
+    /// Open the index at a directory, creating it if it does not exist
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the directory of the sled database
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<UvciIndex, IndexError> {
+        return Ok(UvciIndex {
+            tree: sled::open(path)?,
+        });
+    }
+
+    /// Add a UVCI to the index, true if it was not seen before
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    /// * `source` - where the UVCI was seen, e.g. the input file name
+    pub fn insert(&self, cert_id: &str, source: Option<&str>) -> Result<bool, IndexError> {
+        let cert_id = cert_id.trim();
+        let key = normalized_cert_id(cert_id);
+        let first_seen = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let value = encode_entry(cert_id, first_seen, source);
+        let inserted = self
+            .tree
+            .compare_and_swap(key.as_bytes(), None as Option<&[u8]>, Some(value))?
+            .is_ok();
+        return Ok(inserted);
+    }
+
+    /// Add a vector of UVCIs to the index, returning the number of UVCIs not seen before
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+    /// * `source` - where the UVCIs were seen, e.g. the input file name
+    pub fn insert_all(
+        &self,
+        cert_ids: &[String],
+        source: Option<&str>,
+    ) -> Result<usize, IndexError> {
+        let mut inserted = 0;
+        for cert_id in cert_ids {
+            if self.insert(cert_id, source)? {
+                inserted += 1;
+            }
+        }
+        return Ok(inserted);
+    }
+
+    /// The indexed UVCI and its first-seen metadata, None if the UVCI was never seen
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn lookup(&self, cert_id: &str) -> Result<Option<IndexEntry>, IndexError> {
+        let key = normalized_cert_id(cert_id);
+        return match self.tree.get(key.as_bytes())? {
+            Some(value) => decode_entry(&value)
+                .map(Some)
+                .ok_or(IndexError::InvalidEntry(key)),
+            None => Ok(None),
+        };
+    }
+
+    /// Whether the UVCI was ever seen
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn contains(&self, cert_id: &str) -> Result<bool, IndexError> {
+        let key = normalized_cert_id(cert_id);
+        return Ok(self.tree.contains_key(key.as_bytes())?);
+    }
+
+    /// Number of UVCIs in the index
+    pub fn len(&self) -> usize {
+        return self.tree.len();
+    }
+
+    /// Whether the index is empty
+    pub fn is_empty(&self) -> bool {
+        return self.tree.is_empty();
+    }
+
+    /// Write the pending changes to disk, e.g. at the end of a batch run
+    pub fn flush(&self) -> Result<(), IndexError> {
+        self.tree.flush()?;
+        return Ok(());
+    }
+}
+
+/// Stored entry: the first-seen time (u64 big-endian), the UVCI as seen and, after a newline, the source
+fn encode_entry(cert_id: &str, first_seen: u64, source: Option<&str>) -> Vec<u8> {
+    let mut value = first_seen.to_be_bytes().to_vec();
+    value.extend_from_slice(cert_id.as_bytes());
+    if let Some(source) = source {
+        value.push(b'\n');
+        value.extend_from_slice(source.as_bytes());
+    }
+    return value;
+}
+
+fn decode_entry(value: &[u8]) -> Option<IndexEntry> {
+    if value.len() < 8 {
+        return None;
+    }
+    let mut first_seen = [0u8; 8];
+    first_seen.copy_from_slice(&value[..8]);
+    let text = std::str::from_utf8(&value[8..]).ok()?;
+    let (cert_id, source) = match text.split_once('\n') {
+        Some((cert_id, source)) => (cert_id, Some(source.to_string())),
+        None => (text, None),
+    };
+    return Some(IndexEntry {
+        uvci: parse(cert_id),
+        cert_id: cert_id.to_string(),
+        first_seen: u64::from_be_bytes(first_seen),
+        source,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UvciIndex;
+    use crate::{parse, to_csv};
+
+    #[test]
+    fn uvci_index() {
+        let path = std::env::temp_dir().join(format!("uvci_index_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        {
+            let index = UvciIndex::open(&path).unwrap();
+            assert!(index.is_empty());
+            assert!(index
+                .insert("URN:UVCI:01:SE:EHM/V12907267LAJW#E", Some("batch1.txt"))
+                .unwrap());
+            assert!(!index
+                .insert("urn:uvci:01:se:ehm/v12907267lajw#e", Some("batch2.txt"))
+                .unwrap());
+            let cert_ids = vec![
+                "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+                "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            ];
+            assert!(index.insert_all(&cert_ids, None).unwrap() == 1);
+            index.flush().unwrap();
+        }
+
+        // Repeated runs see the UVCIs of the previous ones
+        let index = UvciIndex::open(&path).unwrap();
+        assert!(index.len() == 2);
+        let entry = index.lookup("01:SE:EHM/V12907267LAJW#E").unwrap().unwrap();
+        assert!(entry.cert_id == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(to_csv(&entry.uvci) == to_csv(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")));
+        assert!(entry.source.as_deref() == Some("batch1.txt"));
+        assert!(entry.first_seen > 0);
+        let entry = index.lookup("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q").unwrap();
+        assert!(entry.unwrap().source.is_none());
+        assert!(index
+            .lookup("URN:UVCI:01:SE:EHM/C878/123456789ABC#B")
+            .unwrap()
+            .is_none());
+        assert!(!index
+            .contains("URN:UVCI:01:SE:EHM/C878/123456789ABC#B")
+            .unwrap());
+
+        // Malformed UVCIs that differ only in blocks the parser drops are distinct entries
+        assert!(index
+            .insert("URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W", None)
+            .unwrap());
+        assert!(index
+            .insert("URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T", None)
+            .unwrap());
+        drop(index);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
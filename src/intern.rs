@@ -0,0 +1,150 @@
+//! Parsing with interned strings, sharing the repeated field values of a batch
+use crate::{parse, split_vaccine_id, CountryCode, SchemaOption, Uvci, YearMonth};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Pool of shared strings, each distinct value is allocated once
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        return Interner::default();
+    }
+
+    /// The shared string of a value, allocated on first use
+    pub fn intern(&mut self, value: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(value) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(value);
+        self.strings.insert(interned.clone());
+        return interned;
+    }
+
+    /// The number of distinct strings in the pool
+    pub fn len(&self) -> usize {
+        return self.strings.len();
+    }
+
+    /// Whether the pool is empty
+    pub fn is_empty(&self) -> bool {
+        return self.strings.is_empty();
+    }
+
+    /// Parse a UVCI, sharing the repeated field values with the UVCIs parsed before
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn parse(&mut self, cert_id: &str) -> InternedUvci {
+        let mut uvci = parse(cert_id);
+        return InternedUvci {
+            version: uvci.version,
+            country: uvci.country,
+            schema_option: uvci.schema_option,
+            issuing_entity: uvci.issuing_entity.take().map(|value| self.intern(&value)),
+            vaccine_id: uvci.vaccine_id.take().map(|value| self.intern(&value)),
+            opaque_unique_string: std::mem::take(&mut uvci.opaque_unique_string),
+            opaque_id: uvci.opaque_id.take(),
+            opaque_issuance: uvci.opaque_issuance.take().map(|value| self.intern(&value)),
+            opaque_vaccination: uvci.opaque_vaccination,
+            checksum: uvci.checksum.take().map(|value| self.intern(&value)),
+            checksum_verification: uvci.checksum_verification,
+        };
+    }
+}
+
+/// EU Digital COVID Certificate UVCI data for batch processing, the fields repeating across UVCIs are
+/// shared strings of an `Interner`
+///
+/// The fields are those of `Uvci`. The issuing entity, vaccine id, opaque issuance and checksum take
+/// a few distinct values in national datasets, so they are shared instead of allocated per UVCI; the
+/// country code and schema option description are never allocated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InternedUvci {
+    pub version: Option<u8>,
+    pub country: Option<CountryCode>,
+    pub schema_option: SchemaOption,
+    pub issuing_entity: Option<Arc<str>>,
+    pub vaccine_id: Option<Arc<str>>,
+    pub opaque_unique_string: String,
+    pub opaque_id: Option<String>,
+    pub opaque_issuance: Option<Arc<str>>,
+    pub opaque_vaccination: Option<YearMonth>,
+    pub checksum: Option<Arc<str>>,
+    pub checksum_verification: bool,
+}
+
+impl InternedUvci {
+    /// The owned `Uvci`, e.g. to export it
+    ///
+    /// The UVCI as given is not kept, `cert_id` is the UVCI rebuilt from the fields.
+    pub fn to_uvci(&self) -> Uvci {
+        let owned = |value: &Option<Arc<str>>| value.as_deref().map(|value| value.to_string());
+        let vaccine_id = owned(&self.vaccine_id);
+        let (vaccine_product_code, vaccine_lot) = split_vaccine_id(&vaccine_id);
+        let mut uvci = Uvci {
+            cert_id: "".to_string(),
+            version: self.version,
+            country: self.country,
+            schema_option: self.schema_option,
+            issuing_entity: owned(&self.issuing_entity),
+            vaccine_id,
+            vaccine_product_code,
+            vaccine_lot,
+            opaque_unique_string: self.opaque_unique_string.clone(),
+            opaque_id: self.opaque_id.clone(),
+            opaque_issuance: owned(&self.opaque_issuance),
+            opaque_vaccination: self.opaque_vaccination,
+            checksum: owned(&self.checksum),
+            checksum_verification: self.checksum_verification,
+        };
+        uvci.cert_id = uvci.rebuild();
+        return uvci;
+    }
+}
+
+/// Parse a vector of EU Digital COVID Certificate UVCIs, sharing the repeated field values
+///
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn parse_interned(cert_ids: &[String]) -> Vec<InternedUvci> {
+    let mut interner = Interner::new();
+    return cert_ids
+        .iter()
+        .map(|cert_id| interner.parse(cert_id))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_interned, Interner};
+    use crate::{parse, to_csv};
+    use std::sync::Arc;
+
+    #[test]
+    fn interned_fields_are_shared() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+        let uvcis = parse_interned(&cert_ids);
+        let first = uvcis[0].issuing_entity.as_ref().unwrap();
+        let second = uvcis[1].issuing_entity.as_ref().unwrap();
+        assert!(Arc::ptr_eq(first, second));
+        for (uvci, cert_id) in uvcis.iter().zip(&cert_ids) {
+            assert!(to_csv(&uvci.to_uvci()) == to_csv(&parse(cert_id)));
+        }
+
+        let mut interner = Interner::new();
+        interner.parse(&cert_ids[0]);
+        interner.parse(&cert_ids[0]);
+        // EHM, LAJW and E
+        assert!(interner.len() == 3);
+    }
+}
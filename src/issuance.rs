@@ -0,0 +1,175 @@
+//! Issuance rate and gaps of schemes with sequential opaque IDs, e.g. Sweden's "V12907267"
+//!
+//! The numeric opaque IDs are sorted and laid over their estimated vaccination months: the highest ID of
+//! a month approximates the number of certificates issued by its end, so the difference between months
+//! is the number issued in between. Ranges of IDs missing from the UVCIs, e.g. a data export that lost a
+//! range, show up as gaps between consecutive IDs far wider than the usual spacing.
+use crate::{parse, YearMonth};
+use std::collections::BTreeMap;
+
+/// Estimated issuance of the certificates of a month
+#[derive(Clone, Debug, PartialEq)]
+pub struct IssuanceRatePoint {
+    /// The estimated vaccination month
+    pub month: YearMonth,
+    /// Number of distinct opaque IDs of the month, reissued certificates share the opaque ID
+    pub certificates: usize,
+    /// Lowest opaque ID of the month
+    pub first_id: u64,
+    /// Highest opaque ID up to the end of the month
+    pub last_id: u64,
+    /// Estimated certificates issued since the previous month with UVCIs
+    pub issued: u64,
+    /// Estimated certificates issued per day since the previous month with UVCIs
+    pub per_day: f64,
+}
+
+/// Range of consecutive opaque IDs without UVCI
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdGap {
+    /// The opaque ID before the gap
+    pub after: u64,
+    /// The opaque ID after the gap
+    pub before: u64,
+    /// Number of opaque IDs missing in between
+    pub missing: u64,
+    /// The estimated vaccination month of the opaque ID before the gap
+    pub after_month: YearMonth,
+    /// The estimated vaccination month of the opaque ID after the gap
+    pub before_month: YearMonth,
+}
+
+/// Issuance rate curve and gaps of a set of UVCIs, see `estimate_issuance_rate`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IssuanceReport {
+    /// Number of distinct numeric opaque IDs with an estimated vaccination month
+    pub ids: usize,
+    /// The issuance of every month with UVCIs, in month order
+    pub points: Vec<IssuanceRatePoint>,
+    /// The gaps of at least the minimum size, in opaque ID order
+    pub gaps: Vec<IdGap>,
+}
+
+/// Estimate the issuance rate by month and find the gaps of the sorted opaque IDs of UVCIs
+///
+/// Only UVCIs with a numeric opaque ID, after its leading letters, and an estimated vaccination month
+/// are used, so currently Sweden EHM-issued UVCIs. The months are estimates, see `dose_curve`.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `min_gap` - the minimum number of missing opaque IDs reported as gap, e.g. 100000 for a full export
+pub fn estimate_issuance_rate(cert_ids: &[String], min_gap: u64) -> IssuanceReport {
+    let mut ids: BTreeMap<u64, YearMonth> = BTreeMap::new();
+    for cert_id in cert_ids {
+        let uvci = parse(cert_id.trim());
+        let opaque_id = uvci
+            .opaque_id
+            .as_deref()
+            .map(|id| id.trim_start_matches(|c: char| c.is_ascii_alphabetic()))
+            .and_then(|id| id.parse::<u64>().ok());
+        if let (Some(opaque_id), Some(month)) = (opaque_id, uvci.opaque_vaccination) {
+            ids.insert(opaque_id, month);
+        }
+    }
+
+    let mut report = IssuanceReport {
+        ids: ids.len(),
+        ..IssuanceReport::default()
+    };
+    let mut months: BTreeMap<YearMonth, (usize, u64, u64)> = BTreeMap::new();
+    for (id, month) in &ids {
+        let entry = months.entry(*month).or_insert((0, *id, *id));
+        entry.0 += 1;
+        entry.2 = entry.2.max(*id);
+    }
+    let mut previous: Option<(YearMonth, u64)> = None;
+    for (month, (certificates, first_id, max_id)) in months {
+        // Months are estimates, so a lower ID may be dated later: the curve keeps the running maximum
+        let last_id = previous.map_or(max_id, |(_, last_id)| last_id.max(max_id));
+        let (issued, days) = match previous {
+            None => (last_id - first_id + 1, days_in_month(month)),
+            Some((previous_month, previous_id)) => {
+                let mut days = 0;
+                let mut day_month = previous_month.next();
+                while day_month <= month {
+                    days += days_in_month(day_month);
+                    day_month = day_month.next();
+                }
+                (last_id - previous_id, days)
+            }
+        };
+        report.points.push(IssuanceRatePoint {
+            month,
+            certificates,
+            first_id,
+            last_id,
+            issued,
+            per_day: issued as f64 / days as f64,
+        });
+        previous = Some((month, last_id));
+    }
+
+    let sorted: Vec<(&u64, &YearMonth)> = ids.iter().collect();
+    for pair in sorted.windows(2) {
+        let missing = pair[1].0 - pair[0].0 - 1;
+        if missing >= min_gap.max(1) {
+            report.gaps.push(IdGap {
+                after: *pair[0].0,
+                before: *pair[1].0,
+                missing,
+                after_month: *pair[0].1,
+                before_month: *pair[1].1,
+            });
+        }
+    }
+    return report;
+}
+
+/// Number of days of a month
+fn days_in_month(month: YearMonth) -> u32 {
+    return match month.month {
+        2 if month.year.is_multiple_of(4)
+            && (!month.year.is_multiple_of(100) || month.year.is_multiple_of(400)) =>
+        {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_issuance_rate;
+
+    #[test]
+    fn issuance_rate() {
+        let cert_ids: Vec<String> = include_str!("../examples/covid_uvci_33.txt")
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        let report = estimate_issuance_rate(&cert_ids, 1000000);
+        assert!(report.ids == 20);
+        assert!(report.points.iter().map(|p| p.certificates).sum::<usize>() == 20);
+        for pair in report.points.windows(2) {
+            assert!(pair[0].month < pair[1].month);
+            assert!(pair[0].last_id <= pair[1].last_id);
+            assert!(pair[1].issued == pair[1].last_id - pair[0].last_id);
+        }
+        assert!(report.points.iter().all(|p| p.per_day >= 0.0));
+
+        // A range missing from the middle of a dense export
+        let dense: Vec<String> = (12907000..12907100)
+            .filter(|n| !(12907040..12907060).contains(n))
+            .map(|n| format!("URN:UVCI:01:SE:EHM/V{}LAJW", n))
+            .collect();
+        let report = estimate_issuance_rate(&dense, 10);
+        assert!(report.ids == 80);
+        assert!(report.gaps.len() == 1);
+        let gap = &report.gaps[0];
+        assert!(gap.after == 12907039 && gap.before == 12907060 && gap.missing == 20);
+        assert!(report.points.len() == 1 && report.points[0].issued == 100);
+        assert!(estimate_issuance_rate(&[], 1).points.is_empty());
+    }
+}
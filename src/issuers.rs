@@ -0,0 +1,114 @@
+//! Curated dataset of the issuing entities of each country and their active periods
+use crate::{Uvci, YearMonth};
+
+/// The curated dataset of issuing entities observed in the UVCIs of each country
+const KNOWN_ISSUERS: &str = include_str!("../data/known_issuers.csv");
+
+/// Vaccination months certified by an issuing entity, open-ended where a bound is unknown
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActivePeriod {
+    /// The first vaccination month certified, None if unknown
+    pub from: Option<YearMonth>,
+    /// The last vaccination month certified, None if unknown or still active
+    pub to: Option<YearMonth>,
+}
+
+impl ActivePeriod {
+    /// Whether the month is within the period
+    pub fn contains(&self, month: YearMonth) -> bool {
+        return self.from.is_none_or(|from| from <= month) && self.to.is_none_or(|to| month <= to);
+    }
+}
+
+/// The issuing entities and their active periods of a country, from the dataset
+fn issuer_records(country: &str) -> impl Iterator<Item = (&'static str, ActivePeriod)> + '_ {
+    return KNOWN_ISSUERS
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(move |line| {
+            let mut fields = line.split(',').map(|field| field.trim());
+            match (fields.next(), fields.next()) {
+                (Some(c), Some(issuer)) if c.eq_ignore_ascii_case(country) => {
+                    let period = ActivePeriod {
+                        from: fields.next().and_then(YearMonth::from_code),
+                        to: fields.next().and_then(YearMonth::from_code),
+                    };
+                    Some((issuer, period))
+                }
+                _ => None,
+            }
+        });
+}
+
+/// The issuing entities observed in the UVCIs of a country, "" for opaque identifiers without issuing entity
+///
+/// # Arguments
+///
+/// * `country` - the ISO 3166-1 country code, e.g. "SE"
+pub fn known_issuers(country: &str) -> Vec<&'static str> {
+    return issuer_records(country).map(|(issuer, _)| issuer).collect();
+}
+
+/// The vaccination months certified by an issuing entity of a country, None if the issuer is not known
+///
+/// # Arguments
+///
+/// * `country` - the ISO 3166-1 country code, e.g. "SE"
+/// * `issuer` - the issuing entity, e.g. "EHM", "" for opaque identifiers without issuing entity
+pub fn issuer_active_period(country: &str, issuer: &str) -> Option<ActivePeriod> {
+    return issuer_records(country)
+        .find(|(known, _)| *known == issuer)
+        .map(|(_, period)| period);
+}
+
+impl Uvci {
+    /// Whether the issuing entity was observed in the UVCIs of the country
+    ///
+    /// False if the country never used the issuing entity, or if the country is not in the dataset.
+    pub fn issuer_known(&self) -> bool {
+        return known_issuers(self.country_str()).contains(&crate::or_empty(&self.issuing_entity));
+    }
+
+    /// Whether the estimated vaccination month is within the active period of the issuing entity
+    ///
+    /// True if the vaccination month is not decoded or the issuing entity is not known, e.g. false for
+    /// a Sweden EHM-issued UVCI implying a vaccination in 2026.
+    pub fn vaccination_in_issuer_period(&self) -> bool {
+        let month = match self.opaque_vaccination {
+            Some(month) => month,
+            None => return true,
+        };
+        return issuer_active_period(self.country_str(), crate::or_empty(&self.issuing_entity))
+            .is_none_or(|period| period.contains(month));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{issuer_active_period, known_issuers};
+    use crate::{parse, YearMonth};
+
+    #[test]
+    fn known_issuer() {
+        assert!(known_issuers("se") == vec!["EHM"]);
+        assert!(parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q").issuer_known());
+        assert!(!parse("URN:UVCI:01:SE:XYZ/V12916227TFJJ").issuer_known());
+        assert!(parse("URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#B").issuer_known());
+        assert!(!parse("URN:UVCI:01:ZZ:EHM/V12916227TFJJ").issuer_known());
+    }
+
+    #[test]
+    fn issuer_period() {
+        let period = issuer_active_period("SE", "EHM").unwrap();
+        assert!(period.from == YearMonth::from_code("2020-12"));
+        assert!(period.contains(YearMonth::from_code("2021-08").unwrap()));
+        assert!(!period.contains(YearMonth::from_code("2026-04").unwrap()));
+        assert!(issuer_active_period("NL", "187").unwrap().to.is_none());
+        assert!(issuer_active_period("SE", "XYZ").is_none());
+
+        assert!(parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q").vaccination_in_issuer_period());
+        // 99999999 doses imply a vaccination in April 2026
+        assert!(!parse("URN:UVCI:01:SE:EHM/V99999999TFJJ").vaccination_in_issuer_period());
+        assert!(parse("URN:UVCI:01:NL:187/37512422923").vaccination_in_issuer_period());
+    }
+}
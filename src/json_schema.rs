@@ -0,0 +1,72 @@
+//! JSON Schema of the JSON objects of parsed UVCIs
+use crate::Uvci;
+use schemars::schema::RootSchema;
+use schemars::JsonSchema;
+
+/// EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier) data, as exported by `uvci_to_json`
+///
+/// Only used to generate the JSON Schema, the export builds the JSON object directly.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(rename = "Uvci")]
+struct UvciJson {
+    /// Version of the UVCI schema, 0 for unknown
+    version: u8,
+    /// Country code is specified by ISO 3166-1, empty if not a valid code
+    country: String,
+    /// Number of the schema option, 0 for unknown
+    schema_option_number: u8,
+    /// Description of the schema option, empty for unknown
+    schema_option_desc: String,
+    /// The authority issuing the COVID certificate
+    issuing_entity: String,
+    /// Vaccine product identifier, vaccine/lot identifier(s) etc
+    vaccine_id: String,
+    /// The unique identifier of the vaccination in the national vaccination registry of the corresponding country
+    opaque_unique_string: String,
+    /// The unique opaque identifier of the vaccination in the national vaccination registry of the corresponding country
+    opaque_id: String,
+    /// The unique opaque issuance of the vaccination in the national vaccination registry of the corresponding country
+    opaque_issuance: String,
+    /// The opaque vaccination month of the vaccination, 0 for unknown
+    opaque_vaccination_month: u8,
+    /// The opaque vaccination year of the vaccination, 0 for unknown
+    opaque_vaccination_year: u16,
+    /// The ISO-7812-1 (LUHN-10) checksum used to verify the integrity of the UVCI
+    checksum: String,
+    /// Checksum verification. For successful verification the value is 'true', else 'false'
+    checksum_verification: bool,
+    /// The input file, only written by `JsonlExporter` when merging several input files
+    source_file: Option<String>,
+    /// The 1-based line of the input file, only written by `JsonlExporter` when merging several input files
+    source_line: Option<usize>,
+}
+
+impl Uvci {
+    /// JSON Schema of the JSON objects of `uvci_to_json`, `uvcis_to_json` and `JsonlExporter`
+    ///
+    /// Lets API teams publish and validate the exact shape of the parser's JSON output in their contracts,
+    /// e.g. `serde_json::to_string_pretty(&Uvci::json_schema())`.
+    pub fn json_schema() -> RootSchema {
+        return schemars::schema_for!(UvciJson);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{uvci_to_json, Uvci};
+
+    #[test]
+    fn json_schema_matches_export() {
+        let schema = serde_json::to_value(Uvci::json_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&uvci_to_json("URN:UVCI:01:SE:EHM/V12907267LAJW#E")).unwrap();
+        for key in json.as_object().unwrap().keys() {
+            assert!(properties.contains_key(key), "{} not in schema", key);
+        }
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.len() == json.as_object().unwrap().len());
+        assert!(schema["title"] == "Uvci");
+    }
+}
@@ -0,0 +1,117 @@
+//! Kafka producer sink publishing parsed UVCIs as JSON or Avro messages
+use crate::{normalized_cert_id, parse, to_json, Uvci};
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+use std::time::Duration;
+
+/// Message format of the Kafka sink
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KafkaMessageFormat {
+    /// The JSON object of `uvci_to_json`
+    Json,
+    /// An Apache Avro datum of `UVCI_AVRO_SCHEMA`, without schema or header
+    #[cfg(feature = "apache-avro")]
+    Avro,
+}
+
+/// Sink publishing parsed EU Digital COVID Certificate UVCIs to a Kafka topic
+///
+/// Every UVCI is published as one message, keyed by the normalized UVCI as given so that all messages of the
+/// same certificate land in the same partition.
+pub struct KafkaSink {
+    producer: BaseProducer,
+    topic: String,
+    format: KafkaMessageFormat,
+    #[cfg(feature = "apache-avro")]
+    schema: apache_avro::Schema,
+}
+
+impl KafkaSink {
+    /// Create a sink for a Kafka cluster
+    ///
+    /// # Arguments
+    ///
+    /// * `brokers` - the bootstrap servers, e.g. "localhost:9092"
+    /// * `topic` - the topic to publish to
+    /// * `format` - the message format
+    pub fn new(
+        brokers: &str,
+        topic: &str,
+        format: KafkaMessageFormat,
+    ) -> Result<KafkaSink, KafkaError> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        return KafkaSink::from_config(&config, topic, format);
+    }
+
+    /// Create a sink from a producer configuration, e.g. with security settings
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - the librdkafka producer configuration
+    /// * `topic` - the topic to publish to
+    /// * `format` - the message format
+    pub fn from_config(
+        config: &ClientConfig,
+        topic: &str,
+        format: KafkaMessageFormat,
+    ) -> Result<KafkaSink, KafkaError> {
+        return Ok(KafkaSink {
+            producer: config.create()?,
+            topic: topic.to_string(),
+            format,
+            #[cfg(feature = "apache-avro")]
+            schema: crate::avro::uvci_avro_schema(),
+        });
+    }
+
+    /// Publish a parsed UVCI, waiting for room in the producer queue if it is full
+    pub fn send(&self, uvci: &Uvci) -> Result<(), KafkaError> {
+        let key = normalized_cert_id(&uvci.cert_id);
+        let payload = self.payload(uvci);
+        let mut record = BaseRecord::to(&self.topic).key(&key).payload(&payload);
+        loop {
+            match self.producer.send(record) {
+                Ok(()) => break,
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), r)) => {
+                    record = r;
+                    self.producer.poll(Duration::from_millis(100));
+                }
+                Err((e, _)) => return Err(e),
+            }
+        }
+        self.producer.poll(Duration::from_millis(0));
+        return Ok(());
+    }
+
+    /// Parse and publish a vector of UVCIs, returning the number of messages published
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+    pub fn send_all(&self, cert_ids: &[String]) -> Result<usize, KafkaError> {
+        for cert_id in cert_ids {
+            self.send(&parse(cert_id))?;
+        }
+        return Ok(cert_ids.len());
+    }
+
+    /// Wait until all published messages are delivered
+    pub fn flush(&self, timeout: Duration) -> Result<(), KafkaError> {
+        return self.producer.flush(timeout);
+    }
+
+    /// Encode the message payload of a parsed UVCI
+    fn payload(&self, uvci: &Uvci) -> Vec<u8> {
+        match self.format {
+            KafkaMessageFormat::Json => to_json(uvci).to_string().into_bytes(),
+            #[cfg(feature = "apache-avro")]
+            KafkaMessageFormat::Avro => {
+                let value = crate::avro::to_avro(&self.schema, uvci.clone());
+                apache_avro::to_avro_datum(&self.schema, value)
+                    .expect("UVCI record does not match the UVCI Avro schema")
+            }
+        }
+    }
+}
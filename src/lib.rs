@@ -1,38 +1,361 @@
-use itertools::Itertools;
+// Explicit returns and single character string pushes are the style of this crate
+#![allow(clippy::needless_return, clippy::single_char_add_str)]
+
+use batch::BatchResult;
+use grammar::ParseError;
 use luhn::Luhn;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fmt;
+use std::io::{self, Write};
+use std::time::Instant;
+
+pub mod analysis;
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+#[cfg(feature = "tokio")]
+pub mod async_export;
+#[cfg(feature = "apache-avro")]
+pub mod avro;
+pub mod base45;
+pub mod batch;
+#[cfg(feature = "bson")]
+pub mod bson_export;
+pub mod compact;
+pub mod compression;
+pub mod conformance;
+#[cfg(feature = "cose")]
+pub mod cose;
+pub mod coverage;
+pub mod dedup;
+#[cfg(feature = "known-issuers")]
+pub mod deployments;
+pub mod diff;
+pub mod dose_curve;
+#[cfg(feature = "duckdb")]
+pub mod duckdb_export;
+pub mod edit;
+pub mod encryption;
+pub mod export;
+pub mod field;
+pub mod follow;
+pub mod grammar;
+#[cfg(feature = "cbor")]
+pub mod hcert;
+pub mod incremental;
+#[cfg(feature = "sled")]
+pub mod index;
+pub mod intern;
+pub mod issuance;
+#[cfg(feature = "known-issuers")]
+pub mod issuers;
+#[cfg(feature = "rdkafka")]
+pub mod kafka;
+pub mod manifest;
+pub mod merge;
+#[cfg(feature = "reqwest")]
+pub mod neo4j_http;
+pub mod pipeline;
+pub mod plausibility;
+#[cfg(feature = "polars")]
+pub mod polars_export;
+pub mod pseudonym;
+#[cfg(feature = "redis")]
+pub mod redis_cache;
+pub mod registry;
+pub mod reject;
+pub mod revocation;
+pub mod sampling;
+pub mod siem;
+#[cfg(feature = "plotters")]
+pub mod svg_chart;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+#[cfg(feature = "ratatui")]
+pub mod tui;
+pub mod urn;
+pub mod value_sets;
+pub mod verification;
+
+mod bloom;
+#[cfg(feature = "schemars")]
+mod json_schema;
+#[cfg(feature = "zeroize")]
+mod zeroize_on_drop;
 
 /// EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier) data.
 #[derive(Clone)]
 pub struct Uvci {
-    /// Version of the UVCI schema, the version is composed of two digits, 0 for unknown
-    pub version: u8,
-    /// Country code is specified by ISO 3166-1
-    pub country: String,
+    /// The UVCI as given without the surrounding whitespace, e.g. to hash the UVCI as issued
+    pub cert_id: String,
+    /// Version of the UVCI schema, the version is composed of two digits, None if not a number
+    pub version: Option<u8>,
+    /// Country code is specified by ISO 3166-1, None if the country block is not a valid code
+    pub country: Option<CountryCode>,
     /// EU member states can deploy different option in different version of the UVCI schema
-    pub schema_option_number: u8,
-    /// EU member states can deploy different option in different version of the UVCI schema, 0 for unknown
-    pub schema_option_desc: String,
+    pub schema_option: SchemaOption,
     /// The authority issuing the COVID certificate
-    pub issuing_entity: String,
+    pub issuing_entity: Option<String>,
     /// Vaccine product identifier, vaccine/lot identifier(s) etc
-    pub vaccine_id: String,
+    pub vaccine_id: Option<String>,
+    /// The vaccine product code of a vaccine product identifier encoding product and lot, e.g. "C878" of "C878-AB123"
+    pub vaccine_product_code: Option<String>,
+    /// The vaccine lot of a vaccine product identifier encoding product and lot, e.g. "AB123" of "C878-AB123"
+    pub vaccine_lot: Option<String>,
     /// The unique identifier of the vaccination in the national vaccination registry of the corresponding country
     pub opaque_unique_string: String,
     /// The unique opaque identifier of the vaccination in the national vaccination registry of the corresponding country
-    pub opaque_id: String,
+    pub opaque_id: Option<String>,
     /// The unique opaque issuance of the vaccination in the national vaccination registry of the corresponding country
-    pub opaque_issuance: String,
-    /// The opaque vaccination month of the vaccination in the national vaccination registry of the corresponding country
-    pub opaque_vaccination_month: u8,
-    /// The opaque vaccination year of the vaccination in the national vaccination registry of the corresponding country
-    pub opaque_vaccination_year: u16,
+    pub opaque_issuance: Option<String>,
+    /// The opaque vaccination year and month of the vaccination in the national vaccination registry of the corresponding country
+    pub opaque_vaccination: Option<YearMonth>,
     /// The ISO-7812-1 (LUHN-10) checksum used to verify the integrity of the UVCI
-    pub checksum: String,
+    pub checksum: Option<String>,
     /// Checksum verification. For successful verification the value is 'true', else 'false'
     pub checksum_verification: bool,
 }
 
+/// Year and month, e.g. of a vaccination
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct YearMonth {
+    /// The year, e.g. 2021
+    pub year: u16,
+    /// The month, from 1 to 12
+    pub month: u8,
+}
+
+impl YearMonth {
+    /// The month of an ISO 8601 code, e.g. "2021-08", None if it is not a valid month
+    pub fn from_code(code: &str) -> Option<YearMonth> {
+        let (year, month) = code.split_once('-')?;
+        let year = year.parse::<u16>().ok()?;
+        let month = month
+            .parse::<u8>()
+            .ok()
+            .filter(|month| (1..=12).contains(month))?;
+        return Some(YearMonth { year, month });
+    }
+
+    /// The number of days from 1970-01-01 to the first day of the month, e.g. 18840 for 2021-08, the
+    /// date value of Arrow and Polars
+    pub fn days_since_epoch(&self) -> i32 {
+        // Days from civil, counting years from March so that the leap day ends the year
+        let (year, march_month) = match self.month {
+            1 | 2 => (self.year as i32 - 1, self.month as i32 + 9),
+            _ => (self.year as i32, self.month as i32 - 3),
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * march_month + 2) / 5;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        return era * 146097 + day_of_era - 719468;
+    }
+
+    /// The following month, e.g. 2022-01 for 2021-12
+    pub fn next(&self) -> YearMonth {
+        if self.month >= 12 {
+            return YearMonth {
+                year: self.year + 1,
+                month: 1,
+            };
+        }
+        return YearMonth {
+            year: self.year,
+            month: self.month + 1,
+        };
+    }
+}
+
+/// Display the year and month as ISO 8601, e.g. "2021-08"
+impl fmt::Display for YearMonth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+/// The value of an optional string field, empty if None, as written by the exports
+pub(crate) fn or_empty(value: &Option<String>) -> &str {
+    return value.as_deref().unwrap_or("");
+}
+
+/// ISO 3166-1 alpha-2 country code of a UVCI, validated to be two uppercase ASCII letters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CountryCode([u8; 2]);
+
+impl CountryCode {
+    /// The country code of a code, None if it is not two uppercase ASCII letters
+    pub fn from_code(code: &str) -> Option<CountryCode> {
+        match code.as_bytes() {
+            [a, b] if a.is_ascii_uppercase() && b.is_ascii_uppercase() => {
+                Some(CountryCode([*a, *b]))
+            }
+            _ => None,
+        }
+    }
+
+    /// The country code, e.g. "SE"
+    pub fn as_str(&self) -> &str {
+        return std::str::from_utf8(&self.0).expect("country code is ASCII");
+    }
+
+    /// The country name in the value sets embedded in this crate, e.g. "Sweden"
+    pub fn name(&self) -> Option<&'static str> {
+        return value_sets::builtin_country_name(self.as_str());
+    }
+
+    /// The country name in the given value sets, e.g. loaded from the published "country-2-codes.json"
+    pub fn name_in<'a>(&self, value_sets: &'a value_sets::ValueSets) -> Option<&'a str> {
+        return value_sets.country_name(self.as_str());
+    }
+}
+
+impl PartialEq<str> for CountryCode {
+    fn eq(&self, other: &str) -> bool {
+        return self.as_str() == other;
+    }
+}
+
+impl PartialEq<&str> for CountryCode {
+    fn eq(&self, other: &&str) -> bool {
+        return self.as_str() == *other;
+    }
+}
+
+/// Display the country code, e.g. "SE"
+impl fmt::Display for CountryCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Schema option of a UVCI, EU member states can deploy different option in different version of the UVCI schema
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SchemaOption {
+    /// Option 1, issuing entity, vaccine identifier and opaque unique string
+    WithSemantics,
+    /// Option 2, opaque unique string only
+    OpaqueNoStructure,
+    /// Option 3, issuing entity and opaque unique string
+    SomeSemantics,
+    /// The identifier does not follow any schema option, e.g. it has more than three slash-separated blocks
+    ///
+    /// The blocks of such identifiers are not mapped to any field: `try_parse` rejects them with
+    /// `ParseErrorKind::TooManyBlocks` and `parse_lenient` reports it as a `Warning::Syntax`.
+    Unknown,
+}
+
+impl SchemaOption {
+    /// The number of the schema option, 0 for unknown
+    pub fn number(&self) -> u8 {
+        match self {
+            SchemaOption::WithSemantics => 1,
+            SchemaOption::OpaqueNoStructure => 2,
+            SchemaOption::SomeSemantics => 3,
+            SchemaOption::Unknown => 0,
+        }
+    }
+
+    /// The schema option of a number, unknown for any number but 1, 2 and 3
+    pub fn from_number(number: u8) -> SchemaOption {
+        match number {
+            1 => SchemaOption::WithSemantics,
+            2 => SchemaOption::OpaqueNoStructure,
+            3 => SchemaOption::SomeSemantics,
+            _ => SchemaOption::Unknown,
+        }
+    }
+
+    /// The description of the schema option, e.g. "some semantics", empty for unknown
+    pub fn desc(&self) -> &'static str {
+        match self {
+            SchemaOption::WithSemantics => "identifier with semantics",
+            SchemaOption::OpaqueNoStructure => "opaque identifier - no structure",
+            SchemaOption::SomeSemantics => "some semantics",
+            SchemaOption::Unknown => "",
+        }
+    }
+}
+
+/// Display the description of the schema option, "unknown" for unknown
+impl fmt::Display for SchemaOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaOption::Unknown => write!(f, "unknown"),
+            _ => write!(f, "{}", self.desc()),
+        }
+    }
+}
+
+impl Uvci {
+    /// The country code, e.g. "SE", empty if the country block is not a valid code
+    pub fn country_str(&self) -> &str {
+        return self.country.as_ref().map_or("", |country| country.as_str());
+    }
+
+    /// The number of the schema option, 0 for unknown
+    pub fn schema_option_number(&self) -> u8 {
+        return self.schema_option.number();
+    }
+
+    /// The description of the schema option, empty for unknown
+    pub fn schema_option_desc(&self) -> &'static str {
+        return self.schema_option.desc();
+    }
+
+    /// The canonical UVCI, i.e. uppercase with the prefix "URN:UVCI:" and, if present, the checksum
+    ///
+    /// The UVCI is rebuilt from the parsed fields, e.g. "01:SE:EHM/V12907267LAJW#E" becomes
+    /// "URN:UVCI:01:SE:EHM/V12907267LAJW#E". A UVCI without valid version, country or schema option
+    /// cannot be rebuilt, it is its `normalized_cert_id` instead, e.g. "URN:UVCI:01DE/IZ12345A/...#W".
+    pub fn canonical(&self) -> String {
+        if self.version.is_none()
+            || self.country.is_none()
+            || self.schema_option == SchemaOption::Unknown
+        {
+            return normalized_cert_id(&self.cert_id);
+        }
+        return self.rebuild();
+    }
+
+    /// The UVCI rebuilt from the parsed fields, whether or not the fields are valid
+    pub(crate) fn rebuild(&self) -> String {
+        let mut output = "URN:UVCI:".to_string();
+        output.push_str(&format!("{:02}", self.version.unwrap_or(0)));
+        output.push_str(":");
+        output.push_str(self.country_str());
+        output.push_str(":");
+        match self.schema_option {
+            SchemaOption::WithSemantics => {
+                output.push_str(or_empty(&self.issuing_entity));
+                output.push_str("/");
+                output.push_str(or_empty(&self.vaccine_id));
+                output.push_str("/");
+                output.push_str(&self.opaque_unique_string);
+            }
+            SchemaOption::SomeSemantics => {
+                output.push_str(or_empty(&self.issuing_entity));
+                output.push_str("/");
+                output.push_str(&self.opaque_unique_string);
+            }
+            _ => output.push_str(&self.opaque_unique_string),
+        }
+        if let Some(checksum) = &self.checksum {
+            output.push_str("#");
+            output.push_str(checksum);
+        }
+        return output;
+    }
+
+    /// The SHA-256 digest of the canonical UVCI, a stable join key with systems storing hashed UVCIs
+    ///
+    /// e.g. "urn:uvci:01:se:ehm/v12907267lajw#e" and "01:SE:EHM/V12907267LAJW#E" have the digest of
+    /// "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn canonical_digest(&self) -> [u8; 32] {
+        return Sha256::digest(self.canonical().as_bytes()).into();
+    }
+}
+
 /// Display the parsed EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier) data
 impl fmt::Display for Uvci {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -51,79 +374,687 @@ impl fmt::Display for Uvci {
             opaque_vaccination_year  : {}\n\
             checksum                 : {}\n\
             checksum_verification    : {}\n",
-            &self.version.to_string(),
-            &self.country,
-            &self.schema_option_number.to_string(),
-            &self.schema_option_desc,
-            &self.issuing_entity,
-            &self.vaccine_id,
+            &self.version.unwrap_or(0).to_string(),
+            self.country_str(),
+            &self.schema_option_number().to_string(),
+            &self.schema_option_desc(),
+            or_empty(&self.issuing_entity),
+            or_empty(&self.vaccine_id),
             &self.opaque_unique_string,
-            &self.opaque_id,
-            &self.opaque_issuance,
-            &self.opaque_vaccination_month,
-            &self.opaque_vaccination_year,
-            &self.checksum,
+            or_empty(&self.opaque_id),
+            or_empty(&self.opaque_issuance),
+            &self.opaque_vaccination.map_or(0, |date| date.month),
+            &self.opaque_vaccination.map_or(0, |date| date.year),
+            or_empty(&self.checksum),
             &self.checksum_verification
         )
     }
 }
 
+/// Parsed UVCI with the input file and line it was read from
+///
+/// Produced by `parse_sourced` and `compression::parse_file`, and exported with the provenance columns
+/// of the CSV and JSON Lines exporters by `export::export_all_sourced`, so any bad record of a merged
+/// output can be traced back to its origin.
+#[derive(Clone)]
+pub struct SourcedUvci {
+    /// The parsed UVCI
+    pub uvci: Uvci,
+    /// The input the UVCI was read from, e.g. the file name
+    pub source: String,
+    /// The line number in the input, from 1
+    pub line: usize,
+}
+
+/// Algorithm of the check character of a UVCI
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgorithm {
+    /// ISO-7812-1 (LUHN-10) mod N over the UVCI alphabet, as specified by the eHealth Network guidelines
+    LuhnModN,
+    /// ISO 7064 MOD 37-2, used by some national schemes
+    Iso7064Mod37_2,
+}
+
+impl Default for ChecksumAlgorithm {
+    fn default() -> ChecksumAlgorithm {
+        return ChecksumAlgorithm::LuhnModN;
+    }
+}
+
+impl ChecksumAlgorithm {
+    /// The checksum algorithm of a code, i.e. "luhn" or "iso7064-mod37-2"
+    pub fn from_code(code: &str) -> Option<ChecksumAlgorithm> {
+        match code {
+            "luhn" => Some(ChecksumAlgorithm::LuhnModN),
+            "iso7064-mod37-2" => Some(ChecksumAlgorithm::Iso7064Mod37_2),
+            _ => None,
+        }
+    }
+
+    /// The expected check character of a UVCI, None if it cannot be computed
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI, the checksum after '#' is ignored, e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn expected_checksum(&self, cert_id: &str) -> Option<char> {
+        match self {
+            ChecksumAlgorithm::LuhnModN => expected_checksum(cert_id),
+            ChecksumAlgorithm::Iso7064Mod37_2 => iso7064_mod37_2_checksum(cert_id),
+        }
+    }
+
+    /// The check characters of the algorithm
+    pub fn alphabet(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::LuhnModN => CHECKSUM_ALPHABET,
+            ChecksumAlgorithm::Iso7064Mod37_2 => ISO7064_MOD37_2_ALPHABET,
+        }
+    }
+
+    /// Whether the checksum of a UVCI is valid for the algorithm
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn verify(&self, cert_id: &str) -> bool {
+        let cert_id = cert_id.to_uppercase();
+        match self {
+            ChecksumAlgorithm::LuhnModN => luhn_valid(&cert_id),
+            ChecksumAlgorithm::Iso7064Mod37_2 => match cert_id.split_once('#') {
+                Some((_, checksum)) => iso7064_mod37_2_checksum(&cert_id)
+                    .is_some_and(|expected| checksum == expected.to_string()),
+                None => false,
+            },
+        }
+    }
+}
+
+/// Severity of a diagnostic reported for a UVCI
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// The UVCI is invalid
+    Error,
+    /// The UVCI can be parsed, but does not follow the guidelines
+    Warning,
+}
+
+/// Display the severity as used in the JSON diagnostics, i.e. "error" or "warning"
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Machine-readable diagnostic for an invalid EU Digital COVID Certificate UVCI, similar to compiler diagnostics
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Stable diagnostic code, e.g. "E009" for a checksum mismatch
+    pub code: String,
+    /// Human-readable description of the problem
+    pub message: String,
+    /// Severity of the problem
+    pub severity: Severity,
+    /// Byte offset of the problem in the input UVCI
+    pub offset: usize,
+    /// Suggested fix, if one can be derived
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(code: &str, severity: Severity, offset: usize, message: String) -> Diagnostic {
+        return Diagnostic {
+            code: code.to_string(),
+            message,
+            severity,
+            offset,
+            suggestion: None,
+        };
+    }
+
+    fn suggest(mut self, suggestion: String) -> Diagnostic {
+        self.suggestion = Some(suggestion);
+        return self;
+    }
+}
+
+/// Quality issue of a UVCI worked around by `parse_lenient`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// Lowercase characters were normalized to uppercase
+    LowercaseNormalized,
+    /// The prefix "URN:UVCI:" was missing and has been added to verify the checksum
+    MissingPrefix,
+    /// The UVCI has no checksum
+    MissingChecksum,
+    /// The checksum does not match
+    ChecksumMismatch,
+    /// The version is not 01, the only version of the guidelines
+    UnknownVersion(u8),
+    /// The UVCI does not follow the grammar, the parts after the error are missing
+    Syntax(ParseError),
+    /// The '#' separator is missing, but the last character is the check character of the rest
+    ChecksumDetectedWithoutSeparator(char),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::LowercaseNormalized => {
+                write!(f, "lowercase characters normalized to uppercase")
+            }
+            Warning::MissingPrefix => write!(f, "missing prefix URN:UVCI: added"),
+            Warning::MissingChecksum => write!(f, "missing checksum"),
+            Warning::ChecksumMismatch => write!(f, "checksum does not match"),
+            Warning::UnknownVersion(version) => write!(f, "unknown version {:02}", version),
+            Warning::Syntax(error) => write!(f, "{}", error),
+            Warning::ChecksumDetectedWithoutSeparator(checksum) => {
+                write!(f, "checksum '{}' detected without '#' separator", checksum)
+            }
+        }
+    }
+}
+
+/// Certificate group of a EU Digital COVID Certificate, i.e. the "v", "r" or "t" array
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupType {
+    /// Vaccination group "v"
+    Vaccination,
+    /// Recovery group "r"
+    Recovery,
+    /// Test group "t"
+    Test,
+}
+
+impl GroupType {
+    /// The key of the group in the EU Digital COVID Certificate JSON, e.g. "v"
+    pub fn key(&self) -> &'static str {
+        match self {
+            GroupType::Vaccination => "v",
+            GroupType::Recovery => "r",
+            GroupType::Test => "t",
+        }
+    }
+}
+
 /// Export a EU Digital COVID Certificate UVCI to CSV
 /// # Arguments
 ///
 /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
 pub fn uvci_to_csv(cert_id: &str) -> String {
-    return to_csv(parse(cert_id));
+    return to_csv(&parse(cert_id));
 }
 
 /// Export the parsed EU Digital COVID Certificate UVCI data to CSV
-fn to_csv(uvci: Uvci) -> String {
-    let mut output = "".to_string();
-    output.push_str(&uvci.version.to_string());
-    output.push_str(",");
-    output.push_str(&uvci.country);
-    output.push_str(",");
-    output.push_str(&uvci.schema_option_number.to_string());
-    output.push_str(",");
-    output.push_str(&uvci.schema_option_desc);
-    output.push_str(",");
-    output.push_str(&uvci.issuing_entity);
-    output.push_str(",");
-    output.push_str(&uvci.vaccine_id);
-    output.push_str(",");
-    output.push_str(&uvci.opaque_unique_string);
-    output.push_str(",");
-    output.push_str(&uvci.opaque_id);
-    output.push_str(",");
-    output.push_str(&uvci.opaque_issuance);
-    output.push_str(",");
-    output.push_str(&uvci.opaque_vaccination_month.to_string());
-    output.push_str(",");
-    output.push_str(&uvci.opaque_vaccination_year.to_string());
-    output.push_str(",");
-    output.push_str(&uvci.checksum);
-    output.push_str(",");
-    output.push_str(&uvci.checksum_verification.to_string());
-    return output.to_string();
+pub(crate) fn to_csv(uvci: &Uvci) -> String {
+    let mut output = String::with_capacity(CSV_LINE_CAPACITY);
+    write_csv(uvci, &mut output);
+    return output;
 }
 
-/// Export a vector of EU Digital COVID Certificate UVCI to Neo4j Cypher Graph
+/// Capacity of a CSV line buffer, enough for the fields of most UVCIs without reallocating
+pub(crate) const CSV_LINE_CAPACITY: usize = 128;
+
+/// Append the CSV line of the parsed EU Digital COVID Certificate UVCI data to a buffer
+///
+/// Batch exports reuse the buffer across lines, the numbers are formatted in place.
+pub(crate) fn write_csv(uvci: &Uvci, output: &mut String) {
+    write_csv_with_vaccination(uvci, None, output);
+}
+
+/// Append the CSV line of the parsed EU Digital COVID Certificate UVCI data to a buffer, with the
+/// vaccination month rendered in one column, or in month and year columns if None
+pub(crate) fn write_csv_with_vaccination(
+    uvci: &Uvci,
+    vaccination: Option<&str>,
+    output: &mut String,
+) {
+    use std::fmt::Write as _;
+
+    // Formatting into a String cannot fail
+    let _ = write!(
+        output,
+        "{},{},{},",
+        uvci.version.unwrap_or(0),
+        uvci.country_str(),
+        uvci.schema_option.number()
+    );
+    for field in &[
+        uvci.schema_option.desc(),
+        or_empty(&uvci.issuing_entity),
+        or_empty(&uvci.vaccine_id),
+        &uvci.opaque_unique_string,
+        or_empty(&uvci.opaque_id),
+        or_empty(&uvci.opaque_issuance),
+    ] {
+        output.push_str(field);
+        output.push(',');
+    }
+    match vaccination {
+        Some(vaccination) => {
+            output.push_str(vaccination);
+            output.push(',');
+        }
+        None => {
+            let _ = write!(
+                output,
+                "{},{},",
+                uvci.opaque_vaccination.map_or(0, |date| date.month),
+                uvci.opaque_vaccination.map_or(0, |date| date.year)
+            );
+        }
+    }
+    let _ = write!(
+        output,
+        "{},{}",
+        or_empty(&uvci.checksum),
+        uvci.checksum_verification
+    );
+}
+
+/// A CSV field quoted, with its quotes doubled, e.g. a file name that may contain commas
+pub(crate) fn csv_quoted(value: &str) -> String {
+    return format!("\"{}\"", value.replace('"', "\"\""));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to CSV, one line per UVCI
 ///
-/// Only for Sweden EHM-issued COVID certificates
 /// # Arguments
 ///
-/// * `cert_ids` - String vector of UVCI (Unique Vaccination Certificate/Assertion Identifier)
-pub fn uvcis_to_graph(cert_ids: &Vec<String>) -> String {
-    let mut cypher_cmd = "".to_string();
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_csv(cert_ids: &[String]) -> String {
+    return write_to_string(|output| uvcis_to_csv_writer(cert_ids, output));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to CSV, streamed to a writer one line per UVCI
+///
+/// The output is the same as `uvcis_to_csv`, without holding it in memory. Returns the summary of the
+/// UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `writer` - the CSV output
+pub fn uvcis_to_csv_writer<W: Write>(
+    cert_ids: &[String],
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let mut line = String::with_capacity(CSV_LINE_CAPACITY);
+    for cert_id in cert_ids {
+        let uvci = parse(cert_id);
+        result.record(cert_id, &uvci);
+        line.clear();
+        write_csv(&uvci, &mut line);
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+    }
+    writer.flush()?;
+    return Ok(result.finish(started));
+}
+
+/// Export a EU Digital COVID Certificate UVCI to a JSON object
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn uvci_to_json(cert_id: &str) -> String {
+    return to_json(&parse(cert_id)).to_string();
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a JSON array
+///
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_json(cert_ids: &[String]) -> String {
+    return write_to_string(|output| uvcis_to_json_writer(cert_ids, output));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a JSON array, streamed to a writer one element per UVCI
+///
+/// The output is the same as `uvcis_to_json`, without holding it in memory. Returns the summary of the
+/// UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `writer` - the JSON output
+pub fn uvcis_to_json_writer<W: Write>(
+    cert_ids: &[String],
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let values = cert_ids.iter().map(|cert_id| {
+        let uvci = parse(cert_id);
+        result.record(cert_id, &uvci);
+        to_json(&uvci)
+    });
+    write_json_array(values, writer)?;
+    return Ok(result.finish(started));
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to a JSON value
+pub(crate) fn to_json(uvci: &Uvci) -> serde_json::Value {
+    return serde_json::json!({
+        "version": uvci.version.unwrap_or(0),
+        "country": uvci.country_str(),
+        "schema_option_number": uvci.schema_option.number(),
+        "schema_option_desc": uvci.schema_option.desc(),
+        "issuing_entity": or_empty(&uvci.issuing_entity),
+        "vaccine_id": or_empty(&uvci.vaccine_id),
+        "opaque_unique_string": uvci.opaque_unique_string,
+        "opaque_id": or_empty(&uvci.opaque_id),
+        "opaque_issuance": or_empty(&uvci.opaque_issuance),
+        "opaque_vaccination_month": uvci.opaque_vaccination.map_or(0, |date| date.month),
+        "opaque_vaccination_year": uvci.opaque_vaccination.map_or(0, |date| date.year),
+        "checksum": or_empty(&uvci.checksum),
+        "checksum_verification": uvci.checksum_verification,
+    });
+}
+
+/// Export a EU Digital COVID Certificate UVCI to a JSON object with the camelCase field names of the eHealth JSON conventions
+///
+/// Unknown fields are null, and the vaccination month and year estimated from the opaque unique string
+/// are objects marked as estimated, e.g. "opaqueVaccinationMonth": {"value": 8, "estimated": true}.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn uvci_to_ehealth_json(cert_id: &str) -> String {
+    return to_ehealth_json(&parse(cert_id)).to_string();
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a JSON array with camelCase field names
+///
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_ehealth_json(cert_ids: &[String]) -> String {
+    return write_to_string(|output| uvcis_to_ehealth_json_writer(cert_ids, output));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a JSON array with camelCase field names, streamed to a writer
+///
+/// The output is the same as `uvcis_to_ehealth_json`, without holding it in memory. Returns the summary
+/// of the UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `writer` - the JSON output
+pub fn uvcis_to_ehealth_json_writer<W: Write>(
+    cert_ids: &[String],
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let values = cert_ids.iter().map(|cert_id| {
+        let uvci = parse(cert_id);
+        result.record(cert_id, &uvci);
+        to_ehealth_json(&uvci)
+    });
+    write_json_array(values, writer)?;
+    return Ok(result.finish(started));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to the newline-delimited format of the Elasticsearch bulk API
+///
+/// Every UVCI is an "index" action line followed by the JSON object of `uvci_to_json` as document, with
+/// the normalized UVCI as given as document id (see `normalized_cert_id`), so indexing the same batch
+/// twice doesn't duplicate documents.
+/// The output can be posted as is to the `_bulk` endpoint.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `index` - the name of the index, e.g. "uvcis"
+pub fn uvcis_to_es_bulk(cert_ids: &[String], index: &str) -> String {
+    return write_to_string(|output| uvcis_to_es_bulk_writer(cert_ids, index, output));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to the Elasticsearch bulk format, streamed to a writer
+///
+/// The output is the same as `uvcis_to_es_bulk`, without holding it in memory. Returns the summary of
+/// the UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `index` - the name of the index, e.g. "uvcis"
+/// * `writer` - the bulk request body
+pub fn uvcis_to_es_bulk_writer<W: Write>(
+    cert_ids: &[String],
+    index: &str,
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    for cert_id in cert_ids {
+        let uvci = parse(cert_id);
+        result.record(cert_id, &uvci);
+        let action = serde_json::json!({
+            "index": { "_index": index, "_id": normalized_cert_id(cert_id) }
+        });
+        serde_json::to_writer(&mut *writer, &action)?;
+        writer.write_all(b"\n")?;
+        serde_json::to_writer(&mut *writer, &to_json(&uvci))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    return Ok(result.finish(started));
+}
+
+/// Write JSON values as a JSON array, one element at a time
+fn write_json_array<W: Write>(
+    values: impl Iterator<Item = serde_json::Value>,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut separator = "";
+    writer.write_all(b"[")?;
+    for value in values {
+        writer.write_all(separator.as_bytes())?;
+        serde_json::to_writer(&mut *writer, &value)?;
+        separator = ",";
+    }
+    writer.write_all(b"]")?;
+    return writer.flush();
+}
+
+/// Collect the output of a writer export into a String
+fn write_to_string<F, T>(export: F) -> String
+where
+    F: FnOnce(&mut Vec<u8>) -> io::Result<T>,
+{
+    let mut output = Vec::new();
+    export(&mut output).expect("writing to a Vec cannot fail");
+    return String::from_utf8(output).expect("the exports are UTF-8");
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to a JSON value with camelCase field names
+pub(crate) fn to_ehealth_json(uvci: &Uvci) -> serde_json::Value {
+    let estimated = |value: Option<serde_json::Value>| match value {
+        Some(value) => serde_json::json!({ "value": value, "estimated": true }),
+        None => serde_json::Value::Null,
+    };
+    return serde_json::json!({
+        "version": uvci.version,
+        "country": uvci.country.map(|country| country.to_string()),
+        "schemaOptionNumber": uvci.schema_option.number(),
+        "schemaOptionDesc": uvci.schema_option.desc(),
+        "issuingEntity": uvci.issuing_entity,
+        "vaccineId": uvci.vaccine_id,
+        "opaqueUniqueString": uvci.opaque_unique_string,
+        "opaqueId": uvci.opaque_id,
+        "opaqueIssuance": uvci.opaque_issuance,
+        "opaqueVaccinationMonth": estimated(uvci.opaque_vaccination.map(|date| date.month.into())),
+        "opaqueVaccinationYear": estimated(uvci.opaque_vaccination.map(|date| date.year.into())),
+        "checksum": uvci.checksum,
+        "checksumVerification": uvci.checksum_verification,
+    });
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a column-aligned text table
+///
+/// The first row holds the column names, followed by a separator row and one row per UVCI.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_table(cert_ids: &[String]) -> String {
+    return write_to_string(|output| uvcis_to_table_writer(cert_ids, output));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a column-aligned text table, streamed to a writer
+///
+/// The output is the same as `uvcis_to_table`. The UVCIs are parsed twice, once for the column widths
+/// and once for the rows, so neither the table nor its rows are held in memory. Returns the summary of
+/// the UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `writer` - the table output
+pub fn uvcis_to_table_writer<W: Write>(
+    cert_ids: &[String],
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let header = table_header();
+
+    // Column widths, counted in characters so that non-ASCII input stays aligned
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for cert_id in cert_ids {
+        for (i, cell) in table_row(cert_id).iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    write_table_row(&header, &widths, writer)?;
+    let separators: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    writer.write_all(separators.join("-+-").as_bytes())?;
+    writer.write_all(b"\n")?;
     for cert_id in cert_ids {
-        cypher_cmd.push_str(&uvci_to_graph(cert_id));
+        result.record(cert_id, &parse(cert_id));
+        write_table_row(&table_row(cert_id), &widths, writer)?;
     }
-    // Remove duplicates
-    let values: Vec<_> = cypher_cmd.split('\n').collect();
-    let values: Vec<_> = values.into_iter().unique().collect();
-    let cypher_output: String = values.into_iter().collect();
-    let cypher_output = cypher_output.replace("CREATE", "\nCREATE");
-    return cypher_output;
+    writer.flush()?;
+    return Ok(result.finish(started));
+}
+
+/// The column names of the text table
+fn table_header() -> Vec<String> {
+    let mut header = vec!["cert_id".to_string()];
+    header.extend(Uvci::FIELD_NAMES.iter().map(|name| name.to_string()));
+    return header;
+}
+
+/// The cells of the text table row of a UVCI
+fn table_row(cert_id: &str) -> Vec<String> {
+    let mut row = vec![cert_id.to_string()];
+    row.extend(parse(cert_id).fields().map(|(_, value)| value.to_string()));
+    return row;
+}
+
+/// Write a row of the text table, padded to the column widths
+fn write_table_row<W: Write>(row: &[String], widths: &[usize], writer: &mut W) -> io::Result<()> {
+    let cells: Vec<String> = row
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+        .collect();
+    writer.write_all(cells.join(" | ").trim_end().as_bytes())?;
+    return writer.write_all(b"\n");
+}
+
+/// Export the diagnostics of a EU Digital COVID Certificate UVCI to JSON
+///
+/// Returns an empty JSON array if no problems were found.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn uvci_to_diagnostics_json(cert_id: &str) -> String {
+    return diagnostics_to_json(&diagnose(cert_id)).to_string();
+}
+
+/// Export the diagnostics of a vector of EU Digital COVID Certificate UVCI to JSON
+///
+/// Only UVCIs with at least one diagnostic are included, each with its index in `cert_ids`
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_diagnostics_json(cert_ids: &[String]) -> String {
+    return write_to_string(|output| uvcis_to_diagnostics_json_writer(cert_ids, output));
+}
+
+/// Export the diagnostics of a vector of EU Digital COVID Certificate UVCI to JSON, streamed to a writer
+///
+/// The output is the same as `uvcis_to_diagnostics_json`, without holding it in memory. Returns the
+/// summary of the UVCIs diagnosed.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `writer` - the JSON output
+pub fn uvcis_to_diagnostics_json_writer<W: Write>(
+    cert_ids: &[String],
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let entries = cert_ids.iter().enumerate().filter_map(|(index, cert_id)| {
+        let diagnostics = diagnose(cert_id);
+        result.record_diagnosed(&parse(cert_id), &diagnostics);
+        if diagnostics.is_empty() {
+            return None;
+        }
+        return Some(serde_json::json!({
+            "index": index,
+            "cert_id": cert_id,
+            "valid": !diagnostics.iter().any(|d| d.severity == Severity::Error),
+            "diagnostics": diagnostics_to_json(&diagnostics),
+        }));
+    });
+    write_json_array(entries, writer)?;
+    return Ok(result.finish(started));
+}
+
+/// Export diagnostics to a JSON array value
+pub(crate) fn diagnostics_to_json(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let values = diagnostics
+        .iter()
+        .map(|d| {
+            serde_json::json!({
+                "code": d.code,
+                "message": d.message,
+                "severity": d.severity.to_string(),
+                "offset": d.offset,
+                "suggestion": d.suggestion,
+            })
+        })
+        .collect();
+    return serde_json::Value::Array(values);
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to Neo4j Cypher Graph
+///
+/// Only for Sweden EHM-issued COVID certificates. The statements of the nodes and edges are streamed to
+/// the output as the UVCIs are parsed, duplicates are skipped with a set of the statements written.
+/// # Arguments
+///
+/// * `cert_ids` - String vector of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_graph(cert_ids: &[String]) -> String {
+    return write_to_string(|output| uvcis_to_graph_writer(cert_ids, output));
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to Neo4j Cypher Graph, streamed to a writer
+///
+/// Only for Sweden EHM-issued COVID certificates. The output is the same as `uvcis_to_graph`, without
+/// holding it in memory. Returns the summary of the UVCIs exported.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `writer` - the Cypher output
+pub fn uvcis_to_graph_writer<W: Write>(
+    cert_ids: &[String],
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    return export::export_all(cert_ids, export::CypherExporter::new(writer));
 }
 
 /// Export a EU Digital COVID Certificate UVCI to Neo4j Cypher Graph
@@ -133,7 +1064,7 @@ pub fn uvcis_to_graph(cert_ids: &Vec<String>) -> String {
 ///
 /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
 pub fn uvci_to_graph(cert_id: &str) -> String {
-    return to_graph(parse(cert_id));
+    return to_graph(&parse(cert_id));
 }
 
 /// Export the parsed EU Digital COVID Certificate UVCI data to Neo4j Cypher Graph
@@ -141,12 +1072,18 @@ pub fn uvci_to_graph(cert_id: &str) -> String {
 /// # Arguments
 ///
 /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
-fn to_graph(uvci_data: Uvci) -> String {
+pub(crate) fn to_graph(uvci_data: &Uvci) -> String {
+    return to_graph_with_dates(uvci_data, false);
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to Neo4j Cypher Graph, with the estimated
+/// vaccination date as a Neo4j `date()` property of the certificate node if `native_dates`
+pub(crate) fn to_graph_with_dates(uvci_data: &Uvci, native_dates: bool) -> String {
     // Only for Sweden EHM-issued COVID certificates
-    if !((uvci_data.version == 1)
-        && (uvci_data.country == "SE")
-        && (uvci_data.issuing_entity == "EHM")
-        && (uvci_data.schema_option_number == 3))
+    if !((uvci_data.version == Some(1))
+        && (uvci_data.country_str() == "SE")
+        && (uvci_data.issuing_entity.as_deref() == Some("EHM"))
+        && (uvci_data.schema_option == SchemaOption::SomeSemantics))
     {
         return "".to_string();
     }
@@ -158,49 +1095,26 @@ fn to_graph(uvci_data: Uvci) -> String {
 
     // CREATE (SE:country {name:'Sweden'})-[:COUNTRY_OF {}]->(EHM:issuing_entity {name:'E-Hälso Myndigheten'})
     cypher_cmd.push_str("CREATE (");
-    cypher_cmd.push_str(&uvci_data.country);
+    cypher_cmd.push_str(uvci_data.country_str());
     cypher_cmd.push_str(":country {name:'");
     cypher_cmd.push_str(var_country);
     cypher_cmd.push_str("'})-[:COUNTRY_OF {}]->(");
-    cypher_cmd.push_str(&uvci_data.issuing_entity);
+    cypher_cmd.push_str(or_empty(&uvci_data.issuing_entity));
     cypher_cmd.push_str(":issuing_entity {name:'");
     cypher_cmd.push_str(var_issuer);
     cypher_cmd.push_str("'})\n");
 
     // CREATE (EHM)-[:ISSUER_OF {}]->(V11916227:opaque_id {name:'V11916227'})
     cypher_cmd.push_str("CREATE (");
-    cypher_cmd.push_str(&uvci_data.issuing_entity);
+    cypher_cmd.push_str(or_empty(&uvci_data.issuing_entity));
     cypher_cmd.push_str(")-[:ISSUER_OF {}]->(");
-    cypher_cmd.push_str(&uvci_data.opaque_id);
+    cypher_cmd.push_str(or_empty(&uvci_data.opaque_id));
     cypher_cmd.push_str(":opaque_id {name:'");
-    cypher_cmd.push_str(&uvci_data.opaque_id);
+    cypher_cmd.push_str(or_empty(&uvci_data.opaque_id));
     cypher_cmd.push_str("'})\n");
 
     // CREATE (d20218:vac_date {name:'Aug 2021'})
-    let mut var_date_name = "d".to_string();
-    var_date_name.push_str(&uvci_data.opaque_vaccination_year.to_string());
-    var_date_name.push_str(&uvci_data.opaque_vaccination_month.to_string());
-
-    let var_month_name;
-    match uvci_data.opaque_vaccination_month {
-        1 => var_month_name = "Jan".to_string(),
-        2 => var_month_name = "Feb".to_string(),
-        3 => var_month_name = "Mar".to_string(),
-        4 => var_month_name = "Apr".to_string(),
-        5 => var_month_name = "May".to_string(),
-        6 => var_month_name = "Jun".to_string(),
-        7 => var_month_name = "Jul".to_string(),
-        8 => var_month_name = "Aug".to_string(),
-        9 => var_month_name = "Sep".to_string(),
-        10 => var_month_name = "Oct".to_string(),
-        11 => var_month_name = "Nov".to_string(),
-        12 => var_month_name = "Dec".to_string(),
-        _ => var_month_name = "Unknown".to_string(),
-    }
-    let mut var_date_data = "".to_string();
-    var_date_data.push_str(&var_month_name);
-    var_date_data.push_str(" ");
-    var_date_data.push_str(&uvci_data.opaque_vaccination_year.to_string());
+    let (var_date_name, var_date_data) = graph_vac_date(uvci_data);
 
     // CREATE (d20218:vac_date {name:'Aug 2021'})
     cypher_cmd.push_str("CREATE (");
@@ -213,22 +1127,183 @@ fn to_graph(uvci_data: Uvci) -> String {
     cypher_cmd.push_str("CREATE (");
     cypher_cmd.push_str(&var_date_name);
     cypher_cmd.push_str(")-[:VAC_DATE_OF {}]->(");
-    cypher_cmd.push_str(&uvci_data.opaque_id);
+    cypher_cmd.push_str(or_empty(&uvci_data.opaque_id));
     cypher_cmd.push_str(")\n");
 
-    // CREATE (V11916227TFJJ:reissue_id {name:'TFJJ'})-[:REISSUE_OF {}]->(V11916227)
+    // CREATE (V11916227TFJJ:reissue_id {name:'TFJJ', checksum_valid:true, quality_score:100, schema_option:3})-[:REISSUE_OF {}]->(V11916227)
+    let (var_certificate_label, var_certificate_properties) =
+        graph_certificate(uvci_data, native_dates);
     cypher_cmd.push_str("CREATE (");
     cypher_cmd.push_str(&uvci_data.opaque_unique_string);
-    cypher_cmd.push_str(":reissue_id {name:'");
-    cypher_cmd.push_str(&uvci_data.opaque_issuance);
-    cypher_cmd.push_str("'})-[:REISSUE_OF {}]->(");
-    cypher_cmd.push_str(&uvci_data.opaque_id);
+    cypher_cmd.push_str(":reissue_id");
+    cypher_cmd.push_str(var_certificate_label);
+    cypher_cmd.push_str(" {name:'");
+    cypher_cmd.push_str(or_empty(&uvci_data.opaque_issuance));
+    cypher_cmd.push_str("', ");
+    cypher_cmd.push_str(&var_certificate_properties);
+    cypher_cmd.push_str("})-[:REISSUE_OF {}]->(");
+    cypher_cmd.push_str(or_empty(&uvci_data.opaque_id));
     cypher_cmd.push_str(")\n");
 
     // cypher_cmd.push_str("return *");
     return cypher_cmd;
 }
 
+/// Neo4j Cypher statements creating the constraints and indexes of the graph
+///
+/// The names of the country, issuing_entity, opaque_id and vac_date nodes are unique, each constraint
+/// is backed by an index, so MERGE-based imports are fast and duplicate nodes are impossible. The
+/// name of a reissue_id node is only unique per opaque_id and is indexed instead. Run them before
+/// importing the graph, one statement per line terminated by ";".
+pub fn graph_constraints() -> String {
+    let mut cypher_cmd = "".to_string();
+    for label in &["country", "issuing_entity", "opaque_id", "vac_date"] {
+        cypher_cmd.push_str(&format!(
+            "CREATE CONSTRAINT {l}_name IF NOT EXISTS FOR (n:{l}) REQUIRE n.name IS UNIQUE;\n",
+            l = label
+        ));
+    }
+    cypher_cmd
+        .push_str("CREATE INDEX reissue_id_name IF NOT EXISTS FOR (n:reissue_id) ON (n.name);\n");
+    return cypher_cmd;
+}
+
+/// Export a EU Digital COVID Certificate UVCI to a self-contained Neo4j Cypher MERGE statement
+///
+/// Only for Sweden EHM-issued COVID certificates. Unlike `uvci_to_graph`, the statement does not depend
+/// on variables of other statements and can be run more than once, so statements can be sent in
+/// separate transactions.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn uvci_to_graph_merge(cert_id: &str) -> String {
+    return to_graph_merge(&parse(cert_id));
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to a self-contained Neo4j Cypher MERGE statement
+pub(crate) fn to_graph_merge(uvci_data: &Uvci) -> String {
+    return to_graph_merge_with_dates(uvci_data, false);
+}
+
+/// Export the parsed EU Digital COVID Certificate UVCI data to a self-contained Neo4j Cypher MERGE
+/// statement, setting the estimated vaccination date as a Neo4j `date()` property of the certificate
+/// node if `native_dates`
+pub(crate) fn to_graph_merge_with_dates(uvci_data: &Uvci, native_dates: bool) -> String {
+    // Only for Sweden EHM-issued COVID certificates
+    if !((uvci_data.version == Some(1))
+        && (uvci_data.country_str() == "SE")
+        && (uvci_data.issuing_entity.as_deref() == Some("EHM"))
+        && (uvci_data.schema_option == SchemaOption::SomeSemantics))
+    {
+        return "".to_string();
+    }
+    let (var_date_name, var_date_data) = graph_vac_date(uvci_data);
+    let (label, checksum_valid, quality_score) = graph_certificate_status(uvci_data);
+    let mut set_extra = "".to_string();
+    if let (true, Some(date)) = (native_dates, graph_vaccination_date(uvci_data)) {
+        set_extra.push_str(&format!(
+            ", {}.vaccination_date = date('{}')",
+            uvci_data.opaque_unique_string, date
+        ));
+    }
+    if !label.is_empty() {
+        set_extra.push_str(&format!(", {}{}", uvci_data.opaque_unique_string, label));
+    }
+
+    return format!(
+        "MERGE ({c}:country {{name:'Sweden'}}) \
+         MERGE ({i}:issuing_entity {{name:'E-Hälso Myndigheten'}}) \
+         MERGE ({c})-[:COUNTRY_OF]->({i}) \
+         MERGE ({o}:opaque_id {{name:'{o}'}}) \
+         MERGE ({i})-[:ISSUER_OF]->({o}) \
+         MERGE ({d}:vac_date {{name:'{dn}'}}) \
+         MERGE ({d})-[:VAC_DATE_OF]->({o}) \
+         MERGE ({r}:reissue_id {{name:'{rn}'}})-[:REISSUE_OF]->({o}) \
+         SET {r}.checksum_valid = {cv}, {r}.quality_score = {qs}, {r}.schema_option = {so}{l}",
+        c = uvci_data.country_str(),
+        i = or_empty(&uvci_data.issuing_entity),
+        o = or_empty(&uvci_data.opaque_id),
+        d = var_date_name,
+        dn = var_date_data,
+        r = uvci_data.opaque_unique_string,
+        rn = or_empty(&uvci_data.opaque_issuance),
+        l = set_extra,
+        cv = checksum_valid,
+        qs = quality_score,
+        so = uvci_data.schema_option.number(),
+    );
+}
+
+/// Neo4j Cypher Graph extra label and properties of the certificate node, i.e. the reissue_id node,
+/// e.g. ("", "checksum_valid:true, quality_score:100, schema_option:3")
+///
+/// Certificates with a checksum that does not verify get the extra label ":invalid_certificate", so
+/// graph queries can isolate suspect identifiers. The quality score is the plausibility score. With
+/// `native_dates`, the estimated vaccination date is added as "vaccination_date:date('2021-08-01')".
+pub(crate) fn graph_certificate(uvci_data: &Uvci, native_dates: bool) -> (&'static str, String) {
+    let (label, checksum_valid, quality_score) = graph_certificate_status(uvci_data);
+    let mut properties = format!(
+        "checksum_valid:{}, quality_score:{}, schema_option:{}",
+        checksum_valid,
+        quality_score,
+        uvci_data.schema_option.number()
+    );
+    if let (true, Some(date)) = (native_dates, graph_vaccination_date(uvci_data)) {
+        properties.push_str(", vaccination_date:date('");
+        properties.push_str(&date);
+        properties.push_str("')");
+    }
+    return (label, properties);
+}
+
+/// ISO 8601 date of the first day of the estimated vaccination month, e.g. "2021-08-01", None if unknown
+pub(crate) fn graph_vaccination_date(uvci_data: &Uvci) -> Option<String> {
+    return uvci_data
+        .opaque_vaccination
+        .map(|date| format!("{:04}-{:02}-01", date.year, date.month));
+}
+
+/// Extra label, checksum validity and quality score of the certificate node of the graph
+pub(crate) fn graph_certificate_status(uvci_data: &Uvci) -> (&'static str, bool, u8) {
+    let label = if uvci_data.checksum.is_some() && !uvci_data.checksum_verification {
+        ":invalid_certificate"
+    } else {
+        ""
+    };
+    let quality_score = plausibility::plausibility_score(uvci_data).value;
+    return (label, uvci_data.checksum_verification, quality_score);
+}
+
+/// Neo4j Cypher Graph variable and name of the vaccination date node, e.g. ("d20218", "Aug 2021")
+pub(crate) fn graph_vac_date(uvci_data: &Uvci) -> (String, String) {
+    let year = uvci_data.opaque_vaccination.map_or(0, |date| date.year);
+    let month = uvci_data.opaque_vaccination.map_or(0, |date| date.month);
+    let mut var_date_name = "d".to_string();
+    var_date_name.push_str(&year.to_string());
+    var_date_name.push_str(&month.to_string());
+
+    let var_month_name = match month {
+        1 => "Jan".to_string(),
+        2 => "Feb".to_string(),
+        3 => "Mar".to_string(),
+        4 => "Apr".to_string(),
+        5 => "May".to_string(),
+        6 => "Jun".to_string(),
+        7 => "Jul".to_string(),
+        8 => "Aug".to_string(),
+        9 => "Sep".to_string(),
+        10 => "Oct".to_string(),
+        11 => "Nov".to_string(),
+        12 => "Dec".to_string(),
+        _ => "Unknown".to_string(),
+    };
+    let mut var_date_data = "".to_string();
+    var_date_data.push_str(&var_month_name);
+    var_date_data.push_str(" ");
+    var_date_data.push_str(&year.to_string());
+    return (var_date_name, var_date_data);
+}
+
 /// ## EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier) Parser
 /// Tool to parse and verify the EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier).
 /// Following the conclusions of the European Council of 10-11 December 2020 and of 21 January 2021 that called for
@@ -273,121 +1348,852 @@ fn to_graph(uvci_data: Uvci) -> String {
 ///
 /// # Arguments
 ///
-/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
-pub fn parse(cert_id: &str) -> Uvci {
-    let mut uvci_data = Uvci {
-        version: 0,
-        country: "".to_string(),
-        schema_option_number: 0,
-        schema_option_desc: "".to_string(),
-        issuing_entity: "".to_string(),
-        vaccine_id: "".to_string(),
-        opaque_unique_string: "".to_string(),
-        opaque_id: "".to_string(),
-        opaque_issuance: "".to_string(),
-        opaque_vaccination_month: 0,
-        opaque_vaccination_year: 0,
-        checksum: "".to_string(),
-        checksum_verification: false,
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn parse(cert_id: &str) -> Uvci {
+    let mut uvci_data = parse_blocks(cert_id);
+    decode_builtin(&mut uvci_data);
+    return uvci_data;
+}
+
+/// The UVCI uppercase and with the prefix "URN:UVCI:" added if missing, the key of a UVCI as given
+///
+/// e.g. " urn:uvci:01:se:ehm/v12907267lajw#e" and "01:SE:EHM/V12907267LAJW#E" become
+/// "URN:UVCI:01:SE:EHM/V12907267LAJW#E". Unlike `Uvci::canonical`, distinct inputs that cannot be
+/// parsed stay distinct.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn normalized_cert_id(cert_id: &str) -> String {
+    let cert_id = cert_id.trim().to_uppercase();
+    if cert_id.starts_with("URN:UVCI:") {
+        return cert_id;
+    }
+    return "URN:UVCI:".to_owned() + &cert_id;
+}
+
+/// The UVCI without the prefix "URN:UVCI:" and without the checksum, as stored by many national databases
+///
+/// e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E" becomes "01:SE:EHM/V12907267LAJW"
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn bare_identifier(cert_id: &str) -> String {
+    let cert_id = cert_id.trim().to_uppercase();
+    let cert_id = cert_id.split('#').next().unwrap_or("");
+    return cert_id
+        .strip_prefix("URN:UVCI:")
+        .unwrap_or(cert_id)
+        .to_string();
+}
+
+/// The UVCI of a bare identifier, with the prefix "URN:UVCI:" and the computed checksum, the inverse of `bare_identifier`
+///
+/// e.g. "01:SE:EHM/V12907267LAJW" becomes "URN:UVCI:01:SE:EHM/V12907267LAJW#E". The checksum is omitted if
+/// the identifier has characters outside the UVCI charset.
+/// # Arguments
+///
+/// * `bare_id` - the UVCI without prefix and checksum, e.g. "01:SE:EHM/V12907267LAJW"
+pub fn with_prefix_and_checksum(bare_id: &str) -> String {
+    let mut output = "URN:UVCI:".to_string();
+    output.push_str(&bare_identifier(bare_id));
+    if let Some(checksum) = expected_checksum(&output) {
+        output.push_str("#");
+        output.push(checksum);
+    }
+    return output;
+}
+
+/// A checksum-valid Swedish EHM UVCI reissuing a certificate with a fresh 4-letter issuance code
+///
+/// Simulates the EHM reissue flow in test environments, e.g. "V12916227" with the existing
+/// "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q" gives "URN:UVCI:01:SE:EHM/V12916227" followed by another issuance
+/// code and its checksum. The code is derived from the SHA-256 of the opaque id and a counter, so the
+/// same inputs always give the same UVCI, and skips the codes already issued.
+/// # Arguments
+///
+/// * `opaque_id` - the Swedish opaque id, e.g. "V12916227"
+/// * `existing` - the UVCIs or issuance codes already issued for the opaque id, e.g. "TFJJ"
+pub fn next_issuance(opaque_id: &str, existing: &[String]) -> String {
+    let opaque_id = opaque_id.trim().to_uppercase();
+    let issued: HashSet<String> = existing
+        .iter()
+        .filter_map(|issue| match parse(issue).opaque_issuance.clone() {
+            Some(code) => Some(code),
+            None => Some(issue.trim().to_uppercase()).filter(|code| code.len() == 4),
+        })
+        .collect();
+    let mut counter: u64 = 0;
+    loop {
+        let digest = Sha256::digest(format!("{}:{}", opaque_id, counter).as_bytes());
+        let code: String = digest[..4]
+            .iter()
+            .map(|b| (b'A' + b % 26) as char)
+            .collect();
+        if !issued.contains(&code) {
+            return with_prefix_and_checksum(&format!("01:SE:EHM/{}{}", opaque_id, code));
+        }
+        counter += 1;
+    }
+}
+
+/// Parse a UVCI strictly per the grammar of the `grammar` module, failing with the position of the first error
+///
+/// Unlike `parse`, lowercase input and any deviation from the grammar are rejected, e.g. a fourth slash
+/// separated block fails with `ParseErrorKind::TooManyBlocks` instead of parsing to an unknown schema option.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn try_parse(cert_id: &str) -> Result<Uvci, ParseError> {
+    if let (_, Some(error)) = grammar::parse_syntax(cert_id) {
+        return Err(error);
+    }
+    return Ok(parse(cert_id));
+}
+
+/// Parse a UVCI best-effort like `parse`, together with the quality issues worked around
+///
+/// Pipelines can keep the data flowing while logging the warnings, e.g. lowercase input normalized,
+/// a missing prefix added, a missing checksum or an unknown version.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn parse_lenient(cert_id: &str) -> (Uvci, Vec<Warning>) {
+    return parse_lenient_with_options(cert_id, &ParseOptions::new());
+}
+
+/// Parse a UVCI best-effort like `parse_with_options`, together with the quality issues worked around
+///
+/// The same as `parse_lenient`, e.g. with `ParseOptions::detect_checksum_without_separator` a check
+/// character appended without '#' is reported as `Warning::ChecksumDetectedWithoutSeparator`.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `options` - the parse options
+pub fn parse_lenient_with_options(cert_id: &str, options: &ParseOptions) -> (Uvci, Vec<Warning>) {
+    let mut warnings = Vec::new();
+    if cert_id.chars().any(|c| c.is_lowercase()) {
+        warnings.push(Warning::LowercaseNormalized);
+    }
+    let cert_id_upper = cert_id.to_uppercase();
+    if !cert_id_upper.is_empty() && !cert_id_upper.starts_with("URN:UVCI:") {
+        warnings.push(Warning::MissingPrefix);
+    }
+    let alphabet = options.algorithm_of(cert_id).unwrap_or_default().alphabet();
+    if let (_, Some(error)) = grammar::parse_syntax_with(&cert_id_upper, alphabet) {
+        warnings.push(Warning::Syntax(error));
+    }
+
+    let uvci = parse_with_options(cert_id, options);
+    if let Some(version) = uvci.version.filter(|version| *version != 1) {
+        warnings.push(Warning::UnknownVersion(version));
+    }
+    let detected = if options.detect_checksum_without_separator {
+        trailing_checksum(&cert_id_upper)
+    } else {
+        None
+    };
+    if let Some(checksum) = detected {
+        warnings.push(Warning::ChecksumDetectedWithoutSeparator(checksum));
+    } else if !cert_id_upper.contains('#') {
+        warnings.push(Warning::MissingChecksum);
+    } else if uvci.checksum.is_some() && !uvci.checksum_verification {
+        warnings.push(Warning::ChecksumMismatch);
+    }
+    return (uvci, warnings);
+}
+
+/// Options of `parse_with_options`
+///
+/// By default the checksum is verified with the LUHN-10 algorithm of the eHealth Network guidelines.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    checksum_algorithm: ChecksumAlgorithm,
+    detect_checksum_algorithm: bool,
+    country_checksum_algorithms: Vec<(String, ChecksumAlgorithm)>,
+    detect_checksum_without_separator: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        return ParseOptions::default();
+    }
+
+    /// Verify the checksum with the algorithm, unless the algorithm is detected
+    pub fn checksum_algorithm(mut self, checksum_algorithm: ChecksumAlgorithm) -> Self {
+        self.checksum_algorithm = checksum_algorithm;
+        return self;
+    }
+
+    /// Detect the checksum algorithm, trying LUHN-10 then the algorithms registered for the claimed country
+    pub fn detect_checksum_algorithm(mut self, detect_checksum_algorithm: bool) -> Self {
+        self.detect_checksum_algorithm = detect_checksum_algorithm;
+        return self;
+    }
+
+    /// Register a checksum algorithm used by a national scheme, for the detection
+    ///
+    /// # Arguments
+    ///
+    /// * `country` - the ISO 3166-1 country code, e.g. "DE"
+    /// * `algorithm` - the checksum algorithm of the national scheme
+    pub fn register_checksum_algorithm(
+        mut self,
+        country: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Self {
+        self.country_checksum_algorithms
+            .push((country.to_uppercase(), algorithm));
+        return self;
+    }
+
+    /// Detect a check character appended without the '#' separator, as left by systems stripping the '#'
+    ///
+    /// If the UVCI has no '#' and its last character is the LUHN-10 check character of the rest, the
+    /// last character is parsed as the checksum. As about one in 38 UVCIs without checksum ends with
+    /// such a character by chance, the detection is a heuristic.
+    pub fn detect_checksum_without_separator(mut self, detect: bool) -> Self {
+        self.detect_checksum_without_separator = detect;
+        return self;
+    }
+
+    /// The checksum algorithms tried by the detection for a country, in order
+    pub fn checksum_algorithms(&self, country: &str) -> Vec<ChecksumAlgorithm> {
+        let mut algorithms = vec![ChecksumAlgorithm::LuhnModN];
+        for (registered, algorithm) in &self.country_checksum_algorithms {
+            if registered == country && !algorithms.contains(algorithm) {
+                algorithms.push(*algorithm);
+            }
+        }
+        return algorithms;
+    }
+
+    /// The checksum algorithm of a UVCI, the detected one if the detection is enabled
+    fn algorithm_of(&self, cert_id: &str) -> Option<ChecksumAlgorithm> {
+        if self.detect_checksum_algorithm {
+            return detect_checksum_algorithm(cert_id, self);
+        }
+        return Some(self.checksum_algorithm);
+    }
+}
+
+/// Parse a EU Digital COVID Certificate UVCI, verifying the checksum per the options
+///
+/// The same as `parse`, but `checksum_verification` is the result of the checksum algorithm of the
+/// options, or of the detected algorithm if the detection is enabled.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `options` - the parse options
+pub fn parse_with_options(cert_id: &str, options: &ParseOptions) -> Uvci {
+    let separated;
+    let mut cert_id = cert_id;
+    if options.detect_checksum_without_separator {
+        // Uppercasing may change the length, e.g. of "ſ", so the uppercase UVCI is split
+        let cert_id_upper = cert_id.to_uppercase();
+        if let Some(checksum) = trailing_checksum(&cert_id_upper) {
+            separated = format!("{}#{}", &cert_id_upper[..cert_id_upper.len() - 1], checksum);
+            cert_id = &separated;
+        }
+    }
+    let algorithm = options.algorithm_of(cert_id);
+    // The check character is of the alphabet of the algorithm, e.g. "*" of ISO 7064 MOD 37-2
+    let mut uvci = parse_blocks_with(cert_id, algorithm.unwrap_or_default());
+    decode_builtin(&mut uvci);
+    if cert_id.is_empty() || cert_id.len() > 72 {
+        return uvci;
+    }
+    uvci.checksum_verification = algorithm.is_some_and(|algorithm| algorithm.verify(cert_id));
+    return uvci;
+}
+
+/// The last character of an uppercase UVCI without '#' if it is the LUHN-10 check character of the rest
+fn trailing_checksum(cert_id: &str) -> Option<char> {
+    if cert_id.contains('#') || cert_id.len() < 2 || cert_id.len() >= 72 {
+        return None;
+    }
+    let checksum = cert_id
+        .chars()
+        .last()
+        .filter(|c| c.is_ascii_alphanumeric())?;
+    let body = &cert_id[..cert_id.len() - 1];
+    return expected_checksum(body).filter(|expected| *expected == checksum);
+}
+
+/// Detect the checksum algorithm of a UVCI, the first of `ParseOptions::checksum_algorithms` of the
+/// claimed country that verifies, None if none does
+///
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `options` - the parse options with the registered algorithms
+pub fn detect_checksum_algorithm(
+    cert_id: &str,
+    options: &ParseOptions,
+) -> Option<ChecksumAlgorithm> {
+    let country = parse_blocks(cert_id).country_str().to_string();
+    return options
+        .checksum_algorithms(&country)
+        .into_iter()
+        .find(|algorithm| algorithm.verify(cert_id));
+}
+
+/// Parse the blocks of a UVCI without decoding the opaque unique string
+pub(crate) fn parse_blocks(cert_id: &str) -> Uvci {
+    return parse_blocks_with(cert_id, ChecksumAlgorithm::LuhnModN);
+}
+
+/// Parse the blocks of a UVCI without decoding the opaque unique string, with the check character of
+/// a checksum algorithm
+fn parse_blocks_with(cert_id: &str, algorithm: ChecksumAlgorithm) -> Uvci {
+    let mut uvci_data = Uvci {
+        cert_id: cert_id.trim().to_string(),
+        version: None,
+        country: None,
+        schema_option: SchemaOption::Unknown,
+        issuing_entity: None,
+        vaccine_id: None,
+        vaccine_product_code: None,
+        vaccine_lot: None,
+        opaque_unique_string: "".to_string(),
+        opaque_id: None,
+        opaque_issuance: None,
+        opaque_vaccination: None,
+        checksum: None,
+        checksum_verification: false,
+    };
+
+    // Reject if empty
+    if cert_id.is_empty() {
+        return uvci_data;
+    }
+
+    // Up to a total length of 72 characters
+    if cert_id.len() > 72 {
+        return uvci_data;
+    }
+
+    // Only uppercase characters are allowed
+    let cert_id = cert_id.to_uppercase();
+    #[cfg(feature = "zeroize")]
+    let cert_id = zeroize::Zeroizing::new(cert_id);
+
+    // Verify integrity of the UVCI, with the prefix "URN:UVCI:" added
+    uvci_data.checksum_verification = luhn_valid(&cert_id);
+
+    // Parse the grammar, keeping the parts before the first error
+    let (syntax, _) = grammar::parse_syntax_with(&cert_id, algorithm.alphabet());
+    uvci_data.version = syntax
+        .version
+        .and_then(|version| version.parse::<u8>().ok());
+    uvci_data.country = syntax.country.and_then(CountryCode::from_code);
+    uvci_data.checksum = syntax.checksum.map(|checksum| checksum.to_string());
+
+    // Detect schema
+    match syntax.blocks.as_slice() {
+        [issuing_entity, vaccine_id, opaque_unique_string] => {
+            uvci_data.schema_option = SchemaOption::WithSemantics;
+            uvci_data.issuing_entity = Some(issuing_entity.to_string());
+            uvci_data.vaccine_id = Some(vaccine_id.to_string());
+            let (product_code, lot) = split_vaccine_id(&uvci_data.vaccine_id);
+            uvci_data.vaccine_product_code = product_code;
+            uvci_data.vaccine_lot = lot;
+            uvci_data.opaque_unique_string = opaque_unique_string.to_string();
+        }
+        [opaque_unique_string] => {
+            uvci_data.schema_option = SchemaOption::OpaqueNoStructure;
+            uvci_data.opaque_unique_string = opaque_unique_string.to_string();
+        }
+        [issuing_entity, opaque_unique_string] => {
+            uvci_data.schema_option = SchemaOption::SomeSemantics;
+            uvci_data.issuing_entity = Some(issuing_entity.to_string());
+            uvci_data.opaque_unique_string = opaque_unique_string.to_string();
+        }
+        _ => (),
+    }
+    // After a syntax error the schema option stays unknown, with the raw rest as opaque unique string
+    if let Some(remainder) = syntax.remainder {
+        uvci_data.opaque_unique_string = remainder.to_string();
+    }
+
+    return uvci_data;
+}
+
+/// The product code and lot of a vaccine product identifier separated by a dash, e.g. "C878-AB123"
+///
+/// The guidelines allow the option 1 vaccine identifier to encode product and lot, the raw value stays
+/// in `vaccine_id`. Both are None if the identifier has no such structure, e.g. "C878".
+pub(crate) fn split_vaccine_id(vaccine_id: &Option<String>) -> (Option<String>, Option<String>) {
+    match vaccine_id.as_deref().and_then(|id| id.split_once('-')) {
+        Some((product_code, lot)) if !product_code.is_empty() && !lot.is_empty() => {
+            (Some(product_code.to_string()), Some(lot.to_string()))
+        }
+        _ => (None, None),
+    }
+}
+
+/// Decode the opaque unique string with the built-in country decoders, returns false if none applies
+pub(crate) fn decode_builtin(uvci_data: &mut Uvci) -> bool {
+    // Only for Sweden EHM-issued COVID certificates
+    if (uvci_data.version == Some(1))
+        && (uvci_data.country_str() == "SE")
+        && (uvci_data.issuing_entity.as_deref() == Some("EHM"))
+        && (uvci_data.schema_option == SchemaOption::SomeSemantics)
+        && (uvci_data.opaque_unique_string.len() == 13)
+    {
+        let opaque_id = uvci_data.opaque_unique_string[0..9].to_string();
+        uvci_data.opaque_issuance = Some(uvci_data.opaque_unique_string[9..13].to_string());
+
+        let vaccination_date = get_vaccination_date_tan(opaque_id.clone());
+        if vaccination_date.1 != 0 {
+            uvci_data.opaque_vaccination = Some(YearMonth {
+                year: vaccination_date.1,
+                month: vaccination_date.0,
+            });
+        }
+        uvci_data.opaque_id = Some(opaque_id);
+        return true;
+    }
+    return false;
+}
+
+/// Parse the UVCIs of an input, keeping the input name and line number of every UVCI
+///
+/// Empty lines are skipped but counted in the line numbers.
+/// # Arguments
+///
+/// * `source` - the name of the input, e.g. the file name
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier), one per input line
+pub fn parse_sourced(source: &str, cert_ids: &[String]) -> Vec<SourcedUvci> {
+    return cert_ids
+        .iter()
+        .enumerate()
+        .filter(|(_, cert_id)| !cert_id.trim().is_empty())
+        .map(|(index, cert_id)| SourcedUvci {
+            uvci: parse(cert_id.trim()),
+            source: source.to_string(),
+            line: index + 1,
+        })
+        .collect();
+}
+
+/// Parse all UVCIs of a decoded EU Digital COVID Certificate JSON payload
+///
+/// Walks the "v", "r" and "t" arrays and parses the "ci" field of every entry.
+/// Returns an empty vector if the payload is not valid JSON.
+/// # Arguments
+///
+/// * `payload` - the EU Digital COVID Certificate JSON, e.g. r#"{"ver":"1.3.0","v":[{"ci":"URN:UVCI:01:SE:EHM/V12907267LAJW#E"}]}"#
+pub fn parse_dcc_json(payload: &str) -> Vec<(GroupType, Uvci)> {
+    let dcc: serde_json::Value = match serde_json::from_str(payload) {
+        Ok(dcc) => dcc,
+        Err(_) => return Vec::new(),
+    };
+    return parse_dcc_value(&dcc);
+}
+
+/// Parse all UVCIs of a EU Digital COVID Certificate JSON value
+pub(crate) fn parse_dcc_value(dcc: &serde_json::Value) -> Vec<(GroupType, Uvci)> {
+    let mut uvcis = Vec::new();
+    for group_type in &[GroupType::Vaccination, GroupType::Recovery, GroupType::Test] {
+        if let Some(entries) = dcc.get(group_type.key()).and_then(|g| g.as_array()) {
+            for entry in entries {
+                if let Some(cert_id) = entry.get("ci").and_then(|ci| ci.as_str()) {
+                    uvcis.push((*group_type, parse(cert_id)));
+                }
+            }
+        }
+    }
+    return uvcis;
+}
+
+/// Whether the '-' at a byte offset of a UVCI separates the product code and lot of an option 1 vaccine id
+fn is_vaccine_id_dash(cert_id: &str, offset: usize) -> bool {
+    let body = cert_id.split('#').next().unwrap_or("");
+    let prefix_len = if body.to_ascii_uppercase().starts_with("URN:UVCI:") {
+        "URN:UVCI:".len()
+    } else {
+        0
     };
+    let identifier_offset = match body[prefix_len..].match_indices(':').nth(1) {
+        Some((colon, _)) => prefix_len + colon + 1,
+        None => return false,
+    };
+    let slashes: Vec<usize> = body[identifier_offset..]
+        .match_indices('/')
+        .map(|(slash, _)| identifier_offset + slash)
+        .collect();
+    return slashes.len() == 2
+        && slashes[0] + 1 < offset
+        && offset + 1 < slashes[1]
+        && cert_id.as_bytes()[offset - 1] != b'-';
+}
+
+/// Diagnose a EU Digital COVID Certificate UVCI (Unique Vaccination Certificate/Assertion Identifier)
+///
+/// Returns one diagnostic per problem found, with the byte offset in `cert_id` and a suggested fix
+/// where one can be derived. An empty vector means the UVCI is valid.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn diagnose(cert_id: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
 
     // Reject if empty
     if cert_id.is_empty() {
-        return uvci_data;
+        diagnostics.push(Diagnostic::new(
+            "E001",
+            Severity::Error,
+            0,
+            "empty UVCI".to_string(),
+        ));
+        return diagnostics;
     }
 
     // Up to a total length of 72 characters
     if cert_id.len() > 72 {
-        return uvci_data;
+        diagnostics.push(
+            Diagnostic::new(
+                "E002",
+                Severity::Error,
+                72,
+                format!(
+                    "UVCI is {} characters long, at most 72 are allowed",
+                    cert_id.len()
+                ),
+            )
+            .suggest("remove the characters after offset 72".to_string()),
+        );
     }
 
-    // Only uppercase characters are allowed
-    let cert_id = cert_id.to_uppercase();
-
-    // Headers
-    let mut cert_id2 = cert_id.clone();
-    if !cert_id.starts_with("URN:UVCI:") {
-        cert_id2 = "URN:UVCI:".to_owned() + &cert_id2;
+    // Only uppercase characters, digits and the separators are allowed
+    let mut lowercase_offset = None;
+    for (offset, c) in cert_id.char_indices() {
+        if c.is_ascii_lowercase() {
+            if lowercase_offset.is_none() {
+                lowercase_offset = Some(offset);
+            }
+        } else if !(c.is_ascii_uppercase()
+            || c.is_ascii_digit()
+            || "/:#".contains(c)
+            || (c == '-' && is_vaccine_id_dash(cert_id, offset)))
+        {
+            diagnostics.push(
+                Diagnostic::new(
+                    "E003",
+                    Severity::Error,
+                    offset,
+                    format!("invalid character '{}' at offset {}", c, offset),
+                )
+                .suggest(format!("remove or replace '{}'", c)),
+            );
+        }
+    }
+    if let Some(offset) = lowercase_offset {
+        diagnostics.push(
+            Diagnostic::new(
+                "W001",
+                Severity::Warning,
+                offset,
+                "only uppercase characters are allowed".to_string(),
+            )
+            .suggest(cert_id.to_ascii_uppercase()),
+        );
     }
-    let cert_id = cert_id2;
 
-    // Verify integrity of the UVCI
-    let l = Luhn::new("/0123456789:ABCDEFGHIJKLMNOPQRSTUVWXYZ").expect("invalid alphabet given");
-    uvci_data.checksum_verification = l.validate(rearrange(cert_id.to_string())).unwrap();
+    // Offsets below are byte offsets, so only ASCII characters are uppercased
+    let cert_id_upper = cert_id.to_ascii_uppercase();
+    let prefix_len = if cert_id_upper.starts_with("URN:UVCI:") {
+        "URN:UVCI:".len()
+    } else {
+        0
+    };
+    let (body, checksum) = match cert_id_upper.find('#') {
+        Some(pos) => (
+            &cert_id_upper[..pos],
+            Some((pos + 1, &cert_id_upper[pos + 1..])),
+        ),
+        None => (&cert_id_upper[..], None),
+    };
 
-    // Start parsing
-    let split_checksum = cert_id.split("#");
-    let vec: Vec<&str> = split_checksum.collect();
-    if vec.len() > 1 {
-        uvci_data.checksum = vec[1].to_string();
+    // Blocks: version, country and the schema option dependent identifier
+    let mut blocks = Vec::new();
+    let mut offset = prefix_len;
+    for block in body[prefix_len.min(body.len())..].splitn(3, ':') {
+        blocks.push((offset, block));
+        offset += block.len() + 1;
     }
-
-    // Verify that the prefix "URN:UVCI:" is added
-    let split_blocks = vec[0].split(":");
-    let vec: Vec<&str> = split_blocks.collect();
-    if vec[0] != "URN" && vec[1] != "UVCI" {
-        return uvci_data;
+    if blocks.len() < 3 {
+        diagnostics.push(
+            Diagnostic::new(
+                "E004",
+                Severity::Error,
+                body.len(),
+                "incomplete UVCI, expected version, country and identifier blocks".to_string(),
+            )
+            .suggest("use the form URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string()),
+        );
     }
-
-    // Detect schema
-    if vec.len() < 4 {
-        return uvci_data;
+    if let Some((offset, version)) = blocks.first() {
+        if !(version.len() == 2 && version.chars().all(|c| c.is_ascii_digit())) {
+            diagnostics.push(
+                Diagnostic::new(
+                    "E005",
+                    Severity::Error,
+                    *offset,
+                    format!("invalid version '{}', expected two digits", version),
+                )
+                .suggest("use the version 01".to_string()),
+            );
+        }
+    }
+    if let Some((offset, country)) = blocks.get(1) {
+        if !(country.len() == 2 && country.chars().all(|c| c.is_ascii_uppercase())) {
+            diagnostics.push(Diagnostic::new(
+                "E006",
+                Severity::Error,
+                *offset,
+                format!(
+                    "invalid country '{}', expected an ISO 3166-1 alpha-2 code",
+                    country
+                ),
+            ));
+        }
+    }
+    if let Some((offset, identifier)) = blocks.get(2) {
+        let options: Vec<&str> = identifier.split('/').collect();
+        if options.len() > 3 || options.iter().any(|o| o.is_empty()) {
+            diagnostics.push(Diagnostic::new(
+                "E007",
+                Severity::Error,
+                *offset,
+                format!(
+                    "unknown schema option, identifier '{}' has {} slash-separated blocks",
+                    identifier,
+                    options.len()
+                ),
+            ));
+        }
     }
 
-    // UVCI schema version
-    let temp = vec[2].to_string();
-    if temp.parse::<u8>().is_ok() {
-        uvci_data.version = temp.parse::<u8>().unwrap();
+    // Checksum
+    match checksum {
+        None => {
+            let mut diagnostic = Diagnostic::new(
+                "W002",
+                Severity::Warning,
+                cert_id.len(),
+                "missing checksum".to_string(),
+            );
+            if let Some(c) = expected_checksum(&cert_id_upper) {
+                diagnostic = diagnostic.suggest(format!("append '#{}'", c));
+            }
+            diagnostics.push(diagnostic);
+        }
+        Some((offset, checksum)) => {
+            let expected = expected_checksum(&cert_id_upper);
+            if let Some(second) = checksum.find('#') {
+                let mut diagnostic = Diagnostic::new(
+                    "E010",
+                    Severity::Error,
+                    offset + second,
+                    "more than one '#' separator".to_string(),
+                );
+                if let Some(c) = expected {
+                    diagnostic =
+                        diagnostic.suggest(format!("replace '#{}' with '#{}'", checksum, c));
+                }
+                diagnostics.push(diagnostic);
+            } else if checksum.chars().count() != 1 {
+                let mut diagnostic = Diagnostic::new(
+                    "E008",
+                    Severity::Error,
+                    offset,
+                    format!("checksum '{}' must be a single character", checksum),
+                );
+                if let Some(c) = expected {
+                    diagnostic = diagnostic.suggest(format!("replace '{}' with '{}'", checksum, c));
+                }
+                diagnostics.push(diagnostic);
+            } else if !CHECKSUM_ALPHABET.contains(checksum) {
+                let mut diagnostic = Diagnostic::new(
+                    "E011",
+                    Severity::Error,
+                    offset,
+                    format!(
+                        "checksum '{}' is not a character of the UVCI alphabet",
+                        checksum
+                    ),
+                );
+                if let Some(c) = expected {
+                    diagnostic = diagnostic.suggest(format!("replace '{}' with '{}'", checksum, c));
+                }
+                diagnostics.push(diagnostic);
+            } else if expected.map(|c| c.to_string()) != Some(checksum.to_string()) {
+                let mut diagnostic = Diagnostic::new(
+                    "E009",
+                    Severity::Error,
+                    offset,
+                    format!("checksum '{}' does not match", checksum),
+                );
+                if let Some(c) = expected {
+                    diagnostic = diagnostic.suggest(format!("replace '{}' with '{}'", checksum, c));
+                }
+                diagnostics.push(diagnostic);
+            }
+        }
     }
 
-    // ISO 3166-1 country code
-    uvci_data.country = vec[3].to_string();
+    return diagnostics;
+}
 
-    // Detect schema
-    if vec.len() < 5 {
-        return uvci_data;
+/// The characters of a UVCI check character, the alphabet of the LUHN-10 mod N checksum
+pub const CHECKSUM_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789/:";
+
+/// The characters of an ISO 7064 MOD 37-2 check character, of which "*" is not in the UVCI alphabet
+pub const ISO7064_MOD37_2_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ*";
+
+/// Compute the expected ISO-7812-1 (LUHN-10) check character of a UVCI, None if it cannot be computed
+///
+/// # Arguments
+///
+/// * `cert_id` - the UVCI, the checksum after '#' is ignored, e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+fn expected_checksum(cert_id: &str) -> Option<char> {
+    let cert_id = cert_id.to_uppercase();
+    let mut body = cert_id.split('#').next().unwrap_or("").to_string();
+    if !body.starts_with("URN:UVCI:") {
+        body = "URN:UVCI:".to_owned() + &body;
     }
-    let split_options = vec[4].split("/");
-    let vec: Vec<&str> = split_options.collect();
-    match vec.len() {
-        3 => {
-            uvci_data.schema_option_number = 1;
-            uvci_data.schema_option_desc = "identifier with semantics".to_string();
-            uvci_data.issuing_entity = vec[0].to_string();
-            uvci_data.vaccine_id = vec[1].to_string();
-            uvci_data.opaque_unique_string = vec[2].to_string();
-        }
-        1 => {
-            uvci_data.schema_option_number = 2;
-            uvci_data.schema_option_desc = "opaque identifier - no structure".to_string();
-            uvci_data.opaque_unique_string = vec[0].to_string();
-        }
-        2 => {
-            uvci_data.schema_option_number = 3;
-            uvci_data.schema_option_desc = "some semantics".to_string();
-            uvci_data.issuing_entity = vec[0].to_string();
-            uvci_data.opaque_unique_string = vec[1].to_string();
+    let l = Luhn::new("/0123456789:ABCDEFGHIJKLMNOPQRSTUVWXYZ").expect("invalid alphabet given");
+    for c in CHECKSUM_ALPHABET.chars() {
+        let candidate = format!("{}#{}", body, c);
+        if l.validate(rearrange(candidate)).unwrap_or(false) {
+            return Some(c);
         }
-        _ => (),
     }
+    return None;
+}
 
-    // Only for Sweden EHM-issued COVID certificates
-    if (uvci_data.version == 1)
-        && (uvci_data.country == "SE")
-        && (uvci_data.issuing_entity == "EHM")
-        && (uvci_data.schema_option_number == 3)
-    {
-        if uvci_data.opaque_unique_string.len() == 13 {
-            uvci_data.opaque_id = (&uvci_data.opaque_unique_string[0..9]).to_string();
-            uvci_data.opaque_issuance = (&uvci_data.opaque_unique_string[9..13]).to_string();
+/// Whether the ISO-7812-1 (LUHN-10) checksum of an uppercase UVCI is valid, with the prefix "URN:UVCI:" added
+fn luhn_valid(cert_id: &str) -> bool {
+    // Exactly one check character after a single '#'
+    if let Some((_, checksum)) = cert_id.split_once('#') {
+        if checksum.chars().count() != 1 {
+            return false;
+        }
+    }
+    let mut prefixed = cert_id.to_string();
+    if !cert_id.starts_with("URN:UVCI:") {
+        prefixed = "URN:UVCI:".to_owned() + &prefixed;
+    }
+    let l = Luhn::new("/0123456789:ABCDEFGHIJKLMNOPQRSTUVWXYZ").expect("invalid alphabet given");
+    return l.validate(rearrange(prefixed)).unwrap_or(false);
+}
+
+/// Error of `verify_checksum`, the UVCI has no checksum that can be verified
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChecksumError {
+    /// The input is empty
+    Empty,
+    /// The input is longer than 72 characters
+    TooLong,
+    /// There is no "#" checksum separator
+    MissingChecksum,
+    /// The checksum is not a single character after a single "#"
+    Checksum,
+    /// A character outside the UVCI alphabet, e.g. "_"
+    InvalidCharacter(char),
+}
 
-            let vaccination_date = get_vaccination_date_tan(uvci_data.opaque_id.clone());
-            uvci_data.opaque_vaccination_month = vaccination_date.0;
-            uvci_data.opaque_vaccination_year = vaccination_date.1;
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChecksumError::Empty => write!(f, "empty UVCI"),
+            ChecksumError::TooLong => write!(f, "UVCI longer than 72 characters"),
+            ChecksumError::MissingChecksum => write!(f, "missing checksum"),
+            ChecksumError::Checksum => write!(f, "checksum must be a single character"),
+            ChecksumError::InvalidCharacter(c) => write!(f, "invalid character '{}'", c),
         }
     }
+}
 
-    return uvci_data;
+impl std::error::Error for ChecksumError {}
+
+/// Whether the ISO-7812-1 (LUHN-10) checksum of a UVCI is valid, without parsing the UVCI
+///
+/// The UVCI is trimmed, its case ignored and the prefix "URN:UVCI:" added if missing, and the checksum
+/// is computed without allocating, for hot verification paths. Ok(false) is a checksum mismatch, the
+/// same as `checksum_verification` of `parse`.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn verify_checksum(cert_id: &str) -> Result<bool, ChecksumError> {
+    let cert_id = cert_id.trim();
+    if cert_id.is_empty() {
+        return Err(ChecksumError::Empty);
+    }
+    if cert_id.len() > 72 {
+        return Err(ChecksumError::TooLong);
+    }
+    let (body, checksum) = match cert_id.split_once('#') {
+        Some(split) => split,
+        None => return Err(ChecksumError::MissingChecksum),
+    };
+    let mut checksum_chars = checksum.chars();
+    let check = match (checksum_chars.next(), checksum_chars.next()) {
+        (Some(check), None) => check,
+        _ => return Err(ChecksumError::Checksum),
+    };
+    let prefixed = body
+        .get(..9)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("URN:UVCI:"));
+    let prefix = if prefixed { "" } else { "URN:UVCI:" };
+
+    // LUHN mod N as 'luhn-rs', from the first character rightwards, every second code doubled
+    let n = CHECKSUM_ALPHABET.len();
+    let mut sum = 0;
+    let mut factor = 1;
+    for c in prefix.chars().chain(body.chars()) {
+        let addend = factor * luhn_code(c).ok_or(ChecksumError::InvalidCharacter(c))?;
+        factor = 3 - factor;
+        sum += addend / n + addend % n;
+    }
+    let check = luhn_code(check).ok_or(ChecksumError::InvalidCharacter(check))?;
+    return Ok(check == (n - sum % n) % n);
+}
+
+/// The code of a UVCI character in the LUHN-10 mod N alphabet, None if it is not of the alphabet
+///
+/// The same codes as `rearrange`, the case is ignored and 'Z' has the code of 'Y'.
+fn luhn_code(c: char) -> Option<usize> {
+    return match c.to_ascii_uppercase() {
+        'Z' => CHECKSUM_ALPHABET.find('Y'),
+        c => CHECKSUM_ALPHABET.find(c),
+    };
+}
+
+/// Compute the ISO 7064 MOD 37-2 check character of a UVCI, None if it cannot be computed
+///
+/// The check character is computed over the alphanumeric characters of the UVCI with the prefix
+/// "URN:UVCI:" added, the ':' and '/' separators are ignored. It is one of "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ*".
+/// # Arguments
+///
+/// * `cert_id` - the UVCI, the checksum after '#' is ignored, e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+fn iso7064_mod37_2_checksum(cert_id: &str) -> Option<char> {
+    let cert_id = cert_id.to_uppercase();
+    let mut body = cert_id.split('#').next().unwrap_or("").to_string();
+    if !body.starts_with("URN:UVCI:") {
+        body = "URN:UVCI:".to_owned() + &body;
+    }
+    let mut p: u32 = 0;
+    for c in body.chars().filter(|c| *c != ':' && *c != '/') {
+        let value = ISO7064_MOD37_2_ALPHABET
+            .find(c)
+            .filter(|value| *value < 36)? as u32;
+        p = ((p + value) * 2) % 37;
+    }
+    return ISO7064_MOD37_2_ALPHABET
+        .chars()
+        .nth(((38 - p) % 37) as usize);
 }
 
 /// Rearrange the UVCI characters to enable validation of the checksum
@@ -449,7 +2255,7 @@ fn rearrange(cert_id: String) -> String {
 fn get_vaccination_date_tan(opaque_id: String) -> (u8, u16) {
     // vaccination_month from 0-xxxx
     let opaque_id = opaque_id.replace("V", "");
-    if !opaque_id.parse::<f32>().is_ok() {
+    if opaque_id.parse::<f32>().is_err() {
         return (0, 0);
     }
     let mut vaccination_doses = opaque_id.parse::<f32>().unwrap();
@@ -472,30 +2278,496 @@ fn get_vaccination_date_tan(opaque_id: String) -> (u8, u16) {
     }
 
     // vaccination_year from 2020-xxxx
-    let vaccination_year;
-    if vaccination_month == 0 {
-        vaccination_year = 2020;
+    let vaccination_year = if vaccination_month == 0 {
+        2020
     } else {
-        vaccination_year = ((vaccination_month - 1) / 12) + 2021;
-    }
+        ((vaccination_month - 1) / 12) + 2021
+    };
 
     // Reformat vaccination_month from 0-11 to 1-12
     if vaccination_month == 0 {
         vaccination_month = 12;
     }
     while vaccination_month > 12 {
-        vaccination_month = vaccination_month - 12;
+        vaccination_month -= 12;
     }
 
     // Return data
-    return (vaccination_month as u8, vaccination_year as u16);
+    return (vaccination_month as u8, vaccination_year);
+}
+
+/// Estimate the cumulative number of doses at the end of a vaccination month, the inverse of `get_vaccination_date_tan`
+///
+/// Returns the opaque_id number from which `get_vaccination_date_tan` estimates a later month, 0 for
+/// months before the vaccinations started in December 2020.
+/// # Arguments
+///
+/// * `month` - the vaccination month, e.g. 2021-08
+pub(crate) fn get_vaccination_doses_tan(month: YearMonth) -> f64 {
+    // vaccination_month from 0-xxxx, 0 for December 2020
+    if month.year < 2021 && !(month.year == 2020 && month.month == 12) {
+        return 0.0;
+    }
+    let vaccination_month = if month.year == 2020 {
+        0.0
+    } else {
+        ((month.year - 2021) as f64) * 12.0 + month.month as f64
+    };
+
+    // Tangent curve, the month is rounded so the next month starts half a month later
+    let doses = 6991632.0 + ((vaccination_month + 0.5 - 5.03) / 1.6).atan() * 5536858.0;
+    if doses <= 13983264.0 {
+        return doses.max(0.0);
+    }
+    // Assuming 1552008 doses a month, the month is truncated
+    return ((vaccination_month + 1.0) * 1552008.0).max(13983264.0);
 }
 
 #[cfg(test)]
 mod tests {
+    use super::bare_identifier;
+    use super::detect_checksum_algorithm;
+    use super::diagnose;
     use super::get_vaccination_date_tan;
+    use super::get_vaccination_doses_tan;
+    use super::next_issuance;
+    use super::normalized_cert_id;
     use super::parse;
+    use super::parse_dcc_json;
+    use super::parse_lenient;
+    use super::parse_lenient_with_options;
+    use super::parse_with_options;
+    use super::try_parse;
     use super::uvci_to_csv;
+    use super::uvci_to_graph;
+    use super::uvci_to_graph_merge;
+    use super::uvcis_to_csv;
+    use super::uvcis_to_csv_writer;
+    use super::uvcis_to_diagnostics_json_writer;
+    use super::uvcis_to_ehealth_json;
+    use super::uvcis_to_es_bulk;
+    use super::uvcis_to_graph;
+    use super::uvcis_to_json;
+    use super::uvcis_to_json_writer;
+    use super::uvcis_to_table;
+    use super::uvcis_to_table_writer;
+    use super::verify_checksum;
+    use super::with_prefix_and_checksum;
+    use super::ChecksumAlgorithm;
+    use super::ChecksumError;
+    use super::CountryCode;
+    use super::GroupType;
+    use super::ParseOptions;
+    use super::SchemaOption;
+    use super::Warning;
+    use super::YearMonth;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn uvci_country_code() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(uvci.country == CountryCode::from_code("SE"));
+        assert!(uvci.country.unwrap() == "SE");
+        assert!(uvci.country.unwrap().name() == Some("Sweden"));
+        assert!(uvci.country_str() == "SE");
+        assert!(parse("URN:UVCI:01:S3:EHM/V12907267LAJW#E")
+            .country
+            .is_none());
+        assert!(parse("URN:UVCI:01:SWE:EHM/V12907267LAJW#E").country_str() == "");
+        assert!(CountryCode::from_code("se").is_none());
+        assert!(format!("{}", CountryCode::from_code("AT").unwrap()) == "AT");
+    }
+
+    #[test]
+    fn uvci_ehealth_json() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC".to_string(),
+        ];
+        let json: serde_json::Value =
+            serde_json::from_str(&uvcis_to_ehealth_json(&cert_ids)).unwrap();
+        assert!(json[0]["schemaOptionNumber"] == 3);
+        assert!(json[0]["opaqueId"] == "V12907267");
+        assert!(json[0]["opaqueVaccinationYear"]["value"] == 2021);
+        assert!(json[0]["opaqueVaccinationYear"]["estimated"] == true);
+        assert!(json[1]["vaccineId"] == "C878");
+        assert!(json[1]["opaqueVaccinationMonth"].is_null());
+        assert!(json[1]["checksum"].is_null());
+    }
+
+    #[test]
+    fn uvcis_es_bulk() {
+        let cert_ids = vec![
+            "urn:uvci:01:se:ehm/v12907267lajw#e".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+        ];
+        let bulk = uvcis_to_es_bulk(&cert_ids, "uvcis");
+        assert!(bulk.ends_with('\n'));
+        let lines: Vec<serde_json::Value> = bulk
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(lines.len() == 4);
+        assert!(lines[0]["index"]["_index"] == "uvcis");
+        assert!(lines[0]["index"]["_id"] == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(lines[1]["opaque_id"] == "V12907267");
+        assert!(lines[2]["index"]["_id"] == "URN:UVCI:01:NL:187/37512422923");
+        assert!(lines[3]["country"] == "NL");
+
+        // Malformed UVCIs that differ only in blocks the parser drops are distinct documents
+        let cert_ids = vec![
+            "URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W".to_string(),
+            "URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T".to_string(),
+        ];
+        let bulk = uvcis_to_es_bulk(&cert_ids, "uvcis");
+        let lines: Vec<serde_json::Value> = bulk
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(lines[0]["index"]["_id"] == "URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W");
+        assert!(lines[2]["index"]["_id"] == "URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T");
+    }
+
+    #[test]
+    fn uvci_unknown_fields() {
+        let uvci = parse("URN:UVCI:AB:SE:123456789ABC");
+        assert!(uvci.version.is_none());
+        assert!(uvci.issuing_entity.is_none() && uvci.vaccine_id.is_none());
+        assert!(uvci.opaque_id.is_none() && uvci.opaque_vaccination.is_none());
+        assert!(uvci.checksum.is_none());
+        let uvci = parse("URN:UVCI:01:SE:EHM/C878/123456789ABC#B");
+        assert!(uvci.version == Some(1));
+        assert!(uvci.vaccine_id.as_deref() == Some("C878"));
+        assert!(uvci.checksum.as_deref() == Some("B"));
+    }
+
+    #[test]
+    fn uvci_vaccine_product_and_lot() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/C878/123456789ABC#B");
+        assert!(uvci.vaccine_product_code.is_none() && uvci.vaccine_lot.is_none());
+        let uvci = parse("URN:UVCI:01:SE:EHM/C878-AB123/123456789ABC");
+        assert!(uvci.schema_option == SchemaOption::WithSemantics);
+        assert!(uvci.vaccine_id.as_deref() == Some("C878-AB123"));
+        assert!(uvci.vaccine_product_code.as_deref() == Some("C878"));
+        assert!(uvci.vaccine_lot.as_deref() == Some("AB123"));
+        assert!(uvci.opaque_unique_string == "123456789ABC");
+        assert!(try_parse("URN:UVCI:01:SE:EHM/C878-AB123/123456789ABC").is_ok());
+        assert!(!diagnose("URN:UVCI:01:SE:EHM/C878-AB123/123456789ABC")
+            .iter()
+            .any(|d| d.code == "E003"));
+        // A dash outside the vaccine id is still invalid
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267-LAJW");
+        assert!(uvci.schema_option == SchemaOption::Unknown && uvci.vaccine_lot.is_none());
+        assert!(diagnose("URN:UVCI:01:SE:EHM/V12907267-LAJW")[0].code == "E003");
+    }
+
+    #[test]
+    fn uvci_schema_option() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/C878/123456789ABC#B");
+        assert!(uvci.schema_option == SchemaOption::WithSemantics);
+        assert!(uvci.schema_option_number() == 1);
+        assert!(uvci.schema_option_desc() == "identifier with semantics");
+        assert!(
+            parse("URN:UVCI:01:SE:123456789ABC").schema_option == SchemaOption::OpaqueNoStructure
+        );
+        assert!(parse("URN:UVCI:01").schema_option.to_string() == "unknown");
+        assert!(SchemaOption::from_number(3) == SchemaOption::SomeSemantics);
+    }
+
+    #[test]
+    fn uvci_bare_identifier() {
+        let bare_id = bare_identifier("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(bare_id == "01:SE:EHM/V12907267LAJW");
+        assert!(bare_identifier("urn:uvci:01:se:ehm/v12982924yqmv#t") == "01:SE:EHM/V12982924YQMV");
+        assert!(bare_identifier("01:SE:EHM/C878/123456789ABC") == "01:SE:EHM/C878/123456789ABC");
+        assert!(with_prefix_and_checksum(&bare_id) == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        let cert_id = with_prefix_and_checksum("01:SE:EHM/V12916227TFJJ");
+        assert!(cert_id == "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q");
+        assert!(parse(&cert_id).checksum_verification);
+    }
+
+    #[test]
+    fn uvci_next_issuance() {
+        let existing = vec!["URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string()];
+        let cert_id = next_issuance("V12916227", &existing);
+        let uvci = parse(&cert_id);
+        assert!(uvci.opaque_id.as_deref() == Some("V12916227"));
+        assert!(uvci.checksum_verification);
+        let issuance = uvci.opaque_issuance.clone().unwrap();
+        assert!(issuance != "TFJJ" && issuance.chars().all(|c| c.is_ascii_uppercase()));
+        assert!(next_issuance("v12916227", &existing) == cert_id);
+
+        // An issuance code already issued is skipped
+        let reissue = next_issuance("V12916227", std::slice::from_ref(&issuance));
+        assert!(parse(&reissue).opaque_issuance.clone().unwrap() != issuance);
+    }
+
+    #[test]
+    fn uvci_canonical_digest() {
+        let digest = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E").canonical_digest();
+        assert!(parse("urn:uvci:01:se:ehm/v12907267lajw#e").canonical_digest() == digest);
+        assert!(parse("01:SE:EHM/V12907267LAJW#E").canonical_digest() == digest);
+        assert!(digest[..] == Sha256::digest(b"URN:UVCI:01:SE:EHM/V12907267LAJW#E")[..]);
+        assert!(parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q").canonical_digest() != digest);
+
+        // UVCIs that cannot be rebuilt from their blocks keep their input
+        let german = parse(" URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W");
+        assert!(german.canonical() == "URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W");
+        assert!(german.cert_id == "URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W");
+        assert!(
+            parse("URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T").canonical()
+                != german.canonical()
+        );
+        assert!(parse("urn:uvci:V1:MT:ABC#X").canonical() == "URN:UVCI:V1:MT:ABC#X");
+        assert!(
+            normalized_cert_id(" 01:se:ehm/v12907267lajw#e")
+                == "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+        );
+    }
+
+    #[test]
+    fn uvci_verify_checksum() {
+        let generated = with_prefix_and_checksum("01:SE:EHM/ZZ12907267LAJW");
+        for cert_id in [
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E",
+            "urn:uvci:01:se:ehm/v12907267lajw#e",
+            " 01:SE:EHM/V12907267LAJW#E",
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#F",
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q",
+            generated.as_str(),
+        ] {
+            assert!(verify_checksum(cert_id) == Ok(parse(cert_id.trim()).checksum_verification));
+        }
+        assert!(verify_checksum(&generated) == Ok(true));
+        assert!(verify_checksum("URN:UVCI:01:SE:EHM/V12907267LAJW#F") == Ok(false));
+        assert!(
+            verify_checksum("URN:UVCI:01:NL:187/37512422923")
+                == Err(ChecksumError::MissingChecksum)
+        );
+        assert!(
+            verify_checksum("URN:UVCI:01:SE:EHM/V1290_7267LAJW#E")
+                == Err(ChecksumError::InvalidCharacter('_'))
+        );
+        assert!(
+            verify_checksum("URN:UVCI:01:SE:EHM/V12907267LAJW#EE") == Err(ChecksumError::Checksum)
+        );
+        assert!(verify_checksum("  ") == Err(ChecksumError::Empty));
+    }
+
+    #[test]
+    fn uvci_try_parse() {
+        let uvci = try_parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E").unwrap();
+        assert!(uvci.opaque_id.as_deref() == Some("V12907267"));
+        let error = try_parse("URN:UVCI:01:SE:EHM/V12907267/LAJW/X#E")
+            .err()
+            .unwrap();
+        assert!(error.kind == super::grammar::ParseErrorKind::TooManyBlocks);
+        assert!(error.offset == 33);
+        assert!(try_parse("urn:uvci:01:se:ehm/v12982924yqmv#t").is_err());
+        // More than three blocks is rejected, and parsed leniently without any identifier field
+        let error = try_parse("URN:UVCI:01:SE://////////").err().unwrap();
+        assert!(error.kind == super::grammar::ParseErrorKind::TooManyBlocks);
+        let (uvci, warnings) = parse_lenient("URN:UVCI:01:SE://////////");
+        assert!(uvci.schema_option == SchemaOption::Unknown && uvci.country_str() == "SE");
+        assert!(uvci.issuing_entity.is_none() && uvci.opaque_unique_string == "//////////");
+        assert!(warnings.contains(&Warning::Syntax(error)));
+        // Invalid characters are no longer a panic
+        assert!(parse("not a UVCI").schema_option == SchemaOption::Unknown);
+        // The blocks before a syntax error are not classified, the raw rest is kept
+        let uvci = parse("URN:UVCI:01:AT:ABC_DEF");
+        assert!(uvci.schema_option == SchemaOption::Unknown);
+        assert!(uvci.opaque_unique_string == "ABC_DEF");
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12916227 TFJJ#Q");
+        assert!(uvci.schema_option == SchemaOption::Unknown && uvci.issuing_entity.is_none());
+        assert!(uvci.opaque_unique_string != "V12916227" && uvci.opaque_id.is_none());
+    }
+
+    #[test]
+    fn uvci_parse_lenient() {
+        let (uvci, warnings) = parse_lenient("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(uvci.opaque_id.as_deref() == Some("V12907267"));
+        assert!(warnings.is_empty());
+
+        let (uvci, warnings) = parse_lenient("02:se:ehm/v12907267lajw");
+        assert!(uvci.country_str() == "SE" && uvci.version == Some(2));
+        assert!(
+            warnings
+                == vec![
+                    Warning::LowercaseNormalized,
+                    Warning::MissingPrefix,
+                    Warning::UnknownVersion(2),
+                    Warning::MissingChecksum,
+                ]
+        );
+
+        let (uvci, warnings) = parse_lenient("URN:UVCI:01:SE:EHM/V1291_227TFJJ#Q");
+        assert!(uvci.schema_option == SchemaOption::Unknown && uvci.issuing_entity.is_none());
+        assert!(uvci.opaque_unique_string == "EHM/V1291_227TFJJ#Q");
+        match &warnings[0] {
+            Warning::Syntax(error) => assert!(error.offset == 24),
+            warning => panic!("unexpected warning {}", warning),
+        }
+    }
+
+    #[test]
+    fn uvci_checksum_algorithms() {
+        let cert_id = "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#G";
+        let mod37 = ChecksumAlgorithm::Iso7064Mod37_2;
+        assert!(mod37.expected_checksum(cert_id) == Some('G'));
+        assert!(mod37.verify(cert_id) && !ChecksumAlgorithm::LuhnModN.verify(cert_id));
+        assert!(!mod37.verify("URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#H"));
+        assert!(ChecksumAlgorithm::from_code("iso7064-mod37-2") == Some(mod37));
+
+        assert!(!parse_with_options(cert_id, &ParseOptions::new()).checksum_verification);
+        let options = ParseOptions::new().checksum_algorithm(mod37);
+        assert!(parse_with_options(cert_id, &options).checksum_verification);
+
+        // '*' is a check character of ISO 7064 MOD 37-2, not of the UVCI alphabet
+        let star = "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD89E#*";
+        assert!(mod37.expected_checksum(star) == Some('*'));
+        let uvci = parse_with_options(star, &options);
+        assert!(uvci.schema_option == SchemaOption::OpaqueNoStructure);
+        assert!(uvci.checksum.as_deref() == Some("*") && uvci.checksum_verification);
+        assert!(parse_lenient_with_options(star, &options).1.is_empty());
+        assert!(parse(star).schema_option == SchemaOption::Unknown);
+
+        let options = ParseOptions::new()
+            .detect_checksum_algorithm(true)
+            .register_checksum_algorithm("at", mod37);
+        assert!(detect_checksum_algorithm(cert_id, &options) == Some(mod37));
+        assert!(parse_with_options(cert_id, &options).checksum_verification);
+        let cert_id = "URN:UVCI:01:SE:EHM/V12907267LAJW#E";
+        assert!(detect_checksum_algorithm(cert_id, &options) == Some(ChecksumAlgorithm::LuhnModN));
+        // Only the algorithms registered for the claimed country are tried
+        let cert_id = "URN:UVCI:01:DE:10807843F94AEE0EE5093FBC254BD813#V";
+        assert!(mod37.verify(cert_id));
+        assert!(detect_checksum_algorithm(cert_id, &options).is_none());
+    }
+
+    #[test]
+    fn uvci_checksum_without_separator() {
+        let cert_id = "URN:UVCI:01:SE:EHM/V12907267LAJWE";
+        let uvci = parse(cert_id);
+        assert!(uvci.checksum.is_none() && uvci.opaque_unique_string == "V12907267LAJWE");
+
+        let options = ParseOptions::new().detect_checksum_without_separator(true);
+        let uvci = parse_with_options(cert_id, &options);
+        assert!(uvci.checksum.as_deref() == Some("E") && uvci.checksum_verification);
+        assert!(uvci.opaque_id.as_deref() == Some("V12907267"));
+        let (_, warnings) = parse_lenient_with_options(cert_id, &options);
+        assert!(warnings == vec![Warning::ChecksumDetectedWithoutSeparator('E')]);
+
+        // Not detected if the last character is not the check character of the rest
+        let cert_id = "URN:UVCI:01:SE:EHM/V12907267LAJW";
+        let (uvci, warnings) = parse_lenient_with_options(cert_id, &options);
+        assert!(uvci.checksum.is_none());
+        assert!(warnings == vec![Warning::MissingChecksum]);
+        let cert_id = "URN:UVCI:01:SE:EHM/V12907267LAJW#E";
+        assert!(parse_with_options(cert_id, &options).checksum.as_deref() == Some("E"));
+
+        // Characters whose uppercase is of another length do not panic
+        for cert_id in [
+            "01:SE:HM/C878/12345679ABC\u{17f}",
+            "01:SEEHM/C878/12345689AB\u{fb00}",
+        ] {
+            let uvci = parse_with_options(cert_id, &options);
+            assert!(uvci.checksum.is_none() || uvci.checksum_verification);
+            let _ = parse_lenient_with_options(cert_id, &options);
+        }
+    }
+
+    #[test]
+    fn uvcis_graph() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+        let graph = uvcis_to_graph(&cert_ids);
+        assert!(graph.starts_with("\nCREATE (SE:country {name:'Sweden'})"));
+        assert!(graph.matches(":country ").count() == 1);
+        assert!(graph.matches(":reissue_id ").count() == 2);
+        assert!(graph
+            .lines()
+            .skip(1)
+            .all(|line| line.starts_with("CREATE (")));
+        assert!(uvcis_to_graph(&Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn uvci_graph_certificate() {
+        let graph = uvci_to_graph("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(graph.contains("CREATE (V12907267LAJW:reissue_id {name:'LAJW', checksum_valid:true, quality_score:100, schema_option:3})"));
+        let graph = uvci_to_graph("URN:UVCI:01:SE:EHM/V12907267LAJW#A");
+        assert!(graph.contains("CREATE (V12907267LAJW:reissue_id:invalid_certificate {name:'LAJW', checksum_valid:false, quality_score:60, schema_option:3})"));
+
+        let merge = uvci_to_graph_merge("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(merge.ends_with("SET V12907267LAJW.checksum_valid = true, V12907267LAJW.quality_score = 100, V12907267LAJW.schema_option = 3"));
+        let merge = uvci_to_graph_merge("URN:UVCI:01:SE:EHM/V12907267LAJW");
+        assert!(merge
+            .contains("V12907267LAJW.checksum_valid = false, V12907267LAJW.quality_score = 90"));
+        assert!(!merge.contains("invalid_certificate"));
+        let merge = uvci_to_graph_merge("URN:UVCI:01:SE:EHM/V12907267LAJW#A");
+        assert!(merge.ends_with(", V12907267LAJW:invalid_certificate"));
+    }
+
+    #[test]
+    fn uvcis_writers() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#X".to_string(),
+        ];
+        let mut csv = Vec::new();
+        uvcis_to_csv_writer(&cert_ids, &mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap() == uvcis_to_csv(&cert_ids));
+        let mut json = Vec::new();
+        uvcis_to_json_writer(&cert_ids, &mut json).unwrap();
+        assert!(String::from_utf8(json).unwrap() == uvcis_to_json(&cert_ids));
+        let mut table = Vec::new();
+        uvcis_to_table_writer(&cert_ids, &mut table).unwrap();
+        assert!(String::from_utf8(table).unwrap() == uvcis_to_table(&cert_ids));
+        let mut diagnostics = Vec::new();
+        uvcis_to_diagnostics_json_writer(&cert_ids, &mut diagnostics).unwrap();
+        let diagnostics: serde_json::Value = serde_json::from_slice(&diagnostics).unwrap();
+        assert!(diagnostics.as_array().unwrap().len() == 1);
+        assert!(uvcis_to_json(&[]) == "[]");
+    }
+
+    #[test]
+    fn year_month_days_since_epoch() {
+        let month = |year, month| YearMonth { year, month };
+        assert!(month(1970, 1).days_since_epoch() == 0);
+        assert!(month(1969, 12).days_since_epoch() == -31);
+        assert!(month(2000, 3).days_since_epoch() == 11017);
+        assert!(month(2021, 8).days_since_epoch() == 18840);
+    }
+
+    #[test]
+    fn vaccination_doses_inverse() {
+        let mut previous = 0.0;
+        for index in 0..24u16 {
+            let month = YearMonth {
+                year: 2021 + index / 12,
+                month: (index % 12 + 1) as u8,
+            };
+            let doses = get_vaccination_doses_tan(month);
+            assert!(doses > previous);
+            // The doses before are estimated in the month, the doses after in a later month, with
+            // a margin for the f32 precision of the estimation
+            let (m, y) = get_vaccination_date_tan(((doses - 4.0) as u32).to_string());
+            assert!(YearMonth { year: y, month: m } <= month, "{}", month);
+            let (m, y) = get_vaccination_date_tan(((doses + 4.0) as u32).to_string());
+            assert!(YearMonth { year: y, month: m } > month, "{}", month);
+            previous = doses;
+        }
+        assert!(
+            get_vaccination_doses_tan(YearMonth {
+                year: 2020,
+                month: 11
+            }) == 0.0
+        );
+    }
 
     #[test]
     fn uvci_csv() {
@@ -505,6 +2777,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn uvcis_csv_json() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V00016227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+        assert!(
+            uvcis_to_csv(&cert_ids)
+                == "1,SE,3,some semantics,EHM,,V00016227TFJJ,V00016227,TFJJ,12,2020,Q,false\n\
+                    1,SE,1,identifier with semantics,EHM,C878,123456789ABC,,,0,0,B,true\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&uvcis_to_json(&cert_ids)).unwrap();
+        assert!(json[1]["vaccine_id"] == "C878");
+        assert!(json[0]["opaque_vaccination_year"] == 2020);
+    }
+
+    #[test]
+    fn uvci_table() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+        ];
+        let table = uvcis_to_table(&cert_ids);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines.len() == 4, "wrong number of table lines");
+        assert!(lines[0].starts_with("cert_id"), "missing header");
+        let column = lines[0].find("| version").unwrap();
+        for line in &lines {
+            assert!(
+                line[column..].starts_with("| ") || line[column..].starts_with("+-"),
+                "misaligned column"
+            );
+        }
+        assert!(lines[3].contains("| C878 "), "missing vaccine_id");
+    }
+
+    #[test]
+    fn uvci_diagnostics() {
+        assert!(diagnose("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q").is_empty());
+
+        let diagnostics = diagnose("URN:UVCI:01:SE:EHM/V12916227TFJJ#B");
+        assert!(diagnostics.len() == 1, "expected one diagnostic");
+        assert!(diagnostics[0].code == "E009", "expected checksum mismatch");
+        assert!(diagnostics[0].offset == 33, "wrong checksum offset");
+        assert!(diagnostics[0].suggestion == Some("replace 'B' with 'Q'".to_string()));
+
+        let diagnostics = diagnose("URN:UVCI:01:SE:EHM/V1291_227TFJJ");
+        assert!(diagnostics[0].code == "E003" && diagnostics[0].offset == 24);
+        assert!(diagnostics.iter().any(|d| d.code == "W002"));
+
+        let diagnostics = diagnose("01:S3:EHM/V12916227TFJJ#Q");
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.code == "E006" && d.offset == 3));
+        let diagnostics = diagnose("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q#X");
+        assert!(diagnostics.len() == 1);
+        assert!(diagnostics[0].code == "E010" && diagnostics[0].offset == 34);
+        assert!(diagnostics[0].suggestion == Some("replace '#Q#X' with '#Q'".to_string()));
+        let diagnostics = diagnose("URN:UVCI:01:SE:EHM/V12916227TFJJ#QEXTRA");
+        assert!(diagnostics.len() == 1 && diagnostics[0].code == "E008");
+        let diagnostics = diagnose("URN:UVCI:01:SE:EHM/V12916227TFJJ#*");
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.code == "E011" && d.offset == 33));
+        assert!(diagnostics.iter().all(|d| d.code != "E009"));
+
+        // Neither has a checksum to verify
+        for cert_id in &[
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q#X",
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#QEXTRA",
+        ] {
+            let uvci = parse(cert_id);
+            assert!(uvci.checksum.is_none() && !uvci.checksum_verification);
+        }
+    }
+
+    #[test]
+    fn dcc_json() {
+        let payload = r#"{
+            "ver": "1.3.0",
+            "v": [{"ci": "URN:UVCI:01:SE:EHM/V12907267LAJW#E", "dn": 2}],
+            "t": [{"ci": "URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#B"}]
+        }"#;
+        let uvcis = parse_dcc_json(payload);
+        assert!(uvcis.len() == 2, "wrong number of UVCIs");
+        assert!(uvcis[0].0 == GroupType::Vaccination && uvcis[0].1.checksum_verification);
+        assert!(uvcis[1].0 == GroupType::Test && uvcis[1].1.country_str() == "AT");
+        assert!(parse_dcc_json("not json").is_empty());
+    }
+
     #[test]
     fn swedish_uvci_opaque_date() {
         assert!(
@@ -557,19 +2919,33 @@ mod tests {
             "wrong opaque_unique_string"
         );
         assert!(
-            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E").opaque_id == "V12907267",
+            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")
+                .opaque_id
+                .as_deref()
+                == Some("V12907267"),
             "wrong opaque_id"
         );
         assert!(
-            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E").opaque_issuance == "LAJW",
+            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")
+                .opaque_issuance
+                .as_deref()
+                == Some("LAJW"),
             "wrong opaque_issuance"
         );
         assert!(
-            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E").opaque_vaccination_month == 8,
+            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")
+                .opaque_vaccination
+                .unwrap()
+                .month
+                == 8,
             "wrong opaque_vaccination_month"
         );
         assert!(
-            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E").opaque_vaccination_year == 2021,
+            parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")
+                .opaque_vaccination
+                .unwrap()
+                .year
+                == 2021,
             "wrong opaque_vaccination_month"
         );
     }
@@ -656,7 +3032,7 @@ mod tests {
         for cert_id in &cert_ids_assorted {
             println!("{}\n{}\n", cert_id, parse(cert_id));
             assert!(
-                parse(cert_id).schema_option_number <= 3,
+                parse(cert_id).schema_option_number() <= 3,
                 "schema_option_number larger than 3"
             );
         }
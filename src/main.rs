@@ -1,43 +1,1663 @@
-use covid_cert_uvci::uvcis_to_graph;
+// Explicit returns, single character string pushes and the usage text are the style of this crate
+#![allow(
+    clippy::needless_return,
+    clippy::single_char_add_str,
+    clippy::print_with_newline,
+    clippy::too_many_arguments
+)]
+
+use covid_cert_uvci::analysis::{
+    analyze_structure, cluster_by_pattern, clusters_to_text, histogram, vaccination_months,
+};
+use covid_cert_uvci::compression::{decompressed_reader, CompressedWriter, Compression};
+use covid_cert_uvci::conformance::{fetch_conformance, run_conformance, DGC_TESTDATA_REPOSITORY};
+use covid_cert_uvci::coverage::{coverage_report, read_population};
+use covid_cert_uvci::diff::diff_datasets;
+#[cfg(feature = "duckdb")]
+use covid_cert_uvci::duckdb_export::DuckDbExporter;
+use covid_cert_uvci::encryption::EncryptedWriter;
+use covid_cert_uvci::export::{
+    ApocIterateExporter, CsvExporter, CypherExporter, CypherShellExporter, DateFormat, DotExporter,
+    EdgeListExporter, ExportOptions, Exporter, JsonlExporter, Neo4jAdminExporter,
+    RedactingExporter, Redaction, SortKey, SortingExporter, SplunkHecExporter,
+};
+use covid_cert_uvci::follow::{extract_uvcis, follow_record, LogFollower};
+use covid_cert_uvci::incremental::{GraphState, IncrementalGraphExporter};
+use covid_cert_uvci::manifest::{Manifest, ManifestFile};
+use covid_cert_uvci::pipeline::{run_pipeline, PipelineOptions};
+use covid_cert_uvci::pseudonym::{anonymized_cert_id, Pseudonymizer, PseudonymizingExporter};
+use covid_cert_uvci::reject::{partition_batch, rejection, RejectLog};
+use covid_cert_uvci::revocation::{
+    revocation_hash, HashEncoding, RevocationFilter, RevocationHashType,
+};
+use covid_cert_uvci::siem::{uvcis_to_siem_writer, SiemFormat, SiemFormatter};
+#[cfg(feature = "ratatui")]
+use covid_cert_uvci::tui::run_tui;
+use covid_cert_uvci::{parse, uvcis_to_diagnostics_json, uvcis_to_table, Uvci};
 use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
     env,
-    fs::File,
-    io::{prelude::*, BufReader},
+    fs::{self, File, OpenOptions},
+    io::{self, prelude::*, BufReader, BufWriter, SeekFrom},
     path::Path,
+    rc::Rc,
+    thread,
+    time::Duration,
 };
 
 fn lines_from_file(filename: impl AsRef<Path>) -> Vec<String> {
     let file = File::open(filename).expect("no such file");
-    let buf = BufReader::new(file);
+    let buf = decompressed_reader(file).expect("Could not read file");
     buf.lines()
         .map(|l| l.expect("Could not parse line"))
         .collect()
 }
 
-/// cargo run covid_uvci.txt graph_cypher.txt
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        print!("USAGE:\n");
-        print!("    [Name of Covid UVCI input file] [Name of Graph Cypher output file]");
-        return;
+/// Command line options and file names
+struct Args {
+    options: HashMap<String, String>,
+    files: Vec<String>,
+}
+
+impl Args {
+    fn option(&self, name: &str, default: &str) -> String {
+        return self
+            .options
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| default.to_string());
     }
-    let infile = &args[1];
-    let outfile = &args[2];
+}
 
-    let cert_ids_sweden = lines_from_file(infile);
-    let mut graph_output = uvcis_to_graph(&cert_ids_sweden);
-    graph_output.push_str("\nRETURN *\n");
+/// Split the arguments into options taking a value, flags and file names, None for unknown options or missing values
+fn parse_args(args: Vec<String>, value_options: &[&str], flags: &[&str]) -> Option<Args> {
+    let mut parsed = Args {
+        options: HashMap::new(),
+        files: Vec::new(),
+    };
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if value_options.contains(&arg.as_str()) {
+            let value = iter.next()?;
+            parsed.options.insert(arg, value);
+        } else if flags.contains(&arg.as_str()) {
+            parsed.options.insert(arg, "".to_string());
+        } else if arg.starts_with("--") {
+            return None;
+        } else {
+            parsed.files.push(arg);
+        }
+    }
+    return Some(parsed);
+}
+
+/// Write the output to the file, or print it to the terminal if no file is given
+fn write_output(outfile: Option<&String>, output: &str) {
+    let outfile = match outfile {
+        Some(outfile) => outfile,
+        None => {
+            print!("{}", output);
+            return;
+        }
+    };
 
     let path = Path::new(outfile);
     let display = path.display();
     // Open a file in write-only mode, returns `io::Result<File>`
-    let mut file = match File::create(&path) {
+    let mut file = match File::create(path) {
         Err(why) => panic!("couldn't create {}: {}", display, why),
         Ok(file) => file,
     };
-    match file.write_all(graph_output.as_bytes()) {
+    match file.write_all(output.as_bytes()) {
         Err(why) => panic!("couldn't write to {}: {}", display, why),
         Ok(_) => println!("successfully wrote to {}", display),
     }
 }
+
+fn print_usage() {
+    print!("USAGE:\n");
+    print!("    [--format graph|neo4j-admin|edgelist|csv|jsonl|dot|table|diagnostics|duckdb|splunk|cef|syslog] [--table name] [--sourcetype name] [--hostname name] [--reject-log file] [--checkpoint-every lines] [--resume] [--compress gz|zst] [--encrypt recipients] [--chunk-size certificates [--chunk-files] | --apoc-batch-size rows] [--constraints] [--native-dates] [--graph-state file] [--sort-by date|country|issuer] [--date-format iso-month|iso-week|month-name[:locale]] [--threads workers] [--redact none|mask|hash | --pseudonymize key-file [--pseudonym-map file]] [--output file] [--force] [Names or glob patterns of Covid UVCI input files] [Name of output file]\n");
+    print!("    analyze [--clusters] [Name of Covid UVCI input file] [Name of output file]\n");
+    print!("    stats [--histogram] [Name of Covid UVCI input file] [Name of output file]\n");
+    print!(
+        "    coverage --population file [Name of Covid UVCI input file] [Name of output file]\n"
+    );
+    print!("    diff [--format text|json] [Name of old Covid UVCI input file] [Name of new Covid UVCI input file] [Name of output file]\n");
+    print!("    conformance [--format text|json] [Name of dgc-testdata directory] [Name of output file]\n");
+    print!(
+        "    conformance --fetch [--countries AT,SE] [--format text|json] [Name of output file]\n"
+    );
+    print!("    follow [--extract] [--from-start] [--interval milliseconds] [--revocation-filter file [--hash-type uci|countrycodeuci]] [Name of log file]\n");
+    print!("    revoke-hash [--hash-type uci|countrycodeuci] [--encoding hex|base64] [--reject-log file] [Name of Covid UVCI input file] [Name of output file]\n");
+    print!("    anonymize [--key-file file [--pseudonym-map file] | --redact mask|hash] [--reject-log file] [Name of Covid UVCI input file] [Name of output file]\n");
+    print!("    tui [Name of Covid UVCI input file]\n");
+    print!("    --partition [Name of Covid UVCI input file] [Name of valid output file] [Name of invalid output file]\n");
+    print!("\n");
+    print!("    The output file may be omitted for all formats except graph, the output is then printed to the terminal\n");
+    print!("    --partition writes the checksum-valid UVCIs to the valid output file and the others as JSON Lines with line number and reason\n");
+    print!("    to the invalid output file\n");
+    print!("    follow prints every UVCI of the lines appended to the log file as NDJSON with its diagnostics, from the end of the file\n");
+    print!("    unless --from-start, --extract scans free text for the UVCIs instead of reading one per line, checked every interval\n");
+    print!("    (default 500); with --revocation-filter, a serialized revocation bloom filter, UVCIs are flagged probably_revoked\n");
+    print!("    With --reject-log, invalid UVCIs are skipped and logged as JSON Lines with line number and reason (not for table, diagnostics, cef and syslog)\n");
+    print!("    With --checkpoint-every, graph, csv and jsonl output files are checkpointed every given number of lines (default 100000 with --resume),\n");
+    print!("    --resume continues an interrupted conversion from the checkpoint, both are an error with --compress, --encrypt,\n");
+    print!("    --chunk-size, --apoc-batch-size, --constraints, --sort-by, --date-format, --native-dates, --threads, --reject-log,\n");
+    print!("    --pseudonymize or --graph-state\n");
+    print!("    Input files may be gzip or Zstandard compressed, --compress compresses the output file (not for table, diagnostics, cef and syslog)\n");
+    print!("    --encrypt encrypts the output file to the comma-separated age recipients, e.g. age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p\n");
+    print!("    With --chunk-size, the graph is written as cypher-shell MERGE statements committed every given number of certificates,\n");
+    print!("    --chunk-files writes every transaction to a numbered chunk file instead\n");
+    print!("    With --apoc-batch-size, the graph is written as one apoc.periodic.iterate call merging the given number of rows per transaction\n");
+    print!("    --redact masks or hashes the opaque identifiers of graph (hash only), csv, jsonl and dot output\n");
+    print!("    --pseudonymize replaces the opaque identifiers by tokens keyed with the bytes of the key file, stable across exports,\n");
+    print!("    --pseudonym-map keeps the token of every UVCI in the mapping file to reuse them\n");
+    print!("    anonymize writes every UVCI with its opaque unique string replaced by its keyed token (--key-file) or its mask or hash,\n");
+    print!("    with a freshly computed checksum, skipping the lines without valid blocks\n");
+    print!("    neo4j-admin writes the nodes and relationships of the graph to [output file].nodes.csv and [output file].relationships.csv\n");
+    print!("    for neo4j-admin database import\n");
+    print!("    edgelist writes the edges of the graph to the output file as a space-separated edge list for NetworkX or igraph,\n");
+    print!(
+        "    and the label and name of its nodes to [output file without extension].nodes.csv\n"
+    );
+    print!("    duckdb appends the UVCIs as typed rows to the table (default uvcis) of the DuckDB database output file, created if needed\n");
+    print!("    (requires the duckdb feature)\n");
+    print!("    splunk writes Splunk HTTP Event Collector JSON events with the sourcetype (default covid_cert_uvci), timestamped with the\n");
+    print!("    estimated vaccination date or the processing time\n");
+    print!("    cef and syslog write the validation result of every UVCI as a CEF or RFC 5424 syslog line with the severity of the outcome\n");
+    print!("    (valid, warnings, invalid) for SIEM ingestion, --hostname sets the host of the lines\n");
+    print!("    --constraints starts the graph with uniqueness constraints and indexes on the node names\n");
+    print!("    --native-dates sets the estimated vaccination date of the certificate nodes of the graph as a Neo4j date() property\n");
+    print!("    --graph-state only writes the certificates adding nodes or edges to the graph of the state file, and adds them to it\n");
+    print!("    (with --chunk-size or --apoc-batch-size), for incremental loads\n");
+    print!("    --sort-by orders csv and jsonl output by estimated vaccination month, country or issuing entity (not with --checkpoint-every)\n");
+    print!("    --date-format writes the estimated vaccination month of csv and jsonl output as one column, e.g. 2021-08, 2021-W30 or Aug 2021,\n");
+    print!("    with the month names of the locale en, de, fr, es, it, nl or sv (not with --checkpoint-every)\n");
+    print!("    --threads sets the number of parser threads between the reader and the writer of graph, neo4j-admin, edgelist, csv, jsonl\n");
+    print!("    and dot output (default one per core)\n");
+    print!("    With several input files, the output file is given with --output; csv and jsonl output then has source file and line provenance columns\n");
+    print!("    An existing output file is only overwritten with --force, the duckdb format and --resume add to it\n");
+    print!("    Conversions to an output file also write a manifest \"[Name of output file].manifest.json\" of the tool version, parameters, inputs, record counts and SHA-256 checksums");
+}
+
+/// cargo run covid_uvci.txt graph_cypher.txt
+/// cargo run -- --format table covid_uvci.txt
+/// cargo run -- --format csv --output merged.csv 'exports/*.txt'
+/// cargo run --features duckdb -- --format duckdb --table uvcis covid_uvci.txt uvcis.duckdb
+/// cargo run -- --format splunk --sourcetype uvci:audit covid_uvci.txt uvcis.hec.json
+/// cargo run -- --format syslog --hostname gate-7 covid_uvci.txt
+/// cargo run -- revoke-hash --encoding base64 covid_uvci.txt
+/// cargo run -- analyze covid_uvci.txt
+/// cargo run -- analyze --clusters mixed_uvci.txt
+/// cargo run -- anonymize --key-file uvci.key covid_uvci.txt anonymized.txt
+/// cargo run -- stats --histogram covid_uvci.txt
+/// cargo run -- coverage --population population.csv covid_uvci.txt
+/// cargo run -- diff old.txt new.txt
+/// cargo run -- conformance dgc-testdata
+/// cargo run --features reqwest -- conformance --fetch --countries AT,SE
+/// cargo run --features ratatui -- tui covid_uvci.txt
+/// cargo run -- follow --extract app.log
+/// cargo run -- --partition covid_uvci.txt valid.txt invalid.jsonl
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|a| a.as_str()) == Some("analyze") {
+        args.remove(0);
+        return analyze(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("stats") {
+        args.remove(0);
+        return stats(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("coverage") {
+        args.remove(0);
+        return coverage(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("diff") {
+        args.remove(0);
+        return diff(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("conformance") {
+        args.remove(0);
+        return conformance(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("tui") {
+        if cfg!(not(feature = "ratatui")) {
+            eprintln!("built without ratatui, the tui mode requires the ratatui feature");
+            return print_usage();
+        }
+        args.remove(0);
+        return tui(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("anonymize") {
+        args.remove(0);
+        return anonymize(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("follow") {
+        args.remove(0);
+        return follow(args);
+    }
+    if args.first().map(|a| a.as_str()) == Some("revoke-hash") {
+        args.remove(0);
+        return revoke_hash(args);
+    }
+    if let Some(index) = args.iter().position(|a| a == "--partition") {
+        args.remove(index);
+        return partition(args);
+    }
+    convert(args);
+}
+
+/// Convert a file of UVCIs to graph, csv, jsonl, dot, table, diagnostics, DuckDB, Splunk HEC, CEF or syslog output
+fn convert(args: Vec<String>) {
+    let args = match parse_args(
+        args,
+        &[
+            "--format",
+            "--reject-log",
+            "--checkpoint-every",
+            "--compress",
+            "--encrypt",
+            "--chunk-size",
+            "--apoc-batch-size",
+            "--sort-by",
+            "--redact",
+            "--pseudonymize",
+            "--pseudonym-map",
+            "--graph-state",
+            "--date-format",
+            "--threads",
+            "--table",
+            "--sourcetype",
+            "--hostname",
+            "--output",
+        ],
+        &[
+            "--force",
+            "--resume",
+            "--chunk-files",
+            "--constraints",
+            "--native-dates",
+        ],
+    ) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    let format = args.option("--format", "graph");
+    let files = &args.files;
+
+    let (inputs, outfile) = match split_files(files, args.options.get("--output")) {
+        Some(split) => split,
+        None => {
+            eprintln!("several input files require the output file to be given with --output");
+            return print_usage();
+        }
+    };
+
+    let formats = [
+        "graph",
+        "neo4j-admin",
+        "edgelist",
+        "csv",
+        "jsonl",
+        "dot",
+        "table",
+        "diagnostics",
+        "duckdb",
+        "splunk",
+        "cef",
+        "syslog",
+    ];
+    // The neo4j-admin and edge list formats write two files next to the output file
+    let file_pair = format == "neo4j-admin" || format == "edgelist";
+    // The duckdb format appends to a table of the output database file
+    let database = format == "duckdb";
+    // The table, diagnostics, cef and syslog formats report on all UVCIs, valid or not
+    let report = ["table", "diagnostics", "cef", "syslog"].contains(&format.as_str());
+    let output_required = format == "graph" || file_pair || database;
+    // The duckdb format and --resume add to an existing output file
+    let appends = database || args.options.contains_key("--resume");
+    if refuses_overwrite(outfile, args.options.contains_key("--force") || appends) {
+        eprintln!(
+            "the output file {} exists, use --force to overwrite it",
+            outfile.unwrap()
+        );
+        return print_usage();
+    }
+    if database && cfg!(not(feature = "duckdb")) {
+        eprintln!("built without duckdb, the duckdb format requires the duckdb feature");
+        return print_usage();
+    }
+    if !formats.contains(&format.as_str())
+        || inputs.is_empty()
+        || (output_required && outfile.is_none())
+        || (args.options.contains_key("--reject-log") && report)
+    {
+        return print_usage();
+    }
+    let compression = match args.options.get("--compress") {
+        Some(code) => match Compression::from_code(code) {
+            Some(compression) if outfile.is_some() && !report && !file_pair && !database => {
+                Some(compression)
+            }
+            _ => return print_usage(),
+        },
+        None => None,
+    };
+    let recipients: Vec<String> = match args.options.get("--encrypt") {
+        Some(recipients) if outfile.is_some() && !file_pair && !database => {
+            recipients.split(',').map(|r| r.to_string()).collect()
+        }
+        Some(_) => return print_usage(),
+        None => Vec::new(),
+    };
+    let chunk_size = match args.options.get("--chunk-size").map(|n| n.parse::<usize>()) {
+        Some(Ok(chunk_size)) if chunk_size > 0 && format == "graph" => Some(chunk_size),
+        Some(_) => return print_usage(),
+        None => None,
+    };
+    let apoc_batch_size = match args
+        .options
+        .get("--apoc-batch-size")
+        .map(|n| n.parse::<usize>())
+    {
+        Some(Ok(batch_size)) if batch_size > 0 && format == "graph" && chunk_size.is_none() => {
+            Some(batch_size)
+        }
+        Some(_) => return print_usage(),
+        None => None,
+    };
+    let sort_by = match args.options.get("--sort-by") {
+        Some(code) => match SortKey::from_code(code) {
+            Some(key) if format == "csv" || format == "jsonl" => Some(key),
+            _ => return print_usage(),
+        },
+        None => None,
+    };
+    let export_options = match args.options.get("--date-format") {
+        Some(code) => match DateFormat::from_code(code) {
+            Some(date_format) if format == "csv" || format == "jsonl" => {
+                ExportOptions::new().date_format(date_format)
+            }
+            _ => return print_usage(),
+        },
+        None => ExportOptions::new(),
+    };
+    let export_options = match args.options.contains_key("--native-dates") {
+        true if format == "graph" => export_options.native_dates(true),
+        true => return print_usage(),
+        false => export_options,
+    };
+    let table = match args.options.get("--table") {
+        Some(table) if database => table.clone(),
+        Some(_) => return print_usage(),
+        None => "uvcis".to_string(),
+    };
+    let sourcetype = match args.options.get("--sourcetype") {
+        Some(sourcetype) if format == "splunk" => sourcetype.clone(),
+        Some(_) => return print_usage(),
+        None => "covid_cert_uvci".to_string(),
+    };
+    let siem_formatter = match (
+        SiemFormat::from_code(&format),
+        args.options.get("--hostname"),
+    ) {
+        (Some(siem_format), Some(hostname)) => {
+            Some(SiemFormatter::new(siem_format).hostname(hostname))
+        }
+        (Some(siem_format), None) => Some(SiemFormatter::new(siem_format)),
+        (None, Some(_)) => return print_usage(),
+        (None, None) => None,
+    };
+    let pipeline = match args.options.get("--threads").map(|n| n.parse::<usize>()) {
+        Some(Ok(threads)) if threads > 0 && !report => PipelineOptions::new().workers(threads),
+        Some(_) => return print_usage(),
+        None => PipelineOptions::new(),
+    };
+    // Masked opaque ids would merge all certificates of the graph
+    let exported = !report;
+    let redaction = match Redaction::from_code(&args.option("--redact", "none")) {
+        Some(Redaction::None) => Redaction::None,
+        Some(Redaction::Mask) if exported && format != "graph" && !file_pair => Redaction::Mask,
+        Some(Redaction::Hash) if exported => Redaction::Hash,
+        _ => return print_usage(),
+    };
+    let mut privacy = match args.options.get("--pseudonymize") {
+        Some(key_file) if exported && redaction == Redaction::None => {
+            let key = match fs::read(key_file) {
+                Err(why) => panic!("couldn't read key {}: {}", key_file, why),
+                Ok(key) => key,
+            };
+            let pseudonymizer = match args.options.get("--pseudonym-map") {
+                Some(path) => match Pseudonymizer::with_mapping_file(&key, path) {
+                    Err(why) => panic!("couldn't read pseudonym mapping {}: {}", path, why),
+                    Ok(pseudonymizer) => pseudonymizer,
+                },
+                None => Pseudonymizer::new(&key),
+            };
+            Privacy::Pseudonymize(pseudonymizer)
+        }
+        Some(_) => return print_usage(),
+        None if args.options.contains_key("--pseudonym-map") => return print_usage(),
+        None => Privacy::Redact(redaction),
+    };
+    let chunk_files = args.options.contains_key("--chunk-files");
+    let constraints = args.options.contains_key("--constraints");
+    if constraints && format != "graph" {
+        return print_usage();
+    }
+    if chunk_files && (chunk_size.is_none() || compression.is_some() || !recipients.is_empty()) {
+        return print_usage();
+    }
+    // The incremental statements are run against the existing graph, so they must be MERGE statements
+    let mut graph_state = match args.options.get("--graph-state") {
+        Some(path) if chunk_size.is_some() || apoc_batch_size.is_some() => {
+            match GraphState::with_state_file(path) {
+                Err(why) => panic!("couldn't read graph state {}: {}", path, why),
+                Ok(state) => Some(state),
+            }
+        }
+        Some(_) => return print_usage(),
+        None => None,
+    };
+    if args.options.contains_key("--checkpoint-every") || args.options.contains_key("--resume") {
+        let interval = match args.option("--checkpoint-every", "100000").parse::<usize>() {
+            Ok(interval) if interval > 0 => interval,
+            _ => return print_usage(),
+        };
+        if !["graph", "csv", "jsonl"].contains(&format.as_str()) {
+            panic!(
+                "--checkpoint-every only supports graph, csv and jsonl output, not {}",
+                format
+            );
+        }
+        let outfile = match outfile {
+            Some(outfile) if inputs.len() == 1 => outfile,
+            _ => panic!("--checkpoint-every needs exactly one input file and an output file"),
+        };
+        // The checkpointed export writes the output file as is, line by line
+        let conflicting: Vec<&str> = [
+            "--compress",
+            "--encrypt",
+            "--chunk-size",
+            "--apoc-batch-size",
+            "--constraints",
+            "--sort-by",
+            "--date-format",
+            "--native-dates",
+            "--threads",
+            "--reject-log",
+            "--pseudonymize",
+            "--graph-state",
+        ]
+        .iter()
+        .copied()
+        .filter(|option| args.options.contains_key(*option))
+        .collect();
+        if !conflicting.is_empty() {
+            panic!(
+                "--checkpoint-every and --resume cannot be combined with {}",
+                conflicting.join(", ")
+            );
+        }
+        export_checkpointed(
+            &format,
+            &inputs[0],
+            outfile,
+            interval,
+            args.options.contains_key("--resume"),
+            redaction,
+        );
+        return write_manifest(
+            &args,
+            &inputs[..1],
+            std::slice::from_ref(outfile),
+            outfile,
+            0,
+        );
+    }
+    let inputs = expand_inputs(inputs);
+    if outfile.is_some_and(|outfile| inputs.contains(outfile)) {
+        panic!("the output file {} is also an input file", outfile.unwrap());
+    }
+    let mut rejects = open_reject_log(&args);
+
+    let output = match format.as_str() {
+        "table" => uvcis_to_table(&inputs.iter().flat_map(lines_from_file).collect::<Vec<_>>()),
+        "diagnostics" => {
+            let cert_ids: Vec<String> = inputs.iter().flat_map(lines_from_file).collect();
+            uvcis_to_diagnostics_json(&cert_ids) + "\n"
+        }
+        "cef" | "syslog" => {
+            let cert_ids: Vec<String> = inputs.iter().flat_map(lines_from_file).collect();
+            let mut lines = Vec::new();
+            if let Err(why) =
+                uvcis_to_siem_writer(&cert_ids, siem_formatter.as_ref().unwrap(), &mut lines)
+            {
+                panic!("couldn't format validation results: {}", why);
+            }
+            String::from_utf8_lossy(&lines).to_string()
+        }
+        "neo4j-admin" => {
+            let (nodes_path, relationships_path) = neo4j_admin_paths(outfile.unwrap());
+            let (nodes, relationships) = create_file_pair(&nodes_path, &relationships_path);
+            return export_file_pair(
+                &args,
+                &inputs,
+                outfile.unwrap(),
+                vec![nodes_path, relationships_path],
+                Neo4jAdminExporter::new(nodes, relationships),
+                rejects,
+                &mut privacy,
+                &pipeline,
+            );
+        }
+        "edgelist" => {
+            let edges_path = outfile.unwrap().to_string();
+            let nodes_path = Path::new(&edges_path)
+                .with_extension("nodes.csv")
+                .display()
+                .to_string();
+            let (edges, nodes) = create_file_pair(&edges_path, &nodes_path);
+            return export_file_pair(
+                &args,
+                &inputs,
+                &edges_path,
+                vec![edges_path.clone(), nodes_path],
+                EdgeListExporter::new(edges, nodes),
+                rejects,
+                &mut privacy,
+                &pipeline,
+            );
+        }
+        #[cfg(feature = "duckdb")]
+        "duckdb" => {
+            let exporter = match DuckDbExporter::open(outfile.unwrap(), &table) {
+                Err(why) => panic!("couldn't open {}: {}", outfile.unwrap(), why),
+                Ok(exporter) => exporter,
+            };
+            if let Err(why) = run_exporter(&inputs, exporter, &mut rejects, &mut privacy, &pipeline)
+            {
+                panic!("couldn't write output: {}", why);
+            }
+            save_pseudonym_mapping(&privacy);
+            let rejected = close_reject_log(&args, rejects);
+            println!("successfully wrote to {}", outfile.unwrap());
+            return write_manifest(
+                &args,
+                &inputs,
+                std::slice::from_ref(outfile.unwrap()),
+                outfile.unwrap(),
+                rejected,
+            );
+        }
+        #[cfg(not(feature = "duckdb"))]
+        "duckdb" => unreachable!(
+            "couldn't append to table {}: the duckdb format is rejected without the duckdb feature",
+            table
+        ),
+        "graph" if chunk_files => {
+            let exporter = ChunkFilesExporter::new(
+                outfile.unwrap(),
+                chunk_size.unwrap(),
+                constraints,
+                &export_options,
+            );
+            let chunks = exporter.chunks.clone();
+            if let Err(why) = run_graph_exporter(
+                &inputs,
+                exporter,
+                &mut rejects,
+                &mut privacy,
+                &mut graph_state,
+                &pipeline,
+            ) {
+                panic!("couldn't write output: {}", why);
+            }
+            save_pseudonym_mapping(&privacy);
+            save_graph_state(&graph_state);
+            let rejected = close_reject_log(&args, rejects);
+            let outputs: Vec<String> = (1..=chunks.get())
+                .map(|chunk| chunk_path(outfile.unwrap(), chunk))
+                .collect();
+            return write_manifest(&args, &inputs, &outputs, outfile.unwrap(), rejected);
+        }
+        _ => {
+            export_files(
+                &format,
+                &inputs,
+                outfile,
+                &mut rejects,
+                compression,
+                &recipients,
+                chunk_size,
+                apoc_batch_size,
+                constraints,
+                sort_by,
+                &export_options,
+                &sourcetype,
+                &mut privacy,
+                &mut graph_state,
+                &pipeline,
+            );
+            save_pseudonym_mapping(&privacy);
+            save_graph_state(&graph_state);
+            let rejected = close_reject_log(&args, rejects);
+            if let Some(outfile) = outfile {
+                write_manifest(
+                    &args,
+                    &inputs,
+                    std::slice::from_ref(outfile),
+                    outfile,
+                    rejected,
+                );
+            }
+            return;
+        }
+    };
+    if recipients.is_empty() {
+        write_output(outfile, &output);
+    } else {
+        write_encrypted_output(outfile.unwrap(), &output, &recipients);
+    }
+    if let Some(outfile) = outfile {
+        write_manifest(&args, &inputs, std::slice::from_ref(outfile), outfile, 0);
+    }
+}
+
+/// Create the two output files of the neo4j-admin and edge list formats
+fn create_file_pair(first: &str, second: &str) -> (BufWriter<File>, BufWriter<File>) {
+    return match (File::create(first), File::create(second)) {
+        (Ok(first), Ok(second)) => (BufWriter::new(first), BufWriter::new(second)),
+        (Err(why), _) => panic!("couldn't create {}: {}", first, why),
+        (_, Err(why)) => panic!("couldn't create {}: {}", second, why),
+    };
+}
+
+/// Export the UVCIs of the input files with an exporter writing two output files, then write the
+/// manifest of the output file
+fn export_file_pair<E: Exporter>(
+    args: &Args,
+    inputs: &[String],
+    outfile: &str,
+    outputs: Vec<String>,
+    exporter: E,
+    mut rejects: Option<RejectLog<BufWriter<File>>>,
+    privacy: &mut Privacy,
+    pipeline: &PipelineOptions,
+) {
+    if let Err(why) = run_exporter(inputs, exporter, &mut rejects, privacy, pipeline) {
+        panic!("couldn't write output: {}", why);
+    }
+    save_pseudonym_mapping(privacy);
+    let rejected = close_reject_log(args, rejects);
+    println!("successfully wrote to {} and {}", outputs[0], outputs[1]);
+    write_manifest(args, inputs, &outputs, outfile, rejected);
+}
+
+/// The node and relationship files of a neo4j-admin import, e.g. "graph.nodes.csv" and
+/// "graph.relationships.csv" for the output file "graph.csv"
+fn neo4j_admin_paths(outfile: &str) -> (String, String) {
+    let stem = outfile.strip_suffix(".csv").unwrap_or(outfile);
+    return (
+        format!("{}.nodes.csv", stem),
+        format!("{}.relationships.csv", stem),
+    );
+}
+
+/// Write the output encrypted to the age recipients to the file
+fn write_encrypted_output(outfile: &str, output: &str, recipients: &[String]) {
+    let result = File::create(outfile)
+        .and_then(|file| EncryptedWriter::new(BufWriter::new(file), recipients))
+        .and_then(|mut writer| {
+            writer.write_all(output.as_bytes())?;
+            return writer.finish()?.flush();
+        });
+    match result {
+        Err(why) => panic!("couldn't write encrypted output to {}: {}", outfile, why),
+        Ok(_) => println!("successfully wrote to {}", outfile),
+    }
+}
+
+/// Write the manifest of an export to "[output file].manifest.json", with the SHA-256 of the inputs
+/// and outputs
+fn write_manifest(
+    args: &Args,
+    inputs: &[String],
+    outputs: &[String],
+    outfile: &str,
+    rejected: usize,
+) {
+    let mut manifest = Manifest::new().parameter("--format", &args.option("--format", "graph"));
+    for (name, value) in &args.options {
+        manifest = manifest.parameter(name, value);
+    }
+    let digested = inputs
+        .iter()
+        .map(ManifestFile::input)
+        .collect::<io::Result<Vec<_>>>()
+        .and_then(|inputs| {
+            let outputs = outputs
+                .iter()
+                .map(ManifestFile::output)
+                .collect::<io::Result<Vec<_>>>()?;
+            return Ok((inputs, outputs));
+        });
+    let (inputs, outputs) = match digested {
+        Err(why) => panic!("couldn't digest the files of the manifest: {}", why),
+        Ok(digested) => digested,
+    };
+    for input in inputs {
+        manifest = manifest.input(input);
+    }
+    for output in outputs {
+        manifest = manifest.output(output);
+    }
+    let manifest = manifest.rejected(rejected);
+    let path = Manifest::path(outfile);
+    if let Err(why) = fs::write(&path, manifest.to_json() + "\n") {
+        panic!("couldn't write manifest {}: {}", path, why);
+    }
+}
+
+/// Split the file names of a conversion into the input files and the output file
+///
+/// With `--output`, all file names are inputs. Without it, a single input file name may be followed by
+/// the output file name; several input files, e.g. a glob expanded by the shell, require `--output` so
+/// that the last input file is never taken as the output file. None if the file names are ambiguous.
+fn split_files<'a>(
+    files: &'a [String],
+    output: Option<&'a String>,
+) -> Option<(&'a [String], Option<&'a String>)> {
+    return match (output, files.len()) {
+        (Some(output), _) => Some((files, Some(output))),
+        (None, 0 | 1) => Some((files, None)),
+        (None, 2) => Some((&files[..1], files.last())),
+        (None, _) => None,
+    };
+}
+
+/// Whether the output file exists and may not be overwritten
+fn refuses_overwrite(outfile: Option<&String>, force: bool) -> bool {
+    return !force && outfile.is_some_and(|outfile| Path::new(outfile).exists());
+}
+
+/// Expand the glob patterns of the input file names, in sorted order of the matching paths
+fn expand_inputs(patterns: &[String]) -> Vec<String> {
+    let mut inputs = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            inputs.push(pattern.clone());
+            continue;
+        }
+        let paths = match glob::glob(pattern) {
+            Err(why) => panic!("invalid pattern {}: {}", pattern, why),
+            Ok(paths) => paths,
+        };
+        let mut matched: Vec<String> = paths
+            .filter_map(|path| path.ok())
+            .map(|path| path.display().to_string())
+            .collect();
+        if matched.is_empty() {
+            panic!("no input file matches {}", pattern);
+        }
+        matched.sort();
+        inputs.append(&mut matched);
+    }
+    return inputs;
+}
+
+/// Open the file of the --reject-log option, if given
+fn open_reject_log(args: &Args) -> Option<RejectLog<BufWriter<File>>> {
+    let path = args.options.get("--reject-log")?;
+    return match File::create(path) {
+        Err(why) => panic!("couldn't create {}: {}", path, why),
+        Ok(file) => Some(RejectLog::new(BufWriter::new(file))),
+    };
+}
+
+/// Flush the reject log and report the number of rejected lines, returned
+fn close_reject_log(args: &Args, rejects: Option<RejectLog<BufWriter<File>>>) -> usize {
+    let mut rejected = 0;
+    if let Some(rejects) = rejects {
+        rejected = rejects.count();
+        if let Err(why) = rejects.into_inner() {
+            panic!("couldn't write reject log: {}", why);
+        }
+        eprintln!(
+            "rejected {} lines, see {}",
+            rejected,
+            args.option("--reject-log", "")
+        );
+    }
+    return rejected;
+}
+
+/// Stream the UVCIs of the input files through the exporter of the format to the file, or to the
+/// terminal if no file is given
+fn export_files(
+    format: &str,
+    inputs: &[String],
+    outfile: Option<&String>,
+    rejects: &mut Option<RejectLog<BufWriter<File>>>,
+    compression: Option<Compression>,
+    recipients: &[String],
+    chunk_size: Option<usize>,
+    apoc_batch_size: Option<usize>,
+    constraints: bool,
+    sort_by: Option<SortKey>,
+    export_options: &ExportOptions,
+    sourcetype: &str,
+    privacy: &mut Privacy,
+    graph_state: &mut Option<GraphState>,
+    pipeline: &PipelineOptions,
+) {
+    let writer: Box<dyn Write> = match outfile {
+        Some(outfile) => match File::create(outfile) {
+            Err(why) => panic!("couldn't create {}: {}", outfile, why),
+            Ok(file) => Box::new(BufWriter::new(file)),
+        },
+        None => Box::new(io::stdout()),
+    };
+    // Compressed before encrypted, encrypted output does not compress
+    let writer = match EncryptedWriter::new(writer, recipients) {
+        Err(why) => panic!("couldn't encrypt output: {}", why),
+        Ok(writer) => writer,
+    };
+    let mut writer = match CompressedWriter::new(writer, compression) {
+        Err(why) => panic!("couldn't compress output: {}", why),
+        Ok(writer) => writer,
+    };
+    let result = match (format, chunk_size) {
+        ("graph", _) if apoc_batch_size.is_some() => run_graph_exporter(
+            inputs,
+            ApocIterateExporter::new(&mut writer, apoc_batch_size.unwrap())
+                .constraints(constraints)
+                .options(export_options.clone()),
+            rejects,
+            privacy,
+            graph_state,
+            pipeline,
+        ),
+        ("graph", Some(chunk_size)) => run_graph_exporter(
+            inputs,
+            CypherShellExporter::new(&mut writer, chunk_size)
+                .constraints(constraints)
+                .options(export_options.clone()),
+            rejects,
+            privacy,
+            graph_state,
+            pipeline,
+        ),
+        ("graph", None) => run_exporter(
+            inputs,
+            CypherExporter::new(&mut writer)
+                .return_all(true)
+                .constraints(constraints)
+                .options(export_options.clone()),
+            rejects,
+            privacy,
+            pipeline,
+        ),
+        ("csv", _) => {
+            let exporter = CsvExporter::new(&mut writer).options(export_options.clone());
+            match sort_by {
+                Some(key) => run_exporter(
+                    inputs,
+                    SortingExporter::new(exporter, key),
+                    rejects,
+                    privacy,
+                    pipeline,
+                ),
+                None => run_exporter(inputs, exporter, rejects, privacy, pipeline),
+            }
+        }
+        ("jsonl", _) => {
+            let exporter = JsonlExporter::new(&mut writer).options(export_options.clone());
+            match sort_by {
+                Some(key) => run_exporter(
+                    inputs,
+                    SortingExporter::new(exporter, key),
+                    rejects,
+                    privacy,
+                    pipeline,
+                ),
+                None => run_exporter(inputs, exporter, rejects, privacy, pipeline),
+            }
+        }
+        ("splunk", _) => run_exporter(
+            inputs,
+            SplunkHecExporter::new(&mut writer).sourcetype(sourcetype),
+            rejects,
+            privacy,
+            pipeline,
+        ),
+        _ => run_exporter(
+            inputs,
+            DotExporter::new(&mut writer),
+            rejects,
+            privacy,
+            pipeline,
+        ),
+    };
+    let result = result.and_then(|_| writer.finish()?.finish()?.flush());
+    match (result, outfile) {
+        (Err(why), _) => panic!("couldn't write output: {}", why),
+        (Ok(_), Some(outfile)) => println!("successfully wrote to {}", outfile),
+        (Ok(_), None) => {}
+    }
+}
+
+/// Exporter splitting the cypher-shell script into numbered chunk files of one transaction each,
+/// e.g. "graph.0001.cypher", "graph.0002.cypher" for the output file "graph.cypher"
+struct ChunkFilesExporter {
+    outfile: String,
+    chunk_size: usize,
+    /// Number of chunk files written, shared to list them after the export
+    chunks: Rc<Cell<usize>>,
+    current: Option<CypherShellExporter<BufWriter<File>>>,
+    constraints: bool,
+    options: ExportOptions,
+}
+
+impl ChunkFilesExporter {
+    fn new(
+        outfile: &str,
+        chunk_size: usize,
+        constraints: bool,
+        options: &ExportOptions,
+    ) -> ChunkFilesExporter {
+        return ChunkFilesExporter {
+            outfile: outfile.to_string(),
+            chunk_size,
+            chunks: Rc::new(Cell::new(0)),
+            current: None,
+            constraints,
+            options: options.clone(),
+        };
+    }
+}
+
+/// Name of the chunk file of the output file with the given number
+fn chunk_path(outfile: &str, chunk: usize) -> String {
+    let path = Path::new(outfile);
+    let stem = path.with_extension("").display().to_string();
+    return match path.extension() {
+        Some(extension) => format!("{}.{:04}.{}", stem, chunk, extension.to_string_lossy()),
+        None => format!("{}.{:04}", stem, chunk),
+    };
+}
+
+impl Exporter for ChunkFilesExporter {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        if self.current.is_none() {
+            self.chunks.set(self.chunks.get() + 1);
+            let file = File::create(chunk_path(&self.outfile, self.chunks.get()))?;
+            // One transaction per chunk file, the constraints in the first one
+            self.current = Some(
+                CypherShellExporter::new(BufWriter::new(file), self.chunk_size)
+                    .constraints(self.constraints && self.chunks.get() == 1)
+                    .options(self.options.clone()),
+            );
+        }
+        let current = self.current.as_mut().unwrap();
+        current.export(uvci)?;
+        if current.statements() == self.chunk_size {
+            self.current.take().unwrap().finish()?;
+        }
+        return Ok(());
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if let Some(current) = self.current.take() {
+            current.finish()?;
+        }
+        println!(
+            "successfully wrote {} chunk files {} to {}",
+            self.chunks.get(),
+            chunk_path(&self.outfile, 1),
+            chunk_path(&self.outfile, self.chunks.get().max(1))
+        );
+        return Ok(());
+    }
+}
+
+/// Writer counting the bytes written, i.e. the output position
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: Rc<Cell<u64>>,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written.set(self.written.get() + written as u64);
+        return Ok(written);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.inner.flush();
+    }
+}
+
+/// Export the input file line by line, writing a checkpoint to "[output file].checkpoint" every
+/// `interval` lines, or continue from the checkpoint of an interrupted export
+fn export_checkpointed(
+    format: &str,
+    infile: &str,
+    outfile: &str,
+    interval: usize,
+    resume: bool,
+    redaction: Redaction,
+) {
+    let checkpoint_path = format!("{}.checkpoint", outfile);
+    let (line, position) = if resume {
+        load_checkpoint(&checkpoint_path, infile, format)
+    } else {
+        (0, 0)
+    };
+
+    // Statements already written, skipped by the Cypher exporter when resuming
+    let mut statements = HashSet::new();
+    if resume && format == "graph" {
+        let written = fs::read(outfile).expect("couldn't read output file to resume");
+        let written = String::from_utf8_lossy(&written[..written.len().min(position as usize)]);
+        statements = written.lines().map(|l| l.to_string()).collect();
+        statements.remove("");
+    }
+
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(outfile)
+    {
+        Err(why) => panic!("couldn't create {}: {}", outfile, why),
+        Ok(file) => file,
+    };
+    if let Err(why) = file
+        .set_len(position)
+        .and_then(|_| file.seek(SeekFrom::Start(position)))
+    {
+        panic!("couldn't truncate {}: {}", outfile, why);
+    }
+    let written = Rc::new(Cell::new(position));
+    let writer = CountingWriter {
+        inner: BufWriter::new(file),
+        written: written.clone(),
+    };
+    let checkpoint =
+        |line: usize| save_checkpoint(&checkpoint_path, infile, format, line, written.get());
+
+    let result = match format {
+        "graph" => run_checkpointed(
+            infile,
+            RedactingExporter::new(
+                CypherExporter::new(writer)
+                    .return_all(true)
+                    .with_statements(statements),
+                redaction,
+            ),
+            line,
+            interval,
+            checkpoint,
+        ),
+        "csv" => run_checkpointed(
+            infile,
+            RedactingExporter::new(CsvExporter::new(writer), redaction),
+            line,
+            interval,
+            checkpoint,
+        ),
+        _ => run_checkpointed(
+            infile,
+            RedactingExporter::new(JsonlExporter::new(writer), redaction),
+            line,
+            interval,
+            checkpoint,
+        ),
+    };
+    match result {
+        Err(why) => panic!("couldn't write output: {}", why),
+        Ok(_) => {
+            let _ = fs::remove_file(&checkpoint_path);
+            println!("successfully wrote to {}", outfile);
+        }
+    }
+}
+
+/// Export the lines of the input file after the first `skip` lines, calling `checkpoint` with the
+/// number of lines done every `interval` lines once the output is flushed
+fn run_checkpointed<E: Exporter, F: Fn(usize) -> io::Result<()>>(
+    infile: &str,
+    mut exporter: E,
+    skip: usize,
+    interval: usize,
+    checkpoint: F,
+) -> io::Result<()> {
+    let reader = decompressed_reader(File::open(infile)?)?;
+    for (index, cert_id) in reader.lines().enumerate().skip(skip) {
+        exporter.export(&parse(&cert_id?))?;
+        if (index + 1) % interval == 0 {
+            exporter.flush()?;
+            checkpoint(index + 1)?;
+        }
+    }
+    return exporter.finish();
+}
+
+/// Atomically replace the checkpoint with the number of lines done and the output position
+fn save_checkpoint(
+    path: &str,
+    infile: &str,
+    format: &str,
+    line: usize,
+    position: u64,
+) -> io::Result<()> {
+    let checkpoint = serde_json::json!({
+        "input": infile,
+        "format": format,
+        "line": line,
+        "output_position": position,
+    });
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, checkpoint.to_string())?;
+    return fs::rename(&temp_path, path);
+}
+
+/// Read the number of lines done and the output position of a checkpoint of the same conversion
+fn load_checkpoint(path: &str, infile: &str, format: &str) -> (usize, u64) {
+    let checkpoint = match fs::read_to_string(path) {
+        Err(why) => panic!("couldn't read checkpoint {}: {}", path, why),
+        Ok(checkpoint) => checkpoint,
+    };
+    let checkpoint: serde_json::Value =
+        serde_json::from_str(&checkpoint).unwrap_or(serde_json::Value::Null);
+    if checkpoint["input"] != infile || checkpoint["format"] != format {
+        panic!("checkpoint {} is not of this conversion", path);
+    }
+    match (
+        checkpoint["line"].as_u64(),
+        checkpoint["output_position"].as_u64(),
+    ) {
+        (Some(line), Some(position)) => (line as usize, position),
+        _ => panic!("invalid checkpoint {}", path),
+    }
+}
+
+/// Redaction or pseudonymization of the opaque identifier fields of the exported UVCIs
+enum Privacy {
+    Redact(Redaction),
+    Pseudonymize(Pseudonymizer),
+}
+
+/// Save the mapping file of the pseudonymizer, if any
+fn save_pseudonym_mapping(privacy: &Privacy) {
+    if let Privacy::Pseudonymize(pseudonymizer) = privacy {
+        if let Err(why) = pseudonymizer.save_mapping() {
+            panic!("couldn't write pseudonym mapping: {}", why);
+        }
+    }
+}
+
+/// Save the graph state file of an incremental graph export, if there is one
+fn save_graph_state(graph_state: &Option<GraphState>) {
+    if let Some(state) = graph_state {
+        if let Err(why) = state.save() {
+            panic!("couldn't write graph state: {}", why);
+        }
+    }
+}
+
+/// Export the graph of the UVCIs of the input files, only the UVCIs new to the graph state if there
+/// is one, see `run_exporter`
+fn run_graph_exporter<E: Exporter>(
+    inputs: &[String],
+    exporter: E,
+    rejects: &mut Option<RejectLog<BufWriter<File>>>,
+    privacy: &mut Privacy,
+    graph_state: &mut Option<GraphState>,
+    pipeline: &PipelineOptions,
+) -> io::Result<()> {
+    return match graph_state {
+        Some(state) => run_exporter(
+            inputs,
+            IncrementalGraphExporter::new(exporter, state),
+            rejects,
+            privacy,
+            pipeline,
+        ),
+        None => run_exporter(inputs, exporter, rejects, privacy, pipeline),
+    };
+}
+
+/// Export the UVCIs of the input files, redacted or pseudonymized, see `export_inputs`
+fn run_exporter<E: Exporter>(
+    inputs: &[String],
+    exporter: E,
+    rejects: &mut Option<RejectLog<BufWriter<File>>>,
+    privacy: &mut Privacy,
+    pipeline: &PipelineOptions,
+) -> io::Result<()> {
+    return match privacy {
+        Privacy::Redact(redaction) => export_inputs(
+            inputs,
+            RedactingExporter::new(exporter, *redaction),
+            rejects,
+            pipeline,
+        ),
+        Privacy::Pseudonymize(pseudonymizer) => export_inputs(
+            inputs,
+            PseudonymizingExporter::new(exporter, pseudonymizer),
+            rejects,
+            pipeline,
+        ),
+    };
+}
+
+/// Export the UVCIs of the input files, or only the valid ones if invalid lines are logged
+///
+/// Every input file is parsed by the pipeline, the UVCIs are exported in input order. When merging
+/// several input files, the input file and line are set as source of every UVCI.
+fn export_inputs<E: Exporter>(
+    inputs: &[String],
+    mut exporter: E,
+    rejects: &mut Option<RejectLog<BufWriter<File>>>,
+    pipeline: &PipelineOptions,
+) -> io::Result<()> {
+    let provenance = inputs.len() > 1;
+    let pipeline = pipeline.clone().diagnose(rejects.is_some());
+    for input in inputs {
+        if let (Some(rejects), true) = (rejects.as_mut(), provenance) {
+            rejects.set_source(input);
+        }
+        let open = || decompressed_reader(File::open(input)?);
+        run_pipeline(open, &pipeline, |parsed| {
+            if let (Some(rejects), Some(diagnostic)) = (rejects.as_mut(), &parsed.rejection) {
+                return rejects.reject(parsed.line, &parsed.cert_id, diagnostic);
+            }
+            if provenance {
+                exporter.set_source(input, parsed.line);
+            }
+            return exporter.export(&parsed.uvci);
+        })?;
+    }
+    return exporter.finish();
+}
+
+/// Report the structure of the opaque segments of the UVCIs in a file, or with --clusters the groups of
+/// UVCIs by structural signature
+fn analyze(args: Vec<String>) {
+    let args = match parse_args(args, &[], &["--clusters"]) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    if args.files.is_empty() || args.files.len() > 2 {
+        return print_usage();
+    }
+    let cert_ids = lines_from_file(&args.files[0]);
+    let output = match args.options.contains_key("--clusters") {
+        true => clusters_to_text(&cluster_by_pattern(&cert_ids)),
+        false => analyze_structure(&cert_ids).to_string(),
+    };
+    write_output(args.files.get(1), &output);
+}
+
+/// Report the number of UVCIs of a file by estimated vaccination month, as a bar chart with --histogram
+fn stats(args: Vec<String>) {
+    let args = match parse_args(args, &[], &["--histogram"]) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    if args.files.is_empty() || args.files.len() > 2 {
+        return print_usage();
+    }
+    let cert_ids: Vec<String> = lines_from_file(&args.files[0])
+        .into_iter()
+        .filter(|cert_id| !cert_id.trim().is_empty())
+        .collect();
+    let months = vaccination_months(&cert_ids);
+    let mut output = "".to_string();
+    output.push_str(&format!("uvcis    : {}\n", cert_ids.len()));
+    output.push_str(&format!("dated    : {}\n", months.values().sum::<usize>()));
+    if args.options.contains_key("--histogram") {
+        output.push_str(&histogram(&months, 50));
+    } else {
+        for (month, count) in &months {
+            output.push_str(&format!("{} {}\n", month, count));
+        }
+    }
+    write_output(args.files.get(1), &output);
+}
+
+/// Report the estimated vaccination coverage of every country of the UVCIs of a file, with the
+/// population of a "country,population" CSV file
+fn coverage(args: Vec<String>) {
+    let args = match parse_args(args, &["--population"], &[]) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    let population_file = match args.options.get("--population") {
+        Some(population_file) => population_file,
+        None => return print_usage(),
+    };
+    if args.files.is_empty() || args.files.len() > 2 {
+        return print_usage();
+    }
+    let population =
+        File::open(population_file).and_then(|file| read_population(BufReader::new(file)));
+    let population = match population {
+        Err(why) => panic!("couldn't read {}: {}", population_file, why),
+        Ok(population) => population,
+    };
+    let report = coverage_report(&lines_from_file(&args.files[0]), &population);
+    write_output(args.files.get(1), &report.to_string());
+}
+
+/// Explore the UVCIs of a file in the interactive terminal UI
+fn tui(args: Vec<String>) {
+    if args.len() != 1 || args[0].starts_with("--") {
+        return print_usage();
+    }
+    #[cfg(feature = "ratatui")]
+    if let Err(why) = run_tui(&lines_from_file(&args[0])) {
+        panic!("couldn't run the terminal UI: {}", why);
+    }
+    #[cfg(not(feature = "ratatui"))]
+    unreachable!("the tui mode is rejected without the ratatui feature");
+}
+
+/// Report the UVCIs added, removed and changed between two dumps, as text or JSON
+fn diff(args: Vec<String>) {
+    let args = match parse_args(args, &["--format"], &[]) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    if args.files.len() < 2 || args.files.len() > 3 {
+        return print_usage();
+    }
+    let diff = diff_datasets(
+        &lines_from_file(&args.files[0]),
+        &lines_from_file(&args.files[1]),
+    );
+    let output = match args.option("--format", "text").as_str() {
+        "text" => diff.to_string(),
+        "json" => diff.to_json() + "\n",
+        _ => return print_usage(),
+    };
+    write_output(args.files.get(2), &output);
+}
+
+/// Report the conformance of the UVCIs of the test files of a dgc-testdata checkout, or of the current
+/// test data downloaded from GitHub, as text or JSON
+fn conformance(args: Vec<String>) {
+    let args = match parse_args(args, &["--format", "--countries"], &["--fetch"]) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    let report = if args.options.contains_key("--fetch") {
+        if args.files.len() > 1 {
+            return print_usage();
+        }
+        let countries: Vec<String> = args
+            .option("--countries", "")
+            .split(',')
+            .map(|country| country.trim().to_string())
+            .filter(|country| !country.is_empty())
+            .collect();
+        match fetch_conformance(DGC_TESTDATA_REPOSITORY, &countries) {
+            Err(why) => panic!("couldn't fetch test data: {}", why),
+            Ok(report) => report,
+        }
+    } else {
+        if args.files.is_empty() || args.files.len() > 2 || args.options.contains_key("--countries")
+        {
+            return print_usage();
+        }
+        match run_conformance(&args.files[0]) {
+            Err(why) => panic!("couldn't read test data {}: {}", args.files[0], why),
+            Ok(report) => report,
+        }
+    };
+    let output_index = if args.options.contains_key("--fetch") {
+        0
+    } else {
+        1
+    };
+    let output = match args.option("--format", "text").as_str() {
+        "text" => report.to_string(),
+        "json" => report.to_json() + "\n",
+        _ => return print_usage(),
+    };
+    write_output(args.files.get(output_index), &output);
+}
+
+/// Partition a file of UVCIs into a file of the checksum-valid UVCIs and a JSON Lines file of the
+/// others with their reason
+fn partition(args: Vec<String>) {
+    let args = match parse_args(args, &[], &[]) {
+        Some(args) if args.files.len() == 3 => args,
+        _ => return print_usage(),
+    };
+    let cert_ids = lines_from_file(&args.files[0]);
+    let (mut valid, mut invalid) = create_file_pair(&args.files[1], &args.files[2]);
+    match partition_batch(&cert_ids, &mut valid, &mut invalid) {
+        Err(why) => panic!("couldn't write output: {}", why),
+        Ok(_) => println!(
+            "successfully wrote to {} and {}",
+            args.files[1], args.files[2]
+        ),
+    }
+}
+
+/// Write the pseudonymized or redacted UVCIs of a file, one UVCI per line
+fn anonymize(args: Vec<String>) {
+    let args = match parse_args(
+        args,
+        &["--key-file", "--pseudonym-map", "--redact", "--reject-log"],
+        &[],
+    ) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    if args.files.is_empty() || args.files.len() > 2 {
+        return print_usage();
+    }
+    let mut privacy = match (args.options.get("--key-file"), args.options.get("--redact")) {
+        (Some(key_file), None) => {
+            let key = match fs::read(key_file) {
+                Err(why) => panic!("couldn't read key {}: {}", key_file, why),
+                Ok(key) => key,
+            };
+            let pseudonymizer = match args.options.get("--pseudonym-map") {
+                Some(path) => match Pseudonymizer::with_mapping_file(&key, path) {
+                    Err(why) => panic!("couldn't read pseudonym mapping {}: {}", path, why),
+                    Ok(pseudonymizer) => pseudonymizer,
+                },
+                None => Pseudonymizer::new(&key),
+            };
+            Privacy::Pseudonymize(pseudonymizer)
+        }
+        (None, Some(code)) if !args.options.contains_key("--pseudonym-map") => {
+            match Redaction::from_code(code) {
+                Some(Redaction::None) | None => return print_usage(),
+                Some(redaction) => Privacy::Redact(redaction),
+            }
+        }
+        _ => return print_usage(),
+    };
+
+    let mut rejects = open_reject_log(&args);
+    let mut output = "".to_string();
+    for (index, cert_id) in lines_from_file(&args.files[0]).iter().enumerate() {
+        if let Some(rejects) = rejects.as_mut() {
+            if let Some(diagnostic) = rejection(cert_id.trim()) {
+                if let Err(why) = rejects.reject(index + 1, cert_id, &diagnostic) {
+                    panic!("couldn't write reject log: {}", why);
+                }
+                continue;
+            }
+        }
+        if cert_id.trim().is_empty() {
+            continue;
+        }
+        let uvci = parse(cert_id.trim());
+        let anonymized = match &mut privacy {
+            Privacy::Pseudonymize(pseudonymizer) => pseudonymizer.pseudonymize(&uvci),
+            Privacy::Redact(redaction) => redaction.redact(&uvci),
+        };
+        if let Some(anonymized) = anonymized_cert_id(&anonymized) {
+            output.push_str(&anonymized);
+            output.push('\n');
+        }
+    }
+    write_output(args.files.get(1), &output);
+    close_reject_log(&args, rejects);
+    save_pseudonym_mapping(&privacy);
+}
+
+/// Stream the UVCIs of the lines appended to a log file as NDJSON to the terminal, until interrupted
+fn follow(args: Vec<String>) {
+    let args = match parse_args(
+        args,
+        &["--interval", "--revocation-filter", "--hash-type"],
+        &["--extract", "--from-start"],
+    ) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    let interval = match args.option("--interval", "500").parse::<u64>() {
+        Ok(interval) if interval > 0 => Duration::from_millis(interval),
+        _ => return print_usage(),
+    };
+    let hash_type = match RevocationHashType::from_code(&args.option("--hash-type", "uci")) {
+        Some(hash_type) => hash_type,
+        None => return print_usage(),
+    };
+    if args.files.len() != 1
+        || (args.options.contains_key("--hash-type")
+            && !args.options.contains_key("--revocation-filter"))
+    {
+        return print_usage();
+    }
+    let filter = match args.options.get("--revocation-filter") {
+        Some(path) => {
+            match fs::read(path).map(|bytes| RevocationFilter::from_bytes(hash_type, &bytes)) {
+                Err(why) => panic!("couldn't read {}: {}", path, why),
+                Ok(Err(why)) => panic!("couldn't load revocation filter {}: {}", path, why),
+                Ok(Ok(filter)) => Some(filter),
+            }
+        }
+        None => None,
+    };
+    let path = &args.files[0];
+    let follower = match args.options.contains_key("--from-start") {
+        true => LogFollower::from_start(path),
+        false => LogFollower::open(path),
+    };
+    let mut follower = match follower {
+        Err(why) => panic!("couldn't open {}: {}", path, why),
+        Ok(follower) => follower,
+    };
+    let extract = args.options.contains_key("--extract");
+    let stdout = io::stdout();
+    loop {
+        let lines = match follower.poll() {
+            Err(why) => panic!("couldn't read {}: {}", path, why),
+            Ok(lines) => lines,
+        };
+        let mut output = stdout.lock();
+        for (line, text) in &lines {
+            let cert_ids = match extract {
+                true => extract_uvcis(text),
+                false if text.trim().is_empty() => Vec::new(),
+                false => vec![text.trim()],
+            };
+            for cert_id in cert_ids {
+                let record = follow_record(cert_id, *line, filter.as_ref());
+                // Stop quietly when the reader of the stream goes away, e.g. head
+                if writeln!(output, "{}", record).is_err() {
+                    return;
+                }
+            }
+        }
+        if output.flush().is_err() {
+            return;
+        }
+        drop(output);
+        thread::sleep(interval);
+    }
+}
+
+/// Emit the revocation hash of every UVCI in a file, one per line
+fn revoke_hash(args: Vec<String>) {
+    let args = match parse_args(args, &["--hash-type", "--encoding", "--reject-log"], &[]) {
+        Some(args) => args,
+        None => return print_usage(),
+    };
+    let hash_type = match RevocationHashType::from_code(&args.option("--hash-type", "uci")) {
+        Some(hash_type) => hash_type,
+        None => return print_usage(),
+    };
+    let encoding = match args.option("--encoding", "hex").as_str() {
+        "hex" => HashEncoding::Hex,
+        "base64" => HashEncoding::Base64,
+        _ => return print_usage(),
+    };
+    if args.files.is_empty() || args.files.len() > 2 {
+        return print_usage();
+    }
+
+    let mut rejects = open_reject_log(&args);
+    let mut output = "".to_string();
+    for (index, cert_id) in lines_from_file(&args.files[0]).iter().enumerate() {
+        if let Some(rejects) = rejects.as_mut() {
+            if let Some(diagnostic) = rejection(cert_id.trim()) {
+                if let Err(why) = rejects.reject(index + 1, cert_id, &diagnostic) {
+                    panic!("couldn't write reject log: {}", why);
+                }
+                continue;
+            }
+        }
+        if cert_id.trim().is_empty() {
+            continue;
+        }
+        output.push_str(&encoding.encode(&revocation_hash(&parse(cert_id.trim()), hash_type)));
+        output.push_str("\n");
+    }
+    write_output(args.files.get(1), &output);
+    close_reject_log(&args, rejects);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{refuses_overwrite, split_files};
+    use std::fs;
+
+    #[test]
+    fn convert_output_file() {
+        // `uvci --format csv exports/*.txt` expanded by the shell
+        let dir = std::env::temp_dir().join(format!("uvci_convert_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files: Vec<String> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, "URN:UVCI:01:SE:EHM/V12907267LAJW#E\n").unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        assert!(split_files(&files, None).is_none());
+        let (inputs, outfile) = split_files(&files[..2], None).unwrap();
+        assert!(inputs == &files[..1] && outfile == Some(&files[1]));
+        assert!(refuses_overwrite(outfile, false));
+        assert!(!refuses_overwrite(outfile, true));
+
+        let output = dir.join("uvcis.csv").to_string_lossy().to_string();
+        let (inputs, outfile) = split_files(&files, Some(&output)).unwrap();
+        assert!(inputs == &files[..] && outfile == Some(&output));
+        assert!(!refuses_overwrite(outfile, false));
+        assert!(split_files(&files[..1], None).unwrap() == (&files[..1], None));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
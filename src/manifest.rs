@@ -0,0 +1,214 @@
+//! Manifest of an export listing its input and output files with their SHA-256 digests
+use crate::compression::decompressed_reader;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Input or output file of an export with its SHA-256 digest
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestFile {
+    /// The path of the file
+    pub file: String,
+    /// The lowercase hex SHA-256 of the file as stored, i.e. still compressed
+    pub sha256: String,
+    /// The size of the file in bytes
+    pub bytes: u64,
+    /// The number of UVCIs of an input file, its non-empty lines after decompression
+    pub records: Option<usize>,
+}
+
+impl ManifestFile {
+    /// Digest an output file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the output file
+    pub fn output<P: AsRef<Path>>(path: P) -> io::Result<ManifestFile> {
+        let (sha256, bytes) = sha256_hex(File::open(&path)?)?;
+        return Ok(ManifestFile {
+            file: path.as_ref().display().to_string(),
+            sha256,
+            bytes,
+            records: None,
+        });
+    }
+
+    /// Digest an input file and count its UVCIs, gzip or Zstandard compressed with the matching feature
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the input file, one UVCI per line
+    pub fn input<P: AsRef<Path>>(path: P) -> io::Result<ManifestFile> {
+        let mut file = ManifestFile::output(&path)?;
+        let mut records = 0;
+        for line in decompressed_reader(File::open(&path)?)?.lines() {
+            if !line?.trim().is_empty() {
+                records += 1;
+            }
+        }
+        file.records = Some(records);
+        return Ok(file);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::json!({
+            "file": self.file,
+            "sha256": self.sha256,
+            "bytes": self.bytes,
+        });
+        if let Some(records) = self.records {
+            value["records"] = serde_json::json!(records);
+        }
+        return value;
+    }
+}
+
+/// Manifest of an export, to prove what was produced from what in audited pipelines
+///
+/// Records the tool version, the parameters, the input files with their number of UVCIs, the number of
+/// exported and rejected UVCIs and the output files, all files with their SHA-256.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    /// The version of this crate
+    pub version: String,
+    /// When the manifest was created, in seconds since the Unix epoch
+    pub created: u64,
+    /// The parameters of the export, e.g. "--format" and "csv"
+    pub parameters: BTreeMap<String, String>,
+    /// The input files
+    pub inputs: Vec<ManifestFile>,
+    /// The output files
+    pub outputs: Vec<ManifestFile>,
+    /// The number of exported UVCIs
+    pub records: usize,
+    /// The number of rejected UVCIs, skipped with a reject log
+    pub rejected: usize,
+}
+
+impl Default for Manifest {
+    fn default() -> Manifest {
+        return Manifest::new();
+    }
+}
+
+impl Manifest {
+    /// Create an empty manifest of the current version and time
+    pub fn new() -> Manifest {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        return Manifest {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created,
+            parameters: BTreeMap::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            records: 0,
+            rejected: 0,
+        };
+    }
+
+    /// Add a parameter of the export
+    pub fn parameter(mut self, name: &str, value: &str) -> Self {
+        self.parameters.insert(name.to_string(), value.to_string());
+        return self;
+    }
+
+    /// Add an input file, see `ManifestFile::input`
+    pub fn input(mut self, input: ManifestFile) -> Self {
+        self.inputs.push(input);
+        return self;
+    }
+
+    /// Add an output file, see `ManifestFile::output`
+    pub fn output(mut self, output: ManifestFile) -> Self {
+        self.outputs.push(output);
+        return self;
+    }
+
+    /// Set the number of rejected UVCIs; the exported UVCIs are the UVCIs of the inputs less the rejected ones
+    pub fn rejected(mut self, rejected: usize) -> Self {
+        let records: usize = self.inputs.iter().filter_map(|input| input.records).sum();
+        self.rejected = rejected;
+        self.records = records.saturating_sub(rejected);
+        return self;
+    }
+
+    /// The manifest as a pretty-printed JSON object
+    pub fn to_json(&self) -> String {
+        let manifest = serde_json::json!({
+            "tool": "covid_cert_uvci",
+            "version": self.version,
+            "created": self.created,
+            "parameters": self.parameters,
+            "inputs": self.inputs.iter().map(ManifestFile::to_json).collect::<Vec<_>>(),
+            "records": self.records,
+            "rejected": self.rejected,
+            "outputs": self.outputs.iter().map(ManifestFile::to_json).collect::<Vec<_>>(),
+        });
+        return serde_json::to_string_pretty(&manifest).unwrap_or_default();
+    }
+
+    /// The path of the manifest of an output file, e.g. "graph.cypher.manifest.json"
+    pub fn path(outfile: &str) -> String {
+        return format!("{}.manifest.json", outfile);
+    }
+}
+
+/// Lowercase hex SHA-256 and length of all bytes of the reader
+fn sha256_hex<R: Read>(mut reader: R) -> io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut bytes = 0;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        bytes += read as u64;
+    }
+    let digest = hasher.finalize();
+    return Ok((digest.iter().map(|b| format!("{:02x}", b)).collect(), bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sha256_hex, Manifest, ManifestFile};
+
+    #[test]
+    fn export_manifest() {
+        let (sha256, bytes) = sha256_hex("abc".as_bytes()).unwrap();
+        assert!(sha256 == "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(bytes == 3);
+
+        let path = std::env::temp_dir().join(format!("uvci_manifest_{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E\n\nURN:UVCI:01:SE:EHM/V12916227TFJJ#Q\n",
+        )
+        .unwrap();
+        let input = ManifestFile::input(&path).unwrap();
+        assert!(input.records == Some(2));
+        assert!(input.bytes == 71);
+        let output = ManifestFile::output(&path).unwrap();
+        assert!(output.records.is_none() && output.sha256 == input.sha256);
+        let _ = std::fs::remove_file(&path);
+
+        let manifest = Manifest::new()
+            .parameter("--format", "csv")
+            .input(input)
+            .output(output)
+            .rejected(1);
+        assert!(manifest.records == 1);
+        let json: serde_json::Value = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert!(json["parameters"]["--format"] == "csv");
+        assert!(json["inputs"][0]["records"] == 2);
+        assert!(json["outputs"][0].get("records").is_none());
+        assert!(json["rejected"] == 1);
+        assert!(Manifest::path("out.csv") == "out.csv.manifest.json");
+    }
+}
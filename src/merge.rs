@@ -0,0 +1,209 @@
+//! Merging of several datasets of UVCIs into one, keeping where each UVCI appeared
+use crate::compression::decompressed_reader;
+use crate::{csv_quoted, normalized_cert_id, parse, to_json, write_csv, Uvci, CSV_LINE_CAPACITY};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+/// Input line a UVCI appeared on
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occurrence {
+    /// The name of the input, e.g. the file name
+    pub source: String,
+    /// The line number in the input, from 1
+    pub line: usize,
+}
+
+/// Unique UVCI of a merged dataset with the input lines it appeared on
+#[derive(Clone)]
+pub struct MergedUvci {
+    /// The canonical UVCI of the first occurrence, see `Uvci::canonical`
+    pub canonical: String,
+    /// The parsed UVCI of the first occurrence
+    pub uvci: Uvci,
+    /// The input lines the UVCI appeared on, in input order
+    pub occurrences: Vec<Occurrence>,
+}
+
+/// Deduplicated UVCIs of several inputs, with the provenance of every UVCI
+#[derive(Clone, Default)]
+pub struct MergedDataset {
+    /// The unique UVCIs, in the order of their first occurrence
+    pub uvcis: Vec<MergedUvci>,
+    /// The number of non-empty input lines
+    pub lines: usize,
+}
+
+impl MergedDataset {
+    /// The number of input lines that repeat a UVCI seen before
+    pub fn duplicates(&self) -> usize {
+        return self.lines - self.uvcis.len();
+    }
+
+    /// One CSV line per unique UVCI, the columns of `uvcis_to_csv` followed by the number of
+    /// occurrences and the occurrences as "source:line" separated by ";", quoted as the sources are
+    /// file names
+    pub fn to_csv(&self) -> String {
+        let mut output = "".to_string();
+        let mut line = String::with_capacity(CSV_LINE_CAPACITY);
+        for merged in &self.uvcis {
+            line.clear();
+            write_csv(&merged.uvci, &mut line);
+            let occurrences: Vec<String> = merged
+                .occurrences
+                .iter()
+                .map(|occurrence| format!("{}:{}", occurrence.source, occurrence.line))
+                .collect();
+            output.push_str(&line);
+            output.push_str(&format!(
+                ",{},{}\n",
+                merged.occurrences.len(),
+                csv_quoted(&occurrences.join(";"))
+            ));
+        }
+        return output;
+    }
+
+    /// A JSON array with one object per unique UVCI, the object of `uvci_to_json` with the
+    /// "canonical" UVCI and its "occurrences" as objects with "source" and "line"
+    pub fn to_json(&self) -> String {
+        let values: Vec<serde_json::Value> = self
+            .uvcis
+            .iter()
+            .map(|merged| {
+                let mut value = to_json(&merged.uvci);
+                value["canonical"] = serde_json::json!(merged.canonical);
+                value["occurrences"] = merged
+                    .occurrences
+                    .iter()
+                    .map(|occurrence| {
+                        serde_json::json!({ "source": occurrence.source, "line": occurrence.line })
+                    })
+                    .collect();
+                value
+            })
+            .collect();
+        return serde_json::Value::Array(values).to_string();
+    }
+}
+
+/// Merge several inputs of UVCIs into one deduplicated dataset, recording where every UVCI appeared
+///
+/// UVCIs are deduplicated by their `normalized_cert_id`, i.e. uppercase with the prefix "URN:UVCI:",
+/// so "01:se:ehm/v12907267lajw#e" and "URN:UVCI:01:SE:EHM/V12907267LAJW#E" are the same UVCI, and
+/// distinct UVCIs that cannot be parsed stay distinct. Empty lines are skipped but counted in the
+/// line numbers.
+/// # Arguments
+///
+/// * `inputs` - the name of every input, e.g. the file name, with its UVCIs one per line
+pub fn merge_datasets(inputs: &[(String, Vec<String>)]) -> MergedDataset {
+    let mut dataset = MergedDataset::default();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (source, cert_ids) in inputs {
+        for (index, cert_id) in cert_ids.iter().enumerate() {
+            let cert_id = cert_id.trim();
+            if cert_id.is_empty() {
+                continue;
+            }
+            dataset.lines += 1;
+            let occurrence = Occurrence {
+                source: source.clone(),
+                line: index + 1,
+            };
+            let key = normalized_cert_id(cert_id);
+            match positions.get(&key) {
+                Some(position) => dataset.uvcis[*position].occurrences.push(occurrence),
+                None => {
+                    positions.insert(key, dataset.uvcis.len());
+                    let uvci = parse(cert_id);
+                    dataset.uvcis.push(MergedUvci {
+                        canonical: uvci.canonical(),
+                        uvci,
+                        occurrences: vec![occurrence],
+                    });
+                }
+            }
+        }
+    }
+    return dataset;
+}
+
+/// Merge files of UVCIs, gzip or Zstandard compressed with the matching feature, see `merge_datasets`
+///
+/// # Arguments
+///
+/// * `paths` - the input files, their paths are the sources of the occurrences
+pub fn merge_files<P: AsRef<Path>>(paths: &[P]) -> io::Result<MergedDataset> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        let reader = decompressed_reader(File::open(path)?)?;
+        let lines = reader.lines().collect::<io::Result<Vec<String>>>()?;
+        inputs.push((path.as_ref().display().to_string(), lines));
+    }
+    return Ok(merge_datasets(&inputs));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_datasets, Occurrence};
+
+    #[test]
+    fn merged_dataset() {
+        let inputs = vec![
+            (
+                "day1.txt".to_string(),
+                vec![
+                    "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+                    "".to_string(),
+                    "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+                ],
+            ),
+            (
+                "day2.txt".to_string(),
+                vec![
+                    "01:se:ehm/v12907267lajw#e".to_string(),
+                    "01:NL:187/37512422923".to_string(),
+                ],
+            ),
+            (
+                "day,3.txt".to_string(),
+                vec![
+                    "URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W".to_string(),
+                    "URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T".to_string(),
+                ],
+            ),
+        ];
+        let dataset = merge_datasets(&inputs);
+        assert!(dataset.lines == 6 && dataset.uvcis.len() == 5);
+        assert!(dataset.duplicates() == 1);
+        let first = &dataset.uvcis[0];
+        assert!(first.canonical == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(
+            first.occurrences
+                == vec![
+                    Occurrence {
+                        source: "day1.txt".to_string(),
+                        line: 1,
+                    },
+                    Occurrence {
+                        source: "day2.txt".to_string(),
+                        line: 1,
+                    },
+                ]
+        );
+        assert!(dataset.uvcis[1].occurrences[0].line == 3);
+
+        let csv = dataset.to_csv();
+        assert!(csv.lines().count() == 5);
+        assert!(csv
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(",2,\"day1.txt:1;day2.txt:1\""));
+        assert!(csv.lines().last().unwrap().ends_with(",1,\"day,3.txt:2\""));
+        let json: serde_json::Value = serde_json::from_str(&dataset.to_json()).unwrap();
+        assert!(json[0]["occurrences"][1]["source"] == "day2.txt");
+        assert!(json[2]["canonical"] == "URN:UVCI:01:NL:187/37512422923");
+    }
+}
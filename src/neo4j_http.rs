@@ -0,0 +1,250 @@
+//! Export of the Neo4j graph through the Neo4j HTTP transactional API
+use crate::uvci_to_graph_merge;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// Error pushing Cypher statements to the Neo4j HTTP transactional API
+#[derive(Debug)]
+pub enum Neo4jHttpError {
+    /// The request could not be sent, after all retries
+    Request(reqwest::Error),
+    /// Neo4j answered with an unexpected HTTP status, after all retries for server errors
+    Status(u16),
+    /// Neo4j rejected a statement, with the Neo4j status code and message
+    Cypher(String, String),
+}
+
+impl fmt::Display for Neo4jHttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Neo4jHttpError::Request(e) => write!(f, "Neo4j request failed: {}", e),
+            Neo4jHttpError::Status(status) => {
+                write!(f, "Neo4j answered with HTTP status {}", status)
+            }
+            Neo4jHttpError::Cypher(code, message) => write!(f, "Neo4j error {}: {}", code, message),
+        }
+    }
+}
+
+impl std::error::Error for Neo4jHttpError {}
+
+/// Exporter pushing the Neo4j Cypher Graph of EU Digital COVID Certificate UVCIs to the Neo4j HTTP
+/// transactional API, for environments where the Bolt port is not reachable
+///
+/// Only for Sweden EHM-issued COVID certificates. Every UVCI is sent as a self-contained MERGE
+/// statement, `batch_size` statements per transaction. Transactions failing with a transport error,
+/// an HTTP server error or a Neo4j transient error are retried with exponential backoff.
+pub struct Neo4jHttpExporter {
+    url: String,
+    credentials: Option<(String, String)>,
+    batch_size: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    client: reqwest::blocking::Client,
+}
+
+impl Neo4jHttpExporter {
+    /// Create an exporter for a Neo4j database, with 1000 statements per transaction and 3 retries
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - the Neo4j HTTP URL, e.g. "http://localhost:7474"
+    /// * `database` - the database name, e.g. "neo4j"
+    pub fn new(base_url: &str, database: &str) -> Neo4jHttpExporter {
+        return Neo4jHttpExporter {
+            url: format!(
+                "{}/db/{}/tx/commit",
+                base_url.trim_end_matches('/'),
+                database
+            ),
+            credentials: None,
+            batch_size: 1000,
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+            client: reqwest::blocking::Client::new(),
+        };
+    }
+
+    /// Authenticate with HTTP basic authentication
+    pub fn credentials(mut self, user: &str, password: &str) -> Neo4jHttpExporter {
+        self.credentials = Some((user.to_string(), password.to_string()));
+        return self;
+    }
+
+    /// Number of statements per transaction, at least 1
+    pub fn batch_size(mut self, batch_size: usize) -> Neo4jHttpExporter {
+        self.batch_size = batch_size.max(1);
+        return self;
+    }
+
+    /// Number of retries of a failed transaction
+    pub fn max_retries(mut self, max_retries: u32) -> Neo4jHttpExporter {
+        self.max_retries = max_retries;
+        return self;
+    }
+
+    /// Delay before the first retry, doubled for every further retry
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Neo4jHttpExporter {
+        self.retry_delay = retry_delay;
+        return self;
+    }
+
+    /// Push the Neo4j Cypher Graph of a vector of UVCIs, returning the number of statements committed
+    ///
+    /// Transactions committed before an error are not rolled back; as the statements are MERGE
+    /// statements, the push can simply be repeated.
+    /// # Arguments
+    ///
+    /// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+    pub fn push(&self, cert_ids: &[String]) -> Result<usize, Neo4jHttpError> {
+        let statements: Vec<String> = cert_ids
+            .iter()
+            .map(|cert_id| uvci_to_graph_merge(cert_id))
+            .filter(|statement| !statement.is_empty())
+            .collect();
+        let mut committed = 0;
+        for batch in statements.chunks(self.batch_size) {
+            self.commit(batch)?;
+            committed += batch.len();
+        }
+        return Ok(committed);
+    }
+
+    /// Commit a batch of statements in one transaction, with retries
+    fn commit(&self, statements: &[String]) -> Result<(), Neo4jHttpError> {
+        let body = serde_json::json!({
+            "statements": statements
+                .iter()
+                .map(|statement| serde_json::json!({ "statement": statement }))
+                .collect::<Vec<_>>(),
+        })
+        .to_string();
+
+        let mut attempt = 0;
+        loop {
+            let result = self.send(&body);
+            let retry = match &result {
+                Err(Neo4jHttpError::Request(_)) => true,
+                Err(Neo4jHttpError::Status(status)) => *status >= 500,
+                Err(Neo4jHttpError::Cypher(code, _)) => code.contains("TransientError"),
+                Ok(_) => false,
+            };
+            if !retry || attempt >= self.max_retries {
+                return result;
+            }
+            thread::sleep(self.retry_delay * 2u32.saturating_pow(attempt));
+            attempt += 1;
+        }
+    }
+
+    /// Send one transaction request and check the response for errors
+    fn send(&self, body: &str) -> Result<(), Neo4jHttpError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .body(body.to_string());
+        if let Some((user, password)) = &self.credentials {
+            request = request.basic_auth(user, Some(password));
+        }
+        let response = request.send().map_err(Neo4jHttpError::Request)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Neo4jHttpError::Status(status.as_u16()));
+        }
+        let response = response.text().map_err(Neo4jHttpError::Request)?;
+        let response: serde_json::Value =
+            serde_json::from_str(&response).unwrap_or(serde_json::Value::Null);
+        if let Some(error) = response["errors"].as_array().and_then(|e| e.first()) {
+            return Err(Neo4jHttpError::Cypher(
+                error["code"].as_str().unwrap_or("").to_string(),
+                error["message"].as_str().unwrap_or("").to_string(),
+            ));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Neo4jHttpExporter;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Serve the given HTTP responses in order, returning the request bodies
+    fn serve(
+        responses: Vec<(&'static str, &'static str)>,
+    ) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if line.to_lowercase().starts_with("content-length:") {
+                        content_length = line[15..].trim().parse().unwrap();
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+                bodies.push(String::from_utf8(request_body).unwrap());
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            return bodies;
+        });
+        return (url, handle);
+    }
+
+    #[test]
+    fn neo4j_http_push_with_retry() {
+        let ok = r#"{"results":[],"errors":[]}"#;
+        let (url, handle) = serve(vec![
+            ("503 Service Unavailable", ""),
+            ("200 OK", ok),
+            ("200 OK", ok),
+        ]);
+        let exporter = Neo4jHttpExporter::new(&url, "neo4j")
+            .batch_size(2)
+            .retry_delay(Duration::from_millis(1));
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:SE:EHM/C878/123456789ABC#B".to_string(),
+            "URN:UVCI:01:SE:EHM/V12920064NYOH#4".to_string(),
+        ];
+        assert!(exporter.push(&cert_ids).unwrap() == 3);
+
+        let bodies = handle.join().unwrap();
+        let batch: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert!(batch["statements"].as_array().unwrap().len() == 2);
+        assert!(bodies[0] == bodies[1], "retry sent a different transaction");
+    }
+
+    #[test]
+    fn neo4j_http_cypher_error() {
+        let error = r#"{"results":[],"errors":[{"code":"Neo.ClientError.Statement.SyntaxError","message":"Invalid input"}]}"#;
+        let (url, handle) = serve(vec![("200 OK", error)]);
+        let exporter = Neo4jHttpExporter::new(&url, "neo4j");
+        let result = exporter.push(&["URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string()]);
+        assert!(result.unwrap_err().to_string().contains("SyntaxError"));
+        handle.join().unwrap();
+    }
+}
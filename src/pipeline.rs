@@ -0,0 +1,246 @@
+//! Parallel parsing pipeline for large inputs, keeping the order of the input lines
+use crate::reject::rejection;
+use crate::{parse, Diagnostic, Uvci};
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Line of the input parsed by a worker of the pipeline
+#[derive(Clone)]
+pub struct ParsedLine {
+    /// The number of the line in the input, starting at 1
+    pub line: usize,
+    /// The line as read
+    pub cert_id: String,
+    /// The UVCI parsed from the line
+    pub uvci: Uvci,
+    /// The first error diagnostic of the line if rejections are diagnosed, see `reject::rejection`
+    pub rejection: Option<Diagnostic>,
+}
+
+/// Options of the multi-threaded parsing pipeline of `run_pipeline`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineOptions {
+    workers: usize,
+    batch_size: usize,
+    capacity: usize,
+    diagnose: bool,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> PipelineOptions {
+        return PipelineOptions {
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            batch_size: 1024,
+            capacity: 16,
+            diagnose: false,
+        };
+    }
+}
+
+impl PipelineOptions {
+    /// Create options with a worker per core, batches of 1024 lines and 16 batches in flight
+    pub fn new() -> PipelineOptions {
+        return PipelineOptions::default();
+    }
+
+    /// Number of parser worker threads, at least 1
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        return self;
+    }
+
+    /// Number of lines parsed by a worker at once, at least 1
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        return self;
+    }
+
+    /// Number of batches read ahead of the sink, at least 1, bounding the memory of the pipeline
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        return self;
+    }
+
+    /// Whether the workers also diagnose the rejection of every line, e.g. for a reject log
+    pub fn diagnose(mut self, diagnose: bool) -> Self {
+        self.diagnose = diagnose;
+        return self;
+    }
+}
+
+/// Batch of lines sent to the workers, with its first line number and the channel of its result
+type Job = (usize, Vec<String>, SyncSender<Vec<ParsedLine>>);
+
+/// Parse the lines of an input with a reader thread, parser worker threads and the calling thread as
+/// writer, connected by bounded channels
+///
+/// The reader thread opens the input and sends batches of lines to the workers, the sink is called on
+/// the calling thread with every parsed line in input order, so exporters need not be thread-safe.
+/// At most `capacity` batches are in flight, so memory stays flat when the sink, e.g. compressed
+/// output, is slower than parsing: the reader then waits for the sink. Returns the first error of
+/// reading the input or of the sink, the pipeline stops reading on an error of the sink.
+/// # Arguments
+///
+/// * `open` - opens the input in the reader thread, e.g. `|| decompressed_reader(File::open(path)?)`
+/// * `options` - the pipeline options
+/// * `sink` - called with every parsed line, in input order
+pub fn run_pipeline<O, R, F>(open: O, options: &PipelineOptions, mut sink: F) -> io::Result<()>
+where
+    O: FnOnce() -> io::Result<R> + Send,
+    R: BufRead,
+    F: FnMut(ParsedLine) -> io::Result<()>,
+{
+    let (job_sender, job_receiver) = mpsc::sync_channel::<Job>(options.capacity);
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    // The result channels of the batches in input order, the error of the input instead of a batch
+    let (order_sender, order_receiver) =
+        mpsc::sync_channel::<io::Result<Receiver<Vec<ParsedLine>>>>(options.capacity);
+    let batch_size = options.batch_size;
+    let diagnose = options.diagnose;
+
+    return thread::scope(|scope| {
+        scope.spawn(move || {
+            let dispatch = |first: usize, lines: Vec<String>| -> bool {
+                let (result_sender, result_receiver) = mpsc::sync_channel(1);
+                return order_sender.send(Ok(result_receiver)).is_ok()
+                    && job_sender.send((first, lines, result_sender)).is_ok();
+            };
+            let reader = match open() {
+                Err(e) => {
+                    let _ = order_sender.send(Err(e));
+                    return;
+                }
+                Ok(reader) => reader,
+            };
+            let mut first = 1;
+            let mut lines = Vec::with_capacity(batch_size);
+            for (index, line) in reader.lines().enumerate() {
+                match line {
+                    Err(e) => {
+                        if dispatch(first, lines) {
+                            let _ = order_sender.send(Err(e));
+                        }
+                        return;
+                    }
+                    Ok(line) => lines.push(line),
+                }
+                if lines.len() == batch_size {
+                    let batch = std::mem::replace(&mut lines, Vec::with_capacity(batch_size));
+                    if !dispatch(first, batch) {
+                        return;
+                    }
+                    first = index + 2;
+                }
+            }
+            if !lines.is_empty() {
+                dispatch(first, lines);
+            }
+        });
+        for _ in 0..options.workers {
+            let job_receiver = Arc::clone(&job_receiver);
+            scope.spawn(move || loop {
+                let job = job_receiver.lock().unwrap().recv();
+                let (first, lines, result_sender) = match job {
+                    Err(_) => return,
+                    Ok(job) => job,
+                };
+                let parsed = lines
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, cert_id)| ParsedLine {
+                        line: first + index,
+                        uvci: parse(&cert_id),
+                        rejection: if diagnose { rejection(&cert_id) } else { None },
+                        cert_id,
+                    })
+                    .collect();
+                let _ = result_sender.send(parsed);
+            });
+        }
+        for batch in order_receiver {
+            let parsed = match batch?.recv() {
+                Err(_) => return Err(io::Error::other("parser worker failed")),
+                Ok(parsed) => parsed,
+            };
+            for line in parsed {
+                sink(line)?;
+            }
+        }
+        return Ok(());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_pipeline, PipelineOptions};
+    use crate::parse;
+    use std::io::{self, BufRead};
+
+    #[test]
+    fn pipeline_order_and_errors() {
+        let cert_ids: Vec<String> = (0..1000)
+            .map(|n| format!("URN:UVCI:01:SE:EHM/V{:08}LAJW", n))
+            .chain(vec!["URN:UVCI:01".to_string()])
+            .collect();
+        let input = cert_ids.join("\n");
+        let options = PipelineOptions::new()
+            .workers(4)
+            .batch_size(7)
+            .capacity(2)
+            .diagnose(true);
+        let mut parsed = Vec::new();
+        run_pipeline(
+            || Ok(input.as_bytes()),
+            &options,
+            |line| {
+                parsed.push(line);
+                return Ok(());
+            },
+        )
+        .unwrap();
+        assert!(parsed.len() == 1001);
+        assert!(parsed
+            .iter()
+            .enumerate()
+            .all(|(index, line)| line.line == index + 1
+                && line.cert_id == cert_ids[index]
+                && line.uvci.canonical() == parse(&cert_ids[index]).canonical()));
+        assert!(parsed[999].rejection.is_none());
+        assert!(parsed[1000].rejection.as_ref().unwrap().code == "E004");
+
+        // An error of the sink stops the pipeline
+        let mut seen = 0;
+        let result = run_pipeline(
+            || Ok(input.as_bytes()),
+            &options,
+            |_| {
+                seen += 1;
+                if seen == 10 {
+                    return Err(io::Error::other("full disk"));
+                }
+                return Ok(());
+            },
+        );
+        assert!(result.is_err() && seen == 10);
+
+        let result = run_pipeline(
+            || -> io::Result<&[u8]> { Err(io::Error::new(io::ErrorKind::NotFound, "no input")) },
+            &options,
+            |_| Ok(()),
+        );
+        assert!(result.unwrap_err().kind() == io::ErrorKind::NotFound);
+        let invalid: &[u8] = b"URN:UVCI:01:SE:EHM/V12907267LAJW#E\n\xff\n";
+        let mut lines = 0;
+        let result = run_pipeline(
+            || Ok(invalid),
+            &options,
+            |_| {
+                lines += 1;
+                return Ok(());
+            },
+        );
+        assert!(result.is_err() && lines == 1 && invalid.lines().count() == 2);
+    }
+}
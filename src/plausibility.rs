@@ -0,0 +1,145 @@
+//! Heuristic plausibility score of a UVCI, to triage suspicious certificates
+use crate::{SchemaOption, Uvci};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Expected length of the opaque unique string of the issuing entities with a known scheme
+const EXPECTED_OPAQUE_LENGTHS: &[(&str, &str, usize)] = &[("SE", "EHM", 13)];
+
+/// Plausibility score of a UVCI, from 0 (certainly fake) to 100 (nothing suspicious)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    /// The score, 100 minus the penalties of the reasons
+    pub value: u8,
+    /// The reasons lowering the score, empty for a score of 100
+    pub reasons: Vec<String>,
+}
+
+impl Score {
+    fn penalize(&mut self, penalty: u8, reason: &str) {
+        self.value = self.value.saturating_sub(penalty);
+        self.reasons.push(reason.to_string());
+    }
+}
+
+/// Heuristic plausibility of a UVCI, to triage suspicious certificates in fraud investigations
+///
+/// Combines the checksum validity, the issuing entity being known for the country (with the
+/// `known-issuers` feature), the expected length and charset of the opaque unique string for the claimed
+/// country and the sanity of the decoded vaccination date. A low score is a reason to look closer, not a
+/// proof of forgery.
+/// # Arguments
+///
+/// * `uvci` - the parsed UVCI
+pub fn plausibility_score(uvci: &Uvci) -> Score {
+    let mut score = Score {
+        value: 100,
+        reasons: Vec::new(),
+    };
+
+    if uvci.schema_option == SchemaOption::Unknown {
+        score.penalize(60, "the UVCI does not follow any schema option");
+    }
+    if uvci.version != Some(1) {
+        score.penalize(20, "the schema version is not 01");
+    }
+    if uvci.country.is_none() {
+        score.penalize(20, "the country is not an ISO 3166-1 alpha-2 code");
+    }
+
+    if uvci.checksum.is_none() {
+        score.penalize(10, "the UVCI has no checksum");
+    } else if !uvci.checksum_verification {
+        score.penalize(40, "the checksum does not verify");
+    }
+
+    #[cfg(feature = "known-issuers")]
+    if uvci.schema_option != SchemaOption::Unknown && !uvci.issuer_known() {
+        score.penalize(15, "the issuing entity is not known for the country");
+    }
+    #[cfg(feature = "known-issuers")]
+    if !uvci.vaccination_in_issuer_period() {
+        score.penalize(
+            20,
+            "the vaccination date is outside the active period of the issuing entity",
+        );
+    }
+
+    let opaque = &uvci.opaque_unique_string;
+    if !opaque
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    {
+        score.penalize(
+            20,
+            "the opaque unique string has characters outside 0-9 and A-Z",
+        );
+    }
+    let expected_length = EXPECTED_OPAQUE_LENGTHS.iter().find(|(country, issuer, _)| {
+        *country == uvci.country_str() && Some(*issuer) == uvci.issuing_entity.as_deref()
+    });
+    if let Some((_, _, length)) = expected_length {
+        if opaque.len() != *length {
+            score.penalize(
+                25,
+                "the opaque unique string does not have the expected length for the country",
+            );
+        }
+    }
+
+    if let Some(date) = uvci.opaque_vaccination {
+        let vaccination = (date.year, date.month);
+        if vaccination < (2020, 12) || vaccination > current_year_month() {
+            score.penalize(
+                25,
+                "the vaccination date is before the vaccination campaigns or in the future",
+            );
+        }
+    }
+    return score;
+}
+
+/// The current year and month, approximated from the system time
+fn current_year_month() -> (u16, u8) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let months = seconds * 12 / 31_556_952;
+    return (1970 + (months / 12) as u16, (months % 12) as u8 + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::plausibility_score;
+    use crate::parse;
+
+    #[test]
+    fn plausible_uvci() {
+        let score = plausibility_score(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E"));
+        assert!(score.value == 100, "{:?}", score.reasons);
+        assert!(score.reasons.is_empty());
+    }
+
+    #[test]
+    fn suspicious_uvci() {
+        let score = plausibility_score(&parse("URN:UVCI:01:SE:EHM/V12907267LAJX#E"));
+        assert!(score.value == 60);
+        assert!(score.reasons == vec!["the checksum does not verify".to_string()]);
+
+        let score = plausibility_score(&parse("URN:UVCI:01:SE:EHM/V1290726LAJW"));
+        assert!(score.value == 65);
+        assert!(score.reasons.len() == 2);
+
+        let score = plausibility_score(&parse("URN:UVCI:01"));
+        assert!(score.value < 20);
+    }
+
+    #[cfg(feature = "known-issuers")]
+    #[test]
+    fn uvci_outside_issuer_period() {
+        // 99999999 doses imply a vaccination in April 2026, after Sweden EHM stopped certifying
+        let score = plausibility_score(&parse("URN:UVCI:01:SE:EHM/V99999999TFJJ"));
+        assert!(score.reasons.contains(
+            &"the vaccination date is outside the active period of the issuing entity".to_string()
+        ));
+    }
+}
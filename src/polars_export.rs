@@ -0,0 +1,101 @@
+//! Export of parsed UVCIs to a Polars data frame
+use crate::{parse, Uvci};
+use polars::prelude::{Column, DataFrame, DataType, NamedFrom, PolarsResult, Series};
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a Polars data frame
+///
+/// The frame has the typed columns of the Arrow export: the fields of `Uvci::FIELD_NAMES`, null when
+/// unknown, followed by the estimated vaccination month as Date "vaccination_date" (the first day of
+/// the month), one row per UVCI.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn uvcis_to_dataframe(cert_ids: &[String]) -> PolarsResult<DataFrame> {
+    let uvcis: Vec<Uvci> = cert_ids.iter().map(|cert_id| parse(cert_id)).collect();
+    let text = |name: &str, value: fn(&Uvci) -> Option<&str>| -> Column {
+        let values: Vec<Option<&str>> = uvcis.iter().map(value).collect();
+        Series::new(name.into(), values).into()
+    };
+    let vaccination_dates: Vec<Option<i32>> = uvcis
+        .iter()
+        .map(|u| u.opaque_vaccination.map(|date| date.days_since_epoch()))
+        .collect();
+    return DataFrame::new(vec![
+        Series::new(
+            "version".into(),
+            uvcis.iter().map(|u| u.version).collect::<Vec<_>>(),
+        )
+        .into(),
+        text("country", |u| {
+            u.country.as_ref().map(|country| country.as_str())
+        }),
+        Series::new(
+            "schema_option_number".into(),
+            uvcis
+                .iter()
+                .map(|u| u.schema_option.number())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        text("schema_option_desc", |u| Some(u.schema_option.desc())),
+        text("issuing_entity", |u| u.issuing_entity.as_deref()),
+        text("vaccine_id", |u| u.vaccine_id.as_deref()),
+        text("opaque_unique_string", |u| {
+            Some(u.opaque_unique_string.as_str())
+        }),
+        text("opaque_id", |u| u.opaque_id.as_deref()),
+        text("opaque_issuance", |u| u.opaque_issuance.as_deref()),
+        Series::new(
+            "opaque_vaccination_month".into(),
+            uvcis
+                .iter()
+                .map(|u| u.opaque_vaccination.map(|date| date.month))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "opaque_vaccination_year".into(),
+            uvcis
+                .iter()
+                .map(|u| u.opaque_vaccination.map(|date| date.year))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        text("checksum", |u| u.checksum.as_deref()),
+        Series::new(
+            "checksum_verification".into(),
+            uvcis
+                .iter()
+                .map(|u| u.checksum_verification)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new("vaccination_date".into(), vaccination_dates)
+            .cast(&DataType::Date)?
+            .into(),
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uvcis_to_dataframe;
+    use polars::prelude::{AnyValue, DataType};
+
+    #[test]
+    fn polars_dataframe() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+        ];
+        let frame = uvcis_to_dataframe(&cert_ids).unwrap();
+        assert!(frame.shape() == (2, 14));
+        let opaque_id = frame.column("opaque_id").unwrap();
+        assert!(opaque_id.get(0).unwrap() == AnyValue::String("V12916227"));
+        let year = frame.column("opaque_vaccination_year").unwrap();
+        assert!(year.dtype() == &DataType::UInt16);
+        assert!(year.null_count() == 1);
+        let date = frame.column("vaccination_date").unwrap();
+        assert!(date.dtype() == &DataType::Date);
+        assert!(date.get(0).unwrap() == AnyValue::Date(18840));
+    }
+}
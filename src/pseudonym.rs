@@ -0,0 +1,300 @@
+//! Pseudonymization of UVCIs with a keyed HMAC-SHA-256
+use crate::export::Exporter;
+use crate::{normalized_cert_id, Uvci};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Block size of SHA-256, the HMAC key block size
+const BLOCK_SIZE: usize = 64;
+
+/// Number of bytes of the keyed hash in a token, 96 bits so collisions are negligible for billions of UVCIs
+const TOKEN_BYTES: usize = 12;
+
+/// Keyed pseudonymization of UVCIs, mapping every UVCI to a stable random-looking token
+///
+/// The token is "P" followed by the first 96 bits of the HMAC-SHA-256 of the `normalized_cert_id` of
+/// the UVCI as given in uppercase hex, so the same UVCI gets the same token in every export with the
+/// same key, enabling longitudinal analysis, and distinct UVCIs that cannot be parsed get distinct
+/// tokens, while the token cannot be reversed or recomputed without the key. Unlike the unkeyed
+/// `Redaction::Hash`, the low-entropy identifiers cannot be brute-forced from the tokens.
+///
+/// With a mapping file, the token of every UVCI seen is persisted with it and reused, so tokens stay
+/// stable if the key is rotated. The mapping file holds the raw identifiers and must be kept by the data
+/// controller, it is only needed to re-identify a token.
+pub struct Pseudonymizer {
+    key: Vec<u8>,
+    mapping: Option<BTreeMap<String, String>>,
+    mapping_path: Option<PathBuf>,
+}
+
+impl Pseudonymizer {
+    /// Create a pseudonymizer without mapping file
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the secret key, at least 32 random bytes
+    pub fn new(key: &[u8]) -> Pseudonymizer {
+        return Pseudonymizer {
+            key: key.to_vec(),
+            mapping: None,
+            mapping_path: None,
+        };
+    }
+
+    /// Create a pseudonymizer with a mapping file, loading the tokens of the file if it exists
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the secret key, at least 32 random bytes
+    /// * `path` - the mapping file, one "normalized UVCI,token" line per UVCI
+    pub fn with_mapping_file<P: AsRef<Path>>(key: &[u8], path: P) -> io::Result<Pseudonymizer> {
+        let mut mapping = BTreeMap::new();
+        if path.as_ref().exists() {
+            for line in fs::read_to_string(&path)?.lines() {
+                if line.is_empty() {
+                    continue;
+                }
+                match line.rsplit_once(',') {
+                    Some((cert_id, token)) => {
+                        mapping.insert(cert_id.to_string(), token.to_string())
+                    }
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid pseudonym mapping line {}", line),
+                        ))
+                    }
+                };
+            }
+        }
+        return Ok(Pseudonymizer {
+            key: key.to_vec(),
+            mapping: Some(mapping),
+            mapping_path: Some(path.as_ref().to_path_buf()),
+        });
+    }
+
+    /// The token of a UVCI, from the mapping file if it has one
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    pub fn token(&mut self, uvci: &Uvci) -> String {
+        let cert_id = normalized_cert_id(&uvci.cert_id);
+        if let Some(token) = self.mapping.as_ref().and_then(|m| m.get(&cert_id)) {
+            return token.clone();
+        }
+        let token = self.keyed_token(&cert_id);
+        if let Some(mapping) = self.mapping.as_mut() {
+            mapping.insert(cert_id, token.clone());
+        }
+        return token;
+    }
+
+    /// The UVCI with the opaque unique string replaced by its token, the opaque id by a keyed token of
+    /// the country, issuing entity and opaque id, so reissues of a certificate stay linked, and without
+    /// opaque issuance, and the UVCI as given replaced by the UVCI rebuilt from these fields
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    pub fn pseudonymize(&mut self, uvci: &Uvci) -> Uvci {
+        let mut pseudonymized = uvci.clone();
+        pseudonymized.opaque_unique_string = self.token(uvci);
+        pseudonymized.opaque_id = uvci.opaque_id.as_ref().map(|opaque_id| {
+            self.keyed_token(&format!(
+                "{}:{}/{}",
+                uvci.country_str(),
+                uvci.issuing_entity.as_deref().unwrap_or(""),
+                opaque_id
+            ))
+        });
+        pseudonymized.opaque_issuance = None;
+        pseudonymized.cert_id = pseudonymized.rebuild();
+        return pseudonymized;
+    }
+
+    /// Number of UVCIs of the mapping, 0 without mapping file
+    pub fn mapped(&self) -> usize {
+        return self.mapping.as_ref().map_or(0, |mapping| mapping.len());
+    }
+
+    /// Atomically replace the mapping file with the tokens of all UVCIs seen, if there is one
+    pub fn save_mapping(&self) -> io::Result<()> {
+        let (mapping, path) = match (&self.mapping, &self.mapping_path) {
+            (Some(mapping), Some(path)) => (mapping, path),
+            _ => return Ok(()),
+        };
+        let mut output = "".to_string();
+        for (cert_id, token) in mapping {
+            output.push_str(cert_id);
+            output.push(',');
+            output.push_str(token);
+            output.push('\n');
+        }
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, output)?;
+        return fs::rename(&temp_path, path);
+    }
+
+    fn keyed_token(&self, value: &str) -> String {
+        let mac = hmac_sha256(&self.key, value.as_bytes());
+        let hex: String = mac[..TOKEN_BYTES]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        return format!("P{}", hex);
+    }
+}
+
+/// The UVCI string of a pseudonymized or redacted UVCI, with a freshly computed checksum
+///
+/// The replaced opaque unique string, e.g. the token of `Pseudonymizer::pseudonymize` or the hash of
+/// `Redaction::Hash`, is re-emitted with the other blocks of the UVCI, so the anonymized dataset can be
+/// processed like the original one. None if the UVCI has no valid blocks to re-emit.
+///
+/// # Arguments
+///
+/// * `uvci` - the pseudonymized or redacted UVCI
+pub fn anonymized_cert_id(uvci: &Uvci) -> Option<String> {
+    return uvci
+        .edit()
+        .build()
+        .ok()
+        .map(|anonymized| anonymized.canonical());
+}
+
+/// HMAC-SHA-256 (RFC 2104) of a message
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.finalize());
+    let mut mac = [0u8; 32];
+    mac.copy_from_slice(&outer.finalize());
+    return mac;
+}
+
+/// Exporter pseudonymizing the UVCIs before exporting them to another exporter
+///
+/// The mapping file of the pseudonymizer is not saved by the exporter, call `save_mapping` after the
+/// export.
+pub struct PseudonymizingExporter<'a, E: Exporter> {
+    exporter: E,
+    pseudonymizer: &'a mut Pseudonymizer,
+}
+
+impl<'a, E: Exporter> PseudonymizingExporter<'a, E> {
+    pub fn new(exporter: E, pseudonymizer: &'a mut Pseudonymizer) -> PseudonymizingExporter<'a, E> {
+        return PseudonymizingExporter {
+            exporter,
+            pseudonymizer,
+        };
+    }
+}
+
+impl<'a, E: Exporter> Exporter for PseudonymizingExporter<'a, E> {
+    fn export(&mut self, uvci: &Uvci) -> io::Result<()> {
+        let pseudonymized = self.pseudonymizer.pseudonymize(uvci);
+        return self.exporter.export(&pseudonymized);
+    }
+
+    fn set_source(&mut self, file: &str, line: usize) {
+        self.exporter.set_source(file, line);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.exporter.flush();
+    }
+
+    fn finish(self) -> io::Result<()> {
+        return self.exporter.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{anonymized_cert_id, hmac_sha256, Pseudonymizer, PseudonymizingExporter};
+    use crate::export::{export_all, CsvExporter, Redaction};
+    use crate::parse;
+
+    #[test]
+    fn keyed_pseudonyms() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert!(mac[..4] == [0x5b, 0xdc, 0xc1, 0x46]);
+
+        let mut pseudonymizer = Pseudonymizer::new(b"01234567890123456789012345678901");
+        let token = pseudonymizer.token(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E"));
+        assert!(token.len() == 25 && token.starts_with('P'));
+        assert!(token == pseudonymizer.token(&parse("01:se:ehm/v12907267lajw#e")));
+        let other = Pseudonymizer::new(b"another key").token(&parse("01:SE:EHM/V12907267LAJW#E"));
+        assert!(token != other);
+        // Distinct UVCIs that cannot be parsed get distinct tokens
+        let german = pseudonymizer.token(&parse("URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W"));
+        assert!(
+            german
+                != pseudonymizer.token(&parse("URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T"))
+        );
+
+        // Reissues keep the same opaque id token
+        let first = pseudonymizer.pseudonymize(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q"));
+        let reissue = pseudonymizer.pseudonymize(&parse("URN:UVCI:01:SE:EHM/V12916227LAJW"));
+        assert!(first.opaque_id == reissue.opaque_id);
+        assert!(first.opaque_unique_string != reissue.opaque_unique_string);
+        assert!(first.opaque_issuance.is_none());
+
+        let cert_ids = vec!["URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string()];
+        let mut csv = Vec::new();
+        export_all(
+            &cert_ids,
+            PseudonymizingExporter::new(CsvExporter::new(&mut csv), &mut pseudonymizer),
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains(&token) && !csv.contains("V12907267"));
+    }
+
+    #[test]
+    fn pseudonym_mapping_file() {
+        let path = std::env::temp_dir().join(format!("uvci_pseudonyms_{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        let mut pseudonymizer = Pseudonymizer::with_mapping_file(b"old key", &path).unwrap();
+        let token = pseudonymizer.token(&uvci);
+        assert!(pseudonymizer.mapped() == 1);
+        pseudonymizer.save_mapping().unwrap();
+
+        // Tokens of the mapping survive a key rotation
+        let mut rotated = Pseudonymizer::with_mapping_file(b"new key", &path).unwrap();
+        assert!(rotated.token(&uvci) == token);
+        assert!(rotated.token(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q")) != token);
+        assert!(rotated.mapped() == 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn anonymized_cert_ids() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        let mut pseudonymizer = Pseudonymizer::new(b"01234567890123456789012345678901");
+        let token = pseudonymizer.token(&uvci);
+        let cert_id = anonymized_cert_id(&pseudonymizer.pseudonymize(&uvci)).unwrap();
+        assert!(cert_id.starts_with(&format!("URN:UVCI:01:SE:EHM/{}#", token)));
+        assert!(parse(&cert_id).checksum_verification);
+
+        let cert_id = anonymized_cert_id(&Redaction::Mask.redact(&uvci)).unwrap();
+        assert!(cert_id.starts_with("URN:UVCI:01:SE:EHM/XXXXXXXXXXXXX#"));
+        assert!(anonymized_cert_id(&parse("URN:UVCI:01")).is_none());
+    }
+}
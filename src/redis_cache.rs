@@ -0,0 +1,144 @@
+//! Cache of the parse and validation results of UVCIs in Redis
+use crate::{diagnose, diagnostics_to_json, normalized_cert_id, parse, to_json, Severity};
+use redis::{Client, Connection, RedisResult};
+use std::time::Duration;
+
+/// Cache of the parse and validation results of UVCIs in Redis, shared by a fleet of verifiers
+///
+/// Every UVCI is stored under the normalized UVCI as given with the key prefix, e.g. "uvci:URN:UVCI:01:SE:EHM/V12907267LAJW#E",
+/// so the same identifier in lowercase or without the prefix is the same entry. The value is the JSON
+/// object of `cache_entry`, it expires after the time to live, so the cache only holds recently seen
+/// identifiers.
+pub struct RedisCache {
+    connection: Connection,
+    ttl: Duration,
+    key_prefix: String,
+}
+
+impl RedisCache {
+    /// Connect to a Redis server
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - the Redis URL, e.g. "redis://127.0.0.1/"
+    /// * `ttl` - the time to live of the entries, at least a second
+    pub fn new(url: &str, ttl: Duration) -> RedisResult<RedisCache> {
+        return Ok(RedisCache {
+            connection: Client::open(url)?.get_connection()?,
+            ttl,
+            key_prefix: "uvci:".to_string(),
+        });
+    }
+
+    /// Set the prefix of the keys, "uvci:" by default, e.g. to share a Redis database
+    pub fn key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_string();
+        return self;
+    }
+
+    /// The key of a UVCI, the key prefix followed by the normalized UVCI as given
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn key(&self, cert_id: &str) -> String {
+        return cache_key(&self.key_prefix, cert_id);
+    }
+
+    /// Parse and validate a UVCI and store the result, returning it
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn put(&mut self, cert_id: &str) -> RedisResult<serde_json::Value> {
+        let entry = cache_entry(cert_id);
+        redis::cmd("SET")
+            .arg(self.key(cert_id))
+            .arg(entry.to_string())
+            .arg("EX")
+            .arg(self.ttl.as_secs().max(1))
+            .query::<()>(&mut self.connection)?;
+        return Ok(entry);
+    }
+
+    /// Parse, validate and store a vector of UVCIs in one round trip, returning the number stored
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+    pub fn put_all(&mut self, cert_ids: &[String]) -> RedisResult<usize> {
+        let mut pipe = redis::pipe();
+        for cert_id in cert_ids {
+            pipe.cmd("SET")
+                .arg(self.key(cert_id))
+                .arg(cache_entry(cert_id).to_string())
+                .arg("EX")
+                .arg(self.ttl.as_secs().max(1))
+                .ignore();
+        }
+        pipe.query::<()>(&mut self.connection)?;
+        return Ok(cert_ids.len());
+    }
+
+    /// The cached result of a UVCI, None if it was not seen within the time to live
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn get(&mut self, cert_id: &str) -> RedisResult<Option<serde_json::Value>> {
+        let value: Option<String> = redis::cmd("GET")
+            .arg(self.key(cert_id))
+            .query(&mut self.connection)?;
+        return Ok(value.and_then(|value| serde_json::from_str(&value).ok()));
+    }
+}
+
+/// The key of a UVCI in the cache, the key prefix followed by the normalized UVCI as given
+fn cache_key(key_prefix: &str, cert_id: &str) -> String {
+    let mut key = key_prefix.to_string();
+    key.push_str(&normalized_cert_id(cert_id));
+    return key;
+}
+
+/// The cached parse and validation result of a UVCI
+///
+/// The JSON object has the UVCI as given and canonical, whether it is valid (no error diagnostic), the
+/// parsed fields of `uvci_to_json` and the diagnostics of `diagnose`.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn cache_entry(cert_id: &str) -> serde_json::Value {
+    let cert_id = cert_id.trim();
+    let uvci = parse(cert_id);
+    let diagnostics = diagnose(cert_id);
+    return serde_json::json!({
+        "cert_id": cert_id,
+        "canonical": uvci.canonical(),
+        "valid": !diagnostics.iter().any(|d| d.severity == Severity::Error),
+        "uvci": to_json(&uvci),
+        "diagnostics": diagnostics_to_json(&diagnostics),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_entry, cache_key, RedisCache};
+    use std::time::Duration;
+
+    #[test]
+    fn redis_cache_entries() {
+        let entry = cache_entry(" urn:uvci:01:se:ehm/v12907267lajw#e ");
+        assert!(entry["canonical"] == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(entry["valid"] == true);
+        assert!(entry["uvci"]["opaque_id"] == "V12907267");
+        let entry = cache_entry("URN:UVCI:01:SE:EHM/V12907267LAJW#X");
+        assert!(entry["valid"] == false);
+        assert!(entry["diagnostics"][0]["code"] == "E009");
+
+        assert!(
+            cache_key("uvci:", "01:SE:EHM/V12907267LAJW#E")
+                == "uvci:URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+        );
+        assert!(RedisCache::new("not a url", Duration::from_secs(60)).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Runtime registry of decoders of private UVCI schemes
+use crate::{decode_builtin, parse_blocks, Uvci};
+
+/// Decoder of a private UVCI scheme, fills the opaque fields and returns true if it applies
+pub type Decoder = Box<dyn Fn(&mut Uvci) -> bool + Send + Sync>;
+
+/// Registry of decoders for private UVCI schemes, e.g. of regional authorities, registered at runtime
+///
+/// The decoders of a country are consulted in registration order by `parse_with_registry`, after the
+/// built-in decoders, until one of them applies.
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<(String, Decoder)>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> DecoderRegistry {
+        return DecoderRegistry {
+            decoders: Vec::new(),
+        };
+    }
+
+    /// Register a decoder for the UVCIs of a country
+    ///
+    /// # Arguments
+    ///
+    /// * `country` - the ISO 3166-1 country code, e.g. "DE"
+    /// * `decoder` - the decoder, returning false if the UVCI is not of its scheme
+    pub fn register<F>(&mut self, country: &str, decoder: F)
+    where
+        F: Fn(&mut Uvci) -> bool + Send + Sync + 'static,
+    {
+        self.decoders
+            .push((country.to_uppercase(), Box::new(decoder)));
+    }
+
+    /// Number of registered decoders
+    pub fn len(&self) -> usize {
+        return self.decoders.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.decoders.is_empty();
+    }
+
+    /// Decode with the first registered decoder of the country that applies, returns false if none applies
+    fn decode(&self, uvci_data: &mut Uvci) -> bool {
+        for (country, decoder) in &self.decoders {
+            if *country == uvci_data.country_str() && decoder(uvci_data) {
+                return true;
+            }
+        }
+        return false;
+    }
+}
+
+/// Parse a EU Digital COVID Certificate UVCI, decoding private schemes with the registered decoders
+///
+/// The same as `parse`, but if no built-in decoder applies the decoders of `registry` are consulted.
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+/// * `registry` - the decoders of private UVCI schemes
+pub fn parse_with_registry(cert_id: &str, registry: &DecoderRegistry) -> Uvci {
+    let mut uvci_data = parse_blocks(cert_id);
+    if !decode_builtin(&mut uvci_data) {
+        registry.decode(&mut uvci_data);
+    }
+    return uvci_data;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_with_registry, DecoderRegistry};
+
+    #[test]
+    fn registry_decoder() {
+        let mut registry = DecoderRegistry::new();
+        registry.register("de", |uvci| {
+            if uvci.issuing_entity.as_deref() != Some("BY") || uvci.opaque_unique_string.len() != 10
+            {
+                return false;
+            }
+            uvci.opaque_id = Some(uvci.opaque_unique_string[0..6].to_string());
+            uvci.opaque_issuance = Some(uvci.opaque_unique_string[6..10].to_string());
+            return true;
+        });
+        assert!(registry.len() == 1);
+
+        let uvci = parse_with_registry("URN:UVCI:01:DE:BY/123456ABCD", &registry);
+        assert!(uvci.opaque_id.as_deref() == Some("123456"));
+        assert!(uvci.opaque_issuance.as_deref() == Some("ABCD"));
+
+        let uvci = parse_with_registry("URN:UVCI:01:DE:HH/123456ABCD", &registry);
+        assert!(uvci.opaque_id.is_none());
+
+        // Built-in decoders take precedence
+        let uvci = parse_with_registry("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q", &registry);
+        assert!(uvci.opaque_id.as_deref() == Some("V12916227"));
+        assert!(uvci.opaque_vaccination.unwrap().year == 2021);
+    }
+}
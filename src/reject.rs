@@ -0,0 +1,168 @@
+//! Logging of rejected input lines as JSON Lines
+use crate::batch::BatchResult;
+use crate::{diagnose, parse, Diagnostic, Severity};
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// Log of rejected input lines, written as one JSON object per line (JSON Lines)
+///
+/// Every entry has the 1-based line number, the raw input, the diagnostic code and the rejection reason,
+/// e.g. {"line":3,"input":"URN:UVCI:01","code":"E004","reason":"..."}, and the input file once a
+/// source file is set.
+pub struct RejectLog<W: Write> {
+    writer: W,
+    count: usize,
+    file: Option<String>,
+}
+
+impl<W: Write> RejectLog<W> {
+    pub fn new(writer: W) -> RejectLog<W> {
+        return RejectLog {
+            writer,
+            count: 0,
+            file: None,
+        };
+    }
+
+    /// Set the input file of the next rejected lines, when reading several input files
+    pub fn set_source(&mut self, file: &str) {
+        self.file = Some(file.to_string());
+    }
+
+    /// Log a rejected input line
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - the 1-based line number of the input
+    /// * `input` - the raw input line
+    /// * `diagnostic` - the reason of the rejection
+    pub fn reject(&mut self, line: usize, input: &str, diagnostic: &Diagnostic) -> io::Result<()> {
+        let mut entry = serde_json::json!({
+            "line": line,
+            "input": input,
+            "code": diagnostic.code,
+            "reason": diagnostic.message,
+        });
+        if let Some(file) = &self.file {
+            entry["file"] = serde_json::json!(file);
+        }
+        self.count += 1;
+        return writeln!(self.writer, "{}", entry);
+    }
+
+    /// Number of rejected lines logged
+    pub fn count(&self) -> usize {
+        return self.count;
+    }
+
+    /// Flush the log and return the writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        return Ok(self.writer);
+    }
+}
+
+/// The first error diagnostic of a UVCI, the reason to reject it, or None if it is valid
+///
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+pub fn rejection(cert_id: &str) -> Option<Diagnostic> {
+    return diagnose(cert_id)
+        .into_iter()
+        .find(|diagnostic| diagnostic.severity == Severity::Error);
+}
+
+/// Write the checksum-valid UVCIs to one writer and the other UVCIs with their reason to another
+///
+/// A UVCI is valid if it has no error diagnostic and a checksum, so its checksum verifies. Valid UVCIs
+/// are written trimmed, one per line, the others as the JSON Lines entries of `RejectLog` with the first
+/// error diagnostic, or the missing checksum warning, as reason. Empty lines are skipped but counted in
+/// the line numbers. Returns the summary of the UVCIs partitioned.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `valid` - the writer of the valid UVCIs
+/// * `invalid` - the writer of the invalid UVCIs
+pub fn partition_batch<V: Write, I: Write>(
+    cert_ids: &[String],
+    valid: &mut V,
+    invalid: &mut I,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    let mut rejects = RejectLog::new(invalid);
+    for (index, cert_id) in cert_ids.iter().enumerate() {
+        let cert_id = cert_id.trim();
+        if cert_id.is_empty() {
+            continue;
+        }
+        let diagnostics = diagnose(cert_id);
+        result.record_diagnosed(&parse(cert_id), &diagnostics);
+        let reason = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+            .or_else(|| {
+                diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.code == "W002")
+            });
+        match reason {
+            Some(diagnostic) => rejects.reject(index + 1, cert_id, diagnostic)?,
+            None => writeln!(valid, "{}", cert_id)?,
+        }
+    }
+    rejects.into_inner()?;
+    valid.flush()?;
+    return Ok(result.finish(started));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{partition_batch, rejection, RejectLog};
+
+    #[test]
+    fn reject_log() {
+        let mut log = RejectLog::new(Vec::new());
+        let input = ["URN:UVCI:01:SE:EHM/V12916227TFJJ#Q", "", "URN:UVCI:01"];
+        for (index, cert_id) in input.iter().enumerate() {
+            if let Some(diagnostic) = rejection(cert_id) {
+                log.reject(index + 1, cert_id, &diagnostic).unwrap();
+            }
+        }
+        assert!(log.count() == 2);
+
+        let output = String::from_utf8(log.into_inner().unwrap()).unwrap();
+        let entries: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(entries[0]["line"] == 2);
+        assert!(entries[0]["code"] == "E001");
+        assert!(entries[1]["line"] == 3);
+        assert!(entries[1]["input"] == "URN:UVCI:01");
+    }
+
+    #[test]
+    fn partition() {
+        let input = vec![
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+            "".to_string(),
+            "URN:UVCI:01:NL:187/37512422923".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#X".to_string(),
+        ];
+        let (mut valid, mut invalid) = (Vec::new(), Vec::new());
+        let result = partition_batch(&input, &mut valid, &mut invalid).unwrap();
+        assert!(result.processed == 3);
+        assert!(String::from_utf8(valid).unwrap() == "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q\n");
+
+        let invalid = String::from_utf8(invalid).unwrap();
+        let entries: Vec<serde_json::Value> = invalid
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(entries.len() == 2);
+        assert!(entries[0]["line"] == 3 && entries[0]["code"] == "W002");
+        assert!(entries[1]["line"] == 4 && entries[1]["code"] == "E009");
+    }
+}
@@ -0,0 +1,374 @@
+//! Revocation of EU Digital COVID Certificates by UVCI hash, following the DCC revocation specification
+use crate::bloom::BloomFilter;
+use crate::{parse, Uvci};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Hash type of a revocation list entry
+///
+/// The "SIGNATURE" hash type of the DCC revocation specification, the hash of the COSE signature of
+/// the certificate, is out of scope: it cannot be computed from a UVCI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RevocationHashType {
+    /// SHA-256 of the UVCI, "UCI"
+    Uci,
+    /// SHA-256 of the issuing country code followed by the UVCI, "COUNTRYCODEUCI"
+    CountryCodeUci,
+}
+
+impl RevocationHashType {
+    /// The hash type as named by the DCC gateway, e.g. "UCI"
+    pub fn code(&self) -> &'static str {
+        match self {
+            RevocationHashType::Uci => "UCI",
+            RevocationHashType::CountryCodeUci => "COUNTRYCODEUCI",
+        }
+    }
+
+    /// Parse a hash type as named by the DCC gateway, e.g. "COUNTRYCODEUCI", case-insensitive
+    pub fn from_code(code: &str) -> Option<RevocationHashType> {
+        match code.to_uppercase().as_str() {
+            "UCI" => Some(RevocationHashType::Uci),
+            "COUNTRYCODEUCI" => Some(RevocationHashType::CountryCodeUci),
+            _ => None,
+        }
+    }
+}
+
+/// Error loading revocation data
+#[derive(Clone, Debug, PartialEq)]
+pub enum RevocationError {
+    /// The bloom filter is truncated, has an unsupported version or inconsistent sizes
+    InvalidFilter,
+}
+
+impl fmt::Display for RevocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevocationError::InvalidFilter => write!(f, "invalid revocation bloom filter"),
+        }
+    }
+}
+
+impl std::error::Error for RevocationError {}
+
+/// Revocation hash of a parsed UVCI, the first 128 bits of the SHA-256 of the UVCI as issued
+///
+/// The UVCI is hashed exactly as given in `uvci.cert_id`, not canonicalized, as the DCC gateway hashes
+/// the "ci" of the certificate, e.g. "01:SE:EHM/V12907267LAJW#E" without prefix is another hash.
+///
+/// # Arguments
+///
+/// * `uvci` - the parsed UVCI
+/// * `hash_type` - the revocation hash type
+pub fn revocation_hash(uvci: &Uvci, hash_type: RevocationHashType) -> [u8; 16] {
+    let mut hasher = Sha256::new();
+    if hash_type == RevocationHashType::CountryCodeUci {
+        hasher.update(uvci.country_str().as_bytes());
+    }
+    hasher.update(uvci.cert_id.as_bytes());
+    let digest = hasher.finalize();
+    let mut hash = [0u8; 16];
+    hash.copy_from_slice(&digest[..16]);
+    return hash;
+}
+
+/// Bloom filter of revoked UVCI hashes, for constant-memory revocation checks
+///
+/// A bloom filter has no false negatives, but may report a UVCI that was not revoked as
+/// `probably_revoked`. Positive results should be confirmed against the full revocation list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevocationFilter {
+    hash_type: RevocationHashType,
+    filter: BloomFilter,
+}
+
+impl RevocationFilter {
+    /// Create an empty revocation filter
+    ///
+    /// # Arguments
+    ///
+    /// * `hash_type` - the revocation hash type of the filter entries
+    /// * `expected_entries` - the number of revocation entries the filter is sized for
+    /// * `false_positive_rate` - the false-positive rate at the expected number of entries, e.g. 0.000001
+    pub fn new(
+        hash_type: RevocationHashType,
+        expected_entries: u32,
+        false_positive_rate: f32,
+    ) -> RevocationFilter {
+        return RevocationFilter {
+            hash_type,
+            filter: BloomFilter::new(expected_entries, false_positive_rate),
+        };
+    }
+
+    /// Load a serialized revocation bloom filter batch
+    ///
+    /// # Arguments
+    ///
+    /// * `hash_type` - the revocation hash type of the batch
+    /// * `bytes` - the serialized bloom filter
+    pub fn from_bytes(
+        hash_type: RevocationHashType,
+        bytes: &[u8],
+    ) -> Result<RevocationFilter, RevocationError> {
+        let filter = BloomFilter::from_bytes(bytes).ok_or(RevocationError::InvalidFilter)?;
+        return Ok(RevocationFilter { hash_type, filter });
+    }
+
+    /// Serialize the revocation bloom filter
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return self.filter.to_bytes();
+    }
+
+    /// The revocation hash type of the filter entries
+    pub fn hash_type(&self) -> RevocationHashType {
+        return self.hash_type;
+    }
+
+    /// Number of revocation entries added to the filter
+    pub fn len(&self) -> u32 {
+        return self.filter.len();
+    }
+
+    /// Whether no revocation entries were added to the filter
+    pub fn is_empty(&self) -> bool {
+        return self.filter.len() == 0;
+    }
+
+    /// Add a revocation hash, i.e. the first 128 bits of the SHA-256 as published by the DCC gateway
+    pub fn insert_hash(&mut self, hash: &[u8]) {
+        self.filter.insert(hash);
+    }
+
+    /// Add a parsed UVCI to the revoked entries
+    pub fn insert(&mut self, uvci: &Uvci) {
+        self.filter.insert(&revocation_hash(uvci, self.hash_type));
+    }
+
+    /// Whether the revocation hash is probably in the filter
+    pub fn contains_hash(&self, hash: &[u8]) -> bool {
+        return self.filter.contains(hash);
+    }
+
+    /// Whether the parsed UVCI is probably revoked, false positives are possible
+    pub fn probably_revoked(&self, uvci: &Uvci) -> bool {
+        return self.filter.contains(&revocation_hash(uvci, self.hash_type));
+    }
+}
+
+/// Text encoding of revocation hashes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashEncoding {
+    /// Lowercase hexadecimal
+    Hex,
+    /// Standard Base64 with padding, as used by the DCC gateway
+    Base64,
+}
+
+impl HashEncoding {
+    /// Encode a revocation hash
+    pub fn encode(&self, hash: &[u8]) -> String {
+        match self {
+            HashEncoding::Hex => hash.iter().map(|b| format!("{:02x}", b)).collect(),
+            HashEncoding::Base64 => base64_encode(hash),
+        }
+    }
+}
+
+/// Revocation batch in the DCC gateway upload format
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevocationBatch {
+    /// ISO 3166-1 country code of the issuing country, taken from the first UVCI
+    pub country: String,
+    /// Expiry of the batch as ISO 8601 date-time, e.g. "2022-12-31T00:00:00Z", to be set by the caller
+    pub expires: String,
+    /// Key identifier of the DSC of the revoked certificates, "UNKNOWN_KID" if not known
+    pub kid: String,
+    /// Hash type of the entries
+    pub hash_type: RevocationHashType,
+    /// Revocation hashes, the first 128 bits of the SHA-256, without duplicates
+    pub entries: Vec<[u8; 16]>,
+}
+
+impl RevocationBatch {
+    /// Export the revocation batch to the JSON upload format of the DCC gateway
+    pub fn to_json(&self) -> String {
+        let entries: Vec<serde_json::Value> = self
+            .entries
+            .iter()
+            .map(|hash| serde_json::json!({ "hash": HashEncoding::Base64.encode(hash) }))
+            .collect();
+        return serde_json::json!({
+            "country": self.country,
+            "expires": self.expires,
+            "kid": self.kid,
+            "hashType": self.hash_type.code(),
+            "entries": entries,
+        })
+        .to_string();
+    }
+}
+
+/// Export a vector of EU Digital COVID Certificate UVCI to a revocation batch for the DCC gateway
+///
+/// Empty lines are skipped and duplicate hashes, i.e. UVCIs issued identically, are removed. All UVCIs are expected to be issued by
+/// the same country; the batch country is taken from the first UVCI. `expires` is left empty and
+/// `kid` is "UNKNOWN_KID".
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `hash_type` - the revocation hash type of the entries
+pub fn uvcis_to_revocation_batch(
+    cert_ids: &[String],
+    hash_type: RevocationHashType,
+) -> RevocationBatch {
+    let mut batch = RevocationBatch {
+        country: "".to_string(),
+        expires: "".to_string(),
+        kid: "UNKNOWN_KID".to_string(),
+        hash_type,
+        entries: Vec::new(),
+    };
+    let mut seen = std::collections::HashSet::new();
+    for cert_id in cert_ids {
+        if cert_id.trim().is_empty() {
+            continue;
+        }
+        let uvci = parse(cert_id.trim());
+        if batch.country.is_empty() {
+            batch.country = uvci.country_str().to_string();
+        }
+        let hash = revocation_hash(&uvci, hash_type);
+        if seen.insert(hash) {
+            batch.entries.push(hash);
+        }
+    }
+    return batch;
+}
+
+/// Encode bytes to standard Base64 with padding
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        base64_encode, revocation_hash, uvcis_to_revocation_batch, HashEncoding, RevocationError,
+        RevocationFilter, RevocationHashType,
+    };
+    use crate::parse;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn revocation_filter() {
+        let mut filter = RevocationFilter::new(RevocationHashType::Uci, 1000, 0.0001);
+        filter.insert(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E"));
+        filter.insert_hash(&revocation_hash(
+            &parse(" URN:UVCI:01:SE:EHM/V12916227TFJJ#Q"),
+            RevocationHashType::Uci,
+        ));
+        assert!(filter.len() == 2);
+
+        let filter =
+            RevocationFilter::from_bytes(RevocationHashType::Uci, &filter.to_bytes()).unwrap();
+        assert!(filter.probably_revoked(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")));
+        assert!(filter.probably_revoked(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q")));
+        assert!(!filter.probably_revoked(&parse("URN:UVCI:01:SE:EHM/V12920064NYOH#4")));
+
+        assert!(
+            RevocationFilter::from_bytes(RevocationHashType::Uci, &[0, 1, 2]).err()
+                == Some(RevocationError::InvalidFilter)
+        );
+    }
+
+    #[test]
+    fn revocation_filter_spec_layout() {
+        // 10 defined entries at a probability rate of 0.01 with the UCI hash of one UVCI: version 1,
+        // 7 hashes, 0.01, 10 defined and 1 current entries, 3 words for the 96 bits
+        let bytes = [
+            0x00, 0x01, 0x07, 0x3c, 0x23, 0xd7, 0x0a, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x80, 0x00,
+            0x00, 0x94, 0x44,
+        ];
+        let filter = RevocationFilter::from_bytes(RevocationHashType::Uci, &bytes).unwrap();
+        assert!(filter.len() == 1);
+        assert!(filter.probably_revoked(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E")));
+        assert!(!filter.probably_revoked(&parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q")));
+        assert!(filter.to_bytes() == bytes);
+
+        let mut filter = RevocationFilter::new(RevocationHashType::Uci, 10, 0.01);
+        filter.insert(&parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E"));
+        assert!(filter.to_bytes() == bytes);
+
+        // The number of words must match the number of bits of the defined entries
+        let mut truncated = bytes[..27].to_vec();
+        truncated[18] = 0x02;
+        assert!(RevocationFilter::from_bytes(RevocationHashType::Uci, &truncated).is_err());
+    }
+
+    #[test]
+    fn revocation_hash_types() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        assert!(
+            revocation_hash(&uvci, RevocationHashType::Uci)[..]
+                == Sha256::digest(b"URN:UVCI:01:SE:EHM/V12907267LAJW#E")[..16]
+        );
+        assert!(
+            revocation_hash(&uvci, RevocationHashType::CountryCodeUci)[..]
+                == Sha256::digest(b"SEURN:UVCI:01:SE:EHM/V12907267LAJW#E")[..16]
+        );
+        // The UVCI as issued is hashed, not the canonical UVCI
+        assert!(
+            revocation_hash(&parse("01:SE:EHM/V12907267LAJW#E"), RevocationHashType::Uci)
+                != revocation_hash(&uvci, RevocationHashType::Uci)
+        );
+        let german = parse("URN:UVCI:01DE/IZ12345A/5CWLU12RNOB9RXSEOP6FG8#W");
+        assert!(
+            revocation_hash(&german, RevocationHashType::Uci)
+                != revocation_hash(
+                    &parse("URN:UVCI:01DE/A80013335/TCXSI5Q08B0DIJGMIZJDF#T"),
+                    RevocationHashType::Uci
+                )
+        );
+        assert!(
+            RevocationHashType::from_code("countrycodeuci")
+                == Some(RevocationHashType::CountryCodeUci)
+        );
+        assert!(RevocationHashType::from_code("SIGNATURE").is_none());
+    }
+
+    #[test]
+    fn revocation_batch() {
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "".to_string(),
+            " URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#Q".to_string(),
+        ];
+        let batch = uvcis_to_revocation_batch(&cert_ids, RevocationHashType::Uci);
+        assert!(batch.country == "SE");
+        assert!(batch.entries.len() == 2, "duplicates not removed");
+
+        let json: serde_json::Value = serde_json::from_str(&batch.to_json()).unwrap();
+        assert!(json["hashType"] == "UCI");
+        assert!(json["entries"][0]["hash"].as_str().unwrap().len() == 24);
+        assert!(base64_encode(b"ietf!") == "aWV0ZiE=");
+        assert!(HashEncoding::Hex.encode(&[0x0a, 0xff]) == "0aff");
+    }
+}
@@ -0,0 +1,76 @@
+//! Uniform random sampling of the lines of an input in one pass
+use std::io::{self, BufRead};
+
+/// Sample `k` lines of an input uniformly at random in one pass, without reading it all into memory
+///
+/// Reservoir sampling (algorithm R): memory is bounded by the `k` sampled lines, whatever the size of
+/// the input, so statistics can be computed over a representative subset of a 100M-line file. Empty
+/// lines are skipped. The sample is the same for the same input and seed. If the input has at most `k`
+/// lines, all of them are returned in input order, otherwise the order of the sample is arbitrary.
+/// # Arguments
+///
+/// * `reader` - the input, e.g. one UVCI per line
+/// * `k` - the number of lines to sample
+/// * `seed` - the seed of the random number generator
+pub fn sample_lines<R: BufRead>(reader: R, k: usize, seed: u64) -> io::Result<Vec<String>> {
+    let mut sample = Vec::with_capacity(k);
+    let mut random = SplitMix64(seed);
+    let mut seen: u64 = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        seen += 1;
+        if sample.len() < k {
+            sample.push(line);
+            continue;
+        }
+        // Replace a sampled line with probability k / seen
+        let index = random.below(seen) as usize;
+        if index < k {
+            sample[index] = line;
+        }
+    }
+    return Ok(sample);
+}
+
+/// SplitMix64 pseudo-random number generator, fast and good enough for sampling
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        return z ^ (z >> 31);
+    }
+
+    /// A number from 0 to `bound` exclusive, by multiply-shift range reduction
+    fn below(&mut self, bound: u64) -> u64 {
+        return ((self.next() as u128 * bound as u128) >> 64) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sample_lines;
+
+    #[test]
+    fn reservoir_sample() {
+        let input: String = (0..1000).map(|i| format!("{}\n\n", i)).collect();
+        let sample = sample_lines(input.as_bytes(), 10, 42).unwrap();
+        assert!(sample.len() == 10);
+        assert!(sample == sample_lines(input.as_bytes(), 10, 42).unwrap());
+        assert!(sample != sample_lines(input.as_bytes(), 10, 7).unwrap());
+        // Lines of the whole input, not only of its start
+        assert!(sample
+            .iter()
+            .any(|line| line.parse::<u32>().unwrap() >= 100));
+
+        let all = sample_lines("a\nb\n".as_bytes(), 10, 42).unwrap();
+        assert!(all == vec!["a".to_string(), "b".to_string()]);
+        assert!(sample_lines("a\n".as_bytes(), 0, 42).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+//! Validation results as CEF or RFC 5424 syslog lines for SIEM ingestion
+//!
+//! Every UVCI becomes one line with the outcome of `diagnose`, so UVCI checks of border control and
+//! fraud teams feed into the correlation rules of an existing SIEM. The severity is mapped from the
+//! outcome: valid UVCIs are informational, UVCIs with warnings only are warnings and invalid UVCIs are
+//! errors, with the code and message of the first error (or warning) as event.
+use crate::batch::BatchResult;
+use crate::{diagnose, parse, Diagnostic, Severity};
+use std::io::{self, Write};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Line format of the SIEM formatter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SiemFormat {
+    /// ArcSight Common Event Format, "CEF:0|vendor|product|version|signature|name|severity|extension"
+    Cef,
+    /// RFC 5424 syslog, "<priority>1 timestamp hostname app-name procid msgid [structured data] message"
+    Syslog,
+}
+
+impl SiemFormat {
+    /// Get the format of its command line code, "cef" or "syslog"
+    pub fn from_code(code: &str) -> Option<SiemFormat> {
+        return match code {
+            "cef" => Some(SiemFormat::Cef),
+            "syslog" => Some(SiemFormat::Syslog),
+            _ => None,
+        };
+    }
+}
+
+/// Outcome of the validation of a UVCI, the SIEM severity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValidationOutcome {
+    /// No diagnostic
+    Valid,
+    /// Warnings only, the UVCI can be parsed
+    Warning,
+    /// At least one error, the UVCI is invalid
+    Invalid,
+}
+
+impl ValidationOutcome {
+    /// The outcome of the diagnostics of a UVCI
+    pub fn from_diagnostics(diagnostics: &[Diagnostic]) -> ValidationOutcome {
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return ValidationOutcome::Invalid;
+        }
+        if diagnostics.is_empty() {
+            return ValidationOutcome::Valid;
+        }
+        return ValidationOutcome::Warning;
+    }
+
+    /// CEF severity from 0 to 10: 3 (low), 5 (medium) or 8 (high)
+    pub fn cef_severity(&self) -> u8 {
+        return match self {
+            ValidationOutcome::Valid => 3,
+            ValidationOutcome::Warning => 5,
+            ValidationOutcome::Invalid => 8,
+        };
+    }
+
+    /// RFC 5424 severity: 6 (informational), 4 (warning) or 3 (error)
+    pub fn syslog_severity(&self) -> u8 {
+        return match self {
+            ValidationOutcome::Valid => 6,
+            ValidationOutcome::Warning => 4,
+            ValidationOutcome::Invalid => 3,
+        };
+    }
+}
+
+/// Formatter of the validation results of UVCIs as CEF or syslog lines
+#[derive(Clone, Debug)]
+pub struct SiemFormatter {
+    format: SiemFormat,
+    hostname: String,
+    facility: u8,
+}
+
+impl SiemFormatter {
+    /// Create a formatter of the format, without hostname and with the log audit facility (13)
+    pub fn new(format: SiemFormat) -> SiemFormatter {
+        return SiemFormatter {
+            format,
+            hostname: "-".to_string(),
+            facility: 13,
+        };
+    }
+
+    /// Set the hostname of the syslog lines and the "dvchost" of the CEF lines
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.hostname = hostname.to_string();
+        return self;
+    }
+
+    /// Set the syslog facility, from 0 to 23, e.g. 10 for security/authorization messages
+    pub fn facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+        return self;
+    }
+
+    /// Format the validation result of a UVCI checked now
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub fn format(&self, cert_id: &str) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+        return self.format_at(cert_id, now);
+    }
+
+    /// Format the validation result of a UVCI checked at a time, e.g. when replaying a log
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    /// * `millis` - the time of the check in milliseconds since the Unix epoch
+    pub fn format_at(&self, cert_id: &str, millis: u64) -> String {
+        let cert_id = cert_id.trim();
+        let diagnostics = diagnose(cert_id);
+        let outcome = ValidationOutcome::from_diagnostics(&diagnostics);
+        // The first error, or the first warning
+        let diagnostic = diagnostics
+            .iter()
+            .find(|d| d.severity == Severity::Error)
+            .or_else(|| diagnostics.first());
+        let (code, message) = match diagnostic {
+            Some(d) => (d.code.as_str(), d.message.as_str()),
+            None => ("UVCI_VALID", "valid UVCI"),
+        };
+        let result = match outcome {
+            ValidationOutcome::Invalid => "failure",
+            _ => "success",
+        };
+        let uvci = parse(cert_id);
+        return match self.format {
+            SiemFormat::Cef => {
+                let mut line = format!(
+                    "CEF:0|{}|{}|{}|{}|{}|{}|",
+                    cef_header("covid_cert_uvci"),
+                    cef_header("covid_cert_uvci"),
+                    cef_header(env!("CARGO_PKG_VERSION")),
+                    cef_header(code),
+                    cef_header(message),
+                    outcome.cef_severity()
+                );
+                line.push_str(&format!("rt={}", millis));
+                if self.hostname != "-" {
+                    line.push_str(&format!(" dvchost={}", cef_extension(&self.hostname)));
+                }
+                line.push_str(&format!(
+                    " outcome={} cs1Label=uvci cs1={} cs2Label=country cs2={} msg={}",
+                    result,
+                    cef_extension(cert_id),
+                    cef_extension(uvci.country_str()),
+                    cef_extension(message)
+                ));
+                line
+            }
+            SiemFormat::Syslog => format!(
+                "<{}>1 {} {} covid_cert_uvci - {} [uvci@32473 uvci=\"{}\" country=\"{}\" outcome=\"{}\"] {}",
+                self.facility as u16 * 8 + outcome.syslog_severity() as u16,
+                rfc3339_utc(millis),
+                self.hostname,
+                code,
+                sd_param(cert_id),
+                sd_param(uvci.country_str()),
+                result,
+                message
+            ),
+        };
+    }
+}
+
+/// Format the validation results of a vector of EU Digital COVID Certificate UVCI, streamed to a writer one line per UVCI
+///
+/// Empty lines are skipped. Returns the summary of the UVCIs formatted.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+/// * `formatter` - the CEF or syslog formatter
+/// * `writer` - the output
+pub fn uvcis_to_siem_writer<W: Write>(
+    cert_ids: &[String],
+    formatter: &SiemFormatter,
+    writer: &mut W,
+) -> io::Result<BatchResult> {
+    let started = Instant::now();
+    let mut result = BatchResult::default();
+    for cert_id in cert_ids.iter().filter(|c| !c.trim().is_empty()) {
+        result.record(cert_id, &parse(cert_id));
+        writeln!(writer, "{}", formatter.format(cert_id))?;
+    }
+    writer.flush()?;
+    return Ok(result.finish(started));
+}
+
+/// Escape a CEF header field, backslashes and pipes
+fn cef_header(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('|', "\\|");
+}
+
+/// Escape a CEF extension value, backslashes, equal signs and line breaks
+fn cef_extension(value: &str) -> String {
+    return value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n");
+}
+
+/// Escape an RFC 5424 structured data parameter value, backslashes, quotes and closing brackets
+fn sd_param(value: &str) -> String {
+    return value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]");
+}
+
+/// RFC 3339 UTC timestamp with milliseconds of a time in milliseconds since the Unix epoch
+fn rfc3339_utc(millis: u64) -> String {
+    let seconds = millis / 1000;
+    let days = (seconds / 86400) as i64;
+    // Civil from days, counting years from March so that the leap day ends the year
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    return format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        millis % 1000
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rfc3339_utc, uvcis_to_siem_writer, SiemFormat, SiemFormatter};
+
+    #[test]
+    fn siem_lines() {
+        let cef = SiemFormatter::new(SiemFormat::Cef).hostname("gate-7");
+        let line = cef.format_at("URN:UVCI:01:SE:EHM/V12907267LAJW#E", 1627776000000);
+        assert!(line.starts_with("CEF:0|covid_cert_uvci|covid_cert_uvci|"));
+        assert!(line.contains("|UVCI_VALID|valid UVCI|3|rt=1627776000000 dvchost=gate-7 "));
+        assert!(
+            line.contains(" outcome=success cs1Label=uvci cs1=URN:UVCI:01:SE:EHM/V12907267LAJW#E ")
+        );
+        let line = cef.format_at("URN:UVCI:01:SE:EHM/V12907267LAJW#X", 0);
+        assert!(line.contains("|E009|"));
+        assert!(line.contains("|8|rt=0 "));
+        assert!(line.contains(" outcome=failure "));
+
+        let syslog = SiemFormatter::new(SiemFormat::Syslog).hostname("gate-7");
+        let line = syslog.format_at("URN:UVCI:01:SE:EHM/V12907267LAJW#E", 1627776000123);
+        assert!(line.starts_with(
+            "<110>1 2021-08-01T00:00:00.123Z gate-7 covid_cert_uvci - UVCI_VALID [uvci@32473 "
+        ));
+        assert!(line.ends_with(" outcome=\"success\"] valid UVCI"));
+        let line = syslog
+            .clone()
+            .facility(10)
+            .format_at("URN:UVCI:01:SE:EHM/V12907267LAJW#X", 0);
+        assert!(line.starts_with("<83>1 1970-01-01T00:00:00.000Z gate-7 covid_cert_uvci - E009 "));
+        assert!(SiemFormat::from_code("leef").is_none());
+
+        assert!(rfc3339_utc(951782400000) == "2000-02-29T00:00:00.000Z");
+        assert!(rfc3339_utc(1709251199999) == "2024-02-29T23:59:59.999Z");
+
+        let cert_ids = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E".to_string(),
+            "".to_string(),
+            "URN:UVCI:01:SE".to_string(),
+        ];
+        let mut output = Vec::new();
+        let result = uvcis_to_siem_writer(&cert_ids, &syslog, &mut output).unwrap();
+        assert!(result.processed == 2);
+        assert!(String::from_utf8(output).unwrap().lines().count() == 2);
+    }
+}
@@ -0,0 +1,114 @@
+//! SVG charts of the vaccination months of a batch of UVCIs
+use crate::analysis::month_series;
+use crate::YearMonth;
+use plotters::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Error drawing a chart
+#[derive(Debug)]
+pub struct ChartError(String);
+
+impl fmt::Display for ChartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "chart could not be drawn: {}", self.0)
+    }
+}
+
+impl std::error::Error for ChartError {}
+
+/// SVG bar chart of the number of UVCIs by vaccination month, to embed in HTML and Markdown reports
+///
+/// Months without UVCIs between the first and the last month are drawn as empty bars.
+/// # Arguments
+///
+/// * `months` - the number of UVCIs by month, e.g. of `analysis::vaccination_months`
+/// * `width` - the width of the SVG in pixels
+/// * `height` - the height of the SVG in pixels
+pub fn timeseries_to_svg(
+    months: &BTreeMap<YearMonth, usize>,
+    width: u32,
+    height: u32,
+) -> Result<String, ChartError> {
+    let series = month_series(months);
+    let max = series
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1) as i32;
+    let mut svg = "".to_string();
+    {
+        let root = SVGBackend::with_string(&mut svg, (width, height)).into_drawing_area();
+        root.fill(&WHITE).map_err(chart_error)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption("UVCIs by vaccination month", ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d((0..series.len().max(1) as i32).into_segmented(), 0..max)
+            .map_err(chart_error)?;
+        let label = |value: &SegmentValue<i32>| match value {
+            SegmentValue::CenterOf(index) | SegmentValue::Exact(index) => series
+                .get(*index as usize)
+                .map_or("".to_string(), |(month, _)| month.to_string()),
+            SegmentValue::Last => "".to_string(),
+        };
+        chart
+            .configure_mesh()
+            .x_labels(series.len().clamp(1, 12))
+            .x_label_formatter(&label)
+            .y_desc("UVCIs")
+            .draw()
+            .map_err(chart_error)?;
+        chart
+            .draw_series(
+                Histogram::vertical(&chart)
+                    .style(BLUE.filled())
+                    .margin(2)
+                    .data(
+                        series
+                            .iter()
+                            .enumerate()
+                            .map(|(index, (_, count))| (index as i32, *count as i32)),
+                    ),
+            )
+            .map_err(chart_error)?;
+        root.present().map_err(chart_error)?;
+    }
+    return Ok(svg);
+}
+
+fn chart_error(e: impl std::error::Error) -> ChartError {
+    return ChartError(e.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::timeseries_to_svg;
+    use crate::YearMonth;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn svg_chart() {
+        let mut months = BTreeMap::new();
+        months.insert(
+            YearMonth {
+                year: 2021,
+                month: 11,
+            },
+            4,
+        );
+        months.insert(
+            YearMonth {
+                year: 2022,
+                month: 1,
+            },
+            1,
+        );
+        let svg = timeseries_to_svg(&months, 640, 480).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("2021-12"));
+        assert!(timeseries_to_svg(&BTreeMap::new(), 640, 480).is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+//! Reference UVCIs with their expected parse results, for conformance checks of UVCI implementations
+use crate::{SchemaOption, Uvci};
+
+/// The bundled test vectors, published eHealth Network examples and known national samples
+const TEST_VECTORS: &str = include_str!("../data/test_vectors.csv");
+
+/// Reference UVCI with its expected parse result
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestVector {
+    /// Where the UVCI was published, e.g. "eHealth Network guidelines Annex 2"
+    pub source: &'static str,
+    /// The UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+    pub cert_id: &'static str,
+    /// The expected version of the UVCI schema
+    pub version: Option<u8>,
+    /// The expected ISO 3166-1 country code
+    pub country: &'static str,
+    /// The expected schema option
+    pub schema_option: SchemaOption,
+    /// The expected issuing entity
+    pub issuing_entity: Option<&'static str>,
+    /// The expected vaccine product identifier
+    pub vaccine_id: Option<&'static str>,
+    /// The expected opaque unique string
+    pub opaque_unique_string: &'static str,
+    /// The expected checksum
+    pub checksum: Option<&'static str>,
+    /// Whether the checksum is expected to verify
+    pub checksum_verification: bool,
+}
+
+impl TestVector {
+    /// The differences between the expected and a parsed UVCI, e.g. "country: expected SE, found NL",
+    /// empty if the parsed UVCI conforms
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the UVCI parsed from `cert_id` by the implementation under test
+    pub fn check(&self, uvci: &Uvci) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let mut compare = |field: &str, expected: String, found: String| {
+            if expected != found {
+                mismatches.push(format!("{}: expected {}, found {}", field, expected, found));
+            }
+        };
+        compare(
+            "version",
+            format!("{:?}", self.version),
+            format!("{:?}", uvci.version),
+        );
+        compare(
+            "country",
+            self.country.to_string(),
+            uvci.country_str().to_string(),
+        );
+        compare(
+            "schema_option",
+            self.schema_option.number().to_string(),
+            uvci.schema_option.number().to_string(),
+        );
+        compare(
+            "issuing_entity",
+            format!("{:?}", self.issuing_entity),
+            format!("{:?}", uvci.issuing_entity.as_deref()),
+        );
+        compare(
+            "vaccine_id",
+            format!("{:?}", self.vaccine_id),
+            format!("{:?}", uvci.vaccine_id.as_deref()),
+        );
+        compare(
+            "opaque_unique_string",
+            self.opaque_unique_string.to_string(),
+            uvci.opaque_unique_string.clone(),
+        );
+        compare(
+            "checksum",
+            format!("{:?}", self.checksum),
+            format!("{:?}", uvci.checksum.as_deref()),
+        );
+        compare(
+            "checksum_verification",
+            self.checksum_verification.to_string(),
+            uvci.checksum_verification.to_string(),
+        );
+        return mismatches;
+    }
+}
+
+/// The bundled test vectors, from data/test_vectors.csv
+pub fn test_vectors() -> Vec<TestVector> {
+    return TEST_VECTORS
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            if fields.len() != 10 {
+                return None;
+            }
+            let optional = |field: &'static str| Some(field).filter(|f| !f.is_empty());
+            return Some(TestVector {
+                source: fields[0],
+                cert_id: fields[1],
+                version: fields[2].parse().ok(),
+                country: fields[3],
+                schema_option: SchemaOption::from_number(fields[4].parse().unwrap_or(0)),
+                issuing_entity: optional(fields[5]),
+                vaccine_id: optional(fields[6]),
+                opaque_unique_string: fields[7],
+                checksum: optional(fields[8]),
+                checksum_verification: fields[9] == "true",
+            });
+        })
+        .collect();
+}
+
+/// Run the test vectors against a parser, returning the vectors it fails with their differences
+///
+/// # Arguments
+///
+/// * `parse` - the parser under test, e.g. `covid_cert_uvci::parse`
+pub fn failed_test_vectors<F: Fn(&str) -> Uvci>(parse: F) -> Vec<(TestVector, Vec<String>)> {
+    return test_vectors()
+        .into_iter()
+        .filter_map(|vector| {
+            let mismatches = vector.check(&parse(vector.cert_id));
+            Some((vector, mismatches)).filter(|(_, mismatches)| !mismatches.is_empty())
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{failed_test_vectors, test_vectors};
+    use crate::{parse, SchemaOption};
+
+    #[test]
+    fn bundled_test_vectors() {
+        let vectors = test_vectors();
+        assert!(vectors.len() == 50);
+        assert!(vectors
+            .iter()
+            .any(|vector| vector.source == "eHealth Network guidelines Annex 2"));
+        for schema_option in [
+            SchemaOption::Unknown,
+            SchemaOption::WithSemantics,
+            SchemaOption::OpaqueNoStructure,
+            SchemaOption::SomeSemantics,
+        ] {
+            assert!(vectors
+                .iter()
+                .any(|vector| vector.schema_option == schema_option));
+        }
+        assert!(vectors
+            .iter()
+            .any(|vector| vector.cert_id.starts_with("URN:UVCI:01DE/")));
+        assert!(failed_test_vectors(parse).is_empty());
+
+        let mismatches = vectors[0].check(&parse("URN:UVCI:01:NL:187/37512422923"));
+        assert!(mismatches.contains(&"country: expected SE, found NL".to_string()));
+    }
+}
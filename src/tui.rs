@@ -0,0 +1,395 @@
+//! Interactive terminal UI to inspect a batch of UVCIs
+use crate::{parse, Uvci};
+use std::collections::BTreeMap;
+use std::io;
+
+/// Number of rows moved by Page Up and Page Down
+const PAGE_ROWS: usize = 20;
+
+/// Number of countries of the bar chart of the summary
+const CHART_COUNTRIES: usize = 8;
+
+/// Checksum status of a UVCI, the color of its row in the terminal UI
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChecksumStatus {
+    /// The checksum matches
+    Verified,
+    /// The checksum does not match
+    Mismatch,
+    /// The UVCI has no checksum
+    Missing,
+}
+
+impl ChecksumStatus {
+    /// The checksum status of a parsed UVCI
+    pub fn of(uvci: &Uvci) -> ChecksumStatus {
+        if uvci.checksum.is_none() {
+            return ChecksumStatus::Missing;
+        }
+        if uvci.checksum_verification {
+            return ChecksumStatus::Verified;
+        }
+        return ChecksumStatus::Mismatch;
+    }
+}
+
+/// Summary of the records shown by the inspector
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InspectorSummary {
+    /// The number of records matching the filter
+    pub shown: usize,
+    /// The number of records of the file
+    pub total: usize,
+    /// The number of shown records with a matching checksum
+    pub verified: usize,
+    /// The number of shown records with a checksum not matching
+    pub mismatch: usize,
+    /// The number of shown records without checksum
+    pub missing: usize,
+    /// The number of shown records by country code, "??" for an unknown country
+    pub per_country: BTreeMap<String, usize>,
+}
+
+/// State of the batch inspector: the parsed records of a file, the live filter and the selected record
+///
+/// The filter matches the records whose UVCI contains it, ignoring case, e.g. "SE:EHM" or "#".
+pub struct Inspector {
+    records: Vec<(String, Uvci)>,
+    filter: String,
+    shown: Vec<usize>,
+    selected: usize,
+    summary: InspectorSummary,
+}
+
+impl Inspector {
+    /// Parse the UVCIs of a file, all shown
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+    pub fn new(cert_ids: &[String]) -> Inspector {
+        let mut inspector = Inspector {
+            records: cert_ids
+                .iter()
+                .map(|cert_id| (cert_id.clone(), parse(cert_id)))
+                .collect(),
+            filter: "".to_string(),
+            shown: Vec::new(),
+            selected: 0,
+            summary: InspectorSummary::default(),
+        };
+        inspector.set_filter("");
+        return inspector;
+    }
+
+    /// The filter
+    pub fn filter(&self) -> &str {
+        return &self.filter;
+    }
+
+    /// Show only the records containing the filter, ignoring case, and select the first one
+    pub fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+        let needle = filter.to_uppercase();
+        self.shown = (0..self.records.len())
+            .filter(|index| self.records[*index].0.to_uppercase().contains(&needle))
+            .collect();
+        self.selected = 0;
+
+        let mut summary = InspectorSummary {
+            shown: self.shown.len(),
+            total: self.records.len(),
+            ..InspectorSummary::default()
+        };
+        for index in &self.shown {
+            let uvci = &self.records[*index].1;
+            match ChecksumStatus::of(uvci) {
+                ChecksumStatus::Verified => summary.verified += 1,
+                ChecksumStatus::Mismatch => summary.mismatch += 1,
+                ChecksumStatus::Missing => summary.missing += 1,
+            }
+            let country = match uvci.country {
+                Some(_) => uvci.country_str(),
+                None => "??",
+            };
+            *summary.per_country.entry(country.to_string()).or_insert(0) += 1;
+        }
+        self.summary = summary;
+    }
+
+    /// The records matching the filter
+    pub fn shown(&self) -> impl Iterator<Item = &(String, Uvci)> {
+        return self.shown.iter().map(move |index| &self.records[*index]);
+    }
+
+    /// The position of the selected record among the shown records
+    pub fn selected_index(&self) -> usize {
+        return self.selected;
+    }
+
+    /// The selected record, None if no record matches the filter
+    pub fn selected(&self) -> Option<&(String, Uvci)> {
+        return self
+            .shown
+            .get(self.selected)
+            .map(|index| &self.records[*index]);
+    }
+
+    /// Select the record `rows` rows below, at most the last one
+    pub fn select_next(&mut self, rows: usize) {
+        self.selected = (self.selected + rows).min(self.shown.len().saturating_sub(1));
+    }
+
+    /// Select the record `rows` rows above, at least the first one
+    pub fn select_previous(&mut self, rows: usize) {
+        self.selected = self.selected.saturating_sub(rows);
+    }
+
+    /// The summary of the shown records
+    pub fn summary(&self) -> &InspectorSummary {
+        return &self.summary;
+    }
+}
+
+/// Explore the UVCIs of a file in an interactive terminal UI until Esc or Ctrl-C is pressed
+///
+/// The UI lists the records with their checksum status in color (green verified, red mismatch, yellow
+/// missing), with a detail pane of the parsed fields of the selected record and a summary of the shown
+/// records. Typing filters the records live, Backspace and Esc edit and clear the filter, the arrow and
+/// page keys move the selection.
+/// # Arguments
+///
+/// * `cert_ids` - String slice of UVCI (Unique Vaccination Certificate/Assertion Identifier)
+pub fn run_tui(cert_ids: &[String]) -> io::Result<()> {
+    return terminal::run(Inspector::new(cert_ids));
+}
+
+mod terminal {
+    use super::{ChecksumStatus, Inspector, CHART_COUNTRIES, PAGE_ROWS};
+    use crate::or_empty;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::layout::{Constraint, Direction, Layout, Rect};
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::{BarChart, Block, Borders, List, ListItem, ListState, Paragraph};
+    use ratatui::{DefaultTerminal, Frame};
+    use std::io;
+
+    /// Run the UI on the terminal, restoring the terminal afterwards
+    pub fn run(mut inspector: Inspector) -> io::Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let result = event_loop(&mut terminal, &mut inspector);
+        ratatui::try_restore()?;
+        return result;
+    }
+
+    fn event_loop(terminal: &mut DefaultTerminal, inspector: &mut Inspector) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| draw(frame, inspector))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Esc if inspector.filter().is_empty() => return Ok(()),
+                KeyCode::Esc => inspector.set_filter(""),
+                KeyCode::Backspace => {
+                    let mut filter = inspector.filter().to_string();
+                    filter.pop();
+                    inspector.set_filter(&filter);
+                }
+                KeyCode::Char(c) => {
+                    let filter = format!("{}{}", inspector.filter(), c);
+                    inspector.set_filter(&filter);
+                }
+                KeyCode::Down => inspector.select_next(1),
+                KeyCode::Up => inspector.select_previous(1),
+                KeyCode::PageDown => inspector.select_next(PAGE_ROWS),
+                KeyCode::PageUp => inspector.select_previous(PAGE_ROWS),
+                _ => {}
+            }
+        }
+    }
+
+    fn color(status: ChecksumStatus) -> Color {
+        match status {
+            ChecksumStatus::Verified => Color::Green,
+            ChecksumStatus::Mismatch => Color::Red,
+            ChecksumStatus::Missing => Color::Yellow,
+        }
+    }
+
+    fn draw(frame: &mut Frame, inspector: &Inspector) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(8),
+                Constraint::Length(9),
+            ])
+            .split(frame.area());
+        let filter = Paragraph::new(inspector.filter()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Filter (Esc clears, Esc again quits)"),
+        );
+        frame.render_widget(filter, rows[0]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(rows[1]);
+        draw_records(frame, inspector, columns[0]);
+        draw_detail(frame, inspector, columns[1]);
+
+        let summary = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(32), Constraint::Min(20)])
+            .split(rows[2]);
+        draw_summary(frame, inspector, summary[0], summary[1]);
+    }
+
+    /// The records around the selection, only the visible ones are rendered for large files
+    fn draw_records(frame: &mut Frame, inspector: &Inspector, area: Rect) {
+        let height = area.height.saturating_sub(2).max(1) as usize;
+        let start = inspector.selected_index().saturating_sub(height - 1);
+        let items: Vec<ListItem> = inspector
+            .shown()
+            .skip(start)
+            .take(height)
+            .map(|(cert_id, uvci)| {
+                ListItem::new(cert_id.as_str())
+                    .style(Style::default().fg(color(ChecksumStatus::of(uvci))))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Records"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        if inspector.selected().is_some() {
+            state.select(Some(inspector.selected_index() - start));
+        }
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_detail(frame: &mut Frame, inspector: &Inspector, area: Rect) {
+        let mut lines = Vec::new();
+        if let Some((cert_id, uvci)) = inspector.selected() {
+            let vaccination = uvci.opaque_vaccination.map_or("".to_string(), |date| {
+                format!("{}-{:02}", date.year, date.month)
+            });
+            let fields = [
+                ("cert_id", cert_id.clone()),
+                (
+                    "version",
+                    uvci.version.map_or("".to_string(), |v| v.to_string()),
+                ),
+                ("country", uvci.country_str().to_string()),
+                ("schema_option", uvci.schema_option.desc().to_string()),
+                ("issuing_entity", or_empty(&uvci.issuing_entity).to_string()),
+                ("vaccine_id", or_empty(&uvci.vaccine_id).to_string()),
+                ("opaque_unique_string", uvci.opaque_unique_string.clone()),
+                ("opaque_id", or_empty(&uvci.opaque_id).to_string()),
+                (
+                    "opaque_issuance",
+                    or_empty(&uvci.opaque_issuance).to_string(),
+                ),
+                ("opaque_vaccination", vaccination),
+                ("checksum", or_empty(&uvci.checksum).to_string()),
+            ];
+            for (name, value) in fields {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{:<22}", name), Style::default().fg(Color::Cyan)),
+                    Span::raw(value),
+                ]));
+            }
+            let status = ChecksumStatus::of(uvci);
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{:<22}", "checksum_status"),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(format!("{:?}", status), Style::default().fg(color(status))),
+            ]));
+        }
+        let detail =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Detail"));
+        frame.render_widget(detail, area);
+    }
+
+    fn draw_summary(frame: &mut Frame, inspector: &Inspector, counts: Rect, chart: Rect) {
+        let summary = inspector.summary();
+        let count = |label: &str, value: usize, color: Color| {
+            Line::from(vec![
+                Span::raw(format!("{:<10}", label)),
+                Span::styled(value.to_string(), Style::default().fg(color)),
+            ])
+        };
+        let lines = vec![
+            Line::from(format!("shown {} of {}", summary.shown, summary.total)),
+            count("verified", summary.verified, Color::Green),
+            count("mismatch", summary.mismatch, Color::Red),
+            count("missing", summary.missing, Color::Yellow),
+        ];
+        let counts_widget =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Checksums"));
+        frame.render_widget(counts_widget, counts);
+
+        let mut countries: Vec<(&String, &usize)> = summary.per_country.iter().collect();
+        countries.sort_by(|a, b| b.1.cmp(a.1));
+        let data: Vec<(&str, u64)> = countries
+            .iter()
+            .take(CHART_COUNTRIES)
+            .map(|(country, count)| (country.as_str(), **count as u64))
+            .collect();
+        let bars = BarChart::default()
+            .block(Block::default().borders(Borders::ALL).title("Countries"))
+            .data(&data)
+            .bar_width(4)
+            .bar_gap(1);
+        frame.render_widget(bars, chart);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChecksumStatus, Inspector};
+
+    #[test]
+    fn inspector_filter_and_selection() {
+        let cert_ids: Vec<String> = vec![
+            "URN:UVCI:01:SE:EHM/V12907267LAJW#E",
+            "URN:UVCI:01:SE:EHM/V12916227TFJJ#X",
+            "URN:UVCI:01:NL:187/37512422923",
+            "URN:UVCI:01",
+        ]
+        .into_iter()
+        .map(|cert_id| cert_id.to_string())
+        .collect();
+        let mut inspector = Inspector::new(&cert_ids);
+        let summary = inspector.summary();
+        assert!(summary.shown == 4 && summary.total == 4);
+        assert!(summary.verified == 1 && summary.mismatch == 1 && summary.missing == 2);
+        assert!(summary.per_country.get("SE") == Some(&2));
+        assert!(summary.per_country.get("??") == Some(&1));
+
+        inspector.select_next(10);
+        assert!(inspector.selected_index() == 3);
+        inspector.select_previous(1);
+        assert!(inspector.selected().unwrap().0 == cert_ids[2]);
+
+        inspector.set_filter("se:ehm");
+        assert!(inspector.summary().shown == 2);
+        assert!(inspector.selected_index() == 0);
+        let (_, uvci) = inspector.shown().nth(1).unwrap();
+        assert!(ChecksumStatus::of(uvci) == ChecksumStatus::Mismatch);
+
+        inspector.set_filter("no match");
+        assert!(inspector.selected().is_none());
+        inspector.select_next(1);
+        assert!(inspector.selected_index() == 0);
+    }
+}
@@ -0,0 +1,170 @@
+//! Handling of the RFC 8141 URN syntax around UVCIs
+use crate::{parse, Uvci};
+use std::fmt;
+
+/// Handling of the RFC 8141 URN syntax that is not part of the UVCI format
+///
+/// UVCIs are URNs, so UVCIs copied out of URL contexts may have percent-encoded characters, e.g.
+/// "URN:UVCI:01:SE:EHM%2FV12907267LAJW%23E", or r- and q-components, e.g.
+/// "URN:UVCI:01:SE:EHM/V12907267LAJW?=lang=en#E". By default both are rejected. The "#" f-component
+/// separator is not handled, as the UVCI format uses it for the checksum.
+#[derive(Clone, Debug, Default)]
+pub struct UrnOptions {
+    decode_percent: bool,
+    strip_components: bool,
+}
+
+impl UrnOptions {
+    pub fn new() -> UrnOptions {
+        return UrnOptions::default();
+    }
+
+    /// Decode percent-encoded characters instead of rejecting them
+    pub fn decode_percent(mut self, decode_percent: bool) -> Self {
+        self.decode_percent = decode_percent;
+        return self;
+    }
+
+    /// Strip the r-component ("?+...") and q-component ("?=...") instead of rejecting them
+    pub fn strip_components(mut self, strip_components: bool) -> Self {
+        self.strip_components = strip_components;
+        return self;
+    }
+}
+
+/// Error for RFC 8141 URN syntax rejected by the `UrnOptions`
+#[derive(Clone, Debug, PartialEq)]
+pub enum UrnError {
+    /// A percent-encoded character at the byte offset
+    PercentEncoded(usize),
+    /// A "%" not followed by two hexadecimal digits, or not decoding to UTF-8, at the byte offset
+    InvalidPercentEncoding(usize),
+    /// An r-component ('r') or q-component ('q') at the byte offset
+    Component(char, usize),
+}
+
+impl fmt::Display for UrnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UrnError::PercentEncoded(offset) => {
+                write!(f, "percent-encoded character at offset {}", offset)
+            }
+            UrnError::InvalidPercentEncoding(offset) => {
+                write!(f, "invalid percent-encoding at offset {}", offset)
+            }
+            UrnError::Component(component, offset) => {
+                write!(f, "URN {}-component at offset {}", component, offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UrnError {}
+
+/// The UVCI of a URN with the r- and q-components and percent-encoding handled per the options
+///
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM%2FV12907267LAJW%23E"
+/// * `options` - whether to decode or reject percent-encoding and components
+pub fn normalize_urn(cert_id: &str, options: &UrnOptions) -> Result<String, UrnError> {
+    // The r- and q-components end at the f-component, i.e. the checksum
+    let (name, checksum) = match cert_id.find('#') {
+        Some(pos) => (&cert_id[..pos], &cert_id[pos..]),
+        None => (cert_id, ""),
+    };
+    let components = ["?+", "?="]
+        .iter()
+        .filter_map(|separator| name.find(separator))
+        .min();
+    let name = match components {
+        Some(offset) if options.strip_components => &name[..offset],
+        Some(offset) => {
+            let component = if name[offset..].starts_with("?+") {
+                'r'
+            } else {
+                'q'
+            };
+            return Err(UrnError::Component(component, offset));
+        }
+        None => name,
+    };
+
+    let mut output = "".to_string();
+    output.push_str(name);
+    output.push_str(checksum);
+    if let Some(offset) = output.find('%') {
+        if !options.decode_percent {
+            return Err(UrnError::PercentEncoded(offset));
+        }
+        return percent_decode(&output);
+    }
+    return Ok(output);
+}
+
+/// Parse a UVCI with the r- and q-components and percent-encoding handled per the options
+///
+/// # Arguments
+///
+/// * `cert_id` - the UVCI (Unique Vaccination Certificate/Assertion Identifier), e.g. "URN:UVCI:01:SE:EHM%2FV12907267LAJW%23E"
+/// * `options` - whether to decode or reject percent-encoding and components
+pub fn parse_urn(cert_id: &str, options: &UrnOptions) -> Result<Uvci, UrnError> {
+    return Ok(parse(&normalize_urn(cert_id, options)?));
+}
+
+/// Decode the percent-encoded characters of a URN
+fn percent_decode(urn: &str) -> Result<String, UrnError> {
+    let bytes = urn.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut offset = 0;
+    while offset < bytes.len() {
+        if bytes[offset] == b'%' {
+            let byte = urn
+                .get(offset + 1..offset + 3)
+                .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match byte {
+                Some(byte) => decoded.push(byte),
+                None => return Err(UrnError::InvalidPercentEncoding(offset)),
+            }
+            offset += 3;
+        } else {
+            decoded.push(bytes[offset]);
+            offset += 1;
+        }
+    }
+    return String::from_utf8(decoded).map_err(|_| UrnError::InvalidPercentEncoding(0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_urn, parse_urn, UrnError, UrnOptions};
+
+    #[test]
+    fn percent_encoding() {
+        let cert_id = "URN:UVCI:01:SE:EHM%2FV12907267LAJW%23E";
+        assert!(normalize_urn(cert_id, &UrnOptions::new()) == Err(UrnError::PercentEncoded(18)));
+        let options = UrnOptions::new().decode_percent(true);
+        let uvci = parse_urn(cert_id, &options).unwrap();
+        assert!(uvci.opaque_unique_string == "V12907267LAJW");
+        assert!(uvci.checksum_verification);
+        assert!(
+            normalize_urn("URN:UVCI:01:SE:EHM%2", &options)
+                == Err(UrnError::InvalidPercentEncoding(18))
+        );
+    }
+
+    #[test]
+    fn components() {
+        let cert_id = "URN:UVCI:01:SE:EHM/V12907267LAJW?+resolve?=lang=en#E";
+        assert!(normalize_urn(cert_id, &UrnOptions::new()) == Err(UrnError::Component('r', 32)));
+        let options = UrnOptions::new().strip_components(true);
+        assert!(normalize_urn(cert_id, &options).unwrap() == "URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        let cert_id = "URN:UVCI:01:SE:EHM/V12907267LAJW?=lang=en";
+        assert!(normalize_urn(cert_id, &UrnOptions::new()) == Err(UrnError::Component('q', 32)));
+        assert!(
+            normalize_urn("URN:UVCI:01:SE:EHM/V12907267LAJW#E", &UrnOptions::new()).unwrap()
+                == "URN:UVCI:01:SE:EHM/V12907267LAJW#E"
+        );
+    }
+}
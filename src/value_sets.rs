@@ -0,0 +1,315 @@
+//! eHealth Network value sets of vaccine products, marketing authorization holders and countries
+use crate::Uvci;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Value set id of the vaccine medicinal products
+pub const VACCINE_PRODUCTS: &str = "vaccines-covid-19-names";
+
+/// Value set id of the vaccine marketing authorization holders and manufacturers
+pub const VACCINE_AUTH_HOLDERS: &str = "vaccines-covid-19-auth-holders";
+
+/// Value set id of the ISO 3166-1 alpha-2 country codes
+pub const COUNTRY_CODES: &str = "country-2-codes";
+
+/// Vaccine medicinal products of the EU value set "vaccines-covid-19-names", code and display name
+const MEDICINAL_PRODUCTS: &[(&str, &str)] = &[
+    ("EU/1/20/1528", "Comirnaty"),
+    ("EU/1/20/1507", "Spikevax"),
+    ("EU/1/21/1529", "Vaxzevria"),
+    ("EU/1/20/1525", "COVID-19 Vaccine Janssen"),
+    ("EU/1/21/1618", "Nuvaxovid"),
+];
+
+/// Marketing authorization holders of the EU value set "vaccines-covid-19-auth-holders", code, display
+/// name and the medicinal product they hold the authorization of
+const AUTH_HOLDERS: &[(&str, &str, &str)] = &[
+    ("ORG-100030215", "Biontech Manufacturing GmbH", "Comirnaty"),
+    ("ORG-100031184", "Moderna Biotech Spain S.L.", "Spikevax"),
+    ("ORG-100001699", "AstraZeneca AB", "Vaxzevria"),
+    (
+        "ORG-100001417",
+        "Janssen-Cilag International",
+        "COVID-19 Vaccine Janssen",
+    ),
+    ("ORG-100032020", "Novavax CZ AS", "Nuvaxovid"),
+];
+
+/// Countries of the EU value set "country-2-codes" participating in the EU Digital COVID Certificate as
+/// EU and EEA member states, code and display name
+const COUNTRIES: &[(&str, &str)] = &[
+    ("AT", "Austria"),
+    ("BE", "Belgium"),
+    ("BG", "Bulgaria"),
+    ("CH", "Switzerland"),
+    ("CY", "Cyprus"),
+    ("CZ", "Czechia"),
+    ("DE", "Germany"),
+    ("DK", "Denmark"),
+    ("EE", "Estonia"),
+    ("ES", "Spain"),
+    ("FI", "Finland"),
+    ("FR", "France"),
+    ("GR", "Greece"),
+    ("HR", "Croatia"),
+    ("HU", "Hungary"),
+    ("IE", "Ireland"),
+    ("IS", "Iceland"),
+    ("IT", "Italy"),
+    ("LI", "Liechtenstein"),
+    ("LT", "Lithuania"),
+    ("LU", "Luxembourg"),
+    ("LV", "Latvia"),
+    ("MT", "Malta"),
+    ("NL", "Netherlands"),
+    ("NO", "Norway"),
+    ("PL", "Poland"),
+    ("PT", "Portugal"),
+    ("RO", "Romania"),
+    ("SE", "Sweden"),
+    ("SI", "Slovenia"),
+    ("SK", "Slovakia"),
+];
+
+/// Error loading a value set
+#[derive(Debug)]
+pub enum ValueSetError {
+    /// A value set file could not be read
+    Io(io::Error),
+    /// The JSON is not a value set, with the reason
+    InvalidValueSet(String),
+}
+
+impl fmt::Display for ValueSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueSetError::Io(e) => write!(f, "value set could not be read: {}", e),
+            ValueSetError::InvalidValueSet(reason) => write!(f, "invalid value set: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ValueSetError {}
+
+/// EU Digital COVID Certificate value sets used to resolve the codes in UVCIs to display names
+///
+/// Codes are matched ignoring separators and case, as a UVCI block cannot contain the "/" and "-" of
+/// the codes, e.g. the vaccine_id "EU1201528" matches "EU/1/20/1528".
+#[derive(Clone, Debug, Default)]
+pub struct ValueSets {
+    /// Display names by normalized code, by value set id
+    sets: HashMap<String, HashMap<String, String>>,
+    /// Medicinal product names of the marketing authorization holders, by normalized code
+    holder_products: HashMap<String, String>,
+}
+
+impl ValueSets {
+    /// The value sets embedded in this crate
+    pub fn builtin() -> ValueSets {
+        let mut value_sets = ValueSets::default();
+        for (code, display) in MEDICINAL_PRODUCTS {
+            value_sets.add_product(code, display);
+        }
+        for (code, display, product) in AUTH_HOLDERS {
+            value_sets.add_holder(code, display);
+            value_sets.add_holder_product(code, product);
+        }
+        for (code, display) in COUNTRIES {
+            value_sets.add(COUNTRY_CODES, code, display);
+        }
+        return value_sets;
+    }
+
+    /// Load a value set from the JSON published by the eHealth Network, e.g. "vaccine-medicinal-product.json"
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - the value set JSON, with "valueSetId" and "valueSetValues"
+    pub fn from_json(json: &[u8]) -> Result<ValueSets, ValueSetError> {
+        let mut value_sets = ValueSets::default();
+        value_sets.add_json(json)?;
+        return Ok(value_sets);
+    }
+
+    /// Load all value sets of a directory of JSON files published by the eHealth Network
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - the directory, every "*.json" file in it is loaded
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<ValueSets, ValueSetError> {
+        let mut value_sets = ValueSets::default();
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(ValueSetError::Io)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "json"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            value_sets.add_json(&fs::read(&path).map_err(ValueSetError::Io)?)?;
+        }
+        return Ok(value_sets);
+    }
+
+    /// Add or replace the values of a value set JSON, keeping the values of other value sets
+    pub fn add_json(&mut self, json: &[u8]) -> Result<(), ValueSetError> {
+        let value_set: serde_json::Value = serde_json::from_slice(json)
+            .map_err(|e| ValueSetError::InvalidValueSet(e.to_string()))?;
+        let id = match value_set["valueSetId"].as_str() {
+            Some(id) => id,
+            None => return Err(ValueSetError::InvalidValueSet("no valueSetId".to_string())),
+        };
+        let values = match value_set["valueSetValues"].as_object() {
+            Some(values) => values,
+            None => {
+                return Err(ValueSetError::InvalidValueSet(
+                    "no valueSetValues".to_string(),
+                ))
+            }
+        };
+        for (code, value) in values {
+            if let Some(display) = value["display"].as_str() {
+                self.add(id, code, display);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Add or replace a value of a value set
+    pub fn add(&mut self, value_set_id: &str, code: &str, display: &str) {
+        self.sets
+            .entry(value_set_id.to_string())
+            .or_default()
+            .insert(normalize(code), display.to_string());
+    }
+
+    /// Add or replace a medicinal product
+    pub fn add_product(&mut self, code: &str, display: &str) {
+        self.add(VACCINE_PRODUCTS, code, display);
+    }
+
+    /// Add or replace a marketing authorization holder
+    pub fn add_holder(&mut self, code: &str, display: &str) {
+        self.add(VACCINE_AUTH_HOLDERS, code, display);
+    }
+
+    /// Add or replace the medicinal product of a marketing authorization holder
+    pub fn add_holder_product(&mut self, code: &str, product: &str) {
+        self.holder_products
+            .insert(normalize(code), product.to_string());
+    }
+
+    /// The display name of a code of a value set
+    ///
+    /// # Arguments
+    ///
+    /// * `value_set_id` - the value set id, e.g. "vaccines-covid-19-names"
+    /// * `code` - the code, e.g. "EU/1/20/1528"
+    pub fn display(&self, value_set_id: &str, code: &str) -> Option<&str> {
+        return self
+            .sets
+            .get(value_set_id)
+            .and_then(|values| values.get(&normalize(code)))
+            .map(|name| name.as_str());
+    }
+
+    /// The display name of the medicinal product of a medicinal product or marketing authorization holder code
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - the code, e.g. "EU/1/20/1528" or "ORG100030215"
+    pub fn product_name(&self, code: &str) -> Option<&str> {
+        return self.display(VACCINE_PRODUCTS, code).or_else(|| {
+            self.holder_products
+                .get(&normalize(code))
+                .map(|name| name.as_str())
+        });
+    }
+
+    /// The display name of a marketing authorization holder code, e.g. "Biontech Manufacturing GmbH"
+    pub fn holder_name(&self, code: &str) -> Option<&str> {
+        return self.display(VACCINE_AUTH_HOLDERS, code);
+    }
+
+    /// The display name of a country code, e.g. "Sweden"
+    pub fn country_name(&self, code: &str) -> Option<&str> {
+        return self.display(COUNTRY_CODES, code);
+    }
+}
+
+/// The display name of a country code in the value sets embedded in this crate, without building them
+pub(crate) fn builtin_country_name(code: &str) -> Option<&'static str> {
+    return COUNTRIES
+        .iter()
+        .find(|(country, _)| *country == code)
+        .map(|(_, name)| *name);
+}
+
+/// Uppercase alphanumeric characters of a code
+fn normalize(code: &str) -> String {
+    return code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+}
+
+impl Uvci {
+    /// The medicinal product name of the vaccine_id of a schema option 1 UVCI, e.g. "Comirnaty"
+    ///
+    /// None if the vaccine_id is not a medicinal product or marketing authorization holder code of the
+    /// value sets embedded in this crate.
+    pub fn vaccine_product(&self) -> Option<String> {
+        return self.vaccine_product_in(&ValueSets::builtin());
+    }
+
+    /// The medicinal product name of the vaccine_id of a schema option 1 UVCI in the given value sets
+    pub fn vaccine_product_in(&self, value_sets: &ValueSets) -> Option<String> {
+        let vaccine_id = self.vaccine_id.as_ref()?;
+        return value_sets
+            .product_name(vaccine_id)
+            .map(|name| name.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueSets;
+    use crate::parse;
+
+    #[test]
+    fn vaccine_product() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/EU1201528/123456789ABC");
+        assert!(uvci.vaccine_product() == Some("Comirnaty".to_string()));
+        let uvci = parse("URN:UVCI:01:SE:EHM/ORG100031184/123456789ABC");
+        assert!(uvci.vaccine_product() == Some("Spikevax".to_string()));
+        let uvci = parse("URN:UVCI:01:SE:EHM/C878/123456789ABC#B");
+        assert!(uvci.vaccine_product().is_none());
+        assert!(ValueSets::builtin().product_name("EU/1/21/1529") == Some("Vaxzevria"));
+        assert!(ValueSets::builtin().holder_name("ORG-100001699") == Some("AstraZeneca AB"));
+    }
+
+    #[test]
+    fn value_set_json() {
+        let json = br#"{
+            "valueSetId": "vaccines-covid-19-names",
+            "valueSetDate": "2022-01-01",
+            "valueSetValues": {
+                "EU/1/22/1709": {"display": "Bimervax", "lang": "en", "active": true, "version": "", "system": "https://ec.europa.eu/health/documents/community-register/html/"}
+            }
+        }"#;
+        let mut value_sets = ValueSets::from_json(json).unwrap();
+        assert!(value_sets.product_name("EU1221709") == Some("Bimervax"));
+        assert!(value_sets.product_name("EU1201528").is_none());
+
+        let countries = br#"{"valueSetId": "country-2-codes", "valueSetValues": {"SE": {"display": "Sweden"}}}"#;
+        value_sets.add_json(countries).unwrap();
+        assert!(value_sets.country_name("SE") == Some("Sweden"));
+        let uvci = parse("URN:UVCI:01:SE:EHM/EU1221709/123456789ABC");
+        assert!(uvci.vaccine_product_in(&value_sets) == Some("Bimervax".to_string()));
+
+        assert!(ValueSets::from_json(b"{}").is_err());
+    }
+}
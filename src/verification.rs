@@ -0,0 +1,371 @@
+//! Online verification of the status of UVCIs through pluggable backends
+use crate::revocation::{revocation_hash, HashEncoding, RevocationFilter, RevocationHashType};
+use crate::Uvci;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Normalized status of a UVCI reported by a verification backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VerificationStatus {
+    /// The certificate is known and valid
+    Valid,
+    /// The certificate was revoked by its issuer
+    Revoked,
+    /// The certificate is temporarily suspended, e.g. pending an investigation
+    Suspended,
+    /// The backend has no status for the certificate
+    Unknown,
+}
+
+impl VerificationStatus {
+    /// The code of the status, i.e. "valid", "revoked", "suspended" or "unknown"
+    pub fn code(&self) -> &'static str {
+        match self {
+            VerificationStatus::Valid => "valid",
+            VerificationStatus::Revoked => "revoked",
+            VerificationStatus::Suspended => "suspended",
+            VerificationStatus::Unknown => "unknown",
+        }
+    }
+
+    /// The status of a status value of a national endpoint, case-insensitive, e.g. "REVOKED"
+    pub fn from_code(code: &str) -> Option<VerificationStatus> {
+        match code.to_ascii_lowercase().as_str() {
+            "valid" | "active" | "ok" => Some(VerificationStatus::Valid),
+            "revoked" | "invalid" | "blocked" => Some(VerificationStatus::Revoked),
+            "suspended" => Some(VerificationStatus::Suspended),
+            "unknown" | "not_found" => Some(VerificationStatus::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Error querying a verification backend
+#[derive(Debug)]
+pub enum VerificationError {
+    /// Querying HTTP endpoints requires the `reqwest` feature
+    Unsupported,
+    /// The request could not be sent
+    #[cfg(feature = "reqwest")]
+    Request(reqwest::Error),
+    /// The endpoint answered with an unexpected HTTP status, with the URL
+    Status(u16, String),
+    /// The answer of the endpoint has no known status, with the reason
+    InvalidResponse(String),
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerificationError::Unsupported => {
+                write!(
+                    f,
+                    "querying verification endpoints requires the reqwest feature"
+                )
+            }
+            #[cfg(feature = "reqwest")]
+            VerificationError::Request(e) => write!(f, "verification request failed: {}", e),
+            VerificationError::Status(status, url) => {
+                write!(f, "{} answered with HTTP status {}", url, status)
+            }
+            VerificationError::InvalidResponse(reason) => {
+                write!(f, "invalid verification response: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for VerificationError {
+    fn from(e: reqwest::Error) -> VerificationError {
+        return VerificationError::Request(e);
+    }
+}
+
+/// Future of the status of a UVCI, boxed so backends can be used as trait objects
+pub type VerificationFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<VerificationStatus, VerificationError>> + Send + 'a>>;
+
+/// Backend reporting the status of parsed UVCIs, e.g. a national verification or revocation endpoint
+///
+/// The futures are runtime-agnostic, except those of `HttpVerificationBackend`, which need a Tokio
+/// runtime like any reqwest request. `VerificationRouter` dispatches a UVCI to the backend of its country.
+pub trait VerificationBackend: Send + Sync {
+    /// Whether the backend can report the status of the UVCI, e.g. only UVCIs of its country
+    fn supports(&self, uvci: &Uvci) -> bool;
+
+    /// Query the status of the UVCI
+    fn verify<'a>(&'a self, uvci: &'a Uvci) -> VerificationFuture<'a>;
+}
+
+/// Backend querying a national HTTP endpoint with a GET request per UVCI
+///
+/// The URL template may contain the placeholders "{uvci}" for the percent-encoded canonical UVCI,
+/// "{country}" for its country code and "{hash}" for its hex UCI revocation hash, the first 128 bits of
+/// its SHA-256 as published by the DCC gateway. Without status field, the HTTP status is the status:
+/// 2xx is valid, 404 unknown and 410 revoked. With a status field, the status is read from the string
+/// of that field of the JSON answer, e.g. "status", see `VerificationStatus::from_code`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpVerificationBackend {
+    url_template: String,
+    countries: Vec<String>,
+    status_field: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpVerificationBackend {
+    /// Create a backend for an endpoint, supporting the UVCIs of all countries
+    ///
+    /// # Arguments
+    ///
+    /// * `url_template` - the URL of the endpoint, e.g. "https://verify.example/api/status/{hash}"
+    pub fn new(url_template: &str) -> HttpVerificationBackend {
+        return HttpVerificationBackend {
+            url_template: url_template.to_string(),
+            countries: Vec::new(),
+            status_field: None,
+            headers: Vec::new(),
+        };
+    }
+
+    /// Only support the UVCIs of the ISO 3166-1 country codes, e.g. "AT"
+    pub fn countries(mut self, countries: &[&str]) -> HttpVerificationBackend {
+        self.countries = countries.iter().map(|c| c.to_uppercase()).collect();
+        return self;
+    }
+
+    /// Read the status from a string field of the JSON answer, e.g. "status"
+    pub fn status_field(mut self, status_field: &str) -> HttpVerificationBackend {
+        self.status_field = Some(status_field.to_string());
+        return self;
+    }
+
+    /// Send a header with every request, e.g. an API key
+    pub fn header(mut self, name: &str, value: &str) -> HttpVerificationBackend {
+        self.headers.push((name.to_string(), value.to_string()));
+        return self;
+    }
+
+    /// The URL queried for a UVCI
+    ///
+    /// # Arguments
+    ///
+    /// * `uvci` - the parsed UVCI
+    pub fn url(&self, uvci: &Uvci) -> String {
+        let hash = revocation_hash(uvci, RevocationHashType::Uci);
+        return self
+            .url_template
+            .replace("{uvci}", &percent_encode(&uvci.canonical()))
+            .replace("{country}", uvci.country_str())
+            .replace("{hash}", &HashEncoding::Hex.encode(&hash));
+    }
+
+    /// The status of an answer of the endpoint, e.g. for integrators sending the requests themselves
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - the URL queried, see `url`
+    /// * `http_status` - the HTTP status of the answer, e.g. 200
+    /// * `body` - the body of the answer
+    pub fn status_of(
+        &self,
+        url: &str,
+        http_status: u16,
+        body: &str,
+    ) -> Result<VerificationStatus, VerificationError> {
+        let field = match &self.status_field {
+            Some(field) if (200..300).contains(&http_status) => field,
+            _ => {
+                return match http_status {
+                    200..=299 => Ok(VerificationStatus::Valid),
+                    404 => Ok(VerificationStatus::Unknown),
+                    410 => Ok(VerificationStatus::Revoked),
+                    _ => Err(VerificationError::Status(http_status, url.to_string())),
+                }
+            }
+        };
+        let answer: serde_json::Value = serde_json::from_str(body)
+            .map_err(|e| VerificationError::InvalidResponse(e.to_string()))?;
+        return match answer[field.as_str()].as_str() {
+            Some(code) => VerificationStatus::from_code(code).ok_or_else(|| {
+                VerificationError::InvalidResponse(format!("unknown status {}", code))
+            }),
+            None => Err(VerificationError::InvalidResponse(format!(
+                "no {} field",
+                field
+            ))),
+        };
+    }
+
+    #[cfg(feature = "reqwest")]
+    async fn query(&self, uvci: &Uvci) -> Result<VerificationStatus, VerificationError> {
+        let url = self.url(uvci);
+        let mut request = reqwest::Client::new().get(&url);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request.send().await?;
+        let http_status = response.status().as_u16();
+        let body = response.text().await?;
+        return self.status_of(&url, http_status, &body);
+    }
+}
+
+impl VerificationBackend for HttpVerificationBackend {
+    fn supports(&self, uvci: &Uvci) -> bool {
+        return self.countries.is_empty()
+            || self
+                .countries
+                .iter()
+                .any(|country| country == uvci.country_str());
+    }
+
+    fn verify<'a>(&'a self, uvci: &'a Uvci) -> VerificationFuture<'a> {
+        #[cfg(feature = "reqwest")]
+        return Box::pin(self.query(uvci));
+        #[cfg(not(feature = "reqwest"))]
+        return {
+            let _ = uvci;
+            Box::pin(async { Err(VerificationError::Unsupported) })
+        };
+    }
+}
+
+/// Backend checking UVCIs against a downloaded revocation bloom filter, without network access
+///
+/// A UVCI in the filter is reported revoked, any other UVCI valid. As the filter may have false
+/// positives, revoked UVCIs should be confirmed against the full revocation list.
+pub struct RevocationFilterBackend {
+    filter: RevocationFilter,
+}
+
+impl RevocationFilterBackend {
+    pub fn new(filter: RevocationFilter) -> RevocationFilterBackend {
+        return RevocationFilterBackend { filter };
+    }
+}
+
+impl VerificationBackend for RevocationFilterBackend {
+    fn supports(&self, _uvci: &Uvci) -> bool {
+        return true;
+    }
+
+    fn verify<'a>(&'a self, uvci: &'a Uvci) -> VerificationFuture<'a> {
+        let status = if self.filter.probably_revoked(uvci) {
+            VerificationStatus::Revoked
+        } else {
+            VerificationStatus::Valid
+        };
+        return Box::pin(async move { Ok(status) });
+    }
+}
+
+/// Backend dispatching every UVCI to the first of its backends supporting it
+///
+/// UVCIs no backend supports are reported unknown, e.g. countries without a public API.
+#[derive(Default)]
+pub struct VerificationRouter {
+    backends: Vec<Box<dyn VerificationBackend>>,
+}
+
+impl VerificationRouter {
+    pub fn new() -> VerificationRouter {
+        return VerificationRouter::default();
+    }
+
+    /// Add a backend, queried if no backend added before supports the UVCI
+    pub fn backend<B: VerificationBackend + 'static>(mut self, backend: B) -> VerificationRouter {
+        self.backends.push(Box::new(backend));
+        return self;
+    }
+}
+
+impl VerificationBackend for VerificationRouter {
+    fn supports(&self, uvci: &Uvci) -> bool {
+        return self.backends.iter().any(|backend| backend.supports(uvci));
+    }
+
+    fn verify<'a>(&'a self, uvci: &'a Uvci) -> VerificationFuture<'a> {
+        return match self.backends.iter().find(|backend| backend.supports(uvci)) {
+            Some(backend) => backend.verify(uvci),
+            None => Box::pin(async { Ok(VerificationStatus::Unknown) }),
+        };
+    }
+}
+
+/// Percent-encode all characters but unreserved ones (RFC 3986), for a UVCI in a URL path or query
+fn percent_encode(value: &str) -> String {
+    let mut encoded = "".to_string();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    return encoded;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        HttpVerificationBackend, RevocationFilterBackend, VerificationBackend, VerificationRouter,
+        VerificationStatus,
+    };
+    use crate::parse;
+    use crate::revocation::{RevocationFilter, RevocationHashType};
+
+    #[tokio::test]
+    async fn verification_backends() {
+        let revoked = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        let valid = parse("URN:UVCI:01:SE:EHM/V12916227TFJJ#Q");
+        let mut filter = RevocationFilter::new(RevocationHashType::Uci, 100, 0.000001);
+        filter.insert(&revoked);
+        let router = VerificationRouter::new()
+            .backend(
+                HttpVerificationBackend::new("https://verify.example/{uvci}").countries(&["at"]),
+            )
+            .backend(RevocationFilterBackend::new(filter));
+        assert!(router.verify(&revoked).await.unwrap() == VerificationStatus::Revoked);
+        assert!(router.verify(&valid).await.unwrap() == VerificationStatus::Valid);
+
+        let austrian = parse("URN:UVCI:01:AT:10807843F94AEE0EE5093FBC254BD813#B");
+        let router = VerificationRouter::new().backend(
+            HttpVerificationBackend::new("https://verify.example/{uvci}").countries(&["AT"]),
+        );
+        assert!(!router.supports(&valid));
+        assert!(router.verify(&valid).await.unwrap() == VerificationStatus::Unknown);
+        assert!(router.supports(&austrian));
+    }
+
+    #[test]
+    fn http_verification_backend() {
+        let uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        let backend =
+            HttpVerificationBackend::new("https://verify.example/{country}/{uvci}?hash={hash}");
+        let url = backend.url(&uvci);
+        assert!(url.starts_with(
+            "https://verify.example/SE/URN%3AUVCI%3A01%3ASE%3AEHM%2FV12907267LAJW%23E?hash="
+        ));
+        assert!(url.len() == url.find("hash=").unwrap() + 5 + 32);
+        assert!(backend.status_of(&url, 410, "").unwrap() == VerificationStatus::Revoked);
+        assert!(backend.status_of(&url, 500, "").is_err());
+
+        let backend = backend.status_field("status");
+        let status = backend.status_of(&url, 200, r#"{"status":"SUSPENDED"}"#);
+        assert!(status.unwrap() == VerificationStatus::Suspended);
+        assert!(backend
+            .status_of(&url, 200, r#"{"state":"valid"}"#)
+            .is_err());
+        assert!(backend.status_of(&url, 404, "").unwrap() == VerificationStatus::Unknown);
+    }
+}
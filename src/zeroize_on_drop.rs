@@ -0,0 +1,55 @@
+//! Wiping of the fields of parsed UVCIs from memory when they are dropped
+use crate::{SchemaOption, Uvci};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Wipe the fields of the UVCI, leaving an empty UVCI of unknown schema option
+impl Zeroize for Uvci {
+    fn zeroize(&mut self) {
+        self.cert_id.zeroize();
+        self.version.zeroize();
+        if let Some(country) = self.country.as_mut() {
+            country.0.zeroize();
+        }
+        self.country = None;
+        self.schema_option = SchemaOption::Unknown;
+        self.issuing_entity.zeroize();
+        self.vaccine_id.zeroize();
+        self.vaccine_product_code.zeroize();
+        self.vaccine_lot.zeroize();
+        self.opaque_unique_string.zeroize();
+        self.opaque_id.zeroize();
+        self.opaque_issuance.zeroize();
+        if let Some(vaccination) = self.opaque_vaccination.as_mut() {
+            vaccination.year.zeroize();
+            vaccination.month.zeroize();
+        }
+        self.opaque_vaccination = None;
+        self.checksum.zeroize();
+        self.checksum_verification.zeroize();
+    }
+}
+
+/// Wipe the fields of the UVCI from memory when it is dropped
+impl Drop for Uvci {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Uvci {}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+    use zeroize::Zeroize;
+
+    #[test]
+    fn uvci_zeroize() {
+        let mut uvci = parse("URN:UVCI:01:SE:EHM/V12907267LAJW#E");
+        uvci.zeroize();
+        assert!(uvci.cert_id.is_empty() && uvci.opaque_unique_string.is_empty());
+        assert!(uvci.issuing_entity.is_none() && uvci.opaque_id.is_none());
+        assert!(uvci.country.is_none() && uvci.opaque_vaccination.is_none());
+        assert!(!uvci.checksum_verification);
+    }
+}